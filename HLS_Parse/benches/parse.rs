@@ -0,0 +1,30 @@
+//! Parsing a 24 hour DVR playlist of 2 second segments on one thread and with
+//! [`MediaPlaylist::parse_parallel`]. Run with `cargo bench --features rayon`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use hls_parsing::{MediaPlaylist, ParseOptions};
+
+fn dvr_playlist(segments: usize) -> String {
+    let mut file = String::from("#EXTM3U\n#EXT-X-VERSION:6\n#EXT-X-TARGETDURATION:2\n#EXT-X-MAP:URI=\"init.mp4\"\n");
+    for index in 0..segments {
+        if index % 900 == 0 {
+            file.push_str(&format!("#EXT-X-KEY:METHOD=SAMPLE-AES,URI=\"skd://{index}\",KEYFORMAT=\"com.apple.streamingkeydelivery\"\n"));
+            file.push_str(&format!("#EXT-X-PROGRAM-DATE-TIME:2024-01-01T{:02}:{:02}:00.000Z\n", index / 1800, index / 30 % 60));
+        }
+        file.push_str(&format!("#EXTINF:2.002,\n#EXT-X-BYTERANGE:{}@{}\nsegment{}.mp4\n", 500_000 + index % 977, index * 600_000, index / 100));
+    }
+    file + "#EXT-X-ENDLIST\n"
+}
+
+fn parse(c: &mut Criterion) {
+    let file = dvr_playlist(43_200);
+    let options = ParseOptions::default();
+    let mut group = c.benchmark_group("dvr_43200_segments");
+    group.sample_size(20);
+    group.bench_function("sequential", |b| b.iter(|| MediaPlaylist::parse_with_options(&file, &options).unwrap()));
+    group.bench_function("parallel", |b| b.iter(|| MediaPlaylist::parse_parallel(&file, &options).unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -0,0 +1,17 @@
+//! Parsing arbitrary bytes must return an error rather than panic. Run with
+//! `cargo +nightly fuzz run parse`.
+
+#![no_main]
+
+use hls_parsing::{MasterPlaylist, MediaPlaylist};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(playlist) = MediaPlaylist::parse_ext_m3u_bytes(data) {
+        let _ = playlist.diagnostics();
+        let _ = playlist.to_string();
+    }
+    if let Ok(playlist) = MasterPlaylist::parse_ext_m3u_bytes(data) {
+        let _ = playlist.to_string();
+    }
+});
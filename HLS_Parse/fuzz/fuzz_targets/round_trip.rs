@@ -0,0 +1,13 @@
+//! Writing out a valid playlist and parsing it again must give the same playlist. Run with
+//! `cargo +nightly fuzz run round_trip`.
+
+#![no_main]
+
+use hls_parsing::{MasterPlaylist, MediaPlaylist};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|playlists: (MediaPlaylist, MasterPlaylist)| {
+    let (media, master) = playlists;
+    assert_eq!(MediaPlaylist::parse_ext_m3u(&media.to_string()).unwrap(), media);
+    assert_eq!(MasterPlaylist::parse_ext_m3u(&master.to_string()).unwrap(), master);
+});
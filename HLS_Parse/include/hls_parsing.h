@@ -0,0 +1,310 @@
+#ifndef HLS_PARSING_H
+#define HLS_PARSING_H
+
+/* Generated with cbindgen from src/ffi.rs, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Information from an EXT-X-KEY tag, which applies to every following media segment until the
+// next EXT-X-KEY tag.
+typedef struct EncryptionKey EncryptionKey;
+
+// Storage for HLS Master Playlist data. Can be constructed from `ext-m3u` data using
+// [`parse_ext_m3u`][MasterPlaylist::parse_ext_m3u].
+typedef struct MasterPlaylist MasterPlaylist;
+
+// Storage for HLS Media Playlist data. Can be constructed from `ext-m3u` data using
+// [`parse_ext_m3u`][MediaPlaylist::parse_ext_m3u].
+typedef struct MediaPlaylist MediaPlaylist;
+
+// A media segment contains information to actually load the presentation. See [the
+// specification][spec] for more details.
+//
+// [spec]: https://datatracker.ietf.org/doc/html/rfc8216#section-3
+typedef struct MediaSegment MediaSegment;
+
+// Information from an EXT-X-MAP tag: where to get the data needed to parse the following media
+// segments, e.g. the `moov` box of fragmented MP4. Applies to every following segment until the
+// next EXT-X-MAP tag.
+typedef struct SegmentMap SegmentMap;
+
+// One version of the presentation, from an EXT-X-STREAM-INF tag and the URI of the media
+// playlist on the line after it.
+typedef struct VariantStream VariantStream;
+
+// A [`ByteRange`], with `has_offset` false when the range starts right after the previous one.
+typedef struct HlsByteRange {
+  uint64_t length;
+  uint64_t offset;
+  bool has_offset;
+} HlsByteRange;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Parses `len` bytes of UTF-8 at `data` into a new playlist handle.
+//
+// On failure returns NULL and, if `error` is not NULL, stores a NUL-terminated message there
+// which must be released with [`hls_string_free`].
+//
+// # Safety
+//
+// `data` must point to `len` readable bytes, and `error` must be NULL or point to writable
+// storage for a pointer.
+struct MediaPlaylist *hls_media_playlist_parse(const uint8_t *data, uintptr_t len, char **error);
+
+// Releases a playlist returned by [`hls_media_playlist_parse`]. NULL is ignored.
+//
+// # Safety
+//
+// `playlist` must be NULL or a handle which hasn't been freed yet.
+void hls_media_playlist_free(struct MediaPlaylist *playlist);
+
+// Releases an error message returned through [`hls_media_playlist_parse`] or
+// [`hls_master_playlist_parse`]. NULL is ignored.
+//
+// # Safety
+//
+// `string` must be NULL or a message which hasn't been freed yet.
+void hls_string_free(char *string);
+
+// See [`MediaPlaylist::version`].
+//
+// # Safety
+//
+// `playlist` must be a live handle.
+uint64_t hls_media_playlist_version(const struct MediaPlaylist *playlist);
+
+// See [`MediaPlaylist::target_duration`], in seconds.
+//
+// # Safety
+//
+// `playlist` must be a live handle.
+double hls_media_playlist_target_duration(const struct MediaPlaylist *playlist);
+
+// See [`MediaPlaylist::ended`].
+//
+// # Safety
+//
+// `playlist` must be a live handle.
+bool hls_media_playlist_ended(const struct MediaPlaylist *playlist);
+
+// Number of segments, for use with [`hls_media_playlist_segment`].
+//
+// # Safety
+//
+// `playlist` must be a live handle.
+uintptr_t hls_media_playlist_segment_count(const struct MediaPlaylist *playlist);
+
+// Borrows the segment at `index`, or returns NULL if it is out of range.
+//
+// # Safety
+//
+// `playlist` must be a live handle.
+const struct MediaSegment *hls_media_playlist_segment(const struct MediaPlaylist *playlist,
+                                                      uintptr_t index);
+
+// See [`MediaSegment::duration`], in seconds.
+//
+// # Safety
+//
+// `segment` must be a handle borrowed from a live playlist.
+double hls_media_segment_duration(const struct MediaSegment *segment);
+
+// See [`MediaSegment::url`]. Returns the start of the UTF-8 bytes and stores their count in
+// `len`.
+//
+// # Safety
+//
+// `segment` must be a handle borrowed from a live playlist and `len` must be writable.
+const uint8_t *hls_media_segment_url(const struct MediaSegment *segment, uintptr_t *len);
+
+// See [`MediaSegment::byte_range`]. Returns false, leaving `byte_range` untouched, if the
+// segment is a whole resource.
+//
+// # Safety
+//
+// `segment` must be a handle borrowed from a live playlist and `byte_range` must be writable.
+bool hls_media_segment_byte_range(const struct MediaSegment *segment,
+                                  struct HlsByteRange *byte_range);
+
+// See [`MediaSegment::discontinuity`].
+//
+// # Safety
+//
+// `segment` must be a handle borrowed from a live playlist.
+bool hls_media_segment_discontinuity(const struct MediaSegment *segment);
+
+// See [`MediaSegment::gap`].
+//
+// # Safety
+//
+// `segment` must be a handle borrowed from a live playlist.
+bool hls_media_segment_gap(const struct MediaSegment *segment);
+
+// See [`MediaSegment::program_date_time`]. Stores the seconds since the Unix epoch and the
+// offset from UTC it was written with, or returns false if the segment has no date.
+//
+// # Safety
+//
+// `segment` must be a handle borrowed from a live playlist, and `seconds` and `offset_minutes`
+// must be writable.
+bool hls_media_segment_program_date_time(const struct MediaSegment *segment,
+                                         double *seconds,
+                                         int16_t *offset_minutes);
+
+// Number of keys applying to the segment, for use with [`hls_media_segment_key`].
+//
+// # Safety
+//
+// `segment` must be a handle borrowed from a live playlist.
+uintptr_t hls_media_segment_key_count(const struct MediaSegment *segment);
+
+// Borrows the key at `index`, or returns NULL if it is out of range.
+//
+// # Safety
+//
+// `segment` must be a handle borrowed from a live playlist.
+const struct EncryptionKey *hls_media_segment_key(const struct MediaSegment *segment,
+                                                  uintptr_t index);
+
+// See [`EncryptionKey::method`], as written in the playlist, e.g. `AES-128`.
+//
+// # Safety
+//
+// `key` must be a handle borrowed from a live playlist and `len` must be writable.
+const uint8_t *hls_encryption_key_method(const struct EncryptionKey *key, uintptr_t *len);
+
+// See [`EncryptionKey::uri`].
+//
+// # Safety
+//
+// `key` must be a handle borrowed from a live playlist and `len` must be writable.
+const uint8_t *hls_encryption_key_uri(const struct EncryptionKey *key, uintptr_t *len);
+
+// See [`EncryptionKey::iv`].
+//
+// # Safety
+//
+// `key` must be a handle borrowed from a live playlist and `len` must be writable.
+const uint8_t *hls_encryption_key_iv(const struct EncryptionKey *key, uintptr_t *len);
+
+// See [`EncryptionKey::key_format`].
+//
+// # Safety
+//
+// `key` must be a handle borrowed from a live playlist and `len` must be writable.
+const uint8_t *hls_encryption_key_format(const struct EncryptionKey *key, uintptr_t *len);
+
+// See [`MediaSegment::map`]. Returns NULL if the segment has no media initialization section.
+//
+// # Safety
+//
+// `segment` must be a handle borrowed from a live playlist.
+const struct SegmentMap *hls_media_segment_map(const struct MediaSegment *segment);
+
+// See [`SegmentMap::uri`].
+//
+// # Safety
+//
+// `map` must be a handle borrowed from a live playlist and `len` must be writable.
+const uint8_t *hls_segment_map_uri(const struct SegmentMap *map, uintptr_t *len);
+
+// See [`SegmentMap::byte_range`], like [`hls_media_segment_byte_range`].
+//
+// # Safety
+//
+// `map` must be a handle borrowed from a live playlist and `byte_range` must be writable.
+bool hls_segment_map_byte_range(const struct SegmentMap *map, struct HlsByteRange *byte_range);
+
+// Parses `len` bytes of UTF-8 at `data` into a new master playlist handle, reporting errors like
+// [`hls_media_playlist_parse`].
+//
+// # Safety
+//
+// As for [`hls_media_playlist_parse`].
+struct MasterPlaylist *hls_master_playlist_parse(const uint8_t *data, uintptr_t len, char **error);
+
+// Releases a playlist returned by [`hls_master_playlist_parse`]. NULL is ignored.
+//
+// # Safety
+//
+// `playlist` must be NULL or a handle which hasn't been freed yet.
+void hls_master_playlist_free(struct MasterPlaylist *playlist);
+
+// See [`MasterPlaylist::version`].
+//
+// # Safety
+//
+// `playlist` must be a live handle.
+uint64_t hls_master_playlist_version(const struct MasterPlaylist *playlist);
+
+// Number of variant streams, for use with [`hls_master_playlist_variant`].
+//
+// # Safety
+//
+// `playlist` must be a live handle.
+uintptr_t hls_master_playlist_variant_count(const struct MasterPlaylist *playlist);
+
+// Borrows the variant stream at `index`, or returns NULL if it is out of range.
+//
+// # Safety
+//
+// `playlist` must be a live handle.
+const struct VariantStream *hls_master_playlist_variant(const struct MasterPlaylist *playlist,
+                                                        uintptr_t index);
+
+// See [`VariantStream::uri`].
+//
+// # Safety
+//
+// `variant` must be a handle borrowed from a live playlist and `len` must be writable.
+const uint8_t *hls_variant_stream_uri(const struct VariantStream *variant, uintptr_t *len);
+
+// See [`VariantStream::bandwidth`].
+//
+// # Safety
+//
+// `variant` must be a handle borrowed from a live playlist.
+uint64_t hls_variant_stream_bandwidth(const struct VariantStream *variant);
+
+// See [`VariantStream::average_bandwidth`]. Returns false if the playlist doesn't state it.
+//
+// # Safety
+//
+// `variant` must be a handle borrowed from a live playlist and `bandwidth` must be writable.
+bool hls_variant_stream_average_bandwidth(const struct VariantStream *variant, uint64_t *bandwidth);
+
+// See [`VariantStream::codecs`].
+//
+// # Safety
+//
+// `variant` must be a handle borrowed from a live playlist and `len` must be writable.
+const uint8_t *hls_variant_stream_codecs(const struct VariantStream *variant, uintptr_t *len);
+
+// See [`VariantStream::resolution`]. Returns false if the playlist doesn't state it.
+//
+// # Safety
+//
+// `variant` must be a handle borrowed from a live playlist, and `width` and `height` must be
+// writable.
+bool hls_variant_stream_resolution(const struct VariantStream *variant,
+                                   uint64_t *width,
+                                   uint64_t *height);
+
+// See [`VariantStream::frame_rate`]. Returns false if the playlist doesn't state it.
+//
+// # Safety
+//
+// `variant` must be a handle borrowed from a live playlist and `frame_rate` must be writable.
+bool hls_variant_stream_frame_rate(const struct VariantStream *variant, double *frame_rate);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* HLS_PARSING_H */
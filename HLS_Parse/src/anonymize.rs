@@ -0,0 +1,169 @@
+//! Replacing the URIs of a playlist with placeholders, so reproduction manifests can be shared in
+//! bug reports without leaking signed URLs, hostnames or key locations.
+
+use std::collections::HashMap;
+
+use crate::{MasterPlaylist, MediaPlaylist};
+
+impl MediaPlaylist {
+    /// Replaces the URI of every segment, key, media initialization section and date range asset
+    /// with a placeholder like `redacted/3.ts`. Placeholders are numbered in order of first appearance
+    /// and keep the extension, so equal URIs stay equal and the output is the same every time.
+    ///
+    /// Unknown tags preceding segments are kept, with the values of their `URI` and `*-URI`
+    /// attributes replaced the same way. If the source was preserved, its formatting and other
+    /// unknown tags are kept too, but comments are dropped, since they may contain anything.
+    pub fn anonymize(&mut self) {
+        let mut placeholders = Placeholders::default();
+        self.map_urls(|uri| placeholders.get(uri));
+        for segment in self.segments_mut() {
+            segment.anonymize_custom_tags(&mut |uri| placeholders.get(uri));
+        }
+        self.source_mut().anonymize(&mut |uri| placeholders.get(uri));
+    }
+}
+
+impl MasterPlaylist {
+    /// Like [`MediaPlaylist::anonymize`], for the URIs of variants, I-frame variants, renditions,
+    /// session data and session keys. Session data values are replaced too, with placeholders
+    /// like `redacted/7`, while their DATA-ID stays to say what the data was.
+    pub fn anonymize(&mut self) {
+        let mut placeholders = Placeholders::default();
+        self.map_urls(|uri| placeholders.get(uri));
+        for data in self.session_data_mut() {
+            let value = data.value().map(|x| placeholders.value(x));
+            data.set_value(value);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Placeholders {
+    assigned: HashMap<String, String>,
+}
+
+impl Placeholders {
+    fn get(&mut self, uri: &str) -> String {
+        let number = self.assigned.len() + 1;
+        let placeholder = self.assigned.entry(uri.to_string()).or_insert_with(|| match extension(uri) {
+            Some(extension) => format!("redacted/{}.{}", number, extension),
+            None => format!("redacted/{}", number),
+        });
+        placeholder.clone()
+    }
+
+    /// Like [`get`][Self::get], for values which aren't URIs and so have no extension to keep.
+    fn value(&mut self, value: &str) -> String {
+        let number = self.assigned.len() + 1;
+        self.assigned.entry(value.to_string()).or_insert_with(|| format!("redacted/{}", number)).clone()
+    }
+}
+
+/// Extension of the last path segment, if it looks like one. The host of an absolute URL without
+/// a path doesn't count.
+fn extension(uri: &str) -> Option<&str> {
+    let path = uri.split(['?', '#']).next()?;
+    let path = match path.split_once("://") {
+        Some((_, rest)) => &rest[rest.find('/')?..],
+        None => path,
+    };
+    let (_, extension) = path.rsplit('/').next()?.rsplit_once('.')?;
+    Some(extension).filter(|x| (1..=5).contains(&x.len()) && x.chars().all(|x| x.is_ascii_alphanumeric()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseOptions;
+
+    #[test]
+    fn replaces_media_playlist_uris() {
+        let mut playlist = MediaPlaylist::parse_with_options(
+            indoc::indoc! {r#"
+                #EXTM3U
+                #EXT-X-VERSION:6
+                #EXT-X-TARGETDURATION:10
+                # origin: https://origin.example.com/secret
+                #EXT-X-MAP:URI="https://cdn.example.com/live/init.mp4?token=abc"
+                #EXT-X-KEY:METHOD=SAMPLE-AES,URI="skd://keys.example.com",KEYFORMAT="com.apple.streamingkeydelivery"
+                #EXT-X-DATERANGE:ID="ad",START-DATE="2024-03-01T12:00:00Z",X-ASSET-URI="https://ads.example.com/1.m3u8"
+                #EXTINF:10,
+                #EXT-X-BYTERANGE:1000@0
+                https://cdn.example.com/live/main.mp4?token=abc
+                #EXTINF:9.5,
+                #EXT-X-BYTERANGE:1000
+                https://cdn.example.com/live/main.mp4?token=abc
+                #EXTINF:10,
+                https://cdn.example.com/live/other.m4s?token=def
+            "#},
+            &ParseOptions { preserve_source: true, ..ParseOptions::default() },
+        )
+        .unwrap();
+        let mut canonical = MediaPlaylist::parse_ext_m3u(&playlist.to_string()).unwrap();
+        playlist.anonymize();
+        canonical.anonymize();
+        assert_eq!(canonical, playlist);
+        assert!(canonical.to_string().contains(r#"X-ASSET-URI="redacted/5.m3u8""#));
+        assert_eq!(
+            playlist.to_string(),
+            indoc::indoc! {r#"
+                #EXTM3U
+                #EXT-X-VERSION:6
+                #EXT-X-TARGETDURATION:10
+                #EXT-X-MAP:URI="redacted/3.mp4"
+                #EXT-X-KEY:METHOD=SAMPLE-AES,URI="redacted/2",KEYFORMAT="com.apple.streamingkeydelivery"
+                #EXT-X-DATERANGE:ID="ad",START-DATE="2024-03-01T12:00:00Z",X-ASSET-URI="redacted/5.m3u8"
+                #EXTINF:10,
+                #EXT-X-BYTERANGE:1000@0
+                redacted/1.mp4
+                #EXTINF:9.5,
+                #EXT-X-BYTERANGE:1000
+                redacted/1.mp4
+                #EXTINF:10,
+                redacted/4.m4s
+            "#}
+        );
+    }
+
+    #[test]
+    fn replaces_master_playlist_uris() {
+        let mut playlist = MasterPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",NAME="English",URI="https://cdn.example.com/en/audio.m3u8?sig=1"
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000,AUDIO="aac"
+            https://cdn.example.com/low/video.m3u8?sig=2
+            #EXT-X-STREAM-INF:BANDWIDTH=2560000,AUDIO="aac"
+            https://cdn.example.com/high/video.m3u8?sig=3
+        "#})
+        .unwrap();
+        playlist.anonymize();
+        let uris: Vec<&str> = playlist.variants().iter().map(|x| x.uri()).collect();
+        assert_eq!(uris, vec!["redacted/1.m3u8", "redacted/2.m3u8"]);
+        assert_eq!(playlist.renditions()[0].uri(), Some("redacted/3.m3u8"));
+    }
+
+    #[test]
+    fn replaces_session_data() {
+        let mut playlist = MasterPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-SESSION-DATA:DATA-ID="com.example.title",VALUE="Customer Town Hall",LANGUAGE="en"
+            #EXT-X-SESSION-DATA:DATA-ID="com.example.chapters",URI="https://api.example.com/chapters.json?sig=4"
+            #EXT-X-SESSION-KEY:METHOD=AES-128,URI="https://keys.example.com/1.key?sig=5"
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000
+            https://cdn.example.com/low/video.m3u8?sig=2
+        "#})
+        .unwrap();
+        playlist.anonymize();
+        assert_eq!(
+            playlist.to_string(),
+            indoc::indoc! {r#"
+                #EXTM3U
+                #EXT-X-SESSION-DATA:DATA-ID="com.example.title",VALUE="redacted/4",LANGUAGE="en"
+                #EXT-X-SESSION-DATA:DATA-ID="com.example.chapters",URI="redacted/2.json"
+                #EXT-X-SESSION-KEY:METHOD=AES-128,URI="redacted/3.key"
+                #EXT-X-STREAM-INF:BANDWIDTH=1280000
+                redacted/1.m3u8
+            "#}
+        );
+    }
+}
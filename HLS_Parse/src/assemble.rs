@@ -0,0 +1,267 @@
+//! Building a master playlist from the media playlists it lists, for packagers which write the
+//! media playlists themselves.
+
+use core::fmt::Write;
+use std::collections::HashSet;
+
+use anyhow::Result;
+
+use crate::consistency::{average_bit_rate, peak_bit_rate};
+use crate::{MasterPlaylist, MediaPlaylist, MediaType, Resolution};
+
+const AUDIO_GROUP: &str = "audio";
+const SUBTITLES_GROUP: &str = "subs";
+
+/// What [`MasterPlaylist::from_media_playlists`] can't tell from a media playlist by itself.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlaylistHints {
+    /// Kind of media, video if absent. Audio and subtitle playlists become renditions, unless
+    /// there is no video, in which case audio playlists become variants.
+    pub media_type: Option<MediaType>,
+
+    /// NAME of a rendition, its URI without `.m3u8` if absent.
+    pub name: Option<String>,
+
+    /// LANGUAGE of a rendition.
+    pub language: Option<String>,
+
+    /// Whether a rendition is the default of its group. Otherwise the first one is.
+    pub default: bool,
+
+    /// Peak bits per second, measured from the segment byte ranges if absent.
+    pub bandwidth: Option<u64>,
+
+    /// Average bits per second, measured from the segment byte ranges if absent.
+    pub average_bandwidth: Option<u64>,
+
+    pub codecs: Option<String>,
+    pub resolution: Option<Resolution>,
+    pub frame_rate: Option<f64>,
+}
+
+/// What the master playlist says about one media playlist.
+pub(crate) struct Entry {
+    pub(crate) uri: String,
+    pub(crate) media_type: MediaType,
+    pub(crate) i_frames_only: bool,
+    pub(crate) name: String,
+    pub(crate) language: Option<String>,
+    pub(crate) default: bool,
+    pub(crate) bandwidth: u64,
+    pub(crate) average_bandwidth: Option<u64>,
+    pub(crate) codecs: Option<String>,
+    pub(crate) resolution: Option<Resolution>,
+    pub(crate) frame_rate: Option<f64>,
+}
+
+impl MasterPlaylist {
+    /// Lists media playlists, given with the URI to refer to them by. Video playlists become
+    /// variants which play with every audio rendition in the `audio` group and every subtitle
+    /// rendition in the `subs` group, so their BANDWIDTH and CODECS include those of the audio.
+    /// I-frame playlists become EXT-X-I-FRAME-STREAM-INF tags. The version is the highest of the
+    /// media playlists.
+    ///
+    /// Returns an error if the bandwidth of a video or audio playlist is neither hinted nor
+    /// measurable, or if nothing would be a variant.
+    pub fn from_media_playlists<'a>(
+        playlists: impl IntoIterator<Item = (&'a str, &'a MediaPlaylist, PlaylistHints)>,
+    ) -> Result<MasterPlaylist> {
+        let mut entries = Vec::new();
+        let mut version = 0;
+        for (uri, playlist, hints) in playlists {
+            let media_type = match hints.media_type {
+                Some(MediaType::ClosedCaptions) => {
+                    return Err(anyhow::anyhow!("Playlist {} can't be a CLOSED-CAPTIONS rendition", uri));
+                }
+                Some(media_type) => media_type,
+                None => MediaType::Video,
+            };
+            let bandwidth = match hints.bandwidth.or_else(|| Some(peak_bit_rate(playlist)?.1.ceil() as u64)) {
+                Some(bandwidth) => bandwidth,
+                None if media_type == MediaType::Subtitles => 0,
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "Playlist {} needs a bandwidth hint, its bit rate can only be measured from byte ranges",
+                        uri
+                    ));
+                }
+            };
+            let average_bandwidth = hints.average_bandwidth.or_else(|| Some(average_bit_rate(playlist)?.ceil() as u64));
+            version = version.max(playlist.version());
+            entries.push(Entry {
+                uri: uri.to_string(),
+                media_type,
+                i_frames_only: playlist.i_frames_only(),
+                name: hints.name.unwrap_or_else(|| uri.trim_end_matches(".m3u8").to_string()),
+                language: hints.language,
+                default: hints.default,
+                bandwidth,
+                average_bandwidth: average_bandwidth.map(|x| x.min(bandwidth)),
+                codecs: hints.codecs,
+                resolution: hints.resolution,
+                frame_rate: hints.frame_rate,
+            });
+        }
+        if !entries.iter().any(|x| !x.i_frames_only && matches!(x.media_type, MediaType::Video | MediaType::Audio)) {
+            return Err(anyhow::Error::msg("Master playlist needs a video or audio playlist as a variant"));
+        }
+        master_playlist(&entries, version)
+    }
+}
+
+/// Renditions first, then a variant for each video entry, or each audio one if there is no
+/// video.
+pub(crate) fn master_playlist(entries: &[Entry], version: u64) -> Result<MasterPlaylist> {
+    let of_type = |media_type: MediaType| entries.iter().filter(move |x| x.media_type == media_type && !x.i_frames_only);
+    let audio_only = of_type(MediaType::Video).next().is_none();
+    let has_audio = !audio_only && of_type(MediaType::Audio).next().is_some();
+    let has_subtitles = of_type(MediaType::Subtitles).next().is_some();
+
+    let mut file = String::from("#EXTM3U\n");
+    if version > 0 {
+        writeln!(file, "#EXT-X-VERSION:{}", version).unwrap();
+    }
+    for (media_type, group) in [(MediaType::Audio, AUDIO_GROUP), (MediaType::Subtitles, SUBTITLES_GROUP)] {
+        if media_type == MediaType::Audio && audio_only {
+            continue;
+        }
+        let renditions: Vec<&Entry> = of_type(media_type).collect();
+        let default = renditions.iter().position(|x| x.default).unwrap_or(0);
+        let mut names: HashSet<String> = HashSet::new();
+        for (index, rendition) in renditions.iter().enumerate() {
+            //names must be unique within the group
+            let mut name = quoted(&rendition.name);
+            if !names.insert(name.clone()) {
+                name = format!("{} ({})", name, quoted(rendition.uri.trim_end_matches(".m3u8")));
+                names.insert(name.clone());
+            }
+            write!(file, "#EXT-X-MEDIA:TYPE={},GROUP-ID=\"{}\",NAME=\"{}\"", media_type.as_str(), group, name).unwrap();
+            if let Some(language) = &rendition.language {
+                write!(file, ",LANGUAGE=\"{}\"", quoted(language)).unwrap();
+            }
+            let default = if index == default { "YES" } else { "NO" };
+            writeln!(file, ",DEFAULT={},AUTOSELECT=YES,URI=\"{}\"", default, quoted(&rendition.uri)).unwrap();
+        }
+    }
+
+    //the variant's bandwidth and codecs cover the audio rendition played along with it
+    let audio = of_type(MediaType::Audio).filter(|_| has_audio);
+    let audio_bandwidth = audio.clone().map(|x| x.bandwidth).max().unwrap_or(0);
+    let audio_average = audio.clone().map(|x| x.average_bandwidth.unwrap_or(x.bandwidth)).max().unwrap_or(0);
+    let audio_codecs = audio.clone().find_map(|x| x.codecs.as_deref());
+    let variant_type = if audio_only { MediaType::Audio } else { MediaType::Video };
+    for variant in of_type(variant_type) {
+        write!(file, "#EXT-X-STREAM-INF:BANDWIDTH={}", variant.bandwidth + audio_bandwidth).unwrap();
+        if let Some(average) = variant.average_bandwidth {
+            write!(file, ",AVERAGE-BANDWIDTH={}", average + audio_average).unwrap();
+        }
+        let codecs: Vec<&str> = variant.codecs.as_deref().into_iter().chain(audio_codecs).collect();
+        if !codecs.is_empty() {
+            write!(file, ",CODECS=\"{}\"", quoted(&codecs.join(","))).unwrap();
+        }
+        if let Some(resolution) = variant.resolution {
+            write!(file, ",RESOLUTION={}", resolution).unwrap();
+        }
+        if let Some(frame_rate) = variant.frame_rate {
+            write!(file, ",FRAME-RATE={:.3}", frame_rate).unwrap();
+        }
+        if has_audio {
+            write!(file, ",AUDIO=\"{}\"", AUDIO_GROUP).unwrap();
+        }
+        if has_subtitles {
+            write!(file, ",SUBTITLES=\"{}\"", SUBTITLES_GROUP).unwrap();
+        }
+        writeln!(file, "\n{}", variant.uri).unwrap();
+    }
+    for variant in entries.iter().filter(|x| x.i_frames_only && x.media_type == variant_type) {
+        write!(file, "#EXT-X-I-FRAME-STREAM-INF:BANDWIDTH={}", variant.bandwidth).unwrap();
+        if let Some(codecs) = &variant.codecs {
+            write!(file, ",CODECS=\"{}\"", quoted(codecs)).unwrap();
+        }
+        if let Some(resolution) = variant.resolution {
+            write!(file, ",RESOLUTION={}", resolution).unwrap();
+        }
+        writeln!(file, ",URI=\"{}\"", quoted(&variant.uri)).unwrap();
+    }
+    MasterPlaylist::parse_ext_m3u(&file)
+}
+
+/// `value` without the characters a quoted string can't contain.
+pub(crate) fn quoted(value: &str) -> String {
+    value.replace(['"', '\r', '\n'], "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn media(file: &str) -> MediaPlaylist {
+        MediaPlaylist::parse_ext_m3u(file).unwrap()
+    }
+
+    #[test]
+    fn lists_media_playlists() {
+        let video = media(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-VERSION:4
+            #EXT-X-TARGETDURATION:4
+            #EXTINF:4,
+            #EXT-X-BYTERANGE:500000@0
+            video.ts
+            #EXTINF:4,
+            #EXT-X-BYTERANGE:1500000
+            video.ts
+            #EXT-X-ENDLIST
+        "});
+        let audio = media("#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:4\n#EXTINF:4.0,\naudio.aac\n#EXT-X-ENDLIST\n");
+        let subtitles = media("#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXTINF:4,\nen.vtt\n#EXT-X-ENDLIST\n");
+        let master = MasterPlaylist::from_media_playlists([
+            (
+                "video.m3u8",
+                &video,
+                PlaylistHints {
+                    codecs: Some("avc1.4d401f".to_string()),
+                    resolution: Some(Resolution { width: 1280, height: 720 }),
+                    ..PlaylistHints::default()
+                },
+            ),
+            (
+                "audio/en.m3u8",
+                &audio,
+                PlaylistHints {
+                    media_type: Some(MediaType::Audio),
+                    name: Some("English".to_string()),
+                    language: Some("en".to_string()),
+                    bandwidth: Some(128000),
+                    codecs: Some("mp4a.40.2".to_string()),
+                    ..PlaylistHints::default()
+                },
+            ),
+            ("subs/en.m3u8", &subtitles, PlaylistHints { media_type: Some(MediaType::Subtitles), ..PlaylistHints::default() }),
+        ])
+        .unwrap();
+        assert_eq!(
+            master.to_string(),
+            indoc::indoc! {r#"
+                #EXTM3U
+                #EXT-X-VERSION:4
+                #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="audio",LANGUAGE="en",NAME="English",DEFAULT=YES,AUTOSELECT=YES,URI="audio/en.m3u8"
+                #EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID="subs",NAME="subs/en",DEFAULT=YES,AUTOSELECT=YES,URI="subs/en.m3u8"
+                #EXT-X-STREAM-INF:BANDWIDTH=3128000,AVERAGE-BANDWIDTH=2128000,CODECS="avc1.4d401f,mp4a.40.2",RESOLUTION=1280x720,AUDIO="audio",SUBTITLES="subs"
+                video.m3u8
+            "#}
+        );
+    }
+
+    #[test]
+    fn needs_bandwidth_and_variants() {
+        let playlist = media("#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXTINF:4,\n1.ts\n");
+        let error = MasterPlaylist::from_media_playlists([("1.m3u8", &playlist, PlaylistHints::default())]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Playlist 1.m3u8 needs a bandwidth hint, its bit rate can only be measured from byte ranges"
+        );
+        let subtitles = PlaylistHints { media_type: Some(MediaType::Subtitles), ..PlaylistHints::default() };
+        assert!(MasterPlaylist::from_media_playlists([("1.m3u8", &playlist, subtitles)]).is_err());
+    }
+}
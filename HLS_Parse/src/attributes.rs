@@ -0,0 +1,282 @@
+//! Parsing and writing of attribute lists. See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.2>.
+
+use core::fmt::{self, Write};
+use core::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use anyhow::Result;
+
+/// The `NAME=value` pairs of a tag, in their original order. Values are kept raw, so quoted
+/// strings still have their quotes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct AttributeList<'a> {
+    attributes: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> AttributeList<'a> {
+    pub(crate) fn parse(list: &'a str) -> Result<Self> {
+        let mut attributes = Vec::new();
+        let mut rest = list;
+        while !rest.is_empty() {
+            let Some((name, after_name)) = rest.split_once('=') else {
+                return Err(anyhow::anyhow!("Attribute without value in \"{}\"", list));
+            };
+            if name.is_empty() || !name.bytes().all(|x| x.is_ascii_uppercase() || x.is_ascii_digit() || x == b'-') {
+                return Err(anyhow::anyhow!("Invalid attribute name \"{}\"", name));
+            }
+            //quoted strings can contain commas, so find the closing quote first
+            let value_len = if let Some(quoted) = after_name.strip_prefix('"') {
+                match quoted.find('"') {
+                    Some(end) => end + 2,
+                    None => return Err(anyhow::anyhow!("Unterminated quoted string for attribute {}", name)),
+                }
+            } else {
+                after_name.find(',').unwrap_or(after_name.len())
+            };
+            let (value, after_value) = after_name.split_at(value_len);
+            if attributes.iter().any(|(existing, _)| *existing == name) {
+                return Err(anyhow::anyhow!("Attribute {} appears more than once", name));
+            }
+            attributes.push((name, value));
+            rest = match after_value.strip_prefix(',') {
+                Some(rest) => rest,
+                None if after_value.is_empty() => after_value,
+                None => return Err(anyhow::anyhow!("Expected ',' after attribute {}", name)),
+            };
+        }
+        Ok(Self { attributes })
+    }
+
+    /// Names and raw values in their original order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&'a str, &'a str)> + '_ {
+        self.attributes.iter().copied()
+    }
+
+    /// Raw value of the attribute.
+    pub(crate) fn get(&self, name: &str) -> Option<&'a str> {
+        self.attributes.iter().find(|(existing, _)| *existing == name).map(|(_, value)| *value)
+    }
+
+    /// Value of a quoted-string attribute, without the quotes.
+    pub(crate) fn quoted_string(&self, name: &str) -> Result<Option<&'a str>> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(value) => match value.strip_prefix('"').and_then(|x| x.strip_suffix('"')) {
+                Some(unquoted) => Ok(Some(unquoted)),
+                None => Err(anyhow::anyhow!("Attribute {} should be a quoted string", name)),
+            },
+        }
+    }
+
+    /// Value of a STABLE-VARIANT-ID or STABLE-RENDITION-ID attribute, a quoted string limited to
+    /// the characters allowed by
+    /// <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.6.2>.
+    pub(crate) fn stable_id(&self, name: &str) -> Result<Option<&'a str>> {
+        let value = self.quoted_string(name)?;
+        let allowed = |x: u8| x.is_ascii_alphanumeric() || b"+/=.-_".contains(&x);
+        if value.is_some_and(|x| x.is_empty() || !x.bytes().all(allowed)) {
+            return Err(anyhow::anyhow!("Attribute {} contains characters not allowed in a stable ID", name));
+        }
+        Ok(value)
+    }
+}
+
+/// The attribute list a value was parsed from, so attributes the crate doesn't model yet can
+/// still be read. Shared between clones, and ignored when comparing and hashing, so parsed values
+/// equal those built in code. Empty for values built in code, and not updated when they change.
+#[derive(Debug, Clone, Default)]
+pub struct RawAttributes(Option<Arc<str>>);
+
+impl RawAttributes {
+    pub(crate) fn new(list: &str) -> Self {
+        Self(Some(Arc::from(list)))
+    }
+
+    /// Raw value of the attribute, with the quotes of a quoted string.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.iter().find(|(existing, _)| *existing == name).map(|(_, value)| value)
+    }
+
+    /// Names and raw values in their original order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        //the list parsed when the value was, so this can't fail
+        let list = self.0.as_deref().and_then(|x| AttributeList::parse(x).ok());
+        list.into_iter().flat_map(|x| x.attributes)
+    }
+
+    /// The whole attribute list, `None` for values built in code.
+    pub fn as_str(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+}
+
+impl PartialEq for RawAttributes {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for RawAttributes {}
+
+impl Hash for RawAttributes {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+/// Builds an attribute list, checking each value against
+/// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.2> as it is added. The list is built
+/// in full either way, with the first invalid value kept for [`finish`][Self::finish].
+#[derive(Debug, Default)]
+pub(crate) struct AttributeWriter {
+    list: String,
+    error: Option<anyhow::Error>,
+}
+
+impl AttributeWriter {
+    /// Adds a value written as it is, e.g. a number or a resolution.
+    pub(crate) fn value(&mut self, name: &str, value: impl fmt::Display) -> &mut Self {
+        let separator = if self.list.is_empty() { "" } else { "," };
+        write!(self.list, "{}{}={}", separator, name, value).unwrap();
+        self
+    }
+
+    /// Adds a quoted string, which can hold commas but not double quotes or line breaks.
+    pub(crate) fn quoted(&mut self, name: &str, value: &str) -> &mut Self {
+        if value.contains(['"', '\r', '\n']) {
+            self.fail(anyhow::anyhow!("Attribute {} can't hold double quotes or line breaks: {:?}", name, value));
+        }
+        self.value(name, format_args!("\"{}\"", value))
+    }
+
+    /// Adds a quoted string holding a comma-separated list, whose items can't hold commas
+    /// themselves.
+    pub(crate) fn quoted_list(&mut self, name: &str, items: &[impl AsRef<str>]) -> &mut Self {
+        let items: Vec<&str> = items.iter().map(AsRef::as_ref).collect();
+        if let Some(item) = items.iter().find(|x| x.is_empty() || x.contains(',')) {
+            self.fail(anyhow::anyhow!("Attribute {} can't hold empty items or items with commas: {:?}", name, item));
+        }
+        self.quoted(name, &items.join(","))
+    }
+
+    /// Adds an enumerated string, which must be one of `allowed`.
+    pub(crate) fn enumerated(&mut self, name: &str, value: &str, allowed: &[&str]) -> &mut Self {
+        if !allowed.contains(&value) {
+            self.fail(anyhow::anyhow!("Attribute {} can't be {}, only one of {}", name, value, allowed.join(", ")));
+        }
+        self.value(name, value)
+    }
+
+    /// Records `check` failing, for values with rules of their own.
+    pub(crate) fn check(&mut self, check: Result<()>) -> &mut Self {
+        if let Err(error) = check {
+            self.fail(error);
+        }
+        self
+    }
+
+    fn fail(&mut self, error: anyhow::Error) {
+        self.error.get_or_insert(error);
+    }
+
+    /// The list, written even if a value was invalid.
+    pub(crate) fn as_str(&self) -> &str {
+        &self.list
+    }
+
+    /// The list, or the first invalid value.
+    pub(crate) fn finish(self) -> Result<String> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.list),
+        }
+    }
+}
+
+/// Number of attributes in the list, counting the commas outside quoted strings, without
+/// parsing it.
+pub(crate) fn count(list: &str) -> usize {
+    if list.is_empty() {
+        return 0;
+    }
+    let mut quoted = false;
+    let mut count = 1;
+    for x in list.bytes() {
+        match x {
+            b'"' => quoted = !quoted,
+            b',' if !quoted => count += 1,
+            _ => {}
+        }
+    }
+    count
+}
+
+/// Whether the attribute holds a URI: `URI`, any `*-URI`, and the X-ASSET-LIST of interstitials.
+pub(crate) fn is_uri_attribute(name: &str) -> bool {
+    name == "URI" || name.ends_with("-URI") || name == "X-ASSET-LIST"
+}
+
+/// Removes whitespace outside of quoted strings, which is never valid there. Returns `None` if
+/// there was none.
+pub(crate) fn strip_whitespace(list: &str) -> Option<String> {
+    let mut quoted = false;
+    let mut changed = false;
+    let mut stripped = String::with_capacity(list.len());
+    for c in list.chars() {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        if !quoted && c.is_whitespace() {
+            changed = true;
+        } else {
+            stripped.push(c);
+        }
+    }
+    changed.then_some(stripped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_quoted_and_enumerated_values() {
+        let list = AttributeList::parse(r#"METHOD=AES-128,URI="https://example.com/key?a=1,b=2",IV=0x1234"#)
+            .expect("should parse");
+        assert_eq!(list.get("METHOD"), Some("AES-128"));
+        assert_eq!(list.quoted_string("URI").unwrap(), Some("https://example.com/key?a=1,b=2"));
+        assert_eq!(list.get("IV"), Some("0x1234"));
+        assert_eq!(list.get("KEYFORMAT"), None);
+        assert!(list.quoted_string("METHOD").is_err());
+    }
+
+    #[test]
+    fn strips_whitespace_outside_quotes() {
+        assert_eq!(
+            strip_whitespace(r#" METHOD = AES-128 , URI = "keys/a key.bin" "#).as_deref(),
+            Some(r#"METHOD=AES-128,URI="keys/a key.bin""#)
+        );
+        assert_eq!(strip_whitespace(r#"METHOD=AES-128,URI="a key""#), None);
+    }
+
+    #[test]
+    fn rejects_malformed_lists() {
+        assert!(AttributeList::parse("METHOD").is_err());
+        assert!(AttributeList::parse(r#"URI="unterminated"#).is_err());
+        assert!(AttributeList::parse("METHOD=NONE,METHOD=NONE").is_err());
+        assert!(AttributeList::parse(r#"URI="a"b"#).is_err());
+        assert!(AttributeList::parse("lower=1").is_err());
+    }
+
+    #[test]
+    fn checks_written_values() {
+        let mut writer = AttributeWriter::default();
+        writer.enumerated("TYPE", "AUDIO", &["AUDIO", "VIDEO"]).quoted("NAME", "a, b").value("BANDWIDTH", 1);
+        assert_eq!(writer.finish().unwrap(), r#"TYPE=AUDIO,NAME="a, b",BANDWIDTH=1"#);
+
+        let mut writer = AttributeWriter::default();
+        writer.quoted("NAME", "say \"hi\"").quoted("URI", "a\nb");
+        assert_eq!(writer.as_str(), "NAME=\"say \"hi\"\",URI=\"a\nb\"");
+        assert!(writer.finish().unwrap_err().to_string().starts_with("Attribute NAME"));
+        assert!(AttributeWriter::default().quoted_list("CODECS", &["avc1", "mp4a,40"]).error.is_some());
+        assert!(AttributeWriter::default().enumerated("TYPE", "MUSIC", &["AUDIO"]).error.is_some());
+    }
+}
@@ -0,0 +1,174 @@
+//! Command line tool for validating and inspecting media playlists, enabled with the `cli`
+//! feature.
+
+use std::collections::BTreeSet;
+use std::io::Read;
+use std::process::ExitCode;
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use hls_parsing::conformance::{self, Profile};
+use hls_parsing::diagnostics::Severity;
+use hls_parsing::{MediaPlaylist, ParseOptions};
+
+#[derive(Debug, Parser)]
+#[command(name = "hls", about = "Validate and inspect HLS playlists")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Check a master or media playlist against the specification, exiting with failure on any
+    /// error.
+    Validate {
+        /// Accept wrongly-cased tags and stray whitespace, reporting them as warnings.
+        #[arg(long)]
+        lenient: bool,
+
+        /// Rules to check against.
+        #[arg(long, value_enum, default_value_t = ProfileArg::Rfc8216)]
+        profile: ProfileArg,
+
+        /// Path, http(s) URL, or `-` for standard input.
+        source: String,
+    },
+    /// Summarize duration, segment count, version and encryption.
+    Info {
+        /// Print the full report as JSON: every segment with resolved URLs, byte ranges, keys
+        /// and parts, and the diagnostics.
+        #[arg(long)]
+        json: bool,
+
+        /// Path, http(s) URL, or `-` for standard input.
+        source: String,
+    },
+    /// List media segments.
+    Segments {
+        /// Print a JSON array instead of one tab-separated line per segment.
+        #[arg(long)]
+        json: bool,
+
+        /// Path, http(s) URL, or `-` for standard input.
+        source: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ProfileArg {
+    /// RFC 8216.
+    Rfc8216,
+    /// The RFC 8216bis draft.
+    Rfc8216bis,
+    /// Apple's HLS Authoring Specification.
+    Apple,
+}
+
+impl From<ProfileArg> for Profile {
+    fn from(profile: ProfileArg) -> Self {
+        match profile {
+            ProfileArg::Rfc8216 => Profile::Rfc8216,
+            ProfileArg::Rfc8216bis => Profile::Rfc8216bis,
+            ProfileArg::Apple => Profile::AppleAuthoring,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("hls: {:#}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<ExitCode> {
+    match cli.command {
+        Command::Validate { lenient, profile, source } => {
+            let options = ParseOptions { lenient, ..ParseOptions::default() };
+            let findings = conformance::validate_with_options(&read_source(&source)?, profile.into(), &options);
+            for finding in &findings {
+                println!("{}: {}", source, finding);
+            }
+            if findings.iter().any(|x| x.diagnostic.severity == Severity::Error) {
+                return Ok(ExitCode::FAILURE);
+            }
+            if findings.is_empty() {
+                println!("{}: valid", source);
+            }
+        }
+        Command::Info { json, source } => {
+            let playlist = parse_source(&source)?;
+            if json {
+                let base_url = Some(source.as_str()).filter(|x| *x != "-");
+                println!("{}", serde_json::to_string_pretty(&playlist.to_report(base_url))?);
+                return Ok(ExitCode::SUCCESS);
+            }
+            let segments = playlist.segments();
+            let duration: f64 = segments.iter().map(|x| x.duration().as_secs_f64()).sum();
+            let methods: BTreeSet<&str> = segments.iter().flat_map(|x| x.keys()).map(|x| x.method().as_str()).collect();
+            let encrypted = segments.iter().filter(|x| !x.keys().is_empty()).count();
+
+            println!("version: {}", playlist.version().max(1));
+            println!("target duration: {}s", playlist.target_duration().as_secs());
+            println!("segments: {}", segments.len());
+            println!("duration: {:.3}s", duration);
+            println!("ended: {}", if playlist.ended() { "yes" } else { "no" });
+            if methods.is_empty() {
+                println!("encryption: none");
+            } else {
+                let methods: Vec<&str> = methods.into_iter().collect();
+                println!("encryption: {} ({} of {} segments)", methods.join(", "), encrypted, segments.len());
+            }
+        }
+        Command::Segments { json, source } => {
+            let playlist = parse_source(&source)?;
+            if json {
+                let segments: Vec<serde_json::Value> = playlist
+                    .segments()
+                    .iter()
+                    .map(|segment| {
+                        serde_json::json!({
+                            "duration": segment.duration().as_secs_f64(),
+                            "url": segment.url().as_str(),
+                            "keys": segment.keys().iter().map(|key| serde_json::json!({
+                                "method": key.method().as_str(),
+                                "uri": key.uri(),
+                                "keyformat": key.key_format(),
+                            })).collect::<Vec<_>>(),
+                        })
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&segments)?);
+            } else {
+                for segment in playlist.segments() {
+                    println!("{:.3}\t{}", segment.duration().as_secs_f64(), segment.url());
+                }
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn parse_source(source: &str) -> Result<MediaPlaylist> {
+    MediaPlaylist::parse_ext_m3u(&read_source(source)?).with_context(|| format!("could not parse {}", source))
+}
+
+/// Reads a playlist from standard input, an http(s) URL or a file.
+fn read_source(source: &str) -> Result<String> {
+    if source == "-" {
+        let mut file = String::new();
+        std::io::stdin().read_to_string(&mut file).context("could not read standard input")?;
+        Ok(file)
+    } else if source.starts_with("http://") || source.starts_with("https://") {
+        reqwest::blocking::get(source)
+            .and_then(reqwest::blocking::Response::error_for_status)
+            .and_then(reqwest::blocking::Response::text)
+            .with_context(|| format!("could not fetch {}", source))
+    } else {
+        std::fs::read_to_string(source).with_context(|| format!("could not read {}", source))
+    }
+}
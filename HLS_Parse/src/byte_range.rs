@@ -0,0 +1,259 @@
+//! Sub-ranges of media resources. See
+//! <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2.2>.
+
+use core::fmt;
+use core::str::FromStr;
+use std::collections::HashMap;
+
+use crate::MediaPlaylist;
+
+/// Value of an EXT-X-BYTERANGE tag, `<length>[@<offset>]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ByteRange {
+    /// Number of bytes in the range.
+    pub length: u64,
+
+    /// Start of the range. When absent, the range starts right after the previous segment's
+    /// range in the same resource.
+    pub offset: Option<u64>,
+}
+
+/// A resource segments are read from, with the parts of it they need, from
+/// [`MediaPlaylist::unique_resources`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentResource<'a> {
+    pub url: &'a str,
+
+    /// Byte ranges the segments need, sorted by offset with overlapping and adjacent ranges
+    /// merged. `None` if the whole resource is needed, because a segment has no byte range or
+    /// one whose offset can't be resolved.
+    pub ranges: Option<Vec<ByteRange>>,
+
+    /// Indexes of the segments read from the resource.
+    pub segments: Vec<usize>,
+}
+
+impl ByteRange {
+    /// Offset one past the last byte of the range, which is where a following range without an
+    /// offset starts. `None` if the offset isn't known (or the end doesn't fit in a `u64`).
+    pub fn end_offset(&self) -> Option<u64> {
+        self.offset?.checked_add(self.length)
+    }
+
+    /// Value for an HTTP `Range` request header, e.g. `bytes=0-99` for the first 100 bytes. `None`
+    /// if the offset isn't known or the range is empty, since neither can be requested.
+    pub fn to_http_range_header(&self) -> Option<String> {
+        let offset = self.offset?;
+        let last = self.end_offset()?.checked_sub(1).filter(|last| *last >= offset)?;
+        Some(format!("bytes={}-{}", offset, last))
+    }
+}
+
+impl MediaPlaylist {
+    /// Byte range of every segment, with implicit offsets filled in from the previous segment
+    /// when it is a sub-range of the same resource. Offsets which can't be inferred (the previous
+    /// segment used a different URI or the whole resource) are left as `None`.
+    pub fn resolved_byte_ranges(&self) -> Vec<Option<ByteRange>> {
+        let mut resolved: Vec<Option<ByteRange>> = Vec::with_capacity(self.segments().len());
+        for (index, segment) in self.segments().iter().enumerate() {
+            let byte_range = segment.byte_range().map(|byte_range| match byte_range.offset {
+                Some(_) => byte_range,
+                None => {
+                    let previous_end = index
+                        .checked_sub(1)
+                        .filter(|previous| self.segments()[*previous].url() == segment.url())
+                        .and_then(|previous| resolved[previous])
+                        .and_then(|previous| previous.end_offset());
+                    ByteRange { length: byte_range.length, offset: previous_end }
+                }
+            });
+            resolved.push(byte_range);
+        }
+        resolved
+    }
+
+    /// Each distinct segment URI in order of first use, with the byte ranges needed from it, so
+    /// a downloader can fetch a resource shared by many segments once or with coalesced ranges.
+    pub fn unique_resources(&self) -> Vec<SegmentResource<'_>> {
+        let mut resources: Vec<SegmentResource> = Vec::new();
+        let mut indexes: HashMap<&str, usize> = HashMap::new();
+        for (index, (segment, byte_range)) in self.segments().iter().zip(self.resolved_byte_ranges()).enumerate() {
+            let resource = *indexes.entry(segment.url().as_str()).or_insert_with(|| {
+                resources.push(SegmentResource { url: segment.url().as_str(), ranges: Some(Vec::new()), segments: Vec::new() });
+                resources.len() - 1
+            });
+            let resource = &mut resources[resource];
+            resource.segments.push(index);
+            match byte_range.filter(|x| x.offset.is_some()) {
+                Some(byte_range) => {
+                    if let Some(ranges) = &mut resource.ranges {
+                        ranges.push(byte_range);
+                    }
+                }
+                None => resource.ranges = None,
+            }
+        }
+        for ranges in resources.iter_mut().filter_map(|x| x.ranges.as_mut()) {
+            *ranges = coalesce(core::mem::take(ranges));
+        }
+        resources
+    }
+}
+
+/// Sorts ranges with known offsets and merges those which overlap or touch.
+fn coalesce(mut ranges: Vec<ByteRange>) -> Vec<ByteRange> {
+    ranges.sort_by_key(|x| x.offset);
+    let mut merged: Vec<ByteRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.offset <= last.end_offset() => {
+                let end = last.end_offset().max(range.end_offset()).unwrap_or(u64::MAX);
+                last.length = end - last.offset.unwrap_or_default();
+            }
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+impl FromStr for ByteRange {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (length, offset) = match value.split_once('@') {
+            Some((length, offset)) => (length, Some(offset)),
+            None => (value, None),
+        };
+        let length = length.parse::<u64>().map_err(|_| anyhow::anyhow!("Invalid byte range length {}", length))?;
+        let offset = match offset {
+            Some(offset) => Some(offset.parse::<u64>().map_err(|_| anyhow::anyhow!("Invalid byte range offset {}", offset))?),
+            None => None,
+        };
+        Ok(Self { length, offset })
+    }
+}
+
+impl fmt::Display for ByteRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.offset {
+            Some(offset) => write!(f, "{}@{}", self.length, offset),
+            None => write!(f, "{}", self.length),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_with_and_without_offset() {
+        assert_eq!("1430680@4048392".parse::<ByteRange>().unwrap(), ByteRange { length: 1430680, offset: Some(4048392) });
+        assert_eq!("840360".parse::<ByteRange>().unwrap(), ByteRange { length: 840360, offset: None });
+        assert!("".parse::<ByteRange>().is_err());
+        assert!("10@".parse::<ByteRange>().is_err());
+        assert!("-1@0".parse::<ByteRange>().is_err());
+    }
+
+    #[test]
+    fn displays_as_tag_value() {
+        assert_eq!(ByteRange { length: 10, offset: Some(5) }.to_string(), "10@5");
+        assert_eq!(ByteRange { length: 10, offset: None }.to_string(), "10");
+    }
+
+    #[test]
+    fn formats_http_range() {
+        let byte_range = ByteRange { length: 1430680, offset: Some(4048392) };
+        assert_eq!(byte_range.end_offset(), Some(5479072));
+        assert_eq!(byte_range.to_http_range_header().as_deref(), Some("bytes=4048392-5479071"));
+        assert_eq!(ByteRange { length: 1, offset: Some(0) }.to_http_range_header().as_deref(), Some("bytes=0-0"));
+        assert_eq!(ByteRange { length: 0, offset: Some(10) }.to_http_range_header(), None);
+        assert_eq!(ByteRange { length: 10, offset: None }.to_http_range_header(), None);
+        assert_eq!(ByteRange { length: 10, offset: None }.end_offset(), None);
+    }
+
+    //implicit offsets after a different resource are invalid, so they need a lenient parse
+    fn parse_lenient(file: &str) -> MediaPlaylist {
+        let options = crate::ParseOptions { lenient: true, ..Default::default() };
+        MediaPlaylist::parse_with_options(file, &options).unwrap()
+    }
+
+    #[test]
+    fn resolves_implicit_offsets() {
+        let file = indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-VERSION:4
+            #EXT-X-TARGETDURATION:10
+            #EXTINF:9,
+            #EXT-X-BYTERANGE:100@50
+            main.ts
+            #EXTINF:9,
+            #EXT-X-BYTERANGE:200
+            main.ts
+            #EXTINF:9,
+            #EXT-X-BYTERANGE:300
+            main.ts
+            #EXTINF:9,
+            #EXT-X-BYTERANGE:400
+            other.ts
+            #EXTINF:9,
+            whole.ts
+        "};
+        let error = MediaPlaylist::parse_ext_m3u(file).unwrap_err().to_string();
+        assert_eq!(error, "Byte range of segment at line 15 has no offset, but the previous segment isn't a sub-range of other.ts");
+        let playlist = parse_lenient(file);
+        assert_eq!(playlist.diagnostics().len(), 1);
+        assert_eq!(
+            playlist.resolved_byte_ranges(),
+            vec![
+                Some(ByteRange { length: 100, offset: Some(50) }),
+                Some(ByteRange { length: 200, offset: Some(150) }),
+                Some(ByteRange { length: 300, offset: Some(350) }),
+                Some(ByteRange { length: 400, offset: None }),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn coalesces_shared_resources() {
+        let playlist = parse_lenient(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-VERSION:4
+            #EXT-X-TARGETDURATION:10
+            #EXTINF:9,
+            #EXT-X-BYTERANGE:100@0
+            main.ts
+            #EXTINF:9,
+            #EXT-X-BYTERANGE:200
+            main.ts
+            #EXTINF:9,
+            #EXT-X-BYTERANGE:100@1000
+            main.ts
+            #EXTINF:9,
+            #EXT-X-BYTERANGE:50@150
+            main.ts
+            #EXTINF:9,
+            whole.ts
+            #EXTINF:9,
+            #EXT-X-BYTERANGE:400
+            other.ts
+        "});
+        assert_eq!(
+            playlist.unique_resources(),
+            vec![
+                SegmentResource {
+                    url: "main.ts",
+                    ranges: Some(vec![
+                        ByteRange { length: 300, offset: Some(0) },
+                        ByteRange { length: 100, offset: Some(1000) },
+                    ]),
+                    segments: vec![0, 1, 2, 3],
+                },
+                SegmentResource { url: "whole.ts", ranges: None, segments: vec![4] },
+                SegmentResource { url: "other.ts", ranges: None, segments: vec![5] },
+            ]
+        );
+    }
+}
@@ -0,0 +1,193 @@
+//! Caching fetched playlists by URL, following the client guidance in
+//! <https://datatracker.ietf.org/doc/html/rfc8216#section-6.3.4>: a media playlist may be fetched
+//! again once its target duration has passed, or half of it if the last fetch didn't change it,
+//! while a master playlist is kept for the whole session. A playlist with an EXT-X-ENDLIST tag
+//! never changes, so it is kept like a master playlist.
+//!
+//! Like [`LiveFollower`][crate::LiveFollower], the cache does no I/O and takes the time from the
+//! caller. Entries live in a [`CacheStore`], [`MemoryStore`] by default, which can be replaced to
+//! share a cache through redis or keep it on disk.
+
+use core::time::Duration;
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use anyhow::Result;
+
+use crate::{MasterPlaylist, MediaPlaylist};
+
+/// A playlist held by a [`CacheStore`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum CachedPlaylist {
+    Master(MasterPlaylist),
+    Media(Box<MediaPlaylist>),
+}
+
+/// A cached playlist with when it was fetched and until when it can be used without fetching it
+/// again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheEntry {
+    pub playlist: CachedPlaylist,
+    pub fetched_at: SystemTime,
+
+    /// `None` for playlists kept for the session.
+    pub fresh_until: Option<SystemTime>,
+}
+
+/// Storage behind a [`PlaylistCache`]. Playlists can be persisted with their `Display` output and
+/// read back with `parse_ext_m3u`.
+pub trait CacheStore {
+    fn get(&mut self, url: &str) -> Result<Option<CacheEntry>>;
+
+    fn put(&mut self, url: &str, entry: CacheEntry) -> Result<()>;
+
+    fn remove(&mut self, url: &str) -> Result<()>;
+}
+
+/// A [`CacheStore`] in a `HashMap`, for a single process.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    entries: HashMap<String, CacheEntry>,
+}
+
+impl CacheStore for MemoryStore {
+    fn get(&mut self, url: &str) -> Result<Option<CacheEntry>> {
+        Ok(self.entries.get(url).cloned())
+    }
+
+    fn put(&mut self, url: &str, entry: CacheEntry) -> Result<()> {
+        self.entries.insert(url.to_string(), entry);
+        Ok(())
+    }
+
+    fn remove(&mut self, url: &str) -> Result<()> {
+        self.entries.remove(url);
+        Ok(())
+    }
+}
+
+/// Playlists by URL, with freshness decided by the rules in the [module documentation][self].
+#[derive(Debug, Clone, Default)]
+pub struct PlaylistCache<S = MemoryStore> {
+    store: S,
+}
+
+impl PlaylistCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S: CacheStore> PlaylistCache<S> {
+    pub fn with_store(store: S) -> Self {
+        Self { store }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    /// The master playlist cached for `url`, if any.
+    pub fn master(&mut self, url: &str) -> Result<Option<MasterPlaylist>> {
+        Ok(match self.store.get(url)? {
+            Some(CacheEntry { playlist: CachedPlaylist::Master(playlist), .. }) => Some(playlist),
+            _ => None,
+        })
+    }
+
+    /// The media playlist cached for `url`, if it is still fresh at `now`.
+    pub fn media(&mut self, url: &str, now: SystemTime) -> Result<Option<MediaPlaylist>> {
+        Ok(match self.store.get(url)? {
+            Some(CacheEntry { playlist: CachedPlaylist::Media(playlist), fresh_until, .. })
+                if fresh_until.is_none_or(|x| now < x) =>
+            {
+                Some(*playlist)
+            }
+            _ => None,
+        })
+    }
+
+    /// Caches a master playlist fetched from `url` for the rest of the session.
+    pub fn insert_master(&mut self, url: &str, playlist: MasterPlaylist, now: SystemTime) -> Result<()> {
+        let entry = CacheEntry { playlist: CachedPlaylist::Master(playlist), fetched_at: now, fresh_until: None };
+        self.store.put(url, entry)
+    }
+
+    /// Caches a media playlist fetched from `url` at `now`, returning when it should be fetched
+    /// again, or `None` if it has ended.
+    pub fn insert_media(&mut self, url: &str, playlist: MediaPlaylist, now: SystemTime) -> Result<Option<SystemTime>> {
+        let fresh_until = if playlist.ended() {
+            None
+        } else {
+            let unchanged = matches!(
+                self.store.get(url)?,
+                Some(CacheEntry { playlist: CachedPlaylist::Media(previous), .. }) if *previous == playlist
+            );
+            Some(now + refresh_interval(playlist.target_duration(), unchanged))
+        };
+        let entry = CacheEntry { playlist: CachedPlaylist::Media(Box::new(playlist)), fetched_at: now, fresh_until };
+        self.store.put(url, entry)?;
+        Ok(fresh_until)
+    }
+
+    /// Removes the playlist cached for `url`, e.g. after the server returned an error for it.
+    pub fn invalidate(&mut self, url: &str) -> Result<()> {
+        self.store.remove(url)
+    }
+}
+
+/// RFC8216 6.3.4, the target duration after a reload with changes and half of it otherwise.
+fn refresh_interval(target_duration: Duration, unchanged: bool) -> Duration {
+    if unchanged {
+        target_duration / 2
+    } else {
+        target_duration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn live(segments: &str) -> MediaPlaylist {
+        MediaPlaylist::parse_ext_m3u(&format!("#EXTM3U\n#EXT-X-TARGETDURATION:6\n{}", segments)).unwrap()
+    }
+
+    #[test]
+    fn media_playlists_go_stale() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut cache = PlaylistCache::new();
+        let first = live("#EXTINF:6,\n1.ts\n");
+
+        let refresh = cache.insert_media("live.m3u8", first.clone(), start).unwrap();
+        assert_eq!(refresh, Some(start + Duration::from_secs(6)));
+        assert_eq!(cache.media("live.m3u8", start + Duration::from_secs(5)).unwrap(), Some(first.clone()));
+        assert_eq!(cache.media("live.m3u8", start + Duration::from_secs(6)).unwrap(), None);
+
+        //no change since the last fetch, so check again sooner
+        let later = start + Duration::from_secs(6);
+        let refresh = cache.insert_media("live.m3u8", first, later).unwrap();
+        assert_eq!(refresh, Some(later + Duration::from_secs(3)));
+
+        let ended = live("#EXTINF:6,\n1.ts\n#EXT-X-ENDLIST\n");
+        assert_eq!(cache.insert_media("live.m3u8", ended.clone(), later).unwrap(), None);
+        assert_eq!(cache.media("live.m3u8", later + Duration::from_secs(3600)).unwrap(), Some(ended));
+
+        cache.invalidate("live.m3u8").unwrap();
+        assert_eq!(cache.media("live.m3u8", later).unwrap(), None);
+    }
+
+    #[test]
+    fn master_playlists_last_the_session() {
+        let master = MasterPlaylist::parse_ext_m3u("#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1000\nlow.m3u8\n").unwrap();
+        let mut cache = PlaylistCache::new();
+        cache.insert_master("main.m3u8", master.clone(), SystemTime::UNIX_EPOCH).unwrap();
+        assert_eq!(cache.master("main.m3u8").unwrap(), Some(master));
+        assert_eq!(cache.media("main.m3u8", SystemTime::UNIX_EPOCH).unwrap(), None);
+        assert_eq!(cache.master("other.m3u8").unwrap(), None);
+    }
+}
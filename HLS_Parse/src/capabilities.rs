@@ -0,0 +1,76 @@
+//! Choosing the variants a device can actually play, based on the output protection and dynamic
+//! ranges it supports.
+
+use crate::{HdcpLevel, MasterPlaylist, VariantStream, VideoRange};
+
+/// What a client device supports, for [`MasterPlaylist::playable_variants`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Highest output protection the device can establish.
+    pub hdcp_level: HdcpLevel,
+
+    /// Dynamic ranges the display can render.
+    pub video_ranges: Vec<VideoRange>,
+}
+
+/// The most restrictive device: no HDCP and only SDR.
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self { hdcp_level: HdcpLevel::None, video_ranges: vec![VideoRange::Sdr] }
+    }
+}
+
+impl Capabilities {
+    /// Whether the device can play the variant. Variants without VIDEO-RANGE are SDR and those
+    /// without HDCP-LEVEL need no protection.
+    pub fn supports(&self, variant: &VariantStream) -> bool {
+        variant.hdcp_level().unwrap_or(HdcpLevel::None) <= self.hdcp_level
+            && self.video_ranges.contains(&variant.video_range().unwrap_or(VideoRange::Sdr))
+    }
+}
+
+impl MasterPlaylist {
+    /// Variants the device can play, in playlist order. For example, leaving out
+    /// [`HdcpLevel::Type1`] and [`VideoRange::Pq`] drops 4K and Dolby Vision/HDR10 variants on a
+    /// device without HDCP 2.2 or an HDR display.
+    pub fn playable_variants(&self, capabilities: &Capabilities) -> Vec<&VariantStream> {
+        self.variants().iter().filter(|x| capabilities.supports(x)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_by_capabilities() {
+        let playlist = MasterPlaylist::parse_ext_m3u(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000
+            sdr.m3u8
+            #EXT-X-STREAM-INF:BANDWIDTH=2560000,VIDEO-RANGE=HLG,HDCP-LEVEL=NONE
+            hlg.m3u8
+            #EXT-X-STREAM-INF:BANDWIDTH=7680000,VIDEO-RANGE=PQ,HDCP-LEVEL=TYPE-0
+            pq.m3u8
+            #EXT-X-STREAM-INF:BANDWIDTH=15360000,VIDEO-RANGE=SDR,HDCP-LEVEL=TYPE-1
+            uhd.m3u8
+        "})
+        .expect("should parse");
+        let playable = |capabilities: Capabilities| -> Vec<String> {
+            playlist.playable_variants(&capabilities).iter().map(|x| x.uri().to_string()).collect()
+        };
+
+        assert_eq!(playable(Capabilities::default()), vec!["sdr.m3u8"]);
+        assert_eq!(
+            playable(Capabilities { hdcp_level: HdcpLevel::Type0, video_ranges: vec![VideoRange::Sdr, VideoRange::Hlg] }),
+            vec!["sdr.m3u8", "hlg.m3u8"]
+        );
+        assert_eq!(
+            playable(Capabilities {
+                hdcp_level: HdcpLevel::Type1,
+                video_ranges: vec![VideoRange::Sdr, VideoRange::Hlg, VideoRange::Pq],
+            }),
+            vec!["sdr.m3u8", "hlg.m3u8", "pq.m3u8", "uhd.m3u8"]
+        );
+    }
+}
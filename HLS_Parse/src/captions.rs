@@ -0,0 +1,119 @@
+//! Closed captions carried in the video of a variant (CEA-608 and CEA-708), described by
+//! CLOSED-CAPTIONS renditions. See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.4.1>
+//! and <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.4.2>.
+
+use core::fmt;
+use core::str::FromStr;
+
+use crate::{MasterPlaylist, MediaType, Rendition, VariantStream};
+
+/// Value of the CLOSED-CAPTIONS attribute of EXT-X-STREAM-INF.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ClosedCaptions {
+    /// GROUP-ID of the CLOSED-CAPTIONS renditions describing the captions in the video.
+    Group(String),
+    /// The video has no closed captions, which clients may rely on to avoid searching for them.
+    None,
+}
+
+/// Caption service within the video, from the INSTREAM-ID attribute of EXT-X-MEDIA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum InstreamId {
+    /// A CEA-608 channel, `CC1` to `CC4`.
+    Cc(u8),
+    /// A CEA-708 service, `SERVICE1` to `SERVICE63`.
+    Service(u8),
+}
+
+impl MasterPlaylist {
+    /// Renditions describing the caption services in the video of `variant`, in playlist order.
+    /// Empty if the variant has no closed captions or doesn't say.
+    pub fn caption_services(&self, variant: &VariantStream) -> Vec<&Rendition> {
+        match variant.closed_captions() {
+            Some(ClosedCaptions::Group(group_id)) => self.rendition_group(MediaType::ClosedCaptions, group_id),
+            Some(ClosedCaptions::None) | None => Vec::new(),
+        }
+    }
+}
+
+impl FromStr for InstreamId {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parsed = if let Some(channel) = value.strip_prefix("CC") {
+            parse_number(channel).filter(|x| (1..=4).contains(x)).map(InstreamId::Cc)
+        } else if let Some(service) = value.strip_prefix("SERVICE") {
+            parse_number(service).filter(|x| (1..=63).contains(x)).map(InstreamId::Service)
+        } else {
+            None
+        };
+        parsed.ok_or_else(|| anyhow::anyhow!("Invalid INSTREAM-ID {}", value))
+    }
+}
+
+/// Parses a string of digits only, since `parse` alone would allow a leading `+`.
+fn parse_number(digits: &str) -> Option<u8> {
+    if digits.is_empty() || !digits.bytes().all(|x| x.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+impl fmt::Display for InstreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstreamId::Cc(channel) => write!(f, "CC{}", channel),
+            InstreamId::Service(service) => write!(f, "SERVICE{}", service),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_instream_ids() {
+        assert_eq!("CC1".parse::<InstreamId>().unwrap(), InstreamId::Cc(1));
+        assert_eq!("SERVICE63".parse::<InstreamId>().unwrap(), InstreamId::Service(63));
+        assert_eq!(InstreamId::Service(3).to_string(), "SERVICE3");
+        for invalid in ["CC0", "CC5", "SERVICE0", "SERVICE64", "cc1", "CC", "CC+1"] {
+            assert!(invalid.parse::<InstreamId>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn lists_caption_services() {
+        let playlist = MasterPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-MEDIA:TYPE=CLOSED-CAPTIONS,GROUP-ID="cc",LANGUAGE="en",NAME="English",INSTREAM-ID="CC1"
+            #EXT-X-MEDIA:TYPE=CLOSED-CAPTIONS,GROUP-ID="cc",LANGUAGE="es",NAME="Español",INSTREAM-ID="SERVICE2"
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000,CLOSED-CAPTIONS="cc"
+            captioned.m3u8
+        "#})
+        .expect("should parse");
+        let variant = &playlist.variants()[0];
+        assert_eq!(variant.closed_captions(), Some(&ClosedCaptions::Group("cc".to_string())));
+        let services: Vec<Option<InstreamId>> =
+            playlist.caption_services(variant).iter().map(|x| x.instream_id()).collect();
+        assert_eq!(services, vec![Some(InstreamId::Cc(1)), Some(InstreamId::Service(2))]);
+    }
+
+    #[test]
+    fn requires_none_on_every_variant() {
+        let error = MasterPlaylist::parse_ext_m3u(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000,CLOSED-CAPTIONS=NONE
+            low.m3u8
+            #EXT-X-STREAM-INF:BANDWIDTH=2560000
+            high.m3u8
+        "})
+        .expect_err("playlist should not parse");
+        assert_eq!(error.to_string(), "Variant high.m3u8 must have CLOSED-CAPTIONS=NONE since other variants do");
+
+        let playlist = MasterPlaylist::parse_ext_m3u("#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1,CLOSED-CAPTIONS=NONE\na.m3u8\n")
+            .expect("should parse");
+        assert_eq!(playlist.variants()[0].closed_captions(), Some(&ClosedCaptions::None));
+        assert!(playlist.caption_services(&playlist.variants()[0]).is_empty());
+    }
+}
@@ -0,0 +1,144 @@
+//! Audio channel layouts from the CHANNELS attribute of EXT-X-MEDIA. See
+//! <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.4.1> and
+//! <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.6.1>.
+
+use core::fmt;
+use core::str::FromStr;
+
+use crate::{MasterPlaylist, MediaType, Rendition};
+
+/// Value of a CHANNELS attribute, `<count>[/<coding>[/<usage>]]` with comma-separated lists of
+/// identifiers in the last two parameters, e.g. `16/JOC` for Dolby Atmos.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Channels {
+    /// Maximum number of independent, simultaneous audio channels.
+    pub count: u32,
+
+    /// Spatial audio coding identifiers, e.g. `JOC` for object-based audio with joint object
+    /// coding. Empty for channel-based audio.
+    pub coding: Vec<String>,
+
+    /// Special usage identifiers, e.g. `BINAURAL`, `IMMERSIVE` or `DOWNMIX`.
+    pub usage: Vec<String>,
+}
+
+impl Channels {
+    /// Whether the audio is object-based (e.g. Dolby Atmos), rather than a fixed channel layout.
+    pub fn is_object_based(&self) -> bool {
+        self.coding.iter().any(|x| x == "JOC")
+    }
+}
+
+impl MasterPlaylist {
+    /// Audio renditions whose channels match `filter`, in playlist order, e.g.
+    /// `|x| x.count <= 2` for stereo or [`Channels::is_object_based`] for Atmos. Renditions
+    /// without a CHANNELS attribute are left out.
+    pub fn audio_renditions_by_channels(&self, filter: impl Fn(&Channels) -> bool) -> Vec<&Rendition> {
+        self.renditions()
+            .iter()
+            .filter(|x| x.media_type() == MediaType::Audio && x.channels().is_some_and(&filter))
+            .collect()
+    }
+}
+
+impl FromStr for Channels {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut parameters = value.split('/');
+        let count = parameters.next().unwrap_or_default();
+        let count = count.parse::<u32>().map_err(|_| anyhow::anyhow!("Invalid channel count {}", count))?;
+        let mut identifiers = || -> anyhow::Result<Vec<String>> {
+            match parameters.next() {
+                None => Ok(Vec::new()),
+                //a parameter can be "-" when a later one is present but this one isn't
+                Some("-") => Ok(Vec::new()),
+                Some(list) => list.split(',').map(parse_identifier).collect(),
+            }
+        };
+        let coding = identifiers()?;
+        let usage = identifiers()?;
+        if parameters.next().is_some() {
+            return Err(anyhow::anyhow!("Too many channels parameters in \"{}\"", value));
+        }
+        Ok(Self { count, coding, usage })
+    }
+}
+
+fn parse_identifier(identifier: &str) -> anyhow::Result<String> {
+    if identifier.is_empty() || !identifier.bytes().all(|x| x.is_ascii_uppercase() || x.is_ascii_digit()) {
+        return Err(anyhow::anyhow!("Invalid channels identifier \"{}\"", identifier));
+    }
+    Ok(identifier.to_string())
+}
+
+impl fmt::Display for Channels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.count)?;
+        if !self.coding.is_empty() || !self.usage.is_empty() {
+            if self.coding.is_empty() {
+                f.write_str("/-")?;
+            } else {
+                write!(f, "/{}", self.coding.join(","))?;
+            }
+        }
+        if !self.usage.is_empty() {
+            write!(f, "/{}", self.usage.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_channel_layouts() {
+        let stereo = "2".parse::<Channels>().unwrap();
+        assert_eq!(stereo, Channels { count: 2, coding: vec![], usage: vec![] });
+        assert!(!stereo.is_object_based());
+
+        let atmos = "16/JOC".parse::<Channels>().unwrap();
+        assert_eq!(atmos, Channels { count: 16, coding: vec!["JOC".to_string()], usage: vec![] });
+        assert!(atmos.is_object_based());
+
+        let binaural = "2/-/BINAURAL".parse::<Channels>().unwrap();
+        assert_eq!(binaural, Channels { count: 2, coding: vec![], usage: vec!["BINAURAL".to_string()] });
+
+        assert!("".parse::<Channels>().is_err());
+        assert!("six".parse::<Channels>().is_err());
+        assert!("6/joc".parse::<Channels>().is_err());
+        assert!("6/JOC/BINAURAL/EXTRA".parse::<Channels>().is_err());
+    }
+
+    #[test]
+    fn displays_as_attribute_value() {
+        for value in ["2", "16/JOC", "2/-/BINAURAL", "12/JOC/IMMERSIVE,DOWNMIX"] {
+            assert_eq!(value.parse::<Channels>().unwrap().to_string(), value);
+        }
+    }
+
+    #[test]
+    fn filters_audio_renditions() {
+        let playlist = MasterPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="stereo",NAME="English",CHANNELS="2",URI="en-2.m3u8"
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="surround",NAME="English",CHANNELS="6",URI="en-6.m3u8"
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="atmos",NAME="English",CHANNELS="16/JOC",URI="en-atmos.m3u8"
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="unknown",NAME="English",URI="en.m3u8"
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000,AUDIO="stereo"
+            low.m3u8
+        "#})
+        .expect("should parse");
+        fn uris(renditions: Vec<&Rendition>) -> Vec<&str> {
+            renditions.into_iter().filter_map(Rendition::uri).collect()
+        }
+        assert_eq!(uris(playlist.audio_renditions_by_channels(Channels::is_object_based)), vec!["en-atmos.m3u8"]);
+        assert_eq!(uris(playlist.audio_renditions_by_channels(|x| x.count <= 2)), vec!["en-2.m3u8"]);
+        assert_eq!(
+            uris(playlist.audio_renditions_by_channels(|x| x.count >= 6 && !x.is_object_based())),
+            vec!["en-6.m3u8"]
+        );
+    }
+}
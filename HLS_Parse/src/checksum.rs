@@ -0,0 +1,215 @@
+//! End-to-end integrity checking with a checksum tag per segment, e.g.
+//! `#EXT-X-CHECKSUM:SHA-256=<hex>`, for archival pipelines. The tag isn't standard, so it's
+//! opt-in: register [`ChecksumHandler`] in
+//! [`ParseOptions::tag_handlers`][crate::ParseOptions::tag_handlers] to read it, and wrap a sink
+//! in [`ChecksumSink`] to verify what was downloaded. Needs the `checksum` feature.
+
+use core::fmt;
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+
+use crate::attributes::AttributeList;
+use crate::segment_tags::TagSlot;
+use crate::{CustomTag, MediaSegment, SegmentContext, SegmentSink, TagExtensions, TagHandler};
+
+/// Name of the checksum tag.
+pub const CHECKSUM_TAG: &str = "EXT-X-CHECKSUM";
+
+/// SHA-256 digest of a segment's bytes, from the attribute list of its checksum tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SegmentChecksum {
+    sha256: [u8; 32],
+}
+
+impl SegmentChecksum {
+    /// The checksum of `data`, e.g. to tag segments when packaging.
+    pub fn of(data: &[u8]) -> Self {
+        Self { sha256: Sha256::digest(data).into() }
+    }
+
+    /// Parses an attribute list like `SHA-256=<64 hex digits>`.
+    pub fn parse(attribute_list: &str) -> Result<Self> {
+        let attributes = AttributeList::parse(attribute_list)?;
+        let hex = attributes.get("SHA-256").ok_or_else(|| anyhow::anyhow!("{} without SHA-256", CHECKSUM_TAG))?;
+        //from_str_radix alone would take a sign, e.g. `+f`
+        if hex.len() != 64 || !hex.bytes().all(|x| x.is_ascii_hexdigit()) {
+            return Err(anyhow::anyhow!("Invalid SHA-256 {}", hex));
+        }
+        let digit = |x: u8| (x as char).to_digit(16).unwrap_or_default() as u8;
+        let mut sha256 = [0; 32];
+        for (byte, digits) in sha256.iter_mut().zip(hex.as_bytes().chunks_exact(2)) {
+            *byte = digit(digits[0]) << 4 | digit(digits[1]);
+        }
+        Ok(Self { sha256 })
+    }
+
+    pub fn sha256(&self) -> &[u8; 32] {
+        &self.sha256
+    }
+
+    /// Checks `data` against the checksum.
+    pub fn verify(&self, data: &[u8]) -> Result<()> {
+        self.check(Self::of(data))
+    }
+
+    fn check(&self, actual: SegmentChecksum) -> Result<()> {
+        if actual != *self {
+            return Err(anyhow::anyhow!("SHA-256 is {}, expected {}", actual, self));
+        }
+        Ok(())
+    }
+}
+
+/// Formats the checksum as the attribute list of its tag.
+impl fmt::Display for SegmentChecksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SHA-256=")?;
+        self.sha256.iter().try_for_each(|x| write!(f, "{:02x}", x))
+    }
+}
+
+/// Stores the [`SegmentChecksum`] of each checksum tag on the segment it precedes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChecksumHandler;
+
+impl TagHandler for ChecksumHandler {
+    fn handle(&self, tag: &CustomTag<'_>, extensions: &mut TagExtensions<'_>) -> Result<()> {
+        if tag.name == CHECKSUM_TAG {
+            extensions.segment.insert(SegmentChecksum::parse(tag.value.unwrap_or_default())?);
+        }
+        Ok(())
+    }
+}
+
+impl MediaSegment {
+    /// The segment's checksum, if it was parsed with [`ChecksumHandler`] or set with
+    /// [`set_checksum`][Self::set_checksum].
+    pub fn checksum(&self) -> Option<&SegmentChecksum> {
+        self.extensions().get()
+    }
+
+    /// Sets the checksum, replacing the segment's checksum tag or adding one ahead of the
+    /// EXTINF tag, or removes it.
+    pub fn set_checksum(&mut self, checksum: Option<SegmentChecksum>) {
+        let prefix = format!("#{}:", CHECKSUM_TAG);
+        let is_checksum = |x: &TagSlot| matches!(x, TagSlot::Custom(line) if line.starts_with(&prefix));
+        let mut order = self.tag_order().to_vec();
+        let position = order.iter().position(is_checksum);
+        order.retain(|x| !is_checksum(x));
+        match checksum {
+            Some(checksum) => {
+                let slot = TagSlot::Custom(format!("#{}:{}", CHECKSUM_TAG, checksum));
+                order.insert(position.unwrap_or(order.len()), slot);
+                self.extensions_mut().insert(checksum);
+            }
+            None => {
+                self.extensions_mut().remove::<SegmentChecksum>();
+            }
+        }
+        self.set_tag_order(order);
+    }
+}
+
+/// A [`SegmentSink`] checking each segment against its checksum before passing it on. Segments
+/// without one fail if checksums are `required`, and are passed on otherwise.
+///
+/// Downloaders can hash a segment as its body arrives with [`hash_chunk`][Self::hash_chunk],
+/// so [`write_segment`][SegmentSink::write_segment] only has to compare the result.
+#[derive(Debug)]
+pub struct ChecksumSink<S> {
+    inner: S,
+    required: bool,
+
+    /// Hash of the chunks of the next segment so far, with their length.
+    pending: Option<(Sha256, usize)>,
+}
+
+impl<S: SegmentSink> ChecksumSink<S> {
+    pub fn new(inner: S, required: bool) -> Self {
+        Self { inner, required, pending: None }
+    }
+
+    /// Hashes the next part of the body of the segment being downloaded, which must then be
+    /// passed to [`write_segment`][SegmentSink::write_segment] whole.
+    pub fn hash_chunk(&mut self, chunk: &[u8]) {
+        let (hasher, length) = self.pending.get_or_insert_with(|| (Sha256::new(), 0));
+        hasher.update(chunk);
+        *length += chunk.len();
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: SegmentSink> SegmentSink for ChecksumSink<S> {
+    fn write_segment(&mut self, context: &SegmentContext<'_>, init: Option<&[u8]>, data: &[u8]) -> Result<()> {
+        let pending = self.pending.take();
+        let checksum = match context.segment.checksum() {
+            Some(checksum) => checksum,
+            None if self.required => return Err(anyhow::anyhow!("Segment {} has no checksum", context.sequence)),
+            None => return self.inner.write_segment(context, init, data),
+        };
+        let verified = match pending {
+            Some((_, length)) if length != data.len() => Err(anyhow::anyhow!(
+                "{} bytes were hashed, but the segment has {}",
+                length,
+                data.len()
+            )),
+            Some((hasher, _)) => checksum.check(SegmentChecksum { sha256: hasher.finalize().into() }),
+            None => checksum.verify(data),
+        };
+        verified.map_err(|error| error.context(format!("Segment {} is corrupt", context.sequence)))?;
+        self.inner.write_segment(context, init, data)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.inner.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MediaPlaylist, ParseOptions};
+
+    #[test]
+    fn verifies_segments() {
+        let empty = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        assert_eq!(SegmentChecksum::of(b"").to_string(), format!("SHA-256={}", empty));
+        let abc = SegmentChecksum::of(b"abc");
+        assert_eq!(abc.to_string(), "SHA-256=ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+        let mut options = ParseOptions::default();
+        options.tag_handlers.push(ChecksumHandler);
+        let file = format!(
+            "#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-CHECKSUM:{}\n#EXTINF:4,\n0.ts\n#EXTINF:4,\n1.ts\n",
+            abc
+        );
+        let mut playlist = MediaPlaylist::parse_with_options(&file, &options).unwrap();
+        assert_eq!(playlist.segments()[0].checksum(), Some(&abc));
+        assert!(MediaPlaylist::parse_with_options(&file.replace("SHA-256=ba", "SHA-256=zz"), &options).is_err());
+        assert!(SegmentChecksum::parse(&abc.to_string().replace("=ba", "=+b")).is_err());
+        let upper = abc.to_string().to_uppercase().replace("SHA-256=", "");
+        assert_eq!(SegmentChecksum::parse(&format!("SHA-256={}", upper)).unwrap(), abc);
+
+        let mut sink = ChecksumSink::new(crate::ConcatenatedTsSink::new(Vec::new()), true);
+        let first = playlist.iter_segments().next().unwrap();
+        assert!(sink.write_segment(&first, None, b"abd").is_err());
+        sink.write_segment(&first, None, b"abc").unwrap();
+        let second = playlist.iter_segments().nth(1).unwrap();
+        assert!(sink.write_segment(&second, None, b"def").is_err());
+        sink.hash_chunk(b"a");
+        sink.hash_chunk(b"bc");
+        sink.write_segment(&first, None, b"abc").unwrap();
+        sink.hash_chunk(b"ab");
+        assert!(sink.write_segment(&first, None, b"abc").is_err());
+
+        playlist.segments_mut()[1].set_checksum(Some(SegmentChecksum::of(b"def")));
+        let reparsed = MediaPlaylist::parse_with_options(&playlist.to_string(), &options).unwrap();
+        assert_eq!(reparsed.segments()[1].checksum(), Some(&SegmentChecksum::of(b"def")));
+        playlist.segments_mut()[0].set_checksum(None);
+        assert_eq!(playlist.to_string().matches(CHECKSUM_TAG).count(), 1);
+    }
+}
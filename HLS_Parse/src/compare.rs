@@ -0,0 +1,370 @@
+//! Comparing playlists by meaning rather than text, e.g. to regression-test packager output where
+//! formatting, tag order and redundant tags differ between versions.
+
+use core::time::Duration;
+
+use crate::segment_tags::custom_lines;
+use crate::{
+    ByteRange, DateRange, MediaPlaylist, MediaSegment, PartialSegment, PlaylistType, PreloadHint, RenditionReport,
+};
+
+/// A difference between two media playlists, from [`MediaPlaylist::diff`]. Segments are matched
+/// by media sequence number.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlaylistChange {
+    VersionChanged { from: u64, to: u64 },
+    TargetDurationChanged { from: Duration, to: Duration },
+    MediaSequenceChanged { from: u64, to: u64 },
+    DiscontinuitySequenceChanged { from: u64, to: u64 },
+    AllowCacheChanged { from: Option<bool>, to: Option<bool> },
+    PlaylistTypeChanged { from: Option<PlaylistType>, to: Option<PlaylistType> },
+    EndListAppeared,
+    EndListRemoved,
+    IFramesOnlyChanged { from: bool, to: bool },
+    PartTargetChanged { from: Option<Duration>, to: Option<Duration> },
+    SkippedSegmentsChanged { from: u64, to: u64 },
+    TrailingPartsChanged { from: Vec<PartialSegment>, to: Vec<PartialSegment> },
+    TrailingDateRangesChanged { from: Vec<DateRange>, to: Vec<DateRange> },
+    PreloadHintsChanged { from: Vec<PreloadHint>, to: Vec<PreloadHint> },
+    RenditionReportsChanged { from: Vec<RenditionReport>, to: Vec<RenditionReport> },
+    SegmentAdded { sequence: u64, segment: Box<MediaSegment> },
+    SegmentRemoved { sequence: u64, segment: Box<MediaSegment> },
+    SegmentChanged { sequence: u64, from: Box<MediaSegment>, to: Box<MediaSegment> },
+}
+
+impl MediaPlaylist {
+    /// Whether the playlists describe the same presentation, i.e. [`diff`][Self::diff] is empty.
+    /// Unlike `==`, a missing EXT-X-VERSION equals version 1, and a byte range with an implicit
+    /// offset equals the same range with the offset written out.
+    pub fn semantic_eq(&self, other: &MediaPlaylist) -> bool {
+        self.diff(other).is_empty()
+    }
+
+    /// Like [`semantic_eq`][Self::semantic_eq], with durations allowed to differ by `epsilon`,
+    /// see [`compare_with_tolerance`][Self::compare_with_tolerance].
+    pub fn semantic_eq_with_tolerance(&self, other: &MediaPlaylist, epsilon: Duration) -> bool {
+        self.compare_with_tolerance(other, epsilon).is_empty()
+    }
+
+    /// Changes needed to turn this playlist into `other`: header values first, then what follows
+    /// the last segment, then segments in media sequence order. Segments skipped by a delta update
+    /// aren't listed, so they are neither added nor removed.
+    ///
+    /// Everything the writer emits is compared except where modeled tags go among a segment's
+    /// tags; custom tags are compared in order. [`Extensions`][crate::Extensions], the preserved
+    /// source and parse notes aren't written, so they aren't compared.
+    pub fn diff(&self, other: &MediaPlaylist) -> Vec<PlaylistChange> {
+        self.compare_with_tolerance(other, Duration::ZERO)
+    }
+
+    /// Like [`diff`][Self::diff], but target, part and segment durations which differ by at most
+    /// `epsilon` are the same, e.g. an EXTINF of 10.500 and one of 10.5000001 written by another
+    /// packager version.
+    pub fn compare_with_tolerance(&self, other: &MediaPlaylist, epsilon: Duration) -> Vec<PlaylistChange> {
+        let mut changes = Vec::new();
+
+        let (from, to) = (self.version().max(1), other.version().max(1));
+        if from != to {
+            changes.push(PlaylistChange::VersionChanged { from, to });
+        }
+        if !within(self.target_duration(), other.target_duration(), epsilon) {
+            changes.push(PlaylistChange::TargetDurationChanged {
+                from: self.target_duration(),
+                to: other.target_duration(),
+            });
+        }
+        if self.media_sequence() != other.media_sequence() {
+            changes.push(PlaylistChange::MediaSequenceChanged {
+                from: self.media_sequence(),
+                to: other.media_sequence(),
+            });
+        }
+        if self.discontinuity_sequence() != other.discontinuity_sequence() {
+            changes.push(PlaylistChange::DiscontinuitySequenceChanged {
+                from: self.discontinuity_sequence(),
+                to: other.discontinuity_sequence(),
+            });
+        }
+        if self.allow_cache() != other.allow_cache() {
+            changes.push(PlaylistChange::AllowCacheChanged { from: self.allow_cache(), to: other.allow_cache() });
+        }
+        if self.playlist_type() != other.playlist_type() {
+            changes.push(PlaylistChange::PlaylistTypeChanged { from: self.playlist_type(), to: other.playlist_type() });
+        }
+        match (self.ended(), other.ended()) {
+            (false, true) => changes.push(PlaylistChange::EndListAppeared),
+            (true, false) => changes.push(PlaylistChange::EndListRemoved),
+            _ => {}
+        }
+        if self.i_frames_only() != other.i_frames_only() {
+            changes.push(PlaylistChange::IFramesOnlyChanged { from: self.i_frames_only(), to: other.i_frames_only() });
+        }
+        let same_part_target = match (self.part_target(), other.part_target()) {
+            (Some(from), Some(to)) => within(from, to, epsilon),
+            (from, to) => from == to,
+        };
+        if !same_part_target {
+            changes.push(PlaylistChange::PartTargetChanged { from: self.part_target(), to: other.part_target() });
+        }
+        if self.skipped_segments() != other.skipped_segments() {
+            changes.push(PlaylistChange::SkippedSegmentsChanged {
+                from: self.skipped_segments(),
+                to: other.skipped_segments(),
+            });
+        }
+        if !same_parts_within(self.trailing_parts(), other.trailing_parts(), epsilon) {
+            changes.push(PlaylistChange::TrailingPartsChanged {
+                from: self.trailing_parts().to_vec(),
+                to: other.trailing_parts().to_vec(),
+            });
+        }
+        if self.trailing_date_ranges() != other.trailing_date_ranges() {
+            changes.push(PlaylistChange::TrailingDateRangesChanged {
+                from: self.trailing_date_ranges().to_vec(),
+                to: other.trailing_date_ranges().to_vec(),
+            });
+        }
+        if self.preload_hints() != other.preload_hints() {
+            changes.push(PlaylistChange::PreloadHintsChanged {
+                from: self.preload_hints().to_vec(),
+                to: other.preload_hints().to_vec(),
+            });
+        }
+        if self.rendition_reports() != other.rendition_reports() {
+            changes.push(PlaylistChange::RenditionReportsChanged {
+                from: self.rendition_reports().to_vec(),
+                to: other.rendition_reports().to_vec(),
+            });
+        }
+
+        let old = sequenced_segments(self);
+        let new = sequenced_segments(other);
+        let (mut old, mut new) = (old.into_iter().peekable(), new.into_iter().peekable());
+        loop {
+            let change = match (old.peek(), new.peek()) {
+                (Some(before), Some(after)) if before.0 == after.0 => {
+                    let ((sequence, from, from_range), (_, to, to_range)) = (old.next().unwrap(), new.next().unwrap());
+                    if same_segment_within(from, from_range, to, to_range, epsilon) {
+                        continue;
+                    }
+                    PlaylistChange::SegmentChanged { sequence, from: Box::new(from.clone()), to: Box::new(to.clone()) }
+                }
+                (Some(before), after) if after.is_none_or(|after| before.0 < after.0) => {
+                    let (sequence, segment, _) = old.next().unwrap();
+                    PlaylistChange::SegmentRemoved { sequence, segment: Box::new(segment.clone()) }
+                }
+                (_, Some(_)) => {
+                    let (sequence, segment, _) = new.next().unwrap();
+                    PlaylistChange::SegmentAdded { sequence, segment: Box::new(segment.clone()) }
+                }
+                _ => break,
+            };
+            changes.push(change);
+        }
+        changes
+    }
+}
+
+/// Listed segments with their media sequence numbers and resolved byte ranges.
+fn sequenced_segments(playlist: &MediaPlaylist) -> Vec<(u64, &MediaSegment, Option<ByteRange>)> {
+    let first = playlist.media_sequence() + playlist.skipped_segments();
+    playlist
+        .segments()
+        .iter()
+        .zip(playlist.resolved_byte_ranges())
+        .enumerate()
+        .map(|(index, (segment, byte_range))| (first + index as u64, segment, byte_range))
+        .collect()
+}
+
+/// Whether a segment is unchanged for [`PlaylistTransition::mutated`][crate::PlaylistTransition::mutated].
+/// Servers may remove the parts of older segments, so parts aren't compared.
+pub(crate) fn same_segment(a: &MediaSegment, a_range: Option<ByteRange>, b: &MediaSegment, b_range: Option<ByteRange>) -> bool {
+    same_media_within(a, a_range, b, b_range, Duration::ZERO)
+}
+
+fn same_segment_within(
+    a: &MediaSegment,
+    a_range: Option<ByteRange>,
+    b: &MediaSegment,
+    b_range: Option<ByteRange>,
+    epsilon: Duration,
+) -> bool {
+    same_media_within(a, a_range, b, b_range, epsilon) && same_parts_within(a.parts(), b.parts(), epsilon)
+}
+
+fn same_parts_within(a: &[PartialSegment], b: &[PartialSegment], epsilon: Duration) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).all(|(a, b)| {
+            (a.exact_duration() == b.exact_duration() || within(a.duration(), b.duration(), epsilon))
+                && a.uri() == b.uri()
+                && a.independent() == b.independent()
+                && a.byte_range() == b.byte_range()
+                && a.gap() == b.gap()
+        })
+}
+
+fn same_media_within(
+    a: &MediaSegment,
+    a_range: Option<ByteRange>,
+    b: &MediaSegment,
+    b_range: Option<ByteRange>,
+    epsilon: Duration,
+) -> bool {
+    (a.exact_duration() == b.exact_duration() || within(a.duration(), b.duration(), epsilon))
+        && a.url() == b.url()
+        && a.title() == b.title()
+        && a.keys() == b.keys()
+        && a_range == b_range
+        && a.discontinuity() == b.discontinuity()
+        && a.map() == b.map()
+        && a.program_date_time() == b.program_date_time()
+        && a.date_ranges() == b.date_ranges()
+        && a.gap() == b.gap()
+        && custom_lines(a.tag_order()).eq(custom_lines(b.tag_order()))
+}
+
+fn within(a: Duration, b: Duration, epsilon: Duration) -> bool {
+    a.abs_diff(b) <= epsilon
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playlist(file: &str) -> MediaPlaylist {
+        MediaPlaylist::parse_ext_m3u(file).expect("test playlist should parse")
+    }
+
+    #[test]
+    fn ignores_formatting() {
+        let packaged = playlist(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-KEY:METHOD=AES-128,URI="1.key"
+            #EXTINF:9.50,
+            #EXT-X-BYTERANGE:100@0
+            main.ts
+            #EXTINF:10,
+            #EXT-X-BYTERANGE:100
+            main.ts
+        "#});
+        let repackaged = playlist(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-VERSION:1
+            # a comment
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-KEY:METHOD=AES-128,URI="1.key"
+            #EXTINF:9.5,
+            #EXT-X-BYTERANGE:100@0
+            main.ts
+            #EXT-X-KEY:METHOD=AES-128,URI="1.key"
+            #EXTINF:10.000,
+            #EXT-X-BYTERANGE:100@100
+            main.ts
+        "#});
+        assert_ne!(packaged, repackaged);
+        assert!(packaged.semantic_eq(&repackaged));
+        assert_eq!(packaged.diff(&repackaged), vec![]);
+
+        let rounded = playlist(&repackaged.to_string().replace("#EXTINF:9.5,", "#EXTINF:9.5000001,"));
+        assert_eq!(packaged.diff(&rounded).len(), 1);
+        assert!(packaged.semantic_eq_with_tolerance(&rounded, Duration::from_micros(1)));
+        assert!(!packaged.semantic_eq_with_tolerance(&rounded, Duration::from_nanos(10)));
+    }
+
+    #[test]
+    fn lists_changes() {
+        let before = playlist(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-MEDIA-SEQUENCE:5
+            #EXTINF:10,
+            5.ts
+            #EXTINF:10,
+            6.ts
+            #EXTINF:10,
+            7.ts
+        "});
+        let after = playlist(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-MEDIA-SEQUENCE:6
+            #EXTINF:10,
+            6.ts
+            #EXTINF:9,
+            7.ts
+            #EXTINF:10,
+            8.ts
+            #EXT-X-ENDLIST
+        "});
+        assert_eq!(
+            before.diff(&after),
+            vec![
+                PlaylistChange::MediaSequenceChanged { from: 5, to: 6 },
+                PlaylistChange::EndListAppeared,
+                PlaylistChange::SegmentRemoved { sequence: 5, segment: Box::new(before.segments()[0].clone()) },
+                PlaylistChange::SegmentChanged {
+                    sequence: 7,
+                    from: Box::new(before.segments()[2].clone()),
+                    to: Box::new(after.segments()[1].clone()),
+                },
+                PlaylistChange::SegmentAdded { sequence: 8, segment: Box::new(after.segments()[2].clone()) },
+            ]
+        );
+        assert!(!before.semantic_eq(&after));
+    }
+
+    #[test]
+    fn compares_everything_written() {
+        let base = indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-VERSION:9
+            #EXT-X-TARGETDURATION:4
+            #EXT-X-PART-INF:PART-TARGET=1
+            #EXT-X-MEDIA-SEQUENCE:10
+            #EXT-X-PART:DURATION=1,URI="10.0.mp4"
+            #EXTINF:4,
+            10.mp4
+            #EXT-X-CUE-OUT:4
+            #EXT-X-GAP
+            #EXTINF:4,
+            11.mp4
+            #EXT-X-PART:DURATION=1,URI="12.0.mp4"
+            #EXT-X-PRELOAD-HINT:TYPE=PART,URI="12.1.mp4"
+            #EXT-X-RENDITION-REPORT:URI="low.m3u8",LAST-MSN=11
+        "#};
+        let diff = |from: &str, to: &str| playlist(base).diff(&playlist(&base.replace(from, to)));
+        let segment_changed = |changes: Vec<PlaylistChange>, segment| {
+            matches!(changes.as_slice(), [PlaylistChange::SegmentChanged { sequence, .. }] if *sequence == segment)
+        };
+        assert!(segment_changed(diff("#EXT-X-GAP\n", ""), 11));
+        assert!(segment_changed(diff("10.0.mp4", "10.0a.mp4"), 10));
+        assert!(segment_changed(diff("CUE-OUT:4", "CUE-OUT:8"), 11));
+        assert!(segment_changed(diff("#EXT-X-GAP\n", "#EXT-X-GAP\n#EXT-X-CUE-IN\n"), 11));
+        assert!(matches!(diff("#EXT-X-PART-INF", "#EXT-X-I-FRAMES-ONLY\n#EXT-X-PART-INF")[..], [
+            PlaylistChange::IFramesOnlyChanged { from: false, to: true }
+        ]));
+        assert!(matches!(diff("TARGET=1", "TARGET=1.002")[..], [PlaylistChange::PartTargetChanged { .. }]));
+        let skip = "#EXT-X-MEDIA-SEQUENCE:9\n#EXT-X-SKIP:SKIPPED-SEGMENTS=1";
+        let skipped = PlaylistChange::SkippedSegmentsChanged { from: 0, to: 1 };
+        assert!(diff("#EXT-X-MEDIA-SEQUENCE:10", skip).contains(&skipped));
+        assert!(matches!(diff("12.0.mp4", "12.0a.mp4")[..], [PlaylistChange::TrailingPartsChanged { .. }]));
+        let date_range = "#EXT-X-DATERANGE:ID=\"ad\",START-DATE=\"2024-01-01T00:00:00Z\"\n#EXT-X-PRELOAD";
+        assert!(matches!(diff("#EXT-X-PRELOAD", date_range)[..], [PlaylistChange::TrailingDateRangesChanged { .. }]));
+        assert!(matches!(diff("12.1.mp4", "12.1a.mp4")[..], [PlaylistChange::PreloadHintsChanged { .. }]));
+        assert!(matches!(diff("LAST-MSN=11", "LAST-MSN=12")[..], [PlaylistChange::RenditionReportsChanged { .. }]));
+        let part_target = playlist(&base.replace("TARGET=1", "TARGET=1.002"));
+        assert!(playlist(base).semantic_eq_with_tolerance(&part_target, Duration::from_millis(2)));
+        let part_durations = playlist(&base.replace("DURATION=1,", "DURATION=1.001,"));
+        assert!(playlist(base).semantic_eq_with_tolerance(&part_durations, Duration::from_millis(2)));
+        assert_eq!(playlist(base).compare_with_tolerance(&part_durations, Duration::ZERO).len(), 2);
+
+        //where modeled tags go among custom ones isn't compared, and neither are extensions
+        assert_eq!(diff("#EXT-X-CUE-OUT:4\n#EXT-X-GAP", "#EXT-X-GAP\n#EXT-X-CUE-OUT:4"), vec![]);
+        let mut extended = playlist(base);
+        extended.extensions_mut().insert(1u32);
+        extended.segments_mut()[0].extensions_mut().insert(2u32);
+        assert!(extended.semantic_eq(&playlist(base)));
+    }
+}
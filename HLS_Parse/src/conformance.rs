@@ -0,0 +1,442 @@
+//! Checking playlists against a choice of rule sets, from the bare RFC to Apple's stricter
+//! [HLS Authoring Specification][apple], like a lightweight `mediastreamvalidator`.
+//!
+//! Each [`Profile`] includes the rules of the ones before it. Every [`Finding`] has a
+//! [`Category`] so reports can be grouped, e.g. to show trick play problems separately.
+//!
+//! [apple]: https://developer.apple.com/documentation/http-live-streaming/hls-authoring-specification-for-apple-devices
+
+use core::fmt;
+use std::collections::BTreeSet;
+
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::events::{self, I_FRAME_STREAM_INF_TAG, MEDIA_TAG, SESSION_DATA_TAG, SESSION_KEY_TAG, STREAM_INF_TAG};
+use crate::{MasterPlaylist, MediaPlaylist, MediaType, ParseOptions, SpecVersion, VariantStream};
+
+/// Codec identifiers of video formats, as the first part of an RFC 6381 codec string.
+pub(crate) const VIDEO_CODECS: [&str; 9] = ["avc1", "avc3", "hvc1", "hev1", "dvh1", "dvhe", "av01", "vp09", "mp4v"];
+
+/// Target duration the authoring specification recommends.
+const APPLE_TARGET_DURATION: u64 = 6;
+
+/// A set of rules to check playlists against, ordered from most lenient to strictest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Profile {
+    /// <https://datatracker.ietf.org/doc/html/rfc8216>.
+    #[default]
+    Rfc8216,
+    /// The recommendations added by
+    /// <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis>.
+    Rfc8216bis,
+    /// The HLS Authoring Specification for Apple Devices, which turns many recommendations into
+    /// requirements.
+    AppleAuthoring,
+}
+
+impl Profile {
+    /// The version of the specification playlists are parsed under.
+    fn spec(self) -> SpecVersion {
+        match self {
+            Profile::Rfc8216 => SpecVersion::Rfc8216,
+            Profile::Rfc8216bis | Profile::AppleAuthoring => SpecVersion::Rfc8216bis,
+        }
+    }
+}
+
+/// What part of a presentation a [`Finding`] is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(rename_all = "kebab-case"))]
+pub enum Category {
+    /// The playlist couldn't be parsed.
+    Syntax,
+    /// Everything [`MediaPlaylist::diagnostics`] reports.
+    Playlist,
+    /// Segment and target durations, and dates.
+    Timing,
+    /// Attributes of variant streams.
+    Variants,
+    /// Alternative renditions and their groups.
+    Renditions,
+    /// I-frame playlists for fast forward and scrubbing.
+    TrickPlay,
+    /// Segment formats and the tags they need, from [`MediaPlaylist::validate_containers`].
+    Containers,
+}
+
+/// A [`Diagnostic`] with the category of the rule it comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Finding {
+    pub category: Category,
+    pub diagnostic: Diagnostic,
+}
+
+/// Parses the given master or media playlist and checks it against `profile`. Master playlists
+/// are recognized by their EXT-X-STREAM-INF, EXT-X-I-FRAME-STREAM-INF, EXT-X-MEDIA,
+/// EXT-X-SESSION-DATA or EXT-X-SESSION-KEY tags.
+pub fn validate(file: &str, profile: Profile) -> Vec<Finding> {
+    validate_with_options(file, profile, &ParseOptions::default())
+}
+
+/// Like [`validate`], with control over how media playlists are parsed. The
+/// [`spec`][ParseOptions::spec] is the one `profile` is based on.
+pub fn validate_with_options(file: &str, profile: Profile, options: &ParseOptions) -> Vec<Finding> {
+    let options = &ParseOptions { spec: profile.spec(), ..options.clone() };
+    let master_tags = [STREAM_INF_TAG, I_FRAME_STREAM_INF_TAG, MEDIA_TAG, SESSION_DATA_TAG, SESSION_KEY_TAG];
+    let is_master = file.lines().any(|x| master_tags.contains(&events::tag_name(x)));
+    if is_master {
+        match MasterPlaylist::parse_with_options(file, options) {
+            Ok(playlist) => check_master(&playlist, profile),
+            Err(error) => vec![finding(Category::Syntax, Diagnostic::error(None, format!("{:#}", error)))],
+        }
+    } else {
+        match MediaPlaylist::parse_all_errors(file, options) {
+            Ok(playlist) => check_media(&playlist, profile),
+            Err(errors) => errors.into_iter().map(|x| finding(Category::Syntax, x.into())).collect(),
+        }
+    }
+}
+
+/// Checks a parsed media playlist against `profile`.
+pub fn check_media(playlist: &MediaPlaylist, profile: Profile) -> Vec<Finding> {
+    let diagnostics = playlist.diagnostics().into_iter();
+    let mut findings: Vec<Finding> = diagnostics.map(|x| finding(Category::Playlist, x)).collect();
+    findings.extend(playlist.validate_containers().into_iter().map(|x| finding(Category::Containers, x)));
+    if profile >= Profile::AppleAuthoring {
+        let target_duration = playlist.target_duration().as_secs();
+        if target_duration != APPLE_TARGET_DURATION && !playlist.i_frames_only() {
+            findings.push(finding(Category::Timing, Diagnostic::warning(None, format!(
+                "Target duration {}s differs from the recommended {}s",
+                target_duration, APPLE_TARGET_DURATION
+            ))));
+        }
+        if !playlist.ended() && playlist.segments().iter().all(|x| x.program_date_time().is_none()) {
+            findings.push(finding(Category::Timing, Diagnostic::warning(
+                None,
+                "Live playlists should have EXT-X-PROGRAM-DATE-TIME tags",
+            )));
+        }
+    }
+    findings
+}
+
+/// Checks a parsed master playlist against `profile`.
+pub fn check_master(playlist: &MasterPlaylist, profile: Profile) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let apple = profile >= Profile::AppleAuthoring;
+    //recommendations the authoring specification makes requirements
+    let recommended = |message: String| {
+        let severity = if apple { Severity::Error } else { Severity::Warning };
+        finding(Category::Variants, Diagnostic { severity, line: None, message })
+    };
+
+    for variant in playlist.variants() {
+        if variant.codecs().is_none() {
+            findings.push(recommended(format!("Variant {} should have a CODECS attribute", variant.uri())));
+        }
+        if has_video(variant) && variant.resolution().is_none() {
+            findings.push(recommended(format!("Variant {} has video but no RESOLUTION", variant.uri())));
+        }
+        if profile >= Profile::Rfc8216bis && has_video(variant) && variant.frame_rate().is_none() {
+            findings.push(recommended(format!("Variant {} has video but no FRAME-RATE", variant.uri())));
+        }
+        if apple && variant.average_bandwidth().is_none() {
+            findings.push(recommended(format!("Variant {} should have an AVERAGE-BANDWIDTH attribute", variant.uri())));
+        }
+    }
+
+    findings.extend(playlist.validate_ladder().into_iter().map(|x| finding(Category::Variants, x)));
+
+    //RFC8216 4.3.4.1.1 requirements
+    let mut groups: Vec<((MediaType, &str), Vec<&str>)> = Vec::new();
+    for rendition in playlist.renditions() {
+        let group = (rendition.media_type(), rendition.group_id());
+        let index = groups.iter().position(|(x, _)| *x == group).unwrap_or_else(|| {
+            groups.push((group, Vec::new()));
+            groups.len() - 1
+        });
+        let names = &mut groups[index].1;
+        if names.contains(&rendition.name()) {
+            findings.push(finding(Category::Renditions, Diagnostic::error(None, format!(
+                "{} group {} has more than one rendition named {}",
+                rendition.media_type(), rendition.group_id(), rendition.name()
+            ))));
+        }
+        names.push(rendition.name());
+    }
+    for &((media_type, group_id), _) in &groups {
+        if playlist.rendition_group(media_type, group_id).iter().filter(|x| x.is_default()).count() > 1 {
+            findings.push(finding(Category::Renditions, Diagnostic::error(None, format!(
+                "{} group {} has more than one rendition with DEFAULT=YES",
+                media_type, group_id
+            ))));
+        }
+    }
+
+    if apple {
+        //every audio group should offer the same choices, so switching variants keeps the audio
+        let audio: Vec<(&str, BTreeSet<&str>)> = groups
+            .iter()
+            .filter(|((media_type, _), _)| *media_type == MediaType::Audio)
+            .map(|((_, group_id), names)| (*group_id, names.iter().copied().collect()))
+            .collect();
+        for window in audio.windows(2) {
+            let [(first, first_names), (group_id, names)] = window else {
+                continue;
+            };
+            for name in first_names.symmetric_difference(names) {
+                let (missing_from, present_in) =
+                    if names.contains(name) { (first, group_id) } else { (group_id, first) };
+                findings.push(finding(Category::Renditions, Diagnostic::error(None, format!(
+                    "AUDIO group {} has no rendition named {} like group {}",
+                    missing_from, name, present_in
+                ))));
+            }
+        }
+
+        if playlist.variants().iter().any(has_video) && playlist.i_frame_variants().is_empty() {
+            findings.push(finding(Category::TrickPlay, Diagnostic::error(
+                None,
+                "Video variants need EXT-X-I-FRAME-STREAM-INF playlists for trick play",
+            )));
+        }
+    }
+    findings
+}
+
+/// Whether the variant carries video, judging by its attributes.
+fn has_video(variant: &VariantStream) -> bool {
+    let video_codec = variant
+        .codecs()
+        .is_some_and(|x| x.split(',').any(|x| VIDEO_CODECS.contains(&x.trim().split('.').next().unwrap_or_default())));
+    video_codec || variant.resolution().is_some() || variant.video().is_some()
+}
+
+/// The findings for each playlist passed to [`validate_batch`], in the order they were passed.
+#[cfg(feature = "rayon")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct BatchReport<N> {
+    pub sources: Vec<SourceReport<N>>,
+}
+
+/// The findings for one playlist of a [`BatchReport`].
+#[cfg(feature = "rayon")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SourceReport<N> {
+    /// What the playlist was passed with, e.g. its path.
+    pub name: N,
+    pub findings: Vec<Finding>,
+}
+
+#[cfg(feature = "rayon")]
+impl<N> SourceReport<N> {
+    pub fn has_errors(&self) -> bool {
+        self.findings.iter().any(|x| x.diagnostic.severity == Severity::Error)
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<N> BatchReport<N> {
+    /// The playlists with at least one error.
+    pub fn failed(&self) -> impl Iterator<Item = &SourceReport<N>> + '_ {
+        self.sources.iter().filter(|x| x.has_errors())
+    }
+
+    /// Number of findings of each severity across every playlist.
+    pub fn count(&self, severity: Severity) -> usize {
+        self.sources.iter().flat_map(|x| &x.findings).filter(|x| x.diagnostic.severity == severity).count()
+    }
+}
+
+/// Validates many playlists at once like [`validate_with_options`], on the rayon thread pool.
+/// `sources` yields each playlist's name and contents, and is only read as fast as playlists
+/// are checked, so it can read files lazily.
+#[cfg(feature = "rayon")]
+pub fn validate_batch<I, N, S>(sources: I, profile: Profile, options: &ParseOptions) -> BatchReport<N>
+where
+    I: IntoIterator<Item = (N, S)>,
+    I::IntoIter: Send,
+    N: Send,
+    S: AsRef<str> + Send,
+{
+    use rayon::prelude::*;
+
+    let mut sources: Vec<(usize, SourceReport<N>)> = sources
+        .into_iter()
+        .enumerate()
+        .par_bridge()
+        .map(|(index, (name, file))| {
+            (index, SourceReport { name, findings: validate_with_options(file.as_ref(), profile, options) })
+        })
+        .collect();
+    sources.sort_by_key(|(index, _)| *index);
+    BatchReport { sources: sources.into_iter().map(|(_, report)| report).collect() }
+}
+
+fn finding(category: Category, diagnostic: Diagnostic) -> Finding {
+    Finding { category, diagnostic }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Category::Syntax => "syntax",
+            Category::Playlist => "playlist",
+            Category::Timing => "timing",
+            Category::Variants => "variants",
+            Category::Renditions => "renditions",
+            Category::TrickPlay => "trick play",
+            Category::Containers => "containers",
+        })
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]", self.diagnostic, self.category)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MASTER: &str = indoc::indoc! {r#"
+        #EXTM3U
+        #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="stereo",NAME="English",DEFAULT=YES,AUTOSELECT=YES,URI="en.m3u8"
+        #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="stereo",NAME="Deutsch",URI="de.m3u8"
+        #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="surround",NAME="English",DEFAULT=YES,AUTOSELECT=YES,URI="en-51.m3u8"
+        #EXT-X-STREAM-INF:BANDWIDTH=1280000,CODECS="avc1.4d401e,mp4a.40.2",RESOLUTION=640x360,AUDIO="stereo"
+        low.m3u8
+        #EXT-X-STREAM-INF:BANDWIDTH=2560000,AVERAGE-BANDWIDTH=2000000,CODECS="avc1.4d401f,ec-3",RESOLUTION=1280x720,FRAME-RATE=30.000,AUDIO="surround"
+        high.m3u8
+    "#};
+
+    fn messages(findings: &[Finding]) -> Vec<String> {
+        findings.iter().map(ToString::to_string).collect()
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn validates_batches() {
+        let media = "#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXTINF:4,\n0.ts\n#EXT-X-ENDLIST\n";
+        let files = ["#EXTM3U\n#EXTINF:4,\n", media, MASTER];
+        let sources = (0..64).map(|x| (x, files[x % 3]));
+        let report = validate_batch(sources, Profile::Rfc8216, &ParseOptions::default());
+        assert_eq!(report.sources.iter().map(|x| x.name).collect::<Vec<_>>(), (0..64).collect::<Vec<_>>());
+        assert_eq!(report.failed().map(|x| x.name % 3).collect::<BTreeSet<_>>(), BTreeSet::from([0]));
+        assert_eq!(report.sources[2].findings, validate(MASTER, Profile::Rfc8216));
+        assert!(report.count(Severity::Error) >= 22);
+    }
+
+    #[test]
+    fn parses_under_profile_spec() {
+        let gap = "#EXTM3U\n#EXT-X-VERSION:8\n#EXT-X-TARGETDURATION:4\n#EXTINF:4,\n#EXT-X-GAP\n0.ts\n#EXT-X-ENDLIST\n";
+        assert_eq!(
+            messages(&validate(gap, Profile::Rfc8216)),
+            vec![
+                "error: line 2: EXT-X-VERSION 8 is not part of RFC 8216 [syntax]",
+                "error: line 5: EXT-X-GAP tag is not part of RFC 8216 [syntax]",
+            ]
+        );
+        assert_eq!(validate(gap, Profile::Rfc8216bis), vec![]);
+    }
+
+    #[test]
+    fn profiles_get_stricter() {
+        assert_eq!(validate(MASTER, Profile::Rfc8216), vec![]);
+        assert_eq!(
+            messages(&validate(MASTER, Profile::Rfc8216bis)),
+            vec!["warning: Variant low.m3u8 has video but no FRAME-RATE [variants]"]
+        );
+        assert_eq!(
+            messages(&validate(MASTER, Profile::AppleAuthoring)),
+            vec![
+                "error: Variant low.m3u8 has video but no FRAME-RATE [variants]",
+                "error: Variant low.m3u8 should have an AVERAGE-BANDWIDTH attribute [variants]",
+                "error: AUDIO group surround has no rendition named Deutsch like group stereo [renditions]",
+                "error: Video variants need EXT-X-I-FRAME-STREAM-INF playlists for trick play [trick play]",
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_rendition_groups() {
+        let playlist = MasterPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",NAME="English",DEFAULT=YES,URI="en.m3u8"
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",NAME="English",DEFAULT=YES,URI="en2.m3u8"
+            #EXT-X-STREAM-INF:BANDWIDTH=128000,AUDIO="aac"
+            audio.m3u8
+        "#})
+        .unwrap();
+        assert_eq!(
+            messages(&check_master(&playlist, Profile::Rfc8216)),
+            vec![
+                "warning: Variant audio.m3u8 should have a CODECS attribute [variants]",
+                "error: AUDIO group aac has more than one rendition named English [renditions]",
+                "error: AUDIO group aac has more than one rendition with DEFAULT=YES [renditions]",
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_media_playlists() {
+        let live = "#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10,\n1.ts\n";
+        assert_eq!(validate(live, Profile::Rfc8216bis), vec![]);
+        assert_eq!(
+            messages(&validate(live, Profile::AppleAuthoring)),
+            vec![
+                "warning: Target duration 10s differs from the recommended 6s [timing]",
+                "warning: Live playlists should have EXT-X-PROGRAM-DATE-TIME tags [timing]",
+            ]
+        );
+        assert_eq!(
+            messages(&validate("#EXTM3U\n#EXTINF:10,\n", Profile::Rfc8216)),
+            vec!["error: EXTINF without URI at line 2 [syntax]"]
+        );
+    }
+
+    #[test]
+    fn checks_containers() {
+        let fmp4 = "#EXTM3U\n#EXT-X-TARGETDURATION:6\n#EXTINF:6,\n0.m4s\n#EXT-X-ENDLIST\n";
+        assert_eq!(
+            messages(&validate(fmp4, Profile::Rfc8216)),
+            vec!["error: Fragmented MP4 segment 1 (0.m4s) has no EXT-X-MAP [containers]"]
+        );
+        let i_frames = indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-VERSION:4
+            #EXT-X-TARGETDURATION:6
+            #EXT-X-I-FRAMES-ONLY
+            #EXTINF:6,
+            #EXT-X-BYTERANGE:1000@376
+            main.ts
+            #EXTINF:6,
+            #EXT-X-BYTERANGE:1000@9000
+            main.ts
+            #EXT-X-ENDLIST
+        "};
+        assert_eq!(
+            messages(&validate(i_frames, Profile::Rfc8216)),
+            vec!["warning: I-frame segment 1 (main.ts) doesn't start its resource and should have an EXT-X-MAP \
+                [containers]"]
+        );
+        let packed_audio = indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-VERSION:6
+            #EXT-X-TARGETDURATION:6
+            #EXT-X-MAP:URI="init.mp4"
+            #EXTINF:6,
+            0.aac
+            #EXT-X-ENDLIST
+        "#};
+        assert_eq!(
+            messages(&validate(packed_audio, Profile::Rfc8216)),
+            vec!["warning: Packed audio has no initialization section, but the playlist has an EXT-X-MAP tag \
+                [containers]"]
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! Checking a media playlist against what its EXT-X-STREAM-INF tag in the master playlist says
+//! about it. See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.4.2>.
+
+use crate::diagnostics::Diagnostic;
+use crate::conformance::VIDEO_CODECS;
+use crate::{Container, MediaPlaylist, VariantStream};
+
+impl VariantStream {
+    /// Cross-checks the media playlist this variant refers to against the variant's attributes:
+    /// the playlist must not be I-frames only, its bit rate (measurable for segments with byte
+    /// ranges) must fit BANDWIDTH and AVERAGE-BANDWIDTH, and packed audio segments mustn't be
+    /// described with video codecs or a RESOLUTION. Problems in the media playlist by itself are
+    /// reported by [`MediaPlaylist::diagnostics`].
+    pub fn validate_media(&self, playlist: &MediaPlaylist) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        if playlist.i_frames_only() {
+            diagnostics.push(Diagnostic::error(None, format!(
+                "Variant {} refers to an I-frame playlist, which belongs in EXT-X-I-FRAME-STREAM-INF",
+                self.uri()
+            )));
+        }
+
+        //RFC8216 4.3.4.2, BANDWIDTH is at least the peak segment bit rate
+        if let Some((index, peak)) = peak_bit_rate(playlist) {
+            if peak > self.bandwidth() as f64 {
+                diagnostics.push(Diagnostic::error(None, format!(
+                    "Peak bit rate {}bps from segment {} ({}) exceeds BANDWIDTH {} of variant {}",
+                    peak.round(), index + 1, playlist.segments()[index].url(), self.bandwidth(), self.uri()
+                )));
+            }
+        }
+        //the average is only approximate, so allow 10% like the HLS authoring specification
+        if let (Some(average), Some(declared)) = (average_bit_rate(playlist), self.average_bandwidth()) {
+            if average > declared as f64 * 1.1 {
+                diagnostics.push(Diagnostic::warning(None, format!(
+                    "Average bit rate {}bps exceeds AVERAGE-BANDWIDTH {} of variant {} by more than 10%",
+                    average.round(), declared, self.uri()
+                )));
+            }
+        }
+
+        let packed_audio = !playlist.segments().is_empty()
+            && playlist.segments().iter().all(|x| matches!(x.container_hint(), Some(Container::PackedAudio(_))));
+        if packed_audio {
+            let mut codecs = self.codecs().unwrap_or_default().split(',').map(str::trim);
+            if let Some(codec) = codecs.find(|x| VIDEO_CODECS.contains(&x.split('.').next().unwrap_or_default())) {
+                diagnostics.push(Diagnostic::error(None, format!(
+                    "Variant {} lists video codec {} but its segments are packed audio",
+                    self.uri(), codec
+                )));
+            }
+            if self.resolution().is_some() {
+                diagnostics.push(Diagnostic::warning(None, format!(
+                    "Variant {} has a RESOLUTION but its segments are packed audio",
+                    self.uri()
+                )));
+            }
+        }
+        diagnostics
+    }
+}
+
+/// The largest bit rate of any run of consecutive segments lasting between 0.5 and 1.5 target
+/// durations, along with the index of the first segment of that run. Only segments with byte
+/// ranges have a known size.
+pub(crate) fn peak_bit_rate(playlist: &MediaPlaylist) -> Option<(usize, f64)> {
+    let target = playlist.target_duration().as_secs_f64();
+    let segments = playlist.segments();
+    let mut peak: Option<(usize, f64)> = None;
+    for start in 0..segments.len() {
+        let (mut bits, mut seconds) = (0u64, 0.0);
+        for segment in &segments[start..] {
+            let Some(byte_range) = segment.byte_range() else {
+                break;
+            };
+            bits += byte_range.length * 8;
+            seconds += segment.duration().as_secs_f64();
+            if seconds > target * 1.5 {
+                break;
+            }
+            if seconds >= target * 0.5 {
+                let rate = bits as f64 / seconds;
+                if peak.is_none_or(|(_, peak)| rate > peak) {
+                    peak = Some((start, rate));
+                }
+            }
+        }
+    }
+    peak
+}
+
+/// Bit rate over all the segments, if they all have byte ranges.
+pub(crate) fn average_bit_rate(playlist: &MediaPlaylist) -> Option<f64> {
+    let mut bits = 0;
+    let mut seconds = 0.0;
+    for segment in playlist.segments() {
+        bits += segment.byte_range()?.length * 8;
+        seconds += segment.duration().as_secs_f64();
+    }
+    (seconds > 0.0).then(|| bits as f64 / seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MasterPlaylist;
+
+    fn variant(attributes: &str) -> VariantStream {
+        let master = MasterPlaylist::parse_ext_m3u(&format!("#EXTM3U\n#EXT-X-STREAM-INF:{}\nmedia.m3u8\n", attributes))
+            .expect("test master playlist should parse");
+        master.variants()[0].clone()
+    }
+
+    fn media(file: &str) -> MediaPlaylist {
+        MediaPlaylist::parse_ext_m3u(file).expect("test media playlist should parse")
+    }
+
+    const SIZED: &str = indoc::indoc! {"
+        #EXTM3U
+        #EXT-X-VERSION:4
+        #EXT-X-TARGETDURATION:4
+        #EXTINF:4,
+        #EXT-X-BYTERANGE:500000@0
+        main.ts
+        #EXTINF:4,
+        #EXT-X-BYTERANGE:1500000@500000
+        main.ts
+        #EXTINF:4,
+        #EXT-X-BYTERANGE:500000@2000000
+        main.ts
+        #EXT-X-ENDLIST
+    "};
+
+    #[test]
+    fn checks_bandwidth() {
+        let playlist = media(SIZED);
+        assert!(variant("BANDWIDTH=3000000,AVERAGE-BANDWIDTH=1700000").validate_media(&playlist).is_empty());
+        assert_eq!(
+            variant("BANDWIDTH=2000000,AVERAGE-BANDWIDTH=1000000").validate_media(&playlist),
+            vec![
+                Diagnostic::error(None, "Peak bit rate 3000000bps from segment 2 (main.ts) exceeds BANDWIDTH 2000000 of variant media.m3u8"),
+                Diagnostic::warning(None, "Average bit rate 1666667bps exceeds AVERAGE-BANDWIDTH 1000000 of variant media.m3u8 by more than 10%"),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_i_frame_playlist() {
+        let playlist = media("#EXTM3U\n#EXT-X-VERSION:4\n#EXT-X-TARGETDURATION:4\n#EXT-X-I-FRAMES-ONLY\n#EXTINF:4,\n1.ts\n");
+        assert!(playlist.i_frames_only());
+        assert!(playlist.diagnostics().is_empty());
+        assert_eq!(
+            variant("BANDWIDTH=1").validate_media(&playlist),
+            vec![Diagnostic::error(
+                None,
+                "Variant media.m3u8 refers to an I-frame playlist, which belongs in EXT-X-I-FRAME-STREAM-INF"
+            )]
+        );
+    }
+
+    #[test]
+    fn checks_packed_audio() {
+        let playlist = media("#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXTINF:4,\n1.aac\n#EXTINF:4,\n2.AAC?token=1\n");
+        assert!(variant("BANDWIDTH=64000,CODECS=\"mp4a.40.2\"").validate_media(&playlist).is_empty());
+        assert_eq!(
+            variant("BANDWIDTH=64000,CODECS=\"avc1.4d401f,mp4a.40.2\",RESOLUTION=640x360").validate_media(&playlist),
+            vec![
+                Diagnostic::error(None, "Variant media.m3u8 lists video codec avc1.4d401f but its segments are packed audio"),
+                Diagnostic::warning(None, "Variant media.m3u8 has a RESOLUTION but its segments are packed audio"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,133 @@
+//! Guessing the container format of segments before downloading them. See
+//! <https://datatracker.ietf.org/doc/html/rfc8216#section-3>.
+
+use crate::diagnostics::Diagnostic;
+use crate::{MediaPlaylist, MediaSegment, SegmentUri};
+
+/// Container format of a media segment, which decides the demuxer a player needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Container {
+    /// MPEG-2 Transport Stream. See <https://datatracker.ietf.org/doc/html/rfc8216#section-3.2>.
+    MpegTs,
+    /// Fragmented MPEG-4, including CMAF. See
+    /// <https://datatracker.ietf.org/doc/html/rfc8216#section-3.3>.
+    FragmentedMp4,
+    /// Elementary audio stream with an ID3 timestamp. See
+    /// <https://datatracker.ietf.org/doc/html/rfc8216#section-3.4>.
+    PackedAudio(PackedAudio),
+    /// See <https://datatracker.ietf.org/doc/html/rfc8216#section-3.5>.
+    WebVtt,
+}
+
+/// Audio format of a packed audio segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PackedAudio {
+    /// AAC with ADTS framing.
+    Aac,
+    Ac3,
+    Ec3,
+    Mp3,
+}
+
+impl MediaSegment {
+    /// Infers the container from the extension of the URL or, failing that, the URI of the media
+    /// initialization section. A segment with an EXT-X-MAP tag but no recognized extension is
+    /// assumed to be fragmented MP4, since that's what initialization sections are mostly for.
+    /// Returns `None` when there's nothing to go by.
+    pub fn container_hint(&self) -> Option<Container> {
+        if let Some(container) = from_extension(self.url()) {
+            return Some(container);
+        }
+        let map = self.map()?;
+        Some(from_extension(&SegmentUri::new(map.uri())).unwrap_or(Container::FragmentedMp4))
+    }
+}
+
+impl MediaPlaylist {
+    /// Checks the segments against the tags their containers need or rule out, going by
+    /// [`MediaSegment::container_hint`]: fragmented MP4 segments must have an EXT-X-MAP, as
+    /// should the first segment of an I-frame playlist (and the first after each discontinuity)
+    /// unless its I-frame is at the start of the resource, and packed audio has neither an
+    /// initialization section nor I-frames. Which version EXT-X-MAP needs is checked by
+    /// [`diagnostics`][Self::diagnostics].
+    pub fn validate_containers(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let segments = self.segments();
+
+        //RFC8216 3.3
+        let unmapped = segments.iter().enumerate().filter(|(_, x)| x.map().is_none());
+        for (index, segment) in unmapped.filter(|(_, x)| x.container_hint() == Some(Container::FragmentedMp4)) {
+            diagnostics.push(Diagnostic::error(None, format!(
+                "Fragmented MP4 segment {} ({}) has no EXT-X-MAP",
+                index + 1, segment.url()
+            )));
+        }
+
+        //RFC8216 4.3.2.5
+        if self.i_frames_only() {
+            for (index, (segment, byte_range)) in segments.iter().zip(self.resolved_byte_ranges()).enumerate() {
+                let starts_group = index == 0 || segment.discontinuity();
+                let mid_resource = byte_range.is_some_and(|x| x.offset != Some(0));
+                if starts_group && mid_resource && segment.map().is_none() {
+                    diagnostics.push(Diagnostic::warning(None, format!(
+                        "I-frame segment {} ({}) doesn't start its resource and should have an EXT-X-MAP",
+                        index + 1, segment.url()
+                    )));
+                }
+            }
+        }
+
+        //RFC8216 3.4
+        let packed_audio = |x: &MediaSegment| matches!(x.container_hint(), Some(Container::PackedAudio(_)));
+        if !segments.is_empty() && segments.iter().all(packed_audio) {
+            if self.i_frames_only() {
+                diagnostics.push(Diagnostic::error(None, "Packed audio playlist has an EXT-X-I-FRAMES-ONLY tag"));
+            }
+            if segments.iter().any(|x| x.map().is_some()) {
+                diagnostics.push(Diagnostic::warning(
+                    None,
+                    "Packed audio has no initialization section, but the playlist has an EXT-X-MAP tag",
+                ));
+            }
+        }
+        diagnostics
+    }
+}
+
+fn from_extension(uri: &SegmentUri) -> Option<Container> {
+    let (_, extension) = uri.path().rsplit_once('.')?;
+    Some(match extension.to_ascii_lowercase().as_str() {
+        "ts" | "m2ts" | "mts" => Container::MpegTs,
+        "mp4" | "m4s" | "m4v" | "m4a" | "cmfv" | "cmfa" | "cmft" => Container::FragmentedMp4,
+        "aac" => Container::PackedAudio(PackedAudio::Aac),
+        "ac3" => Container::PackedAudio(PackedAudio::Ac3),
+        "ec3" => Container::PackedAudio(PackedAudio::Ec3),
+        "mp3" => Container::PackedAudio(PackedAudio::Mp3),
+        "vtt" | "webvtt" => Container::WebVtt,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SegmentMap;
+
+    fn hint(url: &str, map: Option<&str>) -> Option<Container> {
+        let mut segment = MediaSegment::new(core::time::Duration::from_secs(6), url);
+        segment.set_map(map.map(|x| SegmentMap::new(x, None)));
+        segment.container_hint()
+    }
+
+    #[test]
+    fn infers_container() {
+        assert_eq!(hint("1.ts?token=a.mp4", None), Some(Container::MpegTs));
+        assert_eq!(hint("https://cdn.example.com/1.M4S", Some("init.mp4")), Some(Container::FragmentedMp4));
+        assert_eq!(hint("1.aac", None), Some(Container::PackedAudio(PackedAudio::Aac)));
+        assert_eq!(hint("subs/1.webvtt", None), Some(Container::WebVtt));
+        assert_eq!(hint("segment?id=1", Some("init.mp4")), Some(Container::FragmentedMp4));
+        assert_eq!(hint("segment?id=1", Some("pat.ts")), Some(Container::MpegTs));
+        assert_eq!(hint("segment?id=1", Some("init")), Some(Container::FragmentedMp4));
+        assert_eq!(hint("segment?id=1", None), None);
+    }
+}
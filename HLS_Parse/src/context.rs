@@ -0,0 +1,126 @@
+//! Segments together with the state which previous tags put them in, so players don't have to
+//! track it themselves.
+
+use core::time::Duration;
+
+use crate::{ByteRange, EncryptionKey, MediaPlaylist, MediaSegment, ProgramDateTime, SegmentMap};
+
+/// A segment as a player sees it, from [`MediaPlaylist::iter_segments`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentContext<'a> {
+    pub segment: &'a MediaSegment,
+
+    /// Media sequence number, counting segments skipped by a delta update.
+    pub sequence: u64,
+
+    /// Discontinuity sequence number. Segments with the same number share timestamps and
+    /// encoding parameters.
+    pub discontinuity_sequence: u64,
+
+    /// Keys the segment is encrypted with, one per KEYFORMAT. Empty if it isn't encrypted.
+    pub keys: &'a [EncryptionKey],
+
+    /// Media initialization section needed to parse the segment, if any.
+    pub map: Option<&'a SegmentMap>,
+
+    /// Date and time of the first sample: the segment's own EXT-X-PROGRAM-DATE-TIME, or the
+    /// previous segment's plus its duration. `None` before the first date and after a
+    /// discontinuity without one, where carrying it forward would give the wrong time.
+    pub program_date_time: Option<ProgramDateTime>,
+
+    /// Byte range with an implicit offset filled in, see [`MediaPlaylist::resolved_byte_ranges`].
+    pub byte_range: Option<ByteRange>,
+
+    /// Time from the start of the first listed segment to the start of this one.
+    pub start: Duration,
+}
+
+impl MediaPlaylist {
+    /// Iterates over the segments in playback order, with the key, map, program date time,
+    /// discontinuity sequence and media sequence number in effect for each.
+    pub fn iter_segments(&self) -> impl Iterator<Item = SegmentContext<'_>> + '_ {
+        let first_sequence = self.media_sequence() + self.skipped_segments();
+        let mut discontinuity_sequence = self.discontinuity_sequence();
+        let mut next_date_time: Option<ProgramDateTime> = None;
+        let mut start = Duration::ZERO;
+        self.segments().iter().zip(self.resolved_byte_ranges()).enumerate().map(move |(index, (segment, byte_range))| {
+            //the first segment continues from before the playlist, so its discontinuity is already counted
+            if segment.discontinuity() && index > 0 {
+                discontinuity_sequence += 1;
+            }
+            let program_date_time = match segment.program_date_time() {
+                Some(date_time) => Some(date_time),
+                None if segment.discontinuity() => None,
+                None => next_date_time,
+            };
+            next_date_time = program_date_time.and_then(|x| x.checked_add(segment.duration()));
+            let context = SegmentContext {
+                segment,
+                sequence: first_sequence + index as u64,
+                discontinuity_sequence,
+                keys: segment.keys(),
+                map: segment.map(),
+                program_date_time,
+                byte_range,
+                start,
+            };
+            start += segment.duration();
+            context
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_segment_state() {
+        let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-VERSION:6
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-MEDIA-SEQUENCE:20
+            #EXT-X-DISCONTINUITY-SEQUENCE:3
+            #EXT-X-MAP:URI="init.mp4"
+            #EXT-X-KEY:METHOD=AES-128,URI="1.key"
+            #EXT-X-PROGRAM-DATE-TIME:2015-08-25T01:59:23.708Z
+            #EXTINF:9.5,
+            #EXT-X-BYTERANGE:1000@0
+            main.mp4
+            #EXTINF:10,
+            #EXT-X-BYTERANGE:1000
+            main.mp4
+            #EXT-X-DISCONTINUITY
+            #EXT-X-MAP:URI="ad-init.mp4"
+            #EXT-X-KEY:METHOD=NONE
+            #EXTINF:5,
+            ad1.mp4
+            #EXT-X-PROGRAM-DATE-TIME:2015-08-25T02:00:00Z
+            #EXTINF:5,
+            ad2.mp4
+        "#})
+        .unwrap();
+        assert!(playlist.diagnostics().is_empty());
+
+        let contexts: Vec<SegmentContext> = playlist.iter_segments().collect();
+        let sequences: Vec<(u64, u64)> = contexts.iter().map(|x| (x.sequence, x.discontinuity_sequence)).collect();
+        assert_eq!(sequences, vec![(20, 3), (21, 3), (22, 4), (23, 4)]);
+        let maps: Vec<&str> = contexts.iter().map(|x| x.map.unwrap().uri()).collect();
+        assert_eq!(maps, vec!["init.mp4", "init.mp4", "ad-init.mp4", "ad-init.mp4"]);
+        assert_eq!(contexts[1].keys[0].uri(), Some("1.key"));
+        assert!(contexts[2].keys.is_empty());
+        let dates: Vec<Option<String>> = contexts.iter().map(|x| x.program_date_time.map(|x| x.to_string())).collect();
+        assert_eq!(
+            dates,
+            vec![
+                Some("2015-08-25T01:59:23.708Z".to_string()),
+                Some("2015-08-25T01:59:33.208Z".to_string()),
+                None,
+                Some("2015-08-25T02:00:00.000Z".to_string()),
+            ]
+        );
+        assert_eq!(contexts[1].byte_range, Some(ByteRange { length: 1000, offset: Some(1000) }));
+        assert_eq!(contexts[3].start, Duration::from_millis(24500));
+    }
+}
@@ -0,0 +1,92 @@
+//! SCTE-35 cues of the date ranges overlapping each segment, so manifest conditioning can decide
+//! per segment whether it falls inside an ad avail. See
+//! <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2.7.1>.
+
+use crate::MediaPlaylist;
+
+/// A date range with SCTE-35 splice info overlapping a segment, from
+/// [`MediaSegment::cues`][crate::MediaSegment::cues]. Tags with the same ID are one range.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cue {
+    /// ID of the date range.
+    pub id: String,
+
+    /// Whether the range starts within the segment, making it the cue-out point.
+    pub cue_out: bool,
+
+    /// Whether the range ends within the segment, making it the cue-in point. Never for ranges
+    /// whose end isn't known yet, which cover every segment from their start.
+    pub cue_in: bool,
+
+    /// Splice info of the range, as hexadecimal sequences.
+    pub scte35_out: Option<String>,
+    pub scte35_in: Option<String>,
+    pub scte35_cmd: Option<String>,
+}
+
+impl MediaPlaylist {
+    /// The cues of every segment, in order, going by their program date times like
+    /// [`segments_in_daterange`][Self::segments_in_daterange], or nothing if no date range
+    /// carries splice info.
+    pub(crate) fn segment_cues(&self) -> Vec<Vec<Cue>> {
+        let mut cues = Vec::new();
+        let date_ranges = self.merged_date_ranges();
+        let spliced = date_ranges.iter().filter(|x| x.scte35_out().or(x.scte35_in()).or(x.scte35_cmd()).is_some());
+        let mut dates = Vec::new();
+        for date_range in spliced {
+            if dates.is_empty() {
+                dates = self.iter_segments().map(|x| x.program_date_time).collect();
+                cues = vec![Vec::new(); self.segments().len()];
+            }
+            let end = self.date_range_end(date_range);
+            for index in self.segments_in_daterange(date_range) {
+                //only dated segments overlap a range
+                let Some(start) = dates[index] else {
+                    continue;
+                };
+                let segment_end = start.checked_add(self.segments()[index].duration()).unwrap_or(start);
+                cues[index].push(Cue {
+                    id: date_range.id().to_string(),
+                    cue_out: start <= date_range.start_date(),
+                    cue_in: end.is_some_and(|end| end <= segment_end),
+                    scte35_out: date_range.scte35_out().map(str::to_string),
+                    scte35_in: date_range.scte35_in().map(str::to_string),
+                    scte35_cmd: date_range.scte35_cmd().map(str::to_string),
+                });
+            }
+        }
+        cues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::MediaPlaylist;
+
+    #[test]
+    fn associates_cues_with_segments() {
+        let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-TARGETDURATION:4
+            #EXT-X-PROGRAM-DATE-TIME:2024-03-01T12:00:00Z
+            #EXTINF:4,
+            0.ts
+            #EXT-X-DATERANGE:ID="avail",START-DATE="2024-03-01T12:00:04Z",PLANNED-DURATION=8,SCTE35-OUT=0xFC30
+            #EXTINF:4,
+            1.ts
+            #EXT-X-DATERANGE:ID="program",START-DATE="2024-03-01T12:00:00Z",DURATION=60
+            #EXTINF:4,
+            2.ts
+            #EXT-X-DATERANGE:ID="avail",START-DATE="2024-03-01T12:00:04Z",DURATION=8,SCTE35-IN=0xFC31
+            #EXTINF:4,
+            3.ts
+        "#})
+        .unwrap();
+        let cues: Vec<_> = playlist.segments().iter().map(|x| x.cues()).collect();
+        assert!(cues[0].is_empty() && cues[3].is_empty());
+        assert_eq!((cues[1].len(), cues[1][0].cue_out, cues[1][0].cue_in), (1, true, false));
+        assert_eq!((cues[2][0].cue_out, cues[2][0].cue_in), (false, true));
+        let splice_info = (cues[2][0].scte35_out.as_deref(), cues[2][0].scte35_in.as_deref());
+        assert_eq!(splice_info, (Some("0xFC30"), Some("0xFC31")));
+    }
+}
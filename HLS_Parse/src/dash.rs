@@ -0,0 +1,525 @@
+//! Export to static [MPEG-DASH][dash] manifests, so VOD content packaged for HLS can be offered as
+//! DASH from the same model.
+//!
+//! [dash]: https://www.iso.org/standard/83314.html
+
+use core::time::Duration;
+
+use anyhow::Result;
+
+use crate::consistency::average_bit_rate;
+use crate::{ByteRange, MasterPlaylist, MediaPlaylist, MediaSegment, MediaType};
+
+const XML_DECLARATION: &str = r#"<?xml version="1.0" encoding="UTF-8"?>"#;
+const MPD_NAMESPACE: &str = "urn:mpeg:dash:schema:mpd:2011";
+const FULL_PROFILE: &str = "urn:mpeg:dash:profile:full:2011";
+const ROLE_SCHEME: &str = "urn:mpeg:dash:role:2011";
+const CHANNEL_CONFIGURATION_SCHEME: &str = "urn:mpeg:dash:23003:3:audio_channel_configuration:2011";
+
+impl MediaPlaylist {
+    /// Converts the playlist into the `<Period>` element of a static MPD, with one adaptation set
+    /// holding one representation. The bandwidth of the representation is the average bit rate
+    /// if every segment has a byte range, and `0` otherwise since segment sizes aren't known.
+    ///
+    /// Returns an error for anything a single static period can't express: live playlists, delta
+    /// updates, discontinuities, encrypted segments and changing EXT-X-MAP tags.
+    pub fn to_mpd_period(&self) -> Result<String> {
+        let set = AdaptationSet {
+            content_type: None,
+            mime_type: mime_type(self, MediaType::Video)?,
+            language: None,
+            descriptors: Vec::new(),
+            representations: vec![(attribute("bandwidth", bandwidth(self)), self)],
+        };
+        let mut xml = Xml::default();
+        write_period(&mut xml, &[set], Some(total_duration(self)))?;
+        Ok(xml.out)
+    }
+}
+
+impl MasterPlaylist {
+    /// Converts the playlist into a static MPD with a single period: one adaptation set for the
+    /// variants (or one per container format) and one for each audio, video or subtitle rendition
+    /// with its own playlist. `media` returns the media playlist for a URI of the master playlist.
+    ///
+    /// Returns an error if a media playlist is missing, or for anything
+    /// [`MediaPlaylist::to_mpd_period`] can't express.
+    pub fn to_mpd<'a>(&self, media: impl Fn(&str) -> Option<&'a MediaPlaylist>) -> Result<String> {
+        let lookup = |uri: &str| media(uri).ok_or_else(|| anyhow::anyhow!("No media playlist for {}", uri));
+
+        let mut sets: Vec<AdaptationSet> = Vec::new();
+        for variant in self.variants() {
+            let playlist = lookup(variant.uri())?;
+            let mime_type = mime_type(playlist, MediaType::Video)?;
+            let mut attributes = attribute("bandwidth", variant.bandwidth());
+            if let Some(codecs) = variant.codecs() {
+                attributes += &attribute("codecs", codecs);
+            }
+            if let Some(resolution) = variant.resolution() {
+                attributes += &attribute("width", resolution.width);
+                attributes += &attribute("height", resolution.height);
+            }
+            if let Some(frame_rate) = variant.frame_rate() {
+                attributes += &attribute("frameRate", frame_rate_ratio(frame_rate));
+            }
+            match sets.iter_mut().find(|x| x.mime_type == mime_type && x.language.is_none()) {
+                Some(set) => set.representations.push((attributes, playlist)),
+                None => sets.push(AdaptationSet {
+                    content_type: None,
+                    mime_type,
+                    language: None,
+                    descriptors: Vec::new(),
+                    representations: vec![(attributes, playlist)],
+                }),
+            }
+        }
+        for rendition in self.renditions() {
+            //closed captions are carried in the video, and other renditions without a URI too
+            let Some(uri) = rendition.uri().filter(|_| rendition.media_type() != MediaType::ClosedCaptions) else {
+                continue;
+            };
+            let playlist = lookup(uri)?;
+            let mut descriptors = Vec::new();
+            if let Some(channels) = rendition.channels() {
+                descriptors.push(format!(
+                    "AudioChannelConfiguration{}{}/",
+                    attribute("schemeIdUri", CHANNEL_CONFIGURATION_SCHEME),
+                    attribute("value", channels.count)
+                ));
+            }
+            if rendition.is_default() {
+                descriptors.push(format!("Role{}{}/", attribute("schemeIdUri", ROLE_SCHEME), attribute("value", "main")));
+            }
+            descriptors.push(format!("Label>{}</Label", escape(rendition.name())));
+            sets.push(AdaptationSet {
+                content_type: Some(match rendition.media_type() {
+                    MediaType::Audio => "audio",
+                    MediaType::Subtitles => "text",
+                    _ => "video",
+                }),
+                mime_type: mime_type(playlist, rendition.media_type())?,
+                language: rendition.language().map(|x| x.as_str().to_string()),
+                descriptors,
+                representations: vec![(attribute("bandwidth", bandwidth(playlist)), playlist)],
+            });
+        }
+
+        let playlists = sets.iter().flat_map(|x| x.representations.iter().map(|(_, playlist)| *playlist));
+        let duration = playlists.clone().map(total_duration).max().unwrap_or_default();
+        let min_buffer_time = playlists.map(MediaPlaylist::target_duration).max().unwrap_or_default();
+
+        let mut xml = Xml::default();
+        xml.line(XML_DECLARATION);
+        xml.open(&format!(
+            "MPD{}{}{}{}{}",
+            attribute("xmlns", MPD_NAMESPACE),
+            attribute("profiles", FULL_PROFILE),
+            attribute("type", "static"),
+            attribute("mediaPresentationDuration", iso_duration(duration)),
+            attribute("minBufferTime", iso_duration(min_buffer_time))
+        ));
+        write_period(&mut xml, &sets, None)?;
+        xml.close("MPD");
+        Ok(xml.out)
+    }
+}
+
+/// An `<AdaptationSet>` with the attributes of each representation and its media playlist.
+struct AdaptationSet<'a> {
+    /// Defaults to the type of the MIME type, which for MPEG-TS is always video.
+    content_type: Option<&'static str>,
+    mime_type: &'static str,
+    language: Option<String>,
+
+    /// Child elements ahead of the representations, without the outer `<` and `>`.
+    descriptors: Vec<String>,
+
+    representations: Vec<(String, &'a MediaPlaylist)>,
+}
+
+fn write_period(xml: &mut Xml, sets: &[AdaptationSet], duration: Option<Duration>) -> Result<()> {
+    let mut period = format!("Period{}{}", attribute("id", 0), attribute("start", "PT0S"));
+    if let Some(duration) = duration {
+        period += &attribute("duration", iso_duration(duration));
+    }
+    xml.open(&period);
+    let mut representation_id = 0;
+    for (id, set) in sets.iter().enumerate() {
+        let content_type = set.content_type.unwrap_or_else(|| set.mime_type.split('/').next().unwrap_or_default());
+        let mut attributes = attribute("id", id) + &attribute("contentType", content_type);
+        if let Some(language) = &set.language {
+            attributes += &attribute("lang", language);
+        }
+        attributes += &attribute("mimeType", set.mime_type);
+        xml.open(&format!("AdaptationSet{}", attributes));
+        for descriptor in &set.descriptors {
+            xml.line(&format!("<{}>", descriptor));
+        }
+        for (attributes, playlist) in &set.representations {
+            xml.open(&format!("Representation{}{}", attribute("id", representation_id), attributes));
+            write_segments(xml, playlist)?;
+            xml.close("Representation");
+            representation_id += 1;
+        }
+        xml.close("AdaptationSet");
+    }
+    xml.close("Period");
+    Ok(())
+}
+
+/// Writes a `<SegmentTemplate>` if the segment URLs are numbered consecutively and whole
+/// resources, otherwise a `<SegmentList>`. Either way a timeline gives the exact durations.
+fn write_segments(xml: &mut Xml, playlist: &MediaPlaylist) -> Result<()> {
+    if !playlist.ended() {
+        return Err(anyhow::Error::msg("Can't export a live playlist to a static MPD"));
+    }
+    if playlist.skipped_segments() > 0 {
+        return Err(anyhow::Error::msg("Can't export a delta update"));
+    }
+    let segments = playlist.segments();
+    if segments.iter().skip(1).any(MediaSegment::discontinuity) {
+        return Err(anyhow::Error::msg("Can't export discontinuities, which would need a period each"));
+    }
+    if segments.iter().any(|x| !x.keys().is_empty()) {
+        return Err(anyhow::Error::msg("Can't export encrypted segments, DASH has no equivalent of EXT-X-KEY"));
+    }
+    let map = segments.first().and_then(MediaSegment::map);
+    if segments.iter().any(|x| x.map() != map) {
+        return Err(anyhow::Error::msg("Can't export segments with different EXT-X-MAP tags"));
+    }
+
+    //exact for durations with up to nine decimals
+    let scale = segments.iter().map(|x| x.exact_duration().scale()).max().unwrap_or(0).min(9);
+    let timescale = attribute("timescale", 10u64.pow(scale));
+    let durations: Vec<u128> = segments.iter().map(|x| x.duration().as_nanos() / 10u128.pow(9 - scale)).collect();
+
+    let whole_resources = segments.iter().all(|x| x.byte_range().is_none()) && map.is_none_or(|x| x.byte_range().is_none());
+    match number_template(segments).filter(|_| whole_resources) {
+        Some((media, start_number)) => {
+            let mut attributes = timescale + &attribute("media", media) + &attribute("startNumber", start_number);
+            if let Some(map) = map {
+                attributes += &attribute("initialization", map.uri().replace('$', "$$"));
+            }
+            xml.open(&format!("SegmentTemplate{}", attributes));
+            write_timeline(xml, &durations);
+            xml.close("SegmentTemplate");
+        }
+        None => {
+            xml.open(&format!("SegmentList{}", timescale));
+            if let Some(map) = map {
+                xml.line(&format!("<Initialization{}/>", resource_attributes(|| format!("map {}", map.uri()), "sourceURL", map.uri(), map.byte_range())?));
+            }
+            write_timeline(xml, &durations);
+            for (index, (segment, byte_range)) in segments.iter().zip(playlist.resolved_byte_ranges()).enumerate() {
+                let what = || format!("segment {}", index + 1);
+                let attributes = resource_attributes(what, "media", segment.url().as_str(), byte_range)?;
+                xml.line(&format!("<SegmentURL{}/>", attributes));
+            }
+            xml.close("SegmentList");
+        }
+    }
+    Ok(())
+}
+
+/// The URL attribute of a segment or initialization section, with a range attribute for its
+/// byte range. `what` names the resource in errors.
+fn resource_attributes(
+    what: impl FnOnce() -> String,
+    url_attribute: &str,
+    url: &str,
+    byte_range: Option<ByteRange>,
+) -> Result<String> {
+    let mut attributes = attribute(url_attribute, url);
+    if let Some(byte_range) = byte_range {
+        let Some(range) = byte_range.to_http_range_header() else {
+            return Err(anyhow::anyhow!("Can't export byte range {} of {} without a known offset", byte_range, what()));
+        };
+        let name = if url_attribute == "media" { "mediaRange" } else { "range" };
+        attributes += &attribute(name, range.trim_start_matches("bytes="));
+    }
+    Ok(attributes)
+}
+
+/// Writes durations as `<S>` elements, repeating equal ones with `r`.
+fn write_timeline(xml: &mut Xml, durations: &[u128]) {
+    let mut runs: Vec<(u128, usize)> = Vec::new();
+    for duration in durations {
+        match runs.last_mut() {
+            Some((last, count)) if last == duration => *count += 1,
+            _ => runs.push((*duration, 1)),
+        }
+    }
+    xml.open("SegmentTimeline");
+    for (index, (duration, count)) in runs.into_iter().enumerate() {
+        let mut attributes = if index == 0 { attribute("t", 0) } else { String::new() };
+        attributes += &attribute("d", duration);
+        if count > 1 {
+            attributes += &attribute("r", count - 1);
+        }
+        xml.line(&format!("<S{}/>", attributes));
+    }
+    xml.close("SegmentTimeline");
+}
+
+/// `media` template and start number if the segment URLs differ only in a consecutive number,
+/// e.g. `video_7.ts`, `video_8.ts`. Zero-padded numbers keep their width.
+fn number_template(segments: &[MediaSegment]) -> Option<(String, u64)> {
+    let first = segments.first()?.url().as_str();
+    //numbers usually come right before the extension, so try the last one first
+    let mut end = first.len();
+    while let Some(digits_end) = first[..end].rfind(|x: char| x.is_ascii_digit()).map(|x| x + 1) {
+        let start = first[..digits_end].rfind(|x: char| !x.is_ascii_digit()).map_or(0, |x| x + 1);
+        let (prefix, digits, suffix) = (&first[..start], &first[start..digits_end], &first[digits_end..]);
+        let width = digits.len();
+        let padded = width > 1 && digits.starts_with('0');
+        if let Ok(start_number) = digits.parse::<u64>() {
+            let numbered = segments.iter().enumerate().all(|(index, segment)| {
+                let number = start_number + index as u64;
+                let number = if padded { format!("{:0width$}", number) } else { number.to_string() };
+                segment.url().as_str() == format!("{}{}{}", prefix, number, suffix)
+            });
+            if numbered {
+                let placeholder = if padded { format!("$Number%0{}d$", width) } else { "$Number$".to_string() };
+                let media = format!("{}{}{}", prefix.replace('$', "$$"), placeholder, suffix.replace('$', "$$"));
+                return Some((media, start_number));
+            }
+        }
+        end = start;
+    }
+    None
+}
+
+/// MIME type of the container, from the extension of the first segment's URL.
+fn mime_type(playlist: &MediaPlaylist, media_type: MediaType) -> Result<&'static str> {
+    let Some(segment) = playlist.segments().first() else {
+        return Err(anyhow::Error::msg("Can't export a playlist without segments"));
+    };
+    let path = segment.url().path();
+    let extension = path.rsplit_once('.').map(|(_, x)| x.to_ascii_lowercase()).unwrap_or_default();
+    let audio = media_type == MediaType::Audio;
+    Ok(match extension.as_str() {
+        "ts" => "video/mp2t",
+        "mp4" | "m4s" | "m4v" | "cmfv" if audio => "audio/mp4",
+        "mp4" | "m4s" | "m4v" | "cmfv" => "video/mp4",
+        "m4a" | "cmfa" => "audio/mp4",
+        "vtt" | "webvtt" => "text/vtt",
+        "aac" => "audio/aac",
+        "ac3" => "audio/ac3",
+        "ec3" => "audio/eac3",
+        "mp3" => "audio/mpeg",
+        //fragmented MP4 needs a media initialization section
+        _ if segment.map().is_some() && audio => "audio/mp4",
+        _ if segment.map().is_some() => "video/mp4",
+        _ => return Err(anyhow::anyhow!("Can't tell the container of segment {}", segment.url())),
+    })
+}
+
+fn total_duration(playlist: &MediaPlaylist) -> Duration {
+    playlist.segments().iter().map(MediaSegment::duration).sum()
+}
+
+fn bandwidth(playlist: &MediaPlaylist) -> u64 {
+    average_bit_rate(playlist).map_or(0, |x| x.round() as u64)
+}
+
+/// `PT` duration in seconds, e.g. `PT634.566S`.
+fn iso_duration(duration: Duration) -> String {
+    let fraction = format!("{:09}", duration.subsec_nanos());
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        format!("PT{}S", duration.as_secs())
+    } else {
+        format!("PT{}.{}S", duration.as_secs(), fraction)
+    }
+}
+
+/// Frame rate as DASH writes it: whole numbers as they are, NTSC rates like 29.97 as `30000/1001`.
+fn frame_rate_ratio(frame_rate: f64) -> String {
+    let ntsc = (frame_rate * 1.001).round();
+    if (frame_rate - frame_rate.round()).abs() < 0.001 {
+        format!("{}", frame_rate.round())
+    } else if (ntsc / 1.001 - frame_rate).abs() < 0.001 {
+        format!("{}/1001", ntsc * 1000.0)
+    } else {
+        format!("{}/1000", (frame_rate * 1000.0).round())
+    }
+}
+
+/// ` name="value"`, escaped for XML.
+fn attribute(name: &str, value: impl ToString) -> String {
+    format!(r#" {}="{}""#, name, escape(&value.to_string()))
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Indented XML output, one element per line.
+#[derive(Debug, Default)]
+struct Xml {
+    out: String,
+    depth: usize,
+}
+
+impl Xml {
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.out.push_str("  ");
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    /// Starts an element, written without the `<` and `>`.
+    fn open(&mut self, element: &str) {
+        self.line(&format!("<{}>", element));
+        self.depth += 1;
+    }
+
+    fn close(&mut self, name: &str) {
+        self.depth -= 1;
+        self.line(&format!("</{}>", name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn playlist(file: &str) -> MediaPlaylist {
+        MediaPlaylist::parse_ext_m3u(file).expect("test playlist should parse")
+    }
+
+    #[test]
+    fn exports_segment_template() {
+        let master = MasterPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",LANGUAGE="en",NAME="English & more",DEFAULT=YES,CHANNELS="2",URI="en.m3u8"
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000,CODECS="avc1.4d401f,mp4a.40.2",RESOLUTION=640x360,FRAME-RATE=29.970,AUDIO="aac"
+            low.m3u8
+        "#})
+        .unwrap();
+        let video = playlist(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-VERSION:6
+            #EXT-X-TARGETDURATION:6
+            #EXT-X-MAP:URI="init.mp4"
+            #EXTINF:6.006,
+            low_009.m4s
+            #EXTINF:6.006,
+            low_010.m4s
+            #EXTINF:2.5,
+            low_011.m4s
+            #EXT-X-ENDLIST
+        "#});
+        let audio = playlist(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-VERSION:4
+            #EXT-X-TARGETDURATION:6
+            #EXTINF:6,
+            #EXT-X-BYTERANGE:48000@0
+            en.aac
+            #EXTINF:6,
+            #EXT-X-BYTERANGE:48000
+            en.aac
+            #EXT-X-ENDLIST
+        "#});
+        let playlists = HashMap::from([("low.m3u8", video), ("en.m3u8", audio)]);
+        let mpd = master.to_mpd(|uri| playlists.get(uri)).expect("should export");
+        assert_eq!(
+            mpd,
+            indoc::indoc! {r#"
+                <?xml version="1.0" encoding="UTF-8"?>
+                <MPD xmlns="urn:mpeg:dash:schema:mpd:2011" profiles="urn:mpeg:dash:profile:full:2011" type="static" mediaPresentationDuration="PT14.512S" minBufferTime="PT6S">
+                  <Period id="0" start="PT0S">
+                    <AdaptationSet id="0" contentType="video" mimeType="video/mp4">
+                      <Representation id="0" bandwidth="1280000" codecs="avc1.4d401f,mp4a.40.2" width="640" height="360" frameRate="30000/1001">
+                        <SegmentTemplate timescale="1000" media="low_$Number%03d$.m4s" startNumber="9" initialization="init.mp4">
+                          <SegmentTimeline>
+                            <S t="0" d="6006" r="1"/>
+                            <S d="2500"/>
+                          </SegmentTimeline>
+                        </SegmentTemplate>
+                      </Representation>
+                    </AdaptationSet>
+                    <AdaptationSet id="1" contentType="audio" lang="en" mimeType="audio/aac">
+                      <AudioChannelConfiguration schemeIdUri="urn:mpeg:dash:23003:3:audio_channel_configuration:2011" value="2"/>
+                      <Role schemeIdUri="urn:mpeg:dash:role:2011" value="main"/>
+                      <Label>English &amp; more</Label>
+                      <Representation id="1" bandwidth="64000">
+                        <SegmentList timescale="1">
+                          <SegmentTimeline>
+                            <S t="0" d="6" r="1"/>
+                          </SegmentTimeline>
+                          <SegmentURL media="en.aac" mediaRange="0-47999"/>
+                          <SegmentURL media="en.aac" mediaRange="48000-95999"/>
+                        </SegmentList>
+                      </Representation>
+                    </AdaptationSet>
+                  </Period>
+                </MPD>
+            "#}
+        );
+        assert_eq!(master.to_mpd(|_| None).unwrap_err().to_string(), "No media playlist for low.m3u8");
+    }
+
+    #[test]
+    fn exports_period() {
+        let period = playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10,\nintro.ts\n#EXTINF:9.5,\nmain.ts\n#EXT-X-ENDLIST\n")
+            .to_mpd_period()
+            .unwrap();
+        assert_eq!(
+            period,
+            indoc::indoc! {r#"
+                <Period id="0" start="PT0S" duration="PT19.5S">
+                  <AdaptationSet id="0" contentType="video" mimeType="video/mp2t">
+                    <Representation id="0" bandwidth="0">
+                      <SegmentList timescale="10">
+                        <SegmentTimeline>
+                          <S t="0" d="100"/>
+                          <S d="95"/>
+                        </SegmentTimeline>
+                        <SegmentURL media="intro.ts"/>
+                        <SegmentURL media="main.ts"/>
+                      </SegmentList>
+                    </Representation>
+                  </AdaptationSet>
+                </Period>
+            "#}
+        );
+    }
+
+    #[test]
+    fn rejects_what_a_static_period_cant_express() {
+        let error = |file: &str| playlist(file).to_mpd_period().unwrap_err().to_string();
+        assert_eq!(error("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10,\n1.ts\n"), "Can't export a live playlist to a static MPD");
+        assert_eq!(
+            error("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10,\n1.ts\n#EXT-X-DISCONTINUITY\n#EXTINF:10,\n2.ts\n#EXT-X-ENDLIST\n"),
+            "Can't export discontinuities, which would need a period each"
+        );
+        assert_eq!(
+            error("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXT-X-KEY:METHOD=AES-128,URI=\"k\"\n#EXTINF:10,\n1.ts\n#EXT-X-ENDLIST\n"),
+            "Can't export encrypted segments, DASH has no equivalent of EXT-X-KEY"
+        );
+        //the parser rejects implicit offsets without a previous sub-range, but the model allows them
+        let mut unknown_offset = playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10,\n1.ts\n#EXT-X-ENDLIST\n");
+        unknown_offset.segments_mut()[0].set_byte_range(Some(ByteRange { length: 100, offset: None }));
+        assert_eq!(
+            unknown_offset.to_mpd_period().unwrap_err().to_string(),
+            "Can't export byte range 100 of segment 1 without a known offset"
+        );
+    }
+}
@@ -0,0 +1,325 @@
+//! Import of static MPEG-DASH manifests whose segments are listed with `<SegmentList>`, so DASH
+//! VOD libraries can be re-published as HLS.
+
+use core::fmt::Write;
+use std::collections::HashSet;
+
+use anyhow::Result;
+use roxmltree::{Document, Node};
+
+use crate::assemble::{master_playlist, quoted, Entry};
+use crate::{MasterPlaylist, MediaPlaylist, MediaType, SegmentUri};
+
+/// HLS playlists converted from an MPD by [`MasterPlaylist::from_mpd`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MpdImport {
+    pub master: MasterPlaylist,
+
+    /// Media playlist of each representation, with the URI the master playlist refers to it by.
+    /// URIs are made from the representation IDs, e.g. `video-1.m3u8`.
+    pub media_playlists: Vec<(String, MediaPlaylist)>,
+}
+
+impl MasterPlaylist {
+    /// Converts a static MPD with a single period into a master playlist and a media playlist for
+    /// each representation. Video representations become variants, and audio and text adaptation
+    /// sets renditions in the `audio` and `subs` groups. Initialization sections become EXT-X-MAP
+    /// tags and media ranges EXT-X-BYTERANGE tags, with URLs resolved against any `<BaseURL>`.
+    ///
+    /// Returns an error for dynamic MPDs, MPDs with several periods, and representations whose
+    /// segments aren't listed with a `<SegmentList>`.
+    pub fn from_mpd(mpd: &str) -> Result<MpdImport> {
+        let document = Document::parse(mpd).map_err(|error| anyhow::anyhow!("Invalid MPD: {}", error))?;
+        let root = document.root_element();
+        if root.tag_name().name() != "MPD" {
+            return Err(anyhow::Error::msg("Input isn't an MPD"));
+        }
+        if root.attribute("type").is_some_and(|x| x != "static") {
+            return Err(anyhow::Error::msg("Can't import a dynamic MPD"));
+        }
+        let mut periods = elements(root, "Period");
+        let Some(period) = periods.next() else {
+            return Err(anyhow::Error::msg("MPD has no period"));
+        };
+        if periods.next().is_some() {
+            return Err(anyhow::Error::msg("Can't import an MPD with more than 1 period"));
+        }
+
+        let base = base_url(root, "");
+        let base = base_url(period, &base);
+        let mut entries: Vec<Entry> = Vec::new();
+        let mut media_playlists: Vec<(String, MediaPlaylist)> = Vec::new();
+        let mut uris: HashSet<String> = HashSet::new();
+        for set in elements(period, "AdaptationSet") {
+            let set_base = base_url(set, &base);
+            let label = elements(set, "Label").next().and_then(|x| x.text()).map(str::trim);
+            let main = elements(set, "Role").any(|x| x.attribute("value") == Some("main"));
+            for node in elements(set, "Representation") {
+                let inherited = |name: &str| node.attribute(name).or_else(|| set.attribute(name));
+                let id = node.attribute("id").map_or_else(|| format!("representation-{}", entries.len()), str::to_string);
+                let content_type = match set.attribute("contentType").or_else(|| inherited("mimeType")?.split('/').next()) {
+                    Some("video") => MediaType::Video,
+                    Some("audio") => MediaType::Audio,
+                    Some("text") => MediaType::Subtitles,
+                    _ => return Err(anyhow::anyhow!("Can't tell the content type of representation {}", id)),
+                };
+                let Some(segment_list) = [node, set, period].into_iter().find_map(|x| elements(x, "SegmentList").next()) else {
+                    return Err(anyhow::anyhow!("Representation {} has no SegmentList", id));
+                };
+                let playlist = media_playlist(segment_list, &base_url(node, &set_base))
+                    .map_err(|error| error.context(format!("Representation {} can't be imported", id)))?;
+
+                let mut uri = format!("{}.m3u8", file_name(&id));
+                while !uris.insert(uri.clone()) {
+                    uri = format!("{}-{}.m3u8", file_name(&id), uris.len());
+                }
+                let frame_rate = inherited("frameRate").and_then(|x| match x.split_once('/') {
+                    Some((frames, seconds)) => Some(frames.parse::<f64>().ok()? / seconds.parse::<f64>().ok()?),
+                    None => x.parse().ok(),
+                });
+                let resolution = inherited("width").zip(inherited("height"));
+                media_playlists.push((uri.clone(), playlist));
+                entries.push(Entry {
+                    uri,
+                    media_type: content_type,
+                    i_frames_only: false,
+                    name: label.or(inherited("lang")).unwrap_or(&id).to_string(),
+                    language: inherited("lang").map(str::to_string),
+                    default: main,
+                    bandwidth: node.attribute("bandwidth").and_then(|x| x.parse().ok()).unwrap_or(0),
+                    average_bandwidth: None,
+                    codecs: inherited("codecs").map(str::to_string),
+                    resolution: resolution.map(|(width, height)| format!("{}x{}", width, height).parse()).transpose()?,
+                    frame_rate,
+                });
+            }
+        }
+
+        let master = master_playlist(&entries, 0)?;
+        Ok(MpdImport { master, media_playlists })
+    }
+}
+
+/// Builds the media playlist for a `<SegmentList>`, with its durations from a
+/// `<SegmentTimeline>` or the `duration` attribute.
+fn media_playlist(segment_list: Node, base: &str) -> Result<MediaPlaylist> {
+    let timescale = match segment_list.attribute("timescale") {
+        Some(timescale) => timescale.parse::<u64>().ok().filter(|x| *x > 0).ok_or_else(|| anyhow::anyhow!("Invalid timescale {}", timescale))?,
+        None => 1,
+    };
+    let segment_urls: Vec<Node> = elements(segment_list, "SegmentURL").collect();
+    let durations = match elements(segment_list, "SegmentTimeline").next() {
+        Some(timeline) => timeline_durations(timeline)?,
+        None => match segment_list.attribute("duration").map(str::parse::<u64>) {
+            Some(Ok(duration)) => vec![duration; segment_urls.len()],
+            Some(Err(_)) => return Err(anyhow::Error::msg("Invalid SegmentList duration")),
+            None => return Err(anyhow::Error::msg("SegmentList has neither a SegmentTimeline nor a duration")),
+        },
+    };
+    if durations.len() < segment_urls.len() {
+        return Err(anyhow::anyhow!("SegmentTimeline has {} segments but there are {} SegmentURLs", durations.len(), segment_urls.len()));
+    }
+
+    let mut version = 1;
+    let mut segments = String::new();
+    if let Some(initialization) = elements(segment_list, "Initialization").next() {
+        let uri = resolve(base, initialization.attribute("sourceURL").unwrap_or_default());
+        write!(segments, "#EXT-X-MAP:URI=\"{}\"", quoted(&uri)).unwrap();
+        if let Some(range) = initialization.attribute("range") {
+            write!(segments, ",BYTERANGE=\"{}\"", byte_range(range)?).unwrap();
+        }
+        segments.push('\n');
+        version = 6;
+    }
+    let mut target_duration = 1;
+    for (segment_url, duration) in segment_urls.iter().zip(durations) {
+        let extinf = decimal(duration, timescale);
+        if extinf.contains('.') {
+            version = version.max(3);
+        }
+        //EXTINF durations rounded to the nearest integer must not exceed the target duration
+        target_duration = target_duration.max((u128::from(duration) * 2 + u128::from(timescale)) / (u128::from(timescale) * 2));
+        writeln!(segments, "#EXTINF:{},", extinf).unwrap();
+        if let Some(range) = segment_url.attribute("mediaRange") {
+            writeln!(segments, "#EXT-X-BYTERANGE:{}", byte_range(range)?).unwrap();
+            version = version.max(4);
+        }
+        let url = resolve(base, segment_url.attribute("media").unwrap_or_default());
+        if url.is_empty() || url.contains(['\r', '\n']) {
+            return Err(anyhow::anyhow!("Invalid segment URL {:?}", url));
+        }
+        writeln!(segments, "{}", url).unwrap();
+    }
+
+    let mut file = String::from("#EXTM3U\n");
+    if version > 1 {
+        writeln!(file, "#EXT-X-VERSION:{}", version).unwrap();
+    }
+    writeln!(file, "#EXT-X-TARGETDURATION:{}\n{}#EXT-X-ENDLIST", target_duration, segments).unwrap();
+    MediaPlaylist::parse_ext_m3u(&file)
+}
+
+/// Duration of each segment in a `<SegmentTimeline>`, expanding repeats.
+fn timeline_durations(timeline: Node) -> Result<Vec<u64>> {
+    let mut durations = Vec::new();
+    for s in elements(timeline, "S") {
+        let Some(Ok(duration)) = s.attribute("d").map(str::parse::<u64>) else {
+            return Err(anyhow::Error::msg("SegmentTimeline S element has no valid duration"));
+        };
+        let repeat = match s.attribute("r").map(str::parse::<i64>) {
+            None => 0,
+            Some(Ok(repeat)) if repeat >= 0 => repeat as usize,
+            //-1 repeats until the end of the period, whose length a static MPD may not give
+            Some(_) => return Err(anyhow::Error::msg("Can't import SegmentTimeline with a negative or invalid repeat")),
+        };
+        durations.extend(core::iter::repeat_n(duration, repeat + 1));
+    }
+    Ok(durations)
+}
+
+/// `first-last` as an HLS byte range, `<length>@<offset>`.
+fn byte_range(range: &str) -> Result<String> {
+    let invalid = || anyhow::anyhow!("Invalid byte range {}", range);
+    let (first, last) = range.split_once('-').ok_or_else(invalid)?;
+    let (first, last) = (first.parse::<u64>().map_err(|_| invalid())?, last.parse::<u64>().map_err(|_| invalid())?);
+    let length = last.checked_sub(first).ok_or_else(invalid)? + 1;
+    Ok(format!("{}@{}", length, first))
+}
+
+/// `value / timescale` as an EXTINF duration, exact if the timescale is a power of ten. Otherwise
+/// it is rounded to the millisecond, without trailing zeros.
+fn decimal(value: u64, timescale: u64) -> String {
+    let digits = timescale.ilog10();
+    if 10u64.pow(digits) == timescale {
+        return match digits {
+            0 => value.to_string(),
+            _ => format!("{}.{:0width$}", value / timescale, value % timescale, width = digits as usize),
+        };
+    }
+    let millis = (u128::from(value) * 1000 + u128::from(timescale) / 2) / u128::from(timescale);
+    let fraction = format!("{:03}", millis % 1000);
+    match fraction.trim_end_matches('0') {
+        "" => (millis / 1000).to_string(),
+        fraction => format!("{}.{}", millis / 1000, fraction),
+    }
+}
+
+/// Resolves a `<BaseURL>` or segment URL against the base URL in effect.
+fn resolve(base: &str, reference: &str) -> String {
+    SegmentUri::new(reference.trim()).resolve(base).into_string()
+}
+
+/// The base URL for `node`'s children: its first `<BaseURL>` resolved against `base`.
+fn base_url(node: Node, base: &str) -> String {
+    match elements(node, "BaseURL").next().and_then(|x| x.text()) {
+        Some(url) => resolve(base, url),
+        None => base.to_string(),
+    }
+}
+
+fn elements<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> impl Iterator<Item = Node<'a, 'input>> + Clone {
+    node.children().filter(move |x| x.is_element() && x.tag_name().name() == name)
+}
+
+/// A representation ID made safe for a file name.
+fn file_name(id: &str) -> String {
+    id.chars().map(|x| if x.is_ascii_alphanumeric() || matches!(x, '-' | '_' | '.') { x } else { '_' }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MPD: &str = indoc::indoc! {r#"
+        <?xml version="1.0" encoding="UTF-8"?>
+        <MPD xmlns="urn:mpeg:dash:schema:mpd:2011" type="static" mediaPresentationDuration="PT12S" minBufferTime="PT2S">
+          <BaseURL>https://cdn.example.com/vod/</BaseURL>
+          <Period>
+            <AdaptationSet contentType="video" mimeType="video/mp4" codecs="avc1.4d401f" frameRate="30000/1001">
+              <Representation id="video 1" bandwidth="1000000" width="640" height="360">
+                <BaseURL>video/</BaseURL>
+                <SegmentList timescale="90000" duration="540000">
+                  <Initialization sourceURL="init.mp4" range="0-719"/>
+                  <SegmentURL media="main.mp4" mediaRange="720-10719"/>
+                  <SegmentURL media="main.mp4" mediaRange="10720-20719"/>
+                </SegmentList>
+              </Representation>
+            </AdaptationSet>
+            <AdaptationSet contentType="audio" mimeType="audio/mp4" codecs="mp4a.40.2" lang="en">
+              <Role schemeIdUri="urn:mpeg:dash:role:2011" value="main"/>
+              <Label>English</Label>
+              <Representation id="audio" bandwidth="64000">
+                <SegmentList timescale="1000">
+                  <SegmentTimeline>
+                    <S t="0" d="6400"/>
+                    <S d="5600"/>
+                  </SegmentTimeline>
+                  <SegmentURL media="/audio/1.m4a"/>
+                  <SegmentURL media="/audio/2.m4a"/>
+                </SegmentList>
+              </Representation>
+            </AdaptationSet>
+          </Period>
+        </MPD>
+    "#};
+
+    #[test]
+    fn imports_segment_lists() {
+        let import = MasterPlaylist::from_mpd(MPD).expect("should import");
+        assert_eq!(
+            import.master.to_string(),
+            indoc::indoc! {r#"
+                #EXTM3U
+                #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="audio",LANGUAGE="en",NAME="English",DEFAULT=YES,AUTOSELECT=YES,URI="audio.m3u8"
+                #EXT-X-STREAM-INF:BANDWIDTH=1064000,CODECS="avc1.4d401f,mp4a.40.2",RESOLUTION=640x360,FRAME-RATE=29.970,AUDIO="audio"
+                video_1.m3u8
+            "#}
+        );
+        let uris: Vec<&str> = import.media_playlists.iter().map(|(uri, _)| uri.as_str()).collect();
+        assert_eq!(uris, vec!["video_1.m3u8", "audio.m3u8"]);
+        assert_eq!(
+            import.media_playlists[0].1.to_string(),
+            indoc::indoc! {r#"
+                #EXTM3U
+                #EXT-X-VERSION:6
+                #EXT-X-TARGETDURATION:6
+                #EXT-X-MAP:URI="https://cdn.example.com/vod/video/init.mp4",BYTERANGE="720@0"
+                #EXT-X-BYTERANGE:10000@720
+                #EXTINF:6,
+                https://cdn.example.com/vod/video/main.mp4
+                #EXT-X-BYTERANGE:10000@10720
+                #EXTINF:6,
+                https://cdn.example.com/vod/video/main.mp4
+                #EXT-X-ENDLIST
+            "#}
+        );
+        let audio = &import.media_playlists[1].1;
+        assert!(audio.diagnostics().is_empty());
+        assert_eq!(audio.target_duration().as_secs(), 6);
+        let urls: Vec<&str> = audio.segments().iter().map(|x| x.url().as_str()).collect();
+        assert_eq!(urls, vec!["https://cdn.example.com/audio/1.m4a", "https://cdn.example.com/audio/2.m4a"]);
+        assert_eq!(audio.segments()[0].exact_duration().to_string(), "6.400");
+    }
+
+    #[test]
+    fn rejects_unsupported_mpds() {
+        let error = |mpd: &str| MasterPlaylist::from_mpd(mpd).unwrap_err().to_string();
+        assert_eq!(error(r#"<MPD type="dynamic"><Period/></MPD>"#), "Can't import a dynamic MPD");
+        assert_eq!(error("<MPD><Period/><Period/></MPD>"), "Can't import an MPD with more than 1 period");
+        assert_eq!(
+            error(r#"<MPD><Period><AdaptationSet contentType="video"><Representation id="1"><SegmentTemplate/></Representation></AdaptationSet></Period></MPD>"#),
+            "Representation 1 has no SegmentList"
+        );
+        assert!(error("<MPD>").starts_with("Invalid MPD"));
+    }
+
+    #[test]
+    fn rounds_non_decimal_timescales() {
+        assert_eq!(decimal(540000, 90000), "6");
+        assert_eq!(decimal(1001, 30000), "0.033");
+        assert_eq!(decimal(64, 10), "6.4");
+        assert_eq!(decimal(6, 1), "6");
+        assert_eq!(resolve("https://cdn.example.com/a/b.mpd", "/c/1.ts"), "https://cdn.example.com/c/1.ts");
+        assert_eq!(resolve("video/", "1.ts"), "video/1.ts");
+    }
+}
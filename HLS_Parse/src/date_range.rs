@@ -0,0 +1,421 @@
+//! Date ranges of a media playlist, e.g. ad breaks, program boundaries and interstitials. See
+//! <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2.7>.
+
+use core::fmt;
+use core::time::Duration;
+
+use anyhow::Result;
+
+use crate::attributes::{self, AttributeList, RawAttributes};
+use crate::{MediaPlaylist, ProgramDateTime, SegmentDuration};
+
+/// A range of time with attributes, from an EXT-X-DATERANGE tag.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DateRange {
+    /// Identifies the range, which other tags with the same ID add attributes to.
+    id: String,
+
+    /// Client-defined kind of range, whose attributes follow the same semantics, e.g.
+    /// `com.apple.hls.interstitial`.
+    class: Option<String>,
+    start_date: ProgramDateTime,
+
+    /// When to trigger actions for the range, e.g. `PRE` or `POST,ONCE`. See
+    /// <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.5.1>.
+    cue: Option<String>,
+    end_date: Option<ProgramDateTime>,
+    duration: Option<SegmentDuration>,
+
+    /// Expected duration, while the actual one isn't known yet.
+    planned_duration: Option<SegmentDuration>,
+
+    /// Whether the range ends where the next one of the same class starts.
+    end_on_next: bool,
+
+    /// SCTE-35 splice info, as hexadecimal sequences. See
+    /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2.7.1>.
+    scte35_cmd: Option<String>,
+    scte35_out: Option<String>,
+    scte35_in: Option<String>,
+
+    /// `X-` attributes in their original order, with raw values, so quoted strings still have
+    /// their quotes.
+    client_attributes: Vec<(String, String)>,
+
+    /// The attribute list as parsed.
+    raw: RawAttributes,
+}
+
+impl DateRange {
+    pub fn new(id: impl Into<String>, start_date: ProgramDateTime) -> Self {
+        Self {
+            id: id.into(),
+            class: None,
+            start_date,
+            cue: None,
+            end_date: None,
+            duration: None,
+            planned_duration: None,
+            end_on_next: false,
+            scte35_cmd: None,
+            scte35_out: None,
+            scte35_in: None,
+            client_attributes: Vec::new(),
+            raw: RawAttributes::default(),
+        }
+    }
+
+    /// Parses the attribute list of an EXT-X-DATERANGE tag. Unknown attributes which aren't
+    /// client attributes are ignored.
+    pub(crate) fn parse(attribute_list: &str) -> Result<Self> {
+        let attributes = AttributeList::parse(attribute_list)?;
+        let Some(id) = attributes.quoted_string("ID")? else {
+            return Err(anyhow::Error::msg("Date range is missing ID attribute"));
+        };
+        let date = |name: &str| -> Result<Option<ProgramDateTime>> {
+            let Some(value) = attributes.quoted_string(name)? else {
+                return Ok(None);
+            };
+            value.parse().map(Some).map_err(|error: anyhow::Error| error.context(format!("Invalid {}", name)))
+        };
+        let duration = |name: &str| -> Result<Option<SegmentDuration>> {
+            let Some(value) = attributes.get(name) else {
+                return Ok(None);
+            };
+            value.parse().map(Some).map_err(|_| anyhow::anyhow!("Invalid {} {}", name, value))
+        };
+        let Some(start_date) = date("START-DATE")? else {
+            return Err(anyhow::Error::msg("Date range is missing START-DATE attribute"));
+        };
+        let end_on_next = match attributes.get("END-ON-NEXT") {
+            None => false,
+            Some("YES") => true,
+            Some(other) => return Err(anyhow::anyhow!("Invalid END-ON-NEXT {}", other)),
+        };
+
+        let date_range = Self {
+            id: id.to_string(),
+            class: attributes.quoted_string("CLASS")?.map(str::to_string),
+            start_date,
+            cue: attributes.quoted_string("CUE")?.map(str::to_string),
+            end_date: date("END-DATE")?,
+            duration: duration("DURATION")?,
+            planned_duration: duration("PLANNED-DURATION")?,
+            end_on_next,
+            scte35_cmd: attributes.get("SCTE35-CMD").map(str::to_string),
+            scte35_out: attributes.get("SCTE35-OUT").map(str::to_string),
+            scte35_in: attributes.get("SCTE35-IN").map(str::to_string),
+            client_attributes: attributes
+                .iter()
+                .filter(|(name, _)| name.starts_with("X-"))
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            raw: RawAttributes::new(attribute_list),
+        };
+        if date_range.end_date.is_some_and(|x| x < date_range.start_date) {
+            return Err(anyhow::anyhow!("Date range {} ends before it starts", date_range.id));
+        }
+        if date_range.end_on_next && date_range.class.is_none() {
+            return Err(anyhow::anyhow!("Date range {} has END-ON-NEXT but no CLASS", date_range.id));
+        }
+        if date_range.end_on_next && (date_range.duration.is_some() || date_range.end_date.is_some()) {
+            return Err(anyhow::anyhow!("Date range {} has END-ON-NEXT and an end", date_range.id));
+        }
+        Ok(date_range)
+    }
+
+    /// Raw value of the attribute `name` as parsed, see [`RawAttributes`][crate::RawAttributes].
+    /// Only from this tag, when others with the same ID add attributes.
+    pub fn raw_attribute(&self, name: &str) -> Option<&str> {
+        self.raw.get(name)
+    }
+
+    /// Every attribute of the EXT-X-DATERANGE tag as parsed.
+    pub fn raw_attributes(&self) -> &RawAttributes {
+        &self.raw
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn class(&self) -> Option<&str> {
+        self.class.as_deref()
+    }
+
+    pub fn start_date(&self) -> ProgramDateTime {
+        self.start_date
+    }
+
+    pub fn cue(&self) -> Option<&str> {
+        self.cue.as_deref()
+    }
+
+    pub fn end_date(&self) -> Option<ProgramDateTime> {
+        self.end_date
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration.map(|x| x.as_duration())
+    }
+
+    pub fn planned_duration(&self) -> Option<Duration> {
+        self.planned_duration.map(|x| x.as_duration())
+    }
+
+    pub fn end_on_next(&self) -> bool {
+        self.end_on_next
+    }
+
+    /// When the range ends: its END-DATE, or else its START-DATE plus DURATION. `None` if
+    /// neither is known yet.
+    pub fn end(&self) -> Option<ProgramDateTime> {
+        self.end_date.or_else(|| self.start_date.checked_add(self.duration()?))
+    }
+
+    pub fn scte35_cmd(&self) -> Option<&str> {
+        self.scte35_cmd.as_deref()
+    }
+
+    pub fn scte35_out(&self) -> Option<&str> {
+        self.scte35_out.as_deref()
+    }
+
+    pub fn scte35_in(&self) -> Option<&str> {
+        self.scte35_in.as_deref()
+    }
+
+    /// Value of the `X-` attribute, without quotes if it's a quoted string.
+    pub fn client_attribute(&self, name: &str) -> Option<&str> {
+        let (_, value) = self.client_attributes.iter().find(|(existing, _)| existing == name)?;
+        Some(value.strip_prefix('"').and_then(|x| x.strip_suffix('"')).unwrap_or(value))
+    }
+
+    /// Names and raw values of the `X-` attributes, in order.
+    pub fn client_attributes(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.client_attributes.iter().map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    pub fn set_class(&mut self, class: Option<String>) {
+        self.class = class;
+    }
+
+    pub fn set_cue(&mut self, cue: Option<String>) {
+        self.cue = cue;
+    }
+
+    pub fn set_end_date(&mut self, end_date: Option<ProgramDateTime>) {
+        self.end_date = end_date;
+    }
+
+    pub fn set_duration(&mut self, duration: Option<Duration>) {
+        self.duration = duration.map(SegmentDuration::from);
+    }
+
+    pub fn set_planned_duration(&mut self, planned_duration: Option<Duration>) {
+        self.planned_duration = planned_duration.map(SegmentDuration::from);
+    }
+
+    pub fn set_end_on_next(&mut self, end_on_next: bool) {
+        self.end_on_next = end_on_next;
+    }
+
+    pub fn set_scte35_cmd(&mut self, scte35_cmd: Option<String>) {
+        self.scte35_cmd = scte35_cmd;
+    }
+
+    pub fn set_scte35_out(&mut self, scte35_out: Option<String>) {
+        self.scte35_out = scte35_out;
+    }
+
+    pub fn set_scte35_in(&mut self, scte35_in: Option<String>) {
+        self.scte35_in = scte35_in;
+    }
+
+    /// Sets an `X-` attribute to a raw value, e.g. `"\"https://ads.example.com/1.m3u8\""` for a
+    /// quoted string, or removes it if `value` is `None`.
+    pub fn set_client_attribute(&mut self, name: &str, value: Option<String>) {
+        let existing = self.client_attributes.iter().position(|(x, _)| x == name);
+        match (existing, value) {
+            (Some(index), Some(value)) => self.client_attributes[index].1 = value,
+            (Some(index), None) => {
+                self.client_attributes.remove(index);
+            }
+            (None, Some(value)) => self.client_attributes.push((name.to_string(), value)),
+            (None, None) => {}
+        }
+    }
+
+    /// Adds the attributes of a later tag for the same range which this one doesn't have.
+    pub(crate) fn merge(&mut self, later: &DateRange) {
+        fn fill<T: Clone>(existing: &mut Option<T>, later: &Option<T>) {
+            if existing.is_none() {
+                existing.clone_from(later);
+            }
+        }
+        fill(&mut self.class, &later.class);
+        fill(&mut self.cue, &later.cue);
+        fill(&mut self.end_date, &later.end_date);
+        fill(&mut self.duration, &later.duration);
+        fill(&mut self.planned_duration, &later.planned_duration);
+        fill(&mut self.scte35_cmd, &later.scte35_cmd);
+        fill(&mut self.scte35_out, &later.scte35_out);
+        fill(&mut self.scte35_in, &later.scte35_in);
+        self.end_on_next |= later.end_on_next;
+        for (name, value) in &later.client_attributes {
+            if self.client_attributes.iter().all(|(existing, _)| existing != name) {
+                self.client_attributes.push((name.clone(), value.clone()));
+            }
+        }
+    }
+
+    /// Replaces the quoted URIs in client attributes, e.g. X-ASSET-URI, with `map` of them.
+    pub(crate) fn map_urls(&mut self, map: &mut dyn FnMut(&str) -> String) {
+        for (name, value) in &mut self.client_attributes {
+            if let Some(uri) = value.strip_prefix('"').and_then(|x| x.strip_suffix('"')) {
+                if attributes::is_uri_attribute(name) {
+                    *value = format!("\"{}\"", map(uri));
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for DateRange {
+    /// Formats the range as the attribute list of an EXT-X-DATERANGE tag.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ID=\"{}\"", self.id)?;
+        if let Some(class) = &self.class {
+            write!(f, ",CLASS=\"{}\"", class)?;
+        }
+        write!(f, ",START-DATE=\"{}\"", self.start_date)?;
+        if let Some(cue) = &self.cue {
+            write!(f, ",CUE=\"{}\"", cue)?;
+        }
+        if let Some(end_date) = self.end_date {
+            write!(f, ",END-DATE=\"{}\"", end_date)?;
+        }
+        if let Some(duration) = self.duration {
+            write!(f, ",DURATION={}", duration)?;
+        }
+        if let Some(planned_duration) = self.planned_duration {
+            write!(f, ",PLANNED-DURATION={}", planned_duration)?;
+        }
+        for (name, value) in &self.client_attributes {
+            write!(f, ",{}={}", name, value)?;
+        }
+        let scte35 =
+            [("SCTE35-CMD", &self.scte35_cmd), ("SCTE35-OUT", &self.scte35_out), ("SCTE35-IN", &self.scte35_in)];
+        for (name, value) in scte35 {
+            if let Some(value) = value {
+                write!(f, ",{}={}", name, value)?;
+            }
+        }
+        if self.end_on_next {
+            f.write_str(",END-ON-NEXT=YES")?;
+        }
+        Ok(())
+    }
+}
+
+impl MediaPlaylist {
+    /// Indices in [`segments`][Self::segments] of the segments overlapping `date_range`, going by
+    /// their program date times, carried forward from the last EXT-X-PROGRAM-DATE-TIME. A range
+    /// with END-ON-NEXT ends where the next one of its CLASS in the playlist starts, and one
+    /// without a known end covers every segment from its start. Segments without a date can't
+    /// be placed and are left out.
+    pub fn segments_in_daterange(&self, date_range: &DateRange) -> Vec<usize> {
+        let end = self.date_range_end(date_range);
+        self.iter_segments()
+            .enumerate()
+            .filter(|(_, context)| {
+                let Some(start) = context.program_date_time else {
+                    return false;
+                };
+                let segment_end = start.checked_add(context.segment.duration()).unwrap_or(start);
+                segment_end > date_range.start_date() && end.is_none_or(|end| start < end)
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// When `date_range` ends, with END-ON-NEXT going by the playlist's date ranges.
+    pub(crate) fn date_range_end(&self, date_range: &DateRange) -> Option<ProgramDateTime> {
+        if !date_range.end_on_next() {
+            return date_range.end();
+        }
+        self.date_ranges()
+            .filter(|x| x.class() == date_range.class() && x.start_date() > date_range.start_date())
+            .map(|x| x.start_date())
+            .min()
+    }
+
+    /// The playlist's date ranges with the tags for each ID merged, in the order of their first
+    /// tags.
+    pub(crate) fn merged_date_ranges(&self) -> Vec<DateRange> {
+        let mut merged: Vec<DateRange> = Vec::new();
+        for date_range in self.date_ranges() {
+            match merged.iter_mut().find(|x| x.id() == date_range.id()) {
+                Some(existing) => existing.merge(date_range),
+                None => merged.push(date_range.clone()),
+            }
+        }
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_date_ranges() {
+        let range = DateRange::parse(concat!(
+            r#"ID="splice-6FFFFFF0",START-DATE="2014-03-05T11:15:00Z",PLANNED-DURATION=59.993,"#,
+            r#"X-AD-ID="a b",X-COUNT=3,SCTE35-OUT=0xFC002F0000000000FF0"#
+        ))
+        .unwrap();
+        assert_eq!(range.id(), "splice-6FFFFFF0");
+        assert_eq!(range.planned_duration(), Some(Duration::from_millis(59_993)));
+        assert_eq!((range.client_attribute("X-AD-ID"), range.client_attribute("X-COUNT")), (Some("a b"), Some("3")));
+        assert_eq!(range.scte35_out(), Some("0xFC002F0000000000FF0"));
+        assert_eq!(range.end(), None);
+        assert_eq!(DateRange::parse(&range.to_string()).unwrap(), range);
+
+        let range = DateRange::parse(r#"ID="a",START-DATE="2014-03-05T11:15:00Z",DURATION=30"#).unwrap();
+        assert_eq!(range.end(), Some("2014-03-05T11:15:30Z".parse().unwrap()));
+        let backwards = r#"ID="a",START-DATE="2014-03-05T11:15:00Z",END-DATE="2014-03-05T11:14:00Z""#;
+        assert!(DateRange::parse(backwards).is_err());
+        assert!(DateRange::parse(r#"ID="a",START-DATE="2014-03-05T11:15:00Z",END-ON-NEXT=YES"#).is_err());
+        assert!(DateRange::parse(r#"START-DATE="2014-03-05T11:15:00Z""#).is_err());
+    }
+
+    #[test]
+    fn maps_date_ranges_to_segments() {
+        let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-TARGETDURATION:4
+            #EXT-X-PROGRAM-DATE-TIME:2024-03-01T12:00:00Z
+            #EXTINF:4,
+            0.ts
+            #EXT-X-DATERANGE:ID="ad",CLASS="ad",START-DATE="2024-03-01T12:00:06Z",END-ON-NEXT=YES
+            #EXTINF:4,
+            1.ts
+            #EXTINF:4,
+            2.ts
+            #EXT-X-DATERANGE:ID="next",CLASS="ad",START-DATE="2024-03-01T12:00:12Z"
+            #EXTINF:4,
+            3.ts
+            #EXT-X-DISCONTINUITY
+            #EXTINF:4,
+            4.ts
+        "#})
+        .unwrap();
+        let ranges: Vec<&DateRange> = playlist.date_ranges().collect();
+        assert_eq!(playlist.segments_in_daterange(ranges[0]), [1, 2]);
+        assert_eq!(playlist.segments_in_daterange(ranges[1]), [3]);
+        let mut blackout = DateRange::new("blackout", "2024-03-01T11:59:00Z".parse().unwrap());
+        blackout.set_duration(Some(Duration::from_secs(65)));
+        assert_eq!(playlist.segments_in_daterange(&blackout), [0, 1]);
+    }
+}
@@ -0,0 +1,223 @@
+//! Absolute dates from EXT-X-PROGRAM-DATE-TIME tags. See
+//! <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2.6>.
+
+use core::fmt;
+use core::str::FromStr;
+use core::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+
+/// An ISO 8601 date and time with a time zone, e.g. `2010-02-19T14:54:23.031+08:00`. Remembers
+/// the offset it was written with, but orders by the instant it denotes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProgramDateTime {
+    /// Nanoseconds since the Unix epoch.
+    nanos: i128,
+
+    /// Offset from UTC the time was written with.
+    offset_minutes: i16,
+}
+
+impl ProgramDateTime {
+    /// The same instant as `time`, written in UTC.
+    pub fn from_system_time(time: SystemTime) -> Self {
+        let nanos = match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => since.as_nanos() as i128,
+            Err(error) => -(error.duration().as_nanos() as i128),
+        };
+        Self { nanos, offset_minutes: 0 }
+    }
+
+    pub fn to_system_time(&self) -> SystemTime {
+        let since = Duration::from_nanos(self.nanos.unsigned_abs() as u64);
+        if self.nanos >= 0 {
+            UNIX_EPOCH + since
+        } else {
+            UNIX_EPOCH - since
+        }
+    }
+
+    /// Offset from UTC the time was written with, in minutes.
+    pub fn offset_minutes(&self) -> i16 {
+        self.offset_minutes
+    }
+
+    /// The time `duration` later, written with the same offset.
+    pub fn checked_add(&self, duration: Duration) -> Option<Self> {
+        let nanos = self.nanos.checked_add(i128::try_from(duration.as_nanos()).ok()?)?;
+        Some(Self { nanos, ..*self })
+    }
+
+    /// How much later this time is than `earlier`, `None` if it is earlier.
+    pub fn duration_since(&self, earlier: &ProgramDateTime) -> Option<Duration> {
+        let nanos = u64::try_from(self.nanos.checked_sub(earlier.nanos)?).ok()?;
+        Some(Duration::from_nanos(nanos))
+    }
+}
+
+impl FromStr for ProgramDateTime {
+    type Err = anyhow::Error;
+
+    /// Parses `YYYY-MM-DDThh:mm:ss[.fraction](Z|±hh:mm)`. Digits of the fraction beyond
+    /// nanoseconds are ignored.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        parse(value).ok_or_else(|| anyhow::anyhow!("Invalid date and time {}", value))
+    }
+}
+
+fn parse(value: &str) -> Option<ProgramDateTime> {
+    let (date, time) = value.split_once('T')?;
+    let mut date_parts = date.splitn(3, '-');
+    let year: i64 = number(date_parts.next()?, 4)?;
+    let month: u32 = number(date_parts.next()?, 2)?;
+    let day: u32 = number(date_parts.next()?, 2)?;
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    let zone_start = time.find(['Z', '+', '-'])?;
+    let (time, zone) = time.split_at(zone_start);
+    let offset_minutes: i16 = match zone {
+        "Z" => 0,
+        _ => {
+            let (hours, minutes) = zone[1..].split_once(':')?;
+            let (hours, minutes): (i16, i16) = (number(hours, 2)?, number(minutes, 2)?);
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let offset = hours * 60 + minutes;
+            if zone.starts_with('-') {
+                -offset
+            } else {
+                offset
+            }
+        }
+    };
+
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let mut time_parts = time.splitn(3, ':');
+    let hour: i64 = number(time_parts.next()?, 2)?;
+    let minute: i64 = number(time_parts.next()?, 2)?;
+    let second: i64 = number(time_parts.next()?, 2)?;
+    if hour > 23 || minute > 59 || second > 59 || !fraction.bytes().all(|x| x.is_ascii_digit()) {
+        return None;
+    }
+    if time.contains('.') || (value.contains('.') && fraction.is_empty()) {
+        return None;
+    }
+    let fraction_nanos = format!("{:0<9}", &fraction[..fraction.len().min(9)]).parse::<i128>().ok()?;
+
+    let local_seconds = days_from_civil(year, month, day) * 86_400 + hour * 3_600 + minute * 60 + second;
+    let seconds = local_seconds - i64::from(offset_minutes) * 60;
+    Some(ProgramDateTime { nanos: i128::from(seconds) * NANOS_PER_SECOND + fraction_nanos, offset_minutes })
+}
+
+/// Parses exactly `digits` ASCII digits.
+fn number<T: FromStr>(value: &str, digits: usize) -> Option<T> {
+    if value.len() != digits || !value.bytes().all(|x| x.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar, from
+/// <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * i64::from((month + 9) % 12) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of [`days_from_civil`].
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u32;
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+impl fmt::Display for ProgramDateTime {
+    /// Formats the time with its original offset and millisecond precision, or finer if needed.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let local = self.nanos + i128::from(self.offset_minutes) * 60 * NANOS_PER_SECOND;
+        let seconds = local.div_euclid(NANOS_PER_SECOND) as i64;
+        let nanos = local.rem_euclid(NANOS_PER_SECOND);
+        let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+        let second_of_day = seconds.rem_euclid(86_400);
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            year, month, day, second_of_day / 3_600, second_of_day % 3_600 / 60, second_of_day % 60
+        )?;
+        if nanos % 1_000_000 == 0 {
+            write!(f, ".{:03}", nanos / 1_000_000)?;
+        } else if nanos % 1_000 == 0 {
+            write!(f, ".{:06}", nanos / 1_000)?;
+        } else {
+            write!(f, ".{:09}", nanos)?;
+        }
+        match self.offset_minutes {
+            0 => f.write_str("Z"),
+            offset => {
+                let sign = if offset < 0 { '-' } else { '+' };
+                write!(f, "{}{:02}:{:02}", sign, offset.abs() / 60, offset.abs() % 60)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(value: &str) -> ProgramDateTime {
+        value.parse().expect("test time should parse")
+    }
+
+    #[test]
+    fn parses_dates() {
+        let utc = time("2015-08-25T01:59:23.708Z");
+        assert_eq!(utc.to_system_time(), UNIX_EPOCH + Duration::from_millis(1_440_467_963_708));
+        assert_eq!(time("2010-02-19T14:54:23.031+08:00").to_string(), "2010-02-19T14:54:23.031+08:00");
+        assert_eq!(time("2010-02-19T14:54:23+00:00").to_string(), "2010-02-19T14:54:23.000Z");
+        assert_eq!(time("1969-12-31T23:59:59.5-01:30").to_string(), "1969-12-31T23:59:59.500-01:30");
+        assert_eq!(time("2024-02-29T00:00:00.000001Z").to_string(), "2024-02-29T00:00:00.000001Z");
+        assert_eq!(time("2010-02-19T14:54:23.031+08:00"), ProgramDateTime { nanos: 1_266_562_463_031_000_000, offset_minutes: 480 });
+
+        for invalid in ["", "2010-02-19", "2010-02-19T14:54:23", "2023-02-29T00:00:00Z", "2010-2-19T14:54:23Z",
+            "2010-02-19T24:00:00Z", "2010-02-19T14:54:23.Z", "2010-02-19T14:54:23+0800"]
+        {
+            assert!(invalid.parse::<ProgramDateTime>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn adds_durations() {
+        let start = time("2015-08-25T23:59:59.500+02:00");
+        let later = start.checked_add(Duration::from_millis(1500)).unwrap();
+        assert_eq!(later.to_string(), "2015-08-26T00:00:01.000+02:00");
+        assert_eq!(later.duration_since(&start), Some(Duration::from_millis(1500)));
+        assert_eq!(start.duration_since(&later), None);
+        assert!(start < time("2015-08-25T22:00:00Z"));
+        assert_eq!(ProgramDateTime::from_system_time(start.to_system_time()).to_string(), "2015-08-25T21:59:59.500Z");
+    }
+}
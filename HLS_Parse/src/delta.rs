@@ -0,0 +1,120 @@
+//! Playlist delta updates for Low-Latency HLS, which replace the older segments of a live
+//! playlist with an EXT-X-SKIP tag. Servers send them when a client requests `_HLS_skip=YES`.
+//! See <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-6.2.5.1>.
+
+use core::time::Duration;
+
+use anyhow::Result;
+
+use crate::MediaPlaylist;
+
+/// Lowest version supporting EXT-X-SKIP.
+const SKIP_VERSION: u64 = 9;
+
+impl MediaPlaylist {
+    /// Builds the delta update of this playlist, for a server which advertised
+    /// `EXT-X-SERVER-CONTROL:CAN-SKIP-UNTIL=<can_skip_until>`. Segments which end more than
+    /// `can_skip_until` before the end of the playlist are replaced by an
+    /// `EXT-X-SKIP:SKIPPED-SEGMENTS=<n>` tag, and the rest are kept unchanged. The version is
+    /// raised to 9 if needed.
+    ///
+    /// Returns an error if `can_skip_until` is less than six target durations, the minimum the
+    /// specification allows, or if the playlist is already a delta update.
+    pub fn to_delta(&self, can_skip_until: Duration) -> Result<MediaPlaylist> {
+        if can_skip_until < self.target_duration() * 6 {
+            return Err(anyhow::anyhow!(
+                "CAN-SKIP-UNTIL of {}s is less than six target durations ({}s)",
+                can_skip_until.as_secs_f64(), (self.target_duration() * 6).as_secs()
+            ));
+        }
+        if self.skipped_segments() > 0 {
+            return Err(anyhow::Error::msg("Playlist is already a delta update"));
+        }
+
+        let total: Duration = self.segments().iter().map(|x| x.duration()).sum();
+        let skip_boundary = total.saturating_sub(can_skip_until);
+        let mut end = Duration::ZERO;
+        let skipped = self
+            .segments()
+            .iter()
+            .take_while(|segment| {
+                end += segment.duration();
+                end <= skip_boundary
+            })
+            .count();
+
+        let mut delta = self.clone();
+        delta.discard_source();
+        delta.segments_mut().drain(..skipped);
+        delta.set_skipped_segments(skipped as u64);
+        if skipped > 0 {
+            delta.set_version(self.version().max(SKIP_VERSION));
+        }
+        Ok(delta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn live_playlist(segments: usize) -> MediaPlaylist {
+        let mut file = String::from("#EXTM3U\n#EXT-X-VERSION:6\n#EXT-X-TARGETDURATION:4\n#EXT-X-MEDIA-SEQUENCE:100\n");
+        file.push_str("#EXT-X-KEY:METHOD=AES-128,URI=\"1.key\"\n");
+        for index in 0..segments {
+            file.push_str(&format!("#EXTINF:4,\n{}.ts\n", 100 + index));
+        }
+        MediaPlaylist::parse_ext_m3u(&file).expect("test playlist should parse")
+    }
+
+    #[test]
+    fn skips_segments_before_boundary() {
+        let playlist = live_playlist(10);
+        let delta = playlist.to_delta(Duration::from_secs(24)).expect("should build delta");
+        assert_eq!(delta.skipped_segments(), 4);
+        assert_eq!(delta.media_sequence(), 100);
+        assert_eq!(delta.version(), 9);
+        assert_eq!(delta.segments(), &playlist.segments()[4..]);
+        assert_eq!(
+            delta.to_string(),
+            indoc::indoc! {r#"
+                #EXTM3U
+                #EXT-X-VERSION:9
+                #EXT-X-TARGETDURATION:4
+                #EXT-X-MEDIA-SEQUENCE:100
+                #EXT-X-SKIP:SKIPPED-SEGMENTS=4
+                #EXT-X-KEY:METHOD=AES-128,URI="1.key"
+                #EXTINF:4,
+                104.ts
+                #EXTINF:4,
+                105.ts
+                #EXTINF:4,
+                106.ts
+                #EXTINF:4,
+                107.ts
+                #EXTINF:4,
+                108.ts
+                #EXTINF:4,
+                109.ts
+            "#}
+        );
+        assert_eq!(MediaPlaylist::parse_ext_m3u(&delta.to_string()).unwrap(), delta);
+        assert!(delta.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn keeps_short_playlists_whole() {
+        let playlist = live_playlist(5);
+        let delta = playlist.to_delta(Duration::from_secs(24)).expect("should build delta");
+        assert_eq!(delta.skipped_segments(), 0);
+        assert_eq!(delta, playlist);
+    }
+
+    #[test]
+    fn rejects_short_skip_window() {
+        let error = live_playlist(10).to_delta(Duration::from_secs(20)).expect_err("window is too short");
+        assert_eq!(error.to_string(), "CAN-SKIP-UNTIL of 20s is less than six target durations (24s)");
+        let delta = live_playlist(10).to_delta(Duration::from_secs(24)).unwrap();
+        assert!(delta.to_delta(Duration::from_secs(24)).is_err());
+    }
+}
@@ -0,0 +1,374 @@
+//! Problems found while checking a playlist against the specification.
+//!
+//! Parsing stops at the first violation it can't recover from, while [`validate`] reports that
+//! as a [`Diagnostic`] along with anything else worth knowing about a playlist that did parse.
+
+use core::fmt;
+
+use crate::{MediaPlaylist, ParseOptions};
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(rename_all = "lowercase"))]
+pub enum Severity {
+    /// Something the specification recommends against, or which some clients handle poorly.
+    Warning,
+    /// A violation of a MUST in the specification.
+    Error,
+}
+
+/// A single problem with a playlist.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Diagnostic {
+    pub severity: Severity,
+
+    /// 1-based line the problem was found on, if it can be pinned to one.
+    pub line: Option<usize>,
+
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(line: Option<usize>, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Error, line, message: message.into() }
+    }
+
+    pub fn warning(line: Option<usize>, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Warning, line, message: message.into() }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}: line {}: {}", self.severity, line, self.message),
+            None => write!(f, "{}: {}", self.severity, self.message),
+        }
+    }
+}
+
+/// An error from [`MediaPlaylist::parse_all_errors`].
+#[derive(Debug)]
+pub struct ParseError {
+    /// 1-based line the error was found on, `None` for problems with the playlist as a whole,
+    /// e.g. a missing target duration.
+    pub line: Option<usize>,
+
+    pub error: anyhow::Error,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {:#}", line, self.error),
+            None => write!(f, "{:#}", self.error),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<ParseError> for Diagnostic {
+    fn from(error: ParseError) -> Self {
+        Diagnostic::error(error.line, format!("{:#}", error.error))
+    }
+}
+
+/// Diagnostics found while parsing, kept with the playlist. They describe the input rather than
+/// the model, so they never make playlists unequal.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ParseNotes(pub(crate) Vec<Diagnostic>);
+
+impl PartialEq for ParseNotes {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+/// Parses and checks the given media playlist, returning every problem found, see
+/// [`MediaPlaylist::parse_all_errors`]. An empty result means the playlist is valid.
+pub fn validate(file: &str) -> Vec<Diagnostic> {
+    validate_with_options(file, &ParseOptions::default())
+}
+
+/// Like [`validate`], e.g. with [`ParseOptions::lenient`] to report sloppy formatting as warnings
+/// rather than failing at the first instance.
+pub fn validate_with_options(file: &str, options: &ParseOptions) -> Vec<Diagnostic> {
+    match MediaPlaylist::parse_all_errors(file, options) {
+        Ok(playlist) => playlist.diagnostics(),
+        Err(errors) => errors.into_iter().map(Diagnostic::from).collect(),
+    }
+}
+
+/// Checks a reload of a live playlist against the previous version, per
+/// <https://datatracker.ietf.org/doc/html/rfc8216#section-6.2.1>: the server may only append
+/// segments, remove them from the front while advancing EXT-X-MEDIA-SEQUENCE, and add an
+/// EXT-X-ENDLIST. Problems in `current` by itself are not reported, see
+/// [`MediaPlaylist::diagnostics`] for those.
+pub fn validate_reload(previous: &MediaPlaylist, current: &MediaPlaylist) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    if current.target_duration() != previous.target_duration() {
+        diagnostics.push(Diagnostic::error(None, format!(
+            "Target duration changed from {}s to {}s",
+            previous.target_duration().as_secs(), current.target_duration().as_secs()
+        )));
+    }
+    if previous.ended() && current != previous {
+        diagnostics.push(Diagnostic::error(None, "Playlist changed after EXT-X-ENDLIST"));
+    }
+    if previous.ended() && !current.ended() {
+        diagnostics.push(Diagnostic::error(None, "EXT-X-ENDLIST was removed"));
+    }
+
+    let previous_start = previous.media_sequence();
+    let previous_end = previous_start + previous.segments().len() as u64;
+    let current_start = current.media_sequence();
+    if current_start < previous_start {
+        diagnostics.push(Diagnostic::error(None, format!(
+            "Media sequence went backwards from {} to {}",
+            previous_start, current_start
+        )));
+    } else if current_start > previous_end {
+        diagnostics.push(Diagnostic::warning(None, format!(
+            "Media sequence jumped from {} to {}, segments {} to {} were never listed",
+            previous_start, current_start, previous_end, current_start - 1
+        )));
+    }
+
+    //segments present in both versions must be the same. Segments skipped by a delta update
+    //aren't listed, so comparisons start after them
+    let first_listed = current_start + current.skipped_segments();
+    for (offset, segment) in current.segments().iter().enumerate() {
+        let sequence = first_listed + offset as u64;
+        let Some(index) = sequence.checked_sub(previous_start) else {
+            continue;
+        };
+        let Some(old) = previous.segments().get(index as usize) else {
+            break;
+        };
+        if old.url() != segment.url() || old.byte_range() != segment.byte_range() {
+            diagnostics.push(Diagnostic::error(None, format!(
+                "Segment with media sequence {} changed from {} to {}",
+                sequence, old.url(), segment.url()
+            )));
+        }
+    }
+    if (previous_start..=previous_end).contains(&current_start)
+        && first_listed + (current.segments().len() as u64) < previous_end
+    {
+        diagnostics.push(Diagnostic::error(None, format!(
+            "Segments up to media sequence {} were removed from the end",
+            previous_end - 1
+        )));
+    }
+    #[cfg(feature = "tracing")]
+    trace(&diagnostics);
+    diagnostics
+}
+
+/// Emits a tracing event for each diagnostic, at warn level for errors and info for warnings.
+#[cfg(feature = "tracing")]
+pub(crate) fn trace(diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        match diagnostic.severity {
+            Severity::Error => tracing::warn!(line = diagnostic.line, "{}", diagnostic.message),
+            Severity::Warning => tracing::info!(line = diagnostic.line, "{}", diagnostic.message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playlist(file: &str) -> MediaPlaylist {
+        MediaPlaylist::parse_ext_m3u(file).expect("test playlist should parse")
+    }
+
+    #[test]
+    fn reports_parse_error_with_line() {
+        let diagnostics = validate(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-VERSION:three
+        "});
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic::error(Some(3), "Version tag found, but could not parse")]
+        );
+        assert_eq!(diagnostics[0].to_string(), "error: line 3: Version tag found, but could not parse");
+    }
+
+    #[test]
+    fn reports_nothing_for_valid_playlist() {
+        let diagnostics = validate(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-VERSION:3
+            #EXT-X-TARGETDURATION:10
+            #EXTINF:9.5,
+            first.ts
+            #EXT-X-ENDLIST
+        "});
+        assert_eq!(diagnostics, vec![]);
+    }
+
+    #[test]
+    fn reports_lenient_fixes() {
+        let file = indoc::indoc! {"
+            #extm3u
+            #EXT-X-TARGETDURATION: 10
+            #EXTINF:9,
+            first.ts
+        "};
+        assert_eq!(validate(file), vec![Diagnostic::error(None, "Input doesn't start with EXTM3U tag")]);
+        assert_eq!(
+            validate_with_options(file, &ParseOptions { lenient: true, ..ParseOptions::default() }),
+            vec![
+                Diagnostic::warning(Some(1), "Tag #extm3u should be written #EXTM3U"),
+                Diagnostic::warning(Some(2), "Whitespace in value of #EXT-X-TARGETDURATION"),
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_sliding_window() {
+        let previous = playlist(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-MEDIA-SEQUENCE:7
+            #EXTINF:9.5,
+            7.ts
+            #EXTINF:9.5,
+            8.ts
+        "});
+        let current = playlist(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-MEDIA-SEQUENCE:8
+            #EXTINF:9.5,
+            8.ts
+            #EXTINF:9.5,
+            9.ts
+            #EXT-X-ENDLIST
+        "});
+        assert_eq!(validate_reload(&previous, &current), vec![]);
+    }
+
+    #[test]
+    fn compares_delta_update_after_skipped_segments() {
+        let previous = playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:9.5,\n0.ts\n#EXTINF:9.5,\n1.ts\n");
+        let delta = playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXT-X-SKIP:SKIPPED-SEGMENTS=1\n#EXTINF:9.5,\n1.ts\n");
+        assert_eq!(validate_reload(&previous, &delta), vec![]);
+    }
+
+    #[test]
+    fn reports_reload_violations() {
+        let previous = playlist(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-MEDIA-SEQUENCE:7
+            #EXTINF:9.5,
+            7.ts
+            #EXTINF:9.5,
+            8.ts
+        "});
+        let current = playlist(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-TARGETDURATION:8
+            #EXT-X-MEDIA-SEQUENCE:7
+            #EXTINF:7.5,
+            7-replaced.ts
+        "});
+        assert_eq!(
+            validate_reload(&previous, &current),
+            vec![
+                Diagnostic::error(None, "Target duration changed from 10s to 8s"),
+                Diagnostic::error(None, "Segment with media sequence 7 changed from 7.ts to 7-replaced.ts"),
+                Diagnostic::error(None, "Segments up to media sequence 8 were removed from the end"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_sequence_discontinuities() {
+        let previous = playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXT-X-MEDIA-SEQUENCE:7\n#EXTINF:9.5,\n7.ts\n");
+        let skipped = playlist("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXT-X-MEDIA-SEQUENCE:10\n#EXTINF:9.5,\n10.ts\n");
+        assert_eq!(
+            validate_reload(&previous, &skipped),
+            vec![Diagnostic::warning(None, "Media sequence jumped from 7 to 10, segments 8 to 9 were never listed")]
+        );
+        assert_eq!(
+            validate_reload(&skipped, &previous),
+            vec![Diagnostic::error(None, "Media sequence went backwards from 10 to 7")]
+        );
+    }
+
+    /// Records the level and message of every tracing event.
+    #[cfg(feature = "tracing")]
+    #[derive(Default)]
+    struct Recorder(std::sync::Mutex<Vec<(tracing::Level, String)>>);
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for Recorder {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            tracing::span::Id::from_u64(1)
+        }
+
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            struct Message(String);
+            impl tracing::field::Visit for Message {
+                fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn fmt::Debug) {
+                    if field.name() == "message" {
+                        self.0 = format!("{:?}", value);
+                    }
+                }
+            }
+            let mut message = Message(String::new());
+            event.record(&mut message);
+            self.0.lock().unwrap().push((*event.metadata().level(), message.0));
+        }
+
+        fn enter(&self, _: &tracing::span::Id) {}
+
+        fn exit(&self, _: &tracing::span::Id) {}
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn traces_diagnostics_and_failures() {
+        let recorder = std::sync::Arc::new(Recorder::default());
+        tracing::subscriber::with_default(recorder.clone(), || {
+            validate("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10.5,\n1.ts\n");
+            validate("#EXTM3U\n#EXTINF:10,\n");
+        });
+        let events = recorder.0.lock().unwrap();
+        assert!(events.contains(&(tracing::Level::DEBUG, "Parsed media playlist".to_string())));
+        assert!(events.contains(&(
+            tracing::Level::WARN,
+            "Floating-point EXTINF durations require version 3, playlist is version 1".to_string()
+        )));
+        assert!(events.contains(&(
+            tracing::Level::WARN,
+            "Media playlist failed to parse: EXTINF without URI at line 2".to_string()
+        )));
+    }
+}
@@ -0,0 +1,139 @@
+//! Checking EXT-X-PROGRAM-DATE-TIME tags against the segment durations between them, to catch
+//! encoder clock drift and missing segments in live streams.
+
+use core::time::Duration;
+
+use crate::{MediaPlaylist, ProgramDateTime};
+
+/// A segment whose EXT-X-PROGRAM-DATE-TIME differs from the date carried forward through the
+/// EXTINF durations of the segments before it, from [`MediaPlaylist::date_time_mismatches`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DateTimeMismatch {
+    /// Media sequence number of the segment.
+    pub sequence: u64,
+
+    /// The previous date plus the durations since.
+    pub expected: ProgramDateTime,
+
+    /// The date the segment's tag gives.
+    pub actual: ProgramDateTime,
+}
+
+impl DateTimeMismatch {
+    /// How much media seems to be missing before the segment, `None` if the date is early.
+    pub fn gap(&self) -> Option<Duration> {
+        self.actual.duration_since(&self.expected).filter(|x| !x.is_zero())
+    }
+
+    /// How much the segment overlaps the ones before it, `None` if the date is late.
+    pub fn overlap(&self) -> Option<Duration> {
+        self.expected.duration_since(&self.actual).filter(|x| !x.is_zero())
+    }
+}
+
+impl MediaPlaylist {
+    /// Segments whose EXT-X-PROGRAM-DATE-TIME is more than `tolerance` away from the previous
+    /// date plus the durations in between. Dates after a discontinuity start afresh, since the
+    /// timeline may jump there.
+    pub fn date_time_mismatches(&self, tolerance: Duration) -> Vec<DateTimeMismatch> {
+        let mut mismatches = Vec::new();
+        let mut expected: Option<ProgramDateTime> = None;
+        for context in self.iter_segments() {
+            if let (Some(actual), Some(expected), false) =
+                (context.segment.program_date_time(), expected, context.segment.discontinuity())
+            {
+                let difference = actual.duration_since(&expected).or_else(|| expected.duration_since(&actual));
+                if difference.is_some_and(|x| x > tolerance) {
+                    mismatches.push(DateTimeMismatch { sequence: context.sequence, expected, actual });
+                }
+            }
+            expected = context.program_date_time.and_then(|x| x.checked_add(context.segment.duration()));
+        }
+        mismatches
+    }
+
+    /// Date and time the segment with media sequence number `sequence` should start at: its own
+    /// EXT-X-PROGRAM-DATE-TIME, or the latest one before it plus the durations in between. The
+    /// sequence number right after the last segment gives when the next segment is due. `None`
+    /// outside the playlist or if no date applies, see [`SegmentContext`][crate::SegmentContext].
+    pub fn expected_pdt_for(&self, sequence: u64) -> Option<ProgramDateTime> {
+        let mut last = None;
+        for context in self.iter_segments() {
+            if context.sequence == sequence {
+                return context.program_date_time;
+            }
+            last = Some(context);
+        }
+        let last = last.filter(|x| x.sequence + 1 == sequence)?;
+        last.program_date_time?.checked_add(last.segment.duration())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYLIST: &str = indoc::indoc! {"
+        #EXTM3U
+        #EXT-X-VERSION:3
+        #EXT-X-TARGETDURATION:6
+        #EXT-X-MEDIA-SEQUENCE:10
+        #EXT-X-PROGRAM-DATE-TIME:2024-03-01T12:00:00.000Z
+        #EXTINF:6,
+        10.ts
+        #EXT-X-PROGRAM-DATE-TIME:2024-03-01T12:00:06.020Z
+        #EXTINF:6,
+        11.ts
+        #EXT-X-PROGRAM-DATE-TIME:2024-03-01T12:00:18.020Z
+        #EXTINF:6,
+        12.ts
+        #EXT-X-PROGRAM-DATE-TIME:2024-03-01T12:00:23.000Z
+        #EXTINF:6,
+        13.ts
+        #EXT-X-DISCONTINUITY
+        #EXT-X-PROGRAM-DATE-TIME:2024-03-01T13:00:00.000Z
+        #EXTINF:6,
+        14.ts
+        #EXTINF:4.5,
+        15.ts
+    "};
+
+    fn date(value: &str) -> ProgramDateTime {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn finds_gaps_and_overlaps() {
+        let playlist = MediaPlaylist::parse_ext_m3u(PLAYLIST).unwrap();
+        let mismatches = playlist.date_time_mismatches(Duration::from_millis(50));
+        assert_eq!(
+            mismatches,
+            vec![
+                DateTimeMismatch {
+                    sequence: 12,
+                    expected: date("2024-03-01T12:00:12.020Z"),
+                    actual: date("2024-03-01T12:00:18.020Z"),
+                },
+                DateTimeMismatch {
+                    sequence: 13,
+                    expected: date("2024-03-01T12:00:24.020Z"),
+                    actual: date("2024-03-01T12:00:23.000Z"),
+                },
+            ]
+        );
+        assert_eq!(mismatches[0].gap(), Some(Duration::from_secs(6)));
+        assert_eq!(mismatches[0].overlap(), None);
+        assert_eq!(mismatches[1].overlap(), Some(Duration::from_millis(1020)));
+        assert_eq!(playlist.date_time_mismatches(Duration::ZERO).len(), 3);
+    }
+
+    #[test]
+    fn carries_dates_forward() {
+        let playlist = MediaPlaylist::parse_ext_m3u(PLAYLIST).unwrap();
+        assert_eq!(playlist.expected_pdt_for(11), Some(date("2024-03-01T12:00:06.020Z")));
+        assert_eq!(playlist.expected_pdt_for(15), Some(date("2024-03-01T13:00:06.000Z")));
+        assert_eq!(playlist.expected_pdt_for(16), Some(date("2024-03-01T13:00:10.500Z")));
+        assert_eq!(playlist.expected_pdt_for(9), None);
+        assert_eq!(playlist.expected_pdt_for(17), None);
+    }
+}
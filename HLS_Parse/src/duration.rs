@@ -0,0 +1,210 @@
+//! Exact segment durations. EXTINF values are decimals, and going through a float rounds them:
+//! `12.166` becomes 12.165999889s, which breaks equality checks and adds up over long playlists.
+
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::str::FromStr;
+use core::time::Duration;
+
+/// Most digits after the decimal point which fit in a `u64` alongside a useful number of seconds.
+const MAX_SCALE: u32 = 18;
+
+/// A decimal number of seconds as written in an EXTINF tag, stored as `mantissa / 10^scale` so it
+/// converts to [`Duration`] and back to text without rounding. `10.5` and `10.500` are equal, but
+/// each is written back as it was parsed.
+#[derive(Debug, Clone, Copy)]
+pub struct SegmentDuration {
+    mantissa: u64,
+
+    /// Number of digits after the decimal point.
+    scale: u32,
+}
+
+impl SegmentDuration {
+    pub const ZERO: SegmentDuration = SegmentDuration { mantissa: 0, scale: 0 };
+
+    pub fn from_secs(secs: u64) -> Self {
+        Self { mantissa: secs, scale: 0 }
+    }
+
+    /// Whole milliseconds, written with three decimals, e.g. `12166` as `12.166`.
+    pub fn from_millis(millis: u64) -> Self {
+        Self { mantissa: millis, scale: 3 }
+    }
+
+    /// The digits of the value without the decimal point, e.g. `10500` for `10.500`.
+    pub fn mantissa(&self) -> u64 {
+        self.mantissa
+    }
+
+    /// Number of digits after the decimal point, e.g. `3` for `10.500`.
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// The value as a [`Duration`], exact unless written with more than nine decimals, in which
+    /// case it is truncated to nanoseconds.
+    pub fn as_duration(&self) -> Duration {
+        let divisor = 10u64.pow(self.scale);
+        let fraction = self.mantissa % divisor;
+        let nanos = match self.scale.cmp(&9) {
+            Ordering::Greater => fraction / 10u64.pow(self.scale - 9),
+            _ => fraction * 10u64.pow(9 - self.scale),
+        };
+        Duration::new(self.mantissa / divisor, nanos as u32)
+    }
+
+    pub fn as_secs_f64(&self) -> f64 {
+        self.mantissa as f64 / 10f64.powi(self.scale as i32)
+    }
+
+    /// The value scaled to `scale` decimals, for comparing values written with different scales.
+    fn scaled(&self, scale: u32) -> u128 {
+        u128::from(self.mantissa) * 10u128.pow(scale - self.scale)
+    }
+
+    /// Mantissa and scale with trailing zeros removed, identical for equal values.
+    fn normalized(&self) -> (u64, u32) {
+        let (mut mantissa, mut scale) = (self.mantissa, self.scale);
+        while scale > 0 && mantissa % 10 == 0 {
+            mantissa /= 10;
+            scale -= 1;
+        }
+        (mantissa, scale)
+    }
+}
+
+impl Default for SegmentDuration {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl PartialEq for SegmentDuration {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SegmentDuration {}
+
+impl PartialOrd for SegmentDuration {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SegmentDuration {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let scale = self.scale.max(other.scale);
+        self.scaled(scale).cmp(&other.scaled(scale))
+    }
+}
+
+impl Hash for SegmentDuration {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.normalized().hash(state);
+    }
+}
+
+impl From<SegmentDuration> for Duration {
+    fn from(duration: SegmentDuration) -> Self {
+        duration.as_duration()
+    }
+}
+
+/// Exact to the nanosecond, with trailing zeros dropped, e.g. 10.5s becomes `10.5`.
+impl From<Duration> for SegmentDuration {
+    fn from(duration: Duration) -> Self {
+        let nanos = u64::from(duration.subsec_nanos());
+        let exact = duration
+            .as_secs()
+            .checked_mul(1_000_000_000)
+            .and_then(|x| x.checked_add(nanos))
+            .map(|mantissa| Self { mantissa, scale: 9 }.normalized());
+        match exact {
+            Some((mantissa, scale)) => Self { mantissa, scale },
+            //too long for nanosecond precision, which no segment is
+            None => Self::from_secs(duration.as_secs()),
+        }
+    }
+}
+
+impl FromStr for SegmentDuration {
+    type Err = anyhow::Error;
+
+    /// Parses a decimal-floating-point value: digits, optionally followed by `.` and more digits.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || anyhow::anyhow!("Invalid duration {}", value);
+        //one pass over the digits, since every EXTINF tag has one
+        let (mut mantissa, mut scale, mut point) = (0u64, 0, false);
+        for (index, byte) in value.bytes().enumerate() {
+            match byte {
+                b'.' if !point && index > 0 => point = true,
+                b'0'..=b'9' => {
+                    let digit = u64::from(byte - b'0');
+                    mantissa = mantissa.checked_mul(10).and_then(|x| x.checked_add(digit)).ok_or_else(invalid)?;
+                    scale += u32::from(point);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        if value.is_empty() || scale > MAX_SCALE {
+            return Err(invalid());
+        }
+        Ok(Self { mantissa, scale })
+    }
+}
+
+impl fmt::Display for SegmentDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let divisor = 10u64.pow(self.scale);
+        write!(f, "{}", self.mantissa / divisor)?;
+        if self.scale > 0 {
+            write!(f, ".{:0width$}", self.mantissa % divisor, width = self.scale as usize)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_exactly() {
+        let duration = "12.166".parse::<SegmentDuration>().unwrap();
+        assert_eq!(duration.as_duration(), Duration::from_millis(12166));
+        assert_eq!(duration, SegmentDuration::from_millis(12166));
+        assert_eq!("10.500".parse::<SegmentDuration>().unwrap(), "10.5".parse().unwrap());
+        assert_eq!("4".parse::<SegmentDuration>().unwrap().as_duration(), Duration::from_secs(4));
+        assert_eq!("0.0000000015".parse::<SegmentDuration>().unwrap().as_duration(), Duration::from_nanos(1));
+        for invalid in ["", ".5", "-1", "1e3", "1.5.0", " 1", "1,5", "99999999999999999999"] {
+            assert!(invalid.parse::<SegmentDuration>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn writes_back_as_parsed() {
+        for value in ["12.166", "10.500", "4", "4.0", "0.033"] {
+            assert_eq!(value.parse::<SegmentDuration>().unwrap().to_string(), value);
+        }
+        assert_eq!(SegmentDuration::from(Duration::from_millis(10500)).to_string(), "10.5");
+        assert_eq!(SegmentDuration::from(Duration::from_secs(6)).to_string(), "6");
+    }
+
+    #[test]
+    fn sums_without_drift() {
+        let total: Duration = (0..10_000).map(|_| "0.1".parse::<SegmentDuration>().unwrap().as_duration()).sum();
+        assert_eq!(total, Duration::from_secs(1000));
+    }
+
+    #[test]
+    fn orders_by_value() {
+        let short = "9.99".parse::<SegmentDuration>().unwrap();
+        let long = "10.0".parse::<SegmentDuration>().unwrap();
+        assert!(short < long);
+        assert_eq!(long.cmp(&SegmentDuration::from_secs(10)), Ordering::Equal);
+    }
+}
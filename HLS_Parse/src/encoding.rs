@@ -0,0 +1,65 @@
+//! Checking the encoding of raw playlist bytes. Playlists must be UTF-8 without a byte order
+//! mark, see <https://datatracker.ietf.org/doc/html/rfc8216#section-4.1>, but servers often send
+//! a BOM or another encoding.
+
+use anyhow::Result;
+
+use crate::{MasterPlaylist, MediaPlaylist};
+
+pub(crate) const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+impl MediaPlaylist {
+    /// Like [`parse_ext_m3u`][Self::parse_ext_m3u], for bytes as received. A UTF-8 byte order
+    /// mark is skipped, and invalid UTF-8 is an error giving the byte offset of the first bad byte.
+    pub fn parse_ext_m3u_bytes(bytes: &[u8]) -> Result<Self> {
+        Self::parse_ext_m3u(decode(bytes)?)
+    }
+}
+
+impl MasterPlaylist {
+    /// Like [`parse_ext_m3u`][Self::parse_ext_m3u], for bytes as received. A UTF-8 byte order
+    /// mark is skipped, and invalid UTF-8 is an error giving the byte offset of the first bad byte.
+    pub fn parse_ext_m3u_bytes(bytes: &[u8]) -> Result<Self> {
+        Self::parse_ext_m3u(decode(bytes)?)
+    }
+}
+
+/// The text of a playlist, without any UTF-8 byte order mark.
+pub(crate) fn decode(bytes: &[u8]) -> Result<&str> {
+    check_byte_order_mark(bytes)?;
+    let skipped = if bytes.starts_with(UTF8_BOM) { UTF8_BOM.len() } else { 0 };
+    core::str::from_utf8(&bytes[skipped..]).map_err(|error| {
+        let offset = skipped + error.valid_up_to();
+        let line = bytes[..offset].iter().filter(|x| **x == b'\n').count() + 1;
+        anyhow::anyhow!("Invalid UTF-8 at byte offset {} (line {})", offset, line)
+    })
+}
+
+/// Rejects input starting with a UTF-16 byte order mark.
+pub(crate) fn check_byte_order_mark(bytes: &[u8]) -> Result<()> {
+    if bytes.starts_with(b"\xFE\xFF") || bytes.starts_with(b"\xFF\xFE") {
+        return Err(anyhow::Error::msg("Input is UTF-16, but playlists must be UTF-8"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_byte_order_mark() {
+        let playlist = MediaPlaylist::parse_ext_m3u_bytes(b"\xEF\xBB\xBF#EXTM3U\n#EXT-X-TARGETDURATION:10\n").unwrap();
+        assert_eq!(playlist.target_duration().as_secs(), 10);
+        let master = MasterPlaylist::parse_ext_m3u_bytes(b"\xEF\xBB\xBF#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1\na.m3u8\n");
+        assert_eq!(master.unwrap().variants().len(), 1);
+    }
+
+    #[test]
+    fn reports_invalid_encoding() {
+        let error = |bytes: &[u8]| MediaPlaylist::parse_ext_m3u_bytes(bytes).unwrap_err().to_string();
+        assert_eq!(error(b"#EXTM3U\n#EXTINF:10,caf\xE9\n1.ts\n"), "Invalid UTF-8 at byte offset 22 (line 2)");
+        assert_eq!(error(b"\xEF\xBB\xBF#EXTM3U\n\xFF"), "Invalid UTF-8 at byte offset 11 (line 2)");
+        assert_eq!(error(b"\xFF\xFE#\x00E\x00"), "Input is UTF-16, but playlists must be UTF-8");
+    }
+}
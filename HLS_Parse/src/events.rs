@@ -0,0 +1,544 @@
+//! Low-level tokenizer for [ext-m3u][m3u] data.
+//!
+//! [`events`] scans a file line by line and yields an [`Event`] for every tag, URI and comment,
+//! without building a playlist. Useful when only a few tags matter, or when a playlist should be
+//! transformed on the fly.
+//!
+//! [m3u]: https://en.wikipedia.org/wiki/M3U#Extended_M3U
+
+use core::iter::Enumerate;
+use core::str::Lines;
+use std::borrow::Cow;
+
+use anyhow::Result;
+
+use crate::attributes::AttributeList;
+use crate::variables;
+use crate::{
+    ByteRange, DateRange, EncryptionKey, PartialSegment, PlaylistType, PreloadHint, ProgramDateTime, Rendition,
+    RenditionReport, SegmentDuration, SegmentMap, SessionData, VariantStream,
+};
+
+/// RFC8216, Section 4 tag names, without the leading `#`
+pub(crate) const HEADER_TAG: &str = "EXTM3U";
+pub(crate) const VERSION_TAG: &str = "EXT-X-VERSION";
+pub(crate) const ENDLIST_TAG: &str = "EXT-X-ENDLIST";
+pub(crate) const DURATION_TAG: &str = "EXT-X-TARGETDURATION";
+pub(crate) const MEDIA_SEQUENCE_TAG: &str = "EXT-X-MEDIA-SEQUENCE";
+pub(crate) const ALLOW_CACHE_TAG: &str = "EXT-X-ALLOW-CACHE";
+pub(crate) const PLAYLIST_TYPE_TAG: &str = "EXT-X-PLAYLIST-TYPE";
+pub(crate) const SEGMENT_TAG: &str = "EXTINF";
+pub(crate) const BYTERANGE_TAG: &str = "EXT-X-BYTERANGE";
+pub(crate) const KEY_TAG: &str = "EXT-X-KEY";
+pub(crate) const DISCONTINUITY_TAG: &str = "EXT-X-DISCONTINUITY";
+pub(crate) const MEDIA_TAG: &str = "EXT-X-MEDIA";
+pub(crate) const STREAM_INF_TAG: &str = "EXT-X-STREAM-INF";
+pub(crate) const I_FRAME_STREAM_INF_TAG: &str = "EXT-X-I-FRAME-STREAM-INF";
+pub(crate) const SKIP_TAG: &str = "EXT-X-SKIP";
+pub(crate) const I_FRAMES_ONLY_TAG: &str = "EXT-X-I-FRAMES-ONLY";
+pub(crate) const MAP_TAG: &str = "EXT-X-MAP";
+pub(crate) const PROGRAM_DATE_TIME_TAG: &str = "EXT-X-PROGRAM-DATE-TIME";
+pub(crate) const DISCONTINUITY_SEQUENCE_TAG: &str = "EXT-X-DISCONTINUITY-SEQUENCE";
+pub(crate) const GAP_TAG: &str = "EXT-X-GAP";
+pub(crate) const PART_TAG: &str = "EXT-X-PART";
+pub(crate) const PART_INF_TAG: &str = "EXT-X-PART-INF";
+pub(crate) const PRELOAD_HINT_TAG: &str = "EXT-X-PRELOAD-HINT";
+pub(crate) const RENDITION_REPORT_TAG: &str = "EXT-X-RENDITION-REPORT";
+pub(crate) const DATERANGE_TAG: &str = "EXT-X-DATERANGE";
+pub(crate) const DEFINE_TAG: &str = "EXT-X-DEFINE";
+pub(crate) const SESSION_DATA_TAG: &str = "EXT-X-SESSION-DATA";
+pub(crate) const SESSION_KEY_TAG: &str = "EXT-X-SESSION-KEY";
+
+/// Every tag the tokenizer knows, for matching names case-insensitively.
+const TAGS: [&str; 29] = [
+    HEADER_TAG, VERSION_TAG, ENDLIST_TAG, DURATION_TAG, MEDIA_SEQUENCE_TAG, ALLOW_CACHE_TAG, SEGMENT_TAG,
+    BYTERANGE_TAG, KEY_TAG, DISCONTINUITY_TAG, MEDIA_TAG, STREAM_INF_TAG, I_FRAME_STREAM_INF_TAG, SKIP_TAG,
+    I_FRAMES_ONLY_TAG, MAP_TAG, PROGRAM_DATE_TIME_TAG, DISCONTINUITY_SEQUENCE_TAG, GAP_TAG, PART_TAG, PART_INF_TAG,
+    PRELOAD_HINT_TAG, RENDITION_REPORT_TAG, PLAYLIST_TYPE_TAG, DATERANGE_TAG, "EXT-X-INDEPENDENT-SEGMENTS",
+    DEFINE_TAG, SESSION_DATA_TAG, SESSION_KEY_TAG,
+];
+
+/// Tags whose value is an attribute list.
+pub(crate) const ATTRIBUTE_LIST_TAGS: [&str; 14] = [
+    KEY_TAG, MEDIA_TAG, STREAM_INF_TAG, I_FRAME_STREAM_INF_TAG, SKIP_TAG, MAP_TAG, PART_TAG, PART_INF_TAG,
+    PRELOAD_HINT_TAG, RENDITION_REPORT_TAG, DATERANGE_TAG, DEFINE_TAG, SESSION_DATA_TAG, SESSION_KEY_TAG,
+];
+
+/// A single line of an ext-m3u file. Blank lines produce no event.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<'a> {
+    /// The `#EXTM3U` header. See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.1.1>.
+    Header,
+
+    /// See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.1.2>.
+    Version(u64),
+
+    /// See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.3.1>.
+    TargetDuration(u64),
+
+    /// See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.3.2>.
+    MediaSequence(u64),
+
+    /// Whether clients may cache segments, from the deprecated EXT-X-ALLOW-CACHE tag. See
+    /// <https://datatracker.ietf.org/doc/html/draft-pantos-http-live-streaming-13#section-3.4.5>.
+    AllowCache(bool),
+
+    /// See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.3.5>.
+    PlaylistType(PlaylistType),
+
+    /// See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2.1>.
+    ExtInf {
+        duration: SegmentDuration,
+        /// Human-readable title after the comma, empty if there is none.
+        title: &'a str,
+    },
+
+    /// See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2.2>.
+    ByteRange(ByteRange),
+
+    /// See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2.3>.
+    Discontinuity,
+
+    /// See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2.4>.
+    Key(EncryptionKey),
+
+    /// See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2.5>.
+    Map(SegmentMap),
+
+    /// See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2.6>.
+    ProgramDateTime(ProgramDateTime),
+
+    /// See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2.7>.
+    DateRange(DateRange),
+
+    /// See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.3.3>.
+    DiscontinuitySequence(u64),
+
+    /// The next segment is unavailable. See
+    /// <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.4.7>.
+    Gap,
+
+    /// See <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.4.9>.
+    Part(PartialSegment),
+
+    /// Duration no part can exceed, from the PART-TARGET attribute. See
+    /// <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.3.7>.
+    PartInf(SegmentDuration),
+
+    /// See <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.5.3>.
+    PreloadHint(PreloadHint),
+
+    /// See <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.5.4>.
+    RenditionReport(RenditionReport),
+
+    /// A variable for later lines to reference, from the NAME and VALUE attributes. See
+    /// <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.2.3>.
+    Define { name: &'a str, value: &'a str },
+
+    /// See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.3.4>.
+    EndList,
+
+    /// Number of segments left out of a playlist delta update, from the SKIPPED-SEGMENTS
+    /// attribute. See <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.5.2>.
+    Skip(u64),
+
+    /// Each segment is a single I-frame, for trick play. See
+    /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.3.6>.
+    IFramesOnly,
+
+    /// See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.4.1>.
+    Media(Rendition),
+
+    /// See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.4.2>. The URI of the variant
+    /// is on the next line, so [`VariantStream::uri`] is empty here.
+    StreamInf(VariantStream),
+
+    /// An I-frame playlist for trick play, with its URI. See
+    /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.4.3>.
+    IFrameStreamInf(VariantStream),
+
+    /// See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.4.4>.
+    SessionData(SessionData),
+
+    /// A key clients may load before the media playlists that use it. See
+    /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.4.5>.
+    SessionKey(EncryptionKey),
+
+    /// A line which is neither blank nor starts with `#`.
+    Uri(&'a str),
+
+    /// Any `#EXT` tag not covered above, split at the first `:`.
+    Unknown { name: &'a str, value: Option<&'a str> },
+
+    /// A line starting with `#` but not `#EXT`, without the leading `#`.
+    Comment(&'a str),
+}
+
+impl Event<'_> {
+    /// Name of the tag if it isn't in RFC 8216 but was added by the draft of its successor.
+    pub(crate) fn rfc8216bis_tag(&self) -> Option<&'static str> {
+        Some(match self {
+            Event::Gap => GAP_TAG,
+            Event::Part(_) => PART_TAG,
+            Event::PartInf(_) => PART_INF_TAG,
+            Event::PreloadHint(_) => PRELOAD_HINT_TAG,
+            Event::RenditionReport(_) => RENDITION_REPORT_TAG,
+            Event::Skip(_) => SKIP_TAG,
+            Event::Define { .. } => DEFINE_TAG,
+            _ => return None,
+        })
+    }
+}
+
+/// Iterator returned by [`events`]. Yields the (1-based) line number alongside each event.
+#[derive(Debug, Clone)]
+pub struct Events<'a> {
+    lines: Enumerate<Lines<'a>>,
+}
+
+/// Tokenizes the given file. Tags whose values can't be parsed produce an error, but scanning
+/// can continue with the next line.
+pub fn events(file: &str) -> Events<'_> {
+    Events { lines: file.lines().enumerate() }
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Result<(usize, Event<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (index, line) in self.lines.by_ref() {
+            let Some(event) = parse_line(line) else {
+                continue;
+            };
+            return Some(event.map(|event| (index + 1, event)));
+        }
+        None
+    }
+}
+
+/// Name of the tag on the line without the leading `#` or any value. Not meaningful for URIs.
+pub(crate) fn tag_name(line: &str) -> &str {
+    let name = line.trim_start_matches('#');
+    name.split(':').next().unwrap_or(name)
+}
+
+/// Tokenizes one line, returning `None` for blank lines.
+pub(crate) fn parse_line(line: &str) -> Option<Result<Event<'_>>> {
+    //only a line starting with whitespace needs trimming to tell
+    if line.is_empty() || line.starts_with(char::is_whitespace) && line.trim().is_empty() {
+        return None;
+    }
+    Some(parse_event(line))
+}
+
+/// Rewrites a sloppy line into what the tokenizer expects: surrounding whitespace removed, known
+/// tag names in uppercase and whitespace trimmed from tag values. Returns a description of each
+/// fix alongside the line, which is borrowed if nothing needed fixing.
+pub(crate) fn normalize(line: &str) -> (Cow<'_, str>, Vec<String>) {
+    let mut fixes = Vec::new();
+    let trimmed = line.trim();
+    if trimmed.len() != line.len() && !trimmed.is_empty() {
+        fixes.push("Whitespace around line".to_string());
+    }
+    let Some(tag) = trimmed.strip_prefix('#') else {
+        return (Cow::Borrowed(trimmed), fixes);
+    };
+    let (name, value) = match tag.split_once(':') {
+        Some((name, value)) => (name, Some(value)),
+        None => (tag, None),
+    };
+
+    let mut normalized_name = Cow::Borrowed(name);
+    if let Some(known) = TAGS.iter().find(|x| x.eq_ignore_ascii_case(name.trim_end())) {
+        if *known != name {
+            fixes.push(format!("Tag #{} should be written #{}", name, known));
+            normalized_name = Cow::Borrowed(known);
+        }
+    }
+    let mut normalized_value = value.map(Cow::Borrowed);
+    let known = TAGS.contains(&normalized_name.as_ref());
+    if let Some(value) = value.filter(|_| known) {
+        let fixed = if ATTRIBUTE_LIST_TAGS.contains(&normalized_name.as_ref()) {
+            crate::attributes::strip_whitespace(value)
+        } else if normalized_name == SEGMENT_TAG {
+            //only the duration, the title may contain spaces
+            let (duration, title) = value.split_once(',').unwrap_or((value, ""));
+            let trimmed = duration.trim();
+            (trimmed.len() != duration.len()).then(|| {
+                if value.contains(',') {
+                    format!("{},{}", trimmed, title)
+                } else {
+                    trimmed.to_string()
+                }
+            })
+        } else {
+            Some(value.trim()).filter(|x| x.len() != value.len()).map(str::to_string)
+        };
+        if let Some(fixed) = fixed {
+            fixes.push(format!("Whitespace in value of #{}", normalized_name));
+            normalized_value = Some(Cow::Owned(fixed));
+        }
+    }
+
+    if normalized_name == SEGMENT_TAG {
+        if let Some(fixed) = normalized_value.as_deref().and_then(decimal_comma) {
+            let duration = fixed.split(',').next().unwrap_or_default();
+            fixes.push(format!("Comma as decimal separator in #{}, duration read as {}", SEGMENT_TAG, duration));
+            normalized_value = Some(Cow::Owned(fixed));
+        }
+    }
+
+    if fixes.is_empty() {
+        return (Cow::Borrowed(line), fixes);
+    }
+    let line = match normalized_value {
+        Some(value) => format!("#{}:{}", normalized_name, value),
+        None => format!("#{}", normalized_name),
+    };
+    (Cow::Owned(line), fixes)
+}
+
+/// An EXTINF value like `12,166,` written by some encoders with a comma as the decimal separator,
+/// as `12.166,`. The title comma must follow, since `12,166` is a duration of 12 titled 166.
+fn decimal_comma(value: &str) -> Option<String> {
+    let (whole, rest) = value.split_once(',')?;
+    let (fraction, title) = rest.split_once(',')?;
+    let digits = |x: &str| !x.is_empty() && x.bytes().all(|x| x.is_ascii_digit());
+    (digits(whole) && digits(fraction)).then(|| format!("{}.{},{}", whole, fraction, title))
+}
+
+/// The value of an EXTINF tag, the duration and an optional title.
+fn segment_info(info: &str) -> Result<Event<'_>> {
+    let (duration, title) = info.split_once(',').unwrap_or((info, ""));
+    match duration.parse::<SegmentDuration>() {
+        Ok(duration) => Ok(Event::ExtInf { duration, title }),
+        Err(..) => Err(anyhow::Error::msg("Segment tag found, but could not parse duration")),
+    }
+}
+
+fn parse_event(line: &str) -> Result<Event<'_>> {
+    //every segment has one, so it's worth skipping the search for the end of the name
+    if let Some(info) = line.strip_prefix("#EXTINF:") {
+        return segment_info(info);
+    }
+    if !line.starts_with("#EXT") {
+        return Ok(match line.strip_prefix('#') {
+            Some(comment) => Event::Comment(comment),
+            None => Event::Uri(line),
+        });
+    }
+    let tag = &line[1..];
+    let (name, value) = match tag.split_once(':') {
+        Some((name, value)) => (name, Some(value)),
+        None => (tag, None),
+    };
+
+    Ok(match name {
+        HEADER_TAG => Event::Header,
+        VERSION_TAG => match value.map(str::parse::<u64>) {
+            Some(Ok(version)) => Event::Version(version),
+            _ => return Err(anyhow::Error::msg("Version tag found, but could not parse")),
+        },
+        DURATION_TAG => match value.map(str::parse::<u64>) {
+            Some(Ok(duration)) => Event::TargetDuration(duration),
+            _ => return Err(anyhow::Error::msg("Duration tag found, but could not parse")),
+        },
+        MEDIA_SEQUENCE_TAG => match value.map(str::parse::<u64>) {
+            Some(Ok(sequence)) => Event::MediaSequence(sequence),
+            _ => return Err(anyhow::Error::msg("Media sequence tag found, but could not parse")),
+        },
+        ALLOW_CACHE_TAG => match value {
+            Some("YES") => Event::AllowCache(true),
+            Some("NO") => Event::AllowCache(false),
+            _ => return Err(anyhow::Error::msg("Allow cache tag found, but could not parse")),
+        },
+        PLAYLIST_TYPE_TAG => match PlaylistType::parse(value.unwrap_or_default()) {
+            Ok(playlist_type) => Event::PlaylistType(playlist_type),
+            Err(error) => return Err(error.context("Playlist type tag found, but could not parse")),
+        },
+        SEGMENT_TAG => return segment_info(value.unwrap_or_default()),
+        BYTERANGE_TAG => match value.unwrap_or_default().parse::<ByteRange>() {
+            Ok(byte_range) => Event::ByteRange(byte_range),
+            Err(error) => return Err(error.context("Byte range tag found, but could not parse")),
+        },
+        DISCONTINUITY_TAG => Event::Discontinuity,
+        KEY_TAG => match EncryptionKey::parse(value.unwrap_or_default()) {
+            Ok(key) => Event::Key(key),
+            Err(error) => return Err(error.context("Key tag found, but could not parse")),
+        },
+        MAP_TAG => match SegmentMap::parse(value.unwrap_or_default()) {
+            Ok(map) => Event::Map(map),
+            Err(error) => return Err(error.context("Map tag found, but could not parse")),
+        },
+        PROGRAM_DATE_TIME_TAG => match value.unwrap_or_default().parse::<ProgramDateTime>() {
+            Ok(date_time) => Event::ProgramDateTime(date_time),
+            Err(error) => return Err(error.context("Program date time tag found, but could not parse")),
+        },
+        DATERANGE_TAG => match DateRange::parse(value.unwrap_or_default()) {
+            Ok(date_range) => Event::DateRange(date_range),
+            Err(error) => return Err(error.context("Date range tag found, but could not parse")),
+        },
+        DISCONTINUITY_SEQUENCE_TAG => match value.map(str::parse::<u64>) {
+            Some(Ok(sequence)) => Event::DiscontinuitySequence(sequence),
+            _ => return Err(anyhow::Error::msg("Discontinuity sequence tag found, but could not parse")),
+        },
+        GAP_TAG => Event::Gap,
+        PART_TAG => match PartialSegment::parse(value.unwrap_or_default()) {
+            Ok(part) => Event::Part(part),
+            Err(error) => return Err(error.context("Part tag found, but could not parse")),
+        },
+        PART_INF_TAG => match parse_part_inf(value.unwrap_or_default()) {
+            Ok(part_target) => Event::PartInf(part_target),
+            Err(error) => return Err(error.context("Part information tag found, but could not parse")),
+        },
+        PRELOAD_HINT_TAG => match PreloadHint::parse(value.unwrap_or_default()) {
+            Ok(hint) => Event::PreloadHint(hint),
+            Err(error) => return Err(error.context("Preload hint tag found, but could not parse")),
+        },
+        RENDITION_REPORT_TAG => match RenditionReport::parse(value.unwrap_or_default()) {
+            Ok(report) => Event::RenditionReport(report),
+            Err(error) => return Err(error.context("Rendition report tag found, but could not parse")),
+        },
+        DEFINE_TAG => match parse_define(value.unwrap_or_default()) {
+            Ok((name, value)) => Event::Define { name, value },
+            Err(error) => return Err(error.context("Define tag found, but could not parse")),
+        },
+        ENDLIST_TAG => Event::EndList,
+        I_FRAMES_ONLY_TAG => Event::IFramesOnly,
+        SKIP_TAG => match parse_skip(value.unwrap_or_default()) {
+            Ok(skipped_segments) => Event::Skip(skipped_segments),
+            Err(error) => return Err(error.context("Skip tag found, but could not parse")),
+        },
+        MEDIA_TAG => match Rendition::parse(value.unwrap_or_default()) {
+            Ok(rendition) => Event::Media(rendition),
+            Err(error) => return Err(error.context("Media tag found, but could not parse")),
+        },
+        STREAM_INF_TAG => match VariantStream::parse(value.unwrap_or_default()) {
+            Ok(variant) => Event::StreamInf(variant),
+            Err(error) => return Err(error.context("Stream tag found, but could not parse")),
+        },
+        I_FRAME_STREAM_INF_TAG => match VariantStream::parse_i_frame(value.unwrap_or_default()) {
+            Ok(variant) => Event::IFrameStreamInf(variant),
+            Err(error) => return Err(error.context("I-frame stream tag found, but could not parse")),
+        },
+        SESSION_DATA_TAG => match SessionData::parse(value.unwrap_or_default()) {
+            Ok(data) => Event::SessionData(data),
+            Err(error) => return Err(error.context("Session data tag found, but could not parse")),
+        },
+        SESSION_KEY_TAG => match EncryptionKey::parse_session(value.unwrap_or_default()) {
+            Ok(key) => Event::SessionKey(key),
+            Err(error) => return Err(error.context("Session key tag found, but could not parse")),
+        },
+        _ => Event::Unknown { name, value },
+    })
+}
+
+fn parse_skip(attribute_list: &str) -> Result<u64> {
+    let attributes = AttributeList::parse(attribute_list)?;
+    match attributes.get("SKIPPED-SEGMENTS").map(str::parse::<u64>) {
+        Some(Ok(skipped_segments)) => Ok(skipped_segments),
+        Some(Err(_)) => Err(anyhow::Error::msg("Attribute SKIPPED-SEGMENTS should be a decimal integer")),
+        None => Err(anyhow::Error::msg("Skip is missing SKIPPED-SEGMENTS attribute")),
+    }
+}
+
+fn parse_define(attribute_list: &str) -> Result<(&str, &str)> {
+    let attributes = AttributeList::parse(attribute_list)?;
+    if attributes.get("IMPORT").is_some() || attributes.get("QUERYPARAM").is_some() {
+        return Err(anyhow::Error::msg("IMPORT and QUERYPARAM variables are not supported"));
+    }
+    let Some(name) = attributes.quoted_string("NAME")? else {
+        return Err(anyhow::Error::msg("Define is missing NAME attribute"));
+    };
+    if !variables::is_name(name) {
+        return Err(anyhow::anyhow!("Invalid variable name \"{}\"", name));
+    }
+    let Some(value) = attributes.quoted_string("VALUE")? else {
+        return Err(anyhow::Error::msg("Define is missing VALUE attribute"));
+    };
+    Ok((name, value))
+}
+
+fn parse_part_inf(attribute_list: &str) -> Result<SegmentDuration> {
+    let attributes = AttributeList::parse(attribute_list)?;
+    match attributes.get("PART-TARGET").map(str::parse::<SegmentDuration>) {
+        Some(Ok(part_target)) => Ok(part_target),
+        Some(Err(_)) => Err(anyhow::Error::msg("Attribute PART-TARGET should be a decimal floating-point number")),
+        None => Err(anyhow::Error::msg("Part information is missing PART-TARGET attribute")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenizes_lines() {
+        let file = indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-VERSION:4
+            #EXT-X-ALLOW-CACHE:NO
+            #EXT-X-TARGETDURATION:20
+            #EXT-X-MEDIA-SEQUENCE:1
+            # just a comment
+            #EXTINF:12.166,intro
+            #EXT-X-BYTERANGE:1430680@4048392
+            segment_1.ts
+            #EXT-X-INDEPENDENT-SEGMENTS
+            #EXT-X-ENDLIST
+        "};
+        let events: Vec<(usize, Event)> = events(file).collect::<Result<_>>().expect("should tokenize");
+        assert_eq!(
+            events,
+            vec![
+                (1, Event::Header),
+                (2, Event::Version(4)),
+                (3, Event::AllowCache(false)),
+                (4, Event::TargetDuration(20)),
+                (5, Event::MediaSequence(1)),
+                (6, Event::Comment(" just a comment")),
+                (7, Event::ExtInf { duration: SegmentDuration::from_millis(12166), title: "intro" }),
+                (8, Event::ByteRange(ByteRange { length: 1430680, offset: Some(4048392) })),
+                (9, Event::Uri("segment_1.ts")),
+                (10, Event::Unknown { name: "EXT-X-INDEPENDENT-SEGMENTS", value: None }),
+                (11, Event::EndList),
+            ]
+        );
+    }
+
+    #[test]
+    fn normalizes_sloppy_lines() {
+        let (line, fixes) = normalize("#extinf: 9.5 ,a  title");
+        assert_eq!(line, "#EXTINF:9.5,a  title");
+        assert_eq!(fixes, vec!["Tag #extinf should be written #EXTINF", "Whitespace in value of #EXTINF"]);
+
+        let (line, fixes) = normalize(r#"#EXT-X-KEY: METHOD=AES-128, URI="a key.bin""#);
+        assert_eq!(line, r#"#EXT-X-KEY:METHOD=AES-128,URI="a key.bin""#);
+        assert_eq!(fixes, vec!["Whitespace in value of #EXT-X-KEY"]);
+
+        assert_eq!(normalize("  segment.ts ").0, "segment.ts");
+        assert_eq!(normalize("#Ext-X-EndList").0, "#EXT-X-ENDLIST");
+        assert!(matches!(normalize("#EXT-X-TARGETDURATION:10"), (Cow::Borrowed(_), fixes) if fixes.is_empty()));
+        let (line, fixes) = normalize("#EXTINF:12,166,Intro, part 1");
+        assert_eq!(line, "#EXTINF:12.166,Intro, part 1");
+        assert_eq!(fixes, vec!["Comma as decimal separator in #EXTINF, duration read as 12.166"]);
+        assert_eq!(normalize("#EXTINF:12,166").0, "#EXTINF:12,166");
+        assert_eq!(normalize("#EXTINF:12,Take 2,").0, "#EXTINF:12,Take 2,");
+        //unknown tags and comments are left alone
+        assert_eq!(normalize("#ext-x-custom: value").0, "#ext-x-custom: value");
+    }
+
+    #[test]
+    fn continues_after_bad_value() {
+        let file = indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-VERSION:four
+            #EXT-X-TARGETDURATION:20
+        "};
+        let mut events = events(file);
+        assert!(matches!(events.next(), Some(Ok((1, Event::Header)))));
+        assert!(events.next().expect("line 2").is_err());
+        assert!(matches!(events.next(), Some(Ok((3, Event::TargetDuration(20))))));
+        assert!(events.next().is_none());
+    }
+}
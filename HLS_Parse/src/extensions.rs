@@ -0,0 +1,218 @@
+//! Hooks for tags the parser doesn't model, e.g. proprietary CDN or packager tags, so they can
+//! be consumed without forking the parser. A [`TagHandler`] registered in
+//! [`ParseOptions::tag_handlers`][crate::ParseOptions::tag_handlers] sees every unknown tag and
+//! can store typed data about it on the playlist or the segment it precedes.
+
+use core::any::{Any, TypeId};
+use core::fmt;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+
+/// Consumes tags the parser doesn't model.
+pub trait TagHandler: Send + Sync {
+    /// Called for every unknown tag in a media playlist, in order. Handlers ignore tags they
+    /// don't recognize, and an error fails the parse like an invalid standard tag.
+    fn handle(&self, tag: &CustomTag<'_>, extensions: &mut TagExtensions<'_>) -> Result<()>;
+}
+
+/// An unknown tag, passed to [`TagHandler::handle`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CustomTag<'a> {
+    /// Name without the `#`, e.g. `EXT-X-CUE-OUT`.
+    pub name: &'a str,
+
+    /// Everything after the colon, `None` if the tag has no value.
+    pub value: Option<&'a str>,
+
+    /// Line number, counting from 1.
+    pub line: usize,
+
+    /// Index of the segment whose URI comes next, which the tag belongs to if it's a segment
+    /// tag. After the last URI there's no such segment.
+    pub next_segment: usize,
+}
+
+/// Where a [`TagHandler`] stores what it parsed from a tag.
+#[derive(Debug)]
+pub struct TagExtensions<'a> {
+    /// Data for the whole playlist, from [`MediaPlaylist::extensions`][crate::MediaPlaylist::extensions].
+    pub playlist: &'a mut Extensions,
+
+    /// Data for the next segment, from
+    /// [`MediaSegment::extensions`][crate::MediaSegment::extensions]. Discarded if no URI follows.
+    pub segment: &'a mut Extensions,
+}
+
+/// The handlers in [`ParseOptions`][crate::ParseOptions], called in the order they were added.
+#[derive(Clone, Default)]
+pub struct TagHandlers(Vec<Arc<dyn TagHandler>>);
+
+impl TagHandlers {
+    pub fn push(&mut self, handler: impl TagHandler + 'static) {
+        self.0.push(Arc::new(handler));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn handle(&self, tag: &CustomTag<'_>, extensions: &mut TagExtensions<'_>) -> Result<()> {
+        self.0.iter().try_for_each(|handler| handler.handle(tag, extensions))
+    }
+}
+
+impl fmt::Debug for TagHandlers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TagHandlers({})", self.0.len())
+    }
+}
+
+/// Options are equal if they share the same handlers.
+impl PartialEq for TagHandlers {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len() && self.0.iter().zip(&other.0).all(|(a, b)| Arc::ptr_eq(a, b))
+    }
+}
+
+impl Eq for TagHandlers {}
+
+/// Typed data added by [`TagHandler`]s, at most one value of each type.
+#[derive(Debug, Clone, Default)]
+pub struct Extensions(HashMap<TypeId, Box<dyn Extension>>);
+
+impl Extensions {
+    /// Stores `value`, returning the value of the same type it replaces.
+    pub fn insert<T: Any + Clone + Send + Sync + fmt::Debug>(&mut self, value: T) -> Option<T> {
+        let previous = self.0.insert(TypeId::of::<T>(), Box::new(value))?;
+        previous.into_any().downcast().ok().map(|x| *x)
+    }
+
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.0.get(&TypeId::of::<T>()).and_then(|x| (**x).as_any().downcast_ref())
+    }
+
+    pub fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.0.get_mut(&TypeId::of::<T>()).and_then(|x| (**x).as_any_mut().downcast_mut())
+    }
+
+    /// The value of type `T`, inserting the default first if there is none, e.g. to collect
+    /// every tag of a kind into a `Vec`.
+    pub fn get_or_default<T: Any + Clone + Default + Send + Sync + fmt::Debug>(&mut self) -> &mut T {
+        let value = self.0.entry(TypeId::of::<T>()).or_insert_with(|| Box::new(T::default()));
+        (**value).as_any_mut().downcast_mut().expect("extensions are keyed by their type")
+    }
+
+    pub fn remove<T: Any>(&mut self) -> Option<T> {
+        self.0.remove(&TypeId::of::<T>())?.into_any().downcast().ok().map(|x| *x)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Extension data can't be compared, so playlists compare equal whatever handlers added.
+impl PartialEq for Extensions {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Extensions {}
+
+/// A value in [`Extensions`], which has to be cloneable with the playlist. `Box<dyn Extension>`
+/// implements it too, so calls on a box must deref to reach the value.
+trait Extension: Any + Send + Sync + fmt::Debug {
+    fn clone_box(&self) -> Box<dyn Extension>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T: Any + Clone + Send + Sync + fmt::Debug> Extension for T {
+    fn clone_box(&self) -> Box<dyn Extension> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+impl Clone for Box<dyn Extension> {
+    fn clone(&self) -> Self {
+        (**self).clone_box()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MediaPlaylist, ParseOptions};
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct CueOut(f64);
+
+    #[derive(Debug, Clone, Default, PartialEq)]
+    struct Comments(Vec<String>);
+
+    struct CueHandler;
+
+    impl TagHandler for CueHandler {
+        fn handle(&self, tag: &CustomTag<'_>, extensions: &mut TagExtensions<'_>) -> Result<()> {
+            match tag.name {
+                "EXT-X-CUE-OUT" => {
+                    let duration = tag.value.unwrap_or_default().parse()?;
+                    extensions.segment.insert(CueOut(duration));
+                }
+                "EXT-X-PACKAGER" => {
+                    let comments: &mut Comments = extensions.playlist.get_or_default();
+                    comments.0.push(format!("{}@{}", tag.value.unwrap_or_default(), tag.line));
+                }
+                _ => {}
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn stores_handled_tags() {
+        let mut options = ParseOptions::default();
+        options.tag_handlers.push(CueHandler);
+        let file = indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-PACKAGER:acme
+            #EXTINF:10,
+            first.ts
+            #EXT-X-CUE-OUT:30
+            #EXTINF:10,
+            ad.ts
+            #EXT-X-PACKAGER:acme-2
+        "};
+        let playlist = MediaPlaylist::parse_with_options(file, &options).unwrap();
+        assert_eq!(playlist.segments()[0].extensions().get::<CueOut>(), None);
+        assert_eq!(playlist.segments()[1].extensions().get::<CueOut>(), Some(&CueOut(30.0)));
+        let comments = playlist.extensions().get::<Comments>().unwrap();
+        assert_eq!(comments.0, vec!["acme@3".to_string(), "acme-2@9".to_string()]);
+
+        let error = MediaPlaylist::parse_with_options(&file.replace(":30", ":soon"), &options).unwrap_err();
+        assert!(error.to_string().contains("line 6"), "{:#}", error);
+
+        let mut extensions = Extensions::default();
+        assert_eq!(extensions.insert(CueOut(1.0)), None);
+        assert_eq!(extensions.insert(CueOut(2.0)), Some(CueOut(1.0)));
+        assert_eq!(extensions.clone().remove::<CueOut>(), Some(CueOut(2.0)));
+        assert!(!extensions.is_empty());
+    }
+}
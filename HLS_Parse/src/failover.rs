@@ -0,0 +1,96 @@
+//! Redundant variant streams. Per
+//! <https://datatracker.ietf.org/doc/html/rfc8216#section-6.2.3>, variants which only differ in
+//! their URI are backups of each other, usually served from different hosts, which clients can
+//! switch between when one fails.
+
+use crate::{MasterPlaylist, VariantStream};
+
+impl VariantStream {
+    /// Whether both variants have identical attributes, so either can be played in place of the
+    /// other. A variant is redundant with itself.
+    pub fn is_redundant_with(&self, other: &VariantStream) -> bool {
+        self.bandwidth() == other.bandwidth()
+            && self.average_bandwidth() == other.average_bandwidth()
+            && self.codecs() == other.codecs()
+            && self.resolution() == other.resolution()
+            && self.frame_rate() == other.frame_rate()
+            && self.audio() == other.audio()
+            && self.video() == other.video()
+            && self.subtitles() == other.subtitles()
+            && self.closed_captions() == other.closed_captions()
+            && self.video_range() == other.video_range()
+            && self.hdcp_level() == other.hdcp_level()
+            && self.supplemental_codecs() == other.supplemental_codecs()
+    }
+}
+
+impl MasterPlaylist {
+    /// Variants clustered by [`is_redundant_with`][VariantStream::is_redundant_with], in order of
+    /// their first appearance. Variants within a group keep playlist order, which is the order
+    /// clients should try them in. Variants without backups form groups of one.
+    pub fn variant_groups(&self) -> Vec<Vec<&VariantStream>> {
+        let mut groups: Vec<Vec<&VariantStream>> = Vec::new();
+        for variant in self.variants() {
+            match groups.iter_mut().find(|group| group[0].is_redundant_with(variant)) {
+                Some(group) => group.push(variant),
+                None => groups.push(vec![variant]),
+            }
+        }
+        groups
+    }
+
+    /// Other variants to switch to when `variant` fails, in the order to try them. Variants with
+    /// the same URI are left out, since they would fail the same way.
+    pub fn failover_candidates(&self, variant: &VariantStream) -> Vec<&VariantStream> {
+        self.variants()
+            .iter()
+            .filter(|candidate| candidate.is_redundant_with(variant) && candidate.uri() != variant.uri())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MASTER: &str = indoc::indoc! {r#"
+        #EXTM3U
+        #EXT-X-STREAM-INF:BANDWIDTH=1280000,RESOLUTION=640x360
+        https://a.example.com/low.m3u8
+        #EXT-X-STREAM-INF:BANDWIDTH=2560000,RESOLUTION=1280x720
+        https://a.example.com/high.m3u8
+        #EXT-X-STREAM-INF:BANDWIDTH=1280000,RESOLUTION=640x360
+        https://b.example.com/low.m3u8
+        #EXT-X-STREAM-INF:BANDWIDTH=1280000,RESOLUTION=640x360,CODECS="hvc1.1.6.L93.B0"
+        https://a.example.com/low-hevc.m3u8
+        #EXT-X-STREAM-INF:BANDWIDTH=1280000,RESOLUTION=640x360
+        https://c.example.com/low.m3u8
+    "#};
+
+    #[test]
+    fn groups_redundant_variants() {
+        let playlist = MasterPlaylist::parse_ext_m3u(MASTER).expect("should parse");
+        let groups: Vec<Vec<&str>> = playlist
+            .variant_groups()
+            .iter()
+            .map(|group| group.iter().map(|x| x.uri()).collect())
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                vec!["https://a.example.com/low.m3u8", "https://b.example.com/low.m3u8", "https://c.example.com/low.m3u8"],
+                vec!["https://a.example.com/high.m3u8"],
+                vec!["https://a.example.com/low-hevc.m3u8"],
+            ]
+        );
+    }
+
+    #[test]
+    fn lists_failover_candidates() {
+        let playlist = MasterPlaylist::parse_ext_m3u(MASTER).expect("should parse");
+        let candidates: Vec<&str> =
+            playlist.failover_candidates(&playlist.variants()[2]).iter().map(|x| x.uri()).collect();
+        assert_eq!(candidates, vec!["https://a.example.com/low.m3u8", "https://c.example.com/low.m3u8"]);
+        assert!(playlist.failover_candidates(&playlist.variants()[1]).is_empty());
+    }
+}
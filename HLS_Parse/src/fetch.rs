@@ -0,0 +1,481 @@
+//! Scheduling the requests a player makes for media, including resources a low-latency server
+//! announced with EXT-X-PRELOAD-HINT. Like [`LiveFollower`][crate::LiveFollower],
+//! [`FetchScheduler`] does no I/O: callers issue the requests it hands out over their own
+//! connection pool, ideally HTTP/2 so they share a connection, and report when each one finishes.
+//!
+//! Hinted requests are handed out as soon as they are scheduled and don't count against the
+//! pool size, since the server holds them open until the resource is produced. See
+//! <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-6.2.2>.
+
+use core::time::Duration;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Instant;
+
+use crate::{MediaPlaylist, PreloadHint, PreloadHintType, SegmentMap, SegmentUri};
+
+/// What a [`FetchRequest`] loads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FetchKind {
+    Segment,
+    Part,
+
+    /// A media initialization section.
+    Map,
+}
+
+/// The bytes of a resource to request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RequestRange {
+    Whole,
+
+    /// `length` bytes from `offset`, or everything from `offset` if the length isn't known, as for
+    /// a hinted resource which is still being produced.
+    Bytes { offset: u64, length: Option<u64> },
+}
+
+impl RequestRange {
+    /// Value for an HTTP `Range` request header, `None` for the whole resource.
+    pub fn to_http_range_header(&self) -> Option<String> {
+        match *self {
+            RequestRange::Whole => None,
+            RequestRange::Bytes { offset, length: None } => Some(format!("bytes={}-", offset)),
+            RequestRange::Bytes { offset, length: Some(length) } => {
+                Some(format!("bytes={}-{}", offset, (offset + length).saturating_sub(1)))
+            }
+        }
+    }
+}
+
+/// A request to issue, from [`FetchScheduler::next_request`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FetchRequest {
+    /// Identifies the request when reporting its progress to the scheduler.
+    pub id: u64,
+
+    pub kind: FetchKind,
+
+    /// Whether the resource comes from an EXT-X-PRELOAD-HINT and isn't listed yet.
+    pub hinted: bool,
+
+    /// As written in the playlist, to be resolved against its URL.
+    pub uri: SegmentUri,
+
+    pub range: RequestRange,
+
+    /// Media sequence number of the segment the resource belongs to, or is needed to parse for a
+    /// map.
+    pub sequence: u64,
+
+    /// Index of the part within the trailing parts, `None` for a segment or map.
+    pub part: Option<usize>,
+}
+
+/// Progress of a [`FetchScheduler`], to persist and hand to [`FetchScheduler::restore`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FetchState {
+    /// Requests in flight, whose responses may never arrive if the process stops, then the
+    /// queued ones, in the order they were handed out or queued.
+    pub pending: Vec<FetchRequest>,
+
+    /// URI, start offset and media sequence number of everything scheduled, which isn't
+    /// requested again while the playlist lists it.
+    pub requested: Vec<(String, Option<u64>, u64)>,
+}
+
+/// Timing of a finished request, for throughput estimation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestTiming {
+    pub request: FetchRequest,
+    pub bytes: u64,
+    pub started: Instant,
+
+    /// When the first byte arrived, if reported with [`FetchScheduler::first_byte`].
+    pub first_byte: Option<Instant>,
+
+    pub finished: Instant,
+}
+
+impl RequestTiming {
+    /// Time spent receiving the body. For hinted requests this leaves out the time the server
+    /// held the request before the resource existed, which says nothing about bandwidth.
+    pub fn transfer_duration(&self) -> Duration {
+        self.finished.saturating_duration_since(self.first_byte.unwrap_or(self.started))
+    }
+}
+
+/// Told about every request the scheduler hands out, e.g. to measure per-request timing for
+/// adaptive bitrate selection. Every method does nothing by default.
+pub trait FetchObserver: Send {
+    fn request_started(&mut self, _request: &FetchRequest, _now: Instant) {}
+    fn request_finished(&mut self, _timing: &RequestTiming) {}
+    fn request_failed(&mut self, _request: &FetchRequest, _status: Option<u16>, _now: Instant) {}
+}
+
+#[derive(Debug)]
+struct InFlight {
+    request: FetchRequest,
+    started: Instant,
+    first_byte: Option<Instant>,
+}
+
+/// Queues the requests needed to keep a buffer filled, never requesting the same bytes twice,
+/// and hands them out as connections become free.
+pub struct FetchScheduler {
+    /// Number of unhinted requests which may be in flight at once.
+    max_in_flight: usize,
+
+    queue: VecDeque<FetchRequest>,
+    in_flight: HashMap<u64, InFlight>,
+
+    /// URI and start offset of everything scheduled, so a hinted resource isn't requested again
+    /// once it's listed, with the latest media sequence number it was scheduled for.
+    scheduled: HashMap<(String, Option<u64>), u64>,
+
+    next_id: u64,
+    observers: Vec<Box<dyn FetchObserver>>,
+}
+
+impl FetchScheduler {
+    /// A scheduler for a pool of `max_in_flight` concurrent requests, at least 1.
+    pub fn new(max_in_flight: usize) -> Self {
+        Self {
+            max_in_flight: max_in_flight.max(1),
+            queue: VecDeque::new(),
+            in_flight: HashMap::new(),
+            scheduled: HashMap::new(),
+            next_id: 0,
+            observers: Vec::new(),
+        }
+    }
+
+    /// A scheduler carrying on from `state`, from [`state`][Self::state] of an earlier one, e.g.
+    /// persisted with a [`FollowerState`][crate::FollowerState] before an archiver restarted.
+    /// The pending requests are queued again in order, with new IDs.
+    pub fn restore(max_in_flight: usize, state: FetchState) -> Self {
+        let mut scheduler = Self::new(max_in_flight);
+        let requested = state.requested.into_iter().map(|(uri, offset, sequence)| ((uri, offset), sequence));
+        scheduler.scheduled = requested.collect();
+        for request in state.pending {
+            let key = schedule_key(&request.uri, request.range);
+            scheduler.scheduled.remove(&key);
+            scheduler.push(request.kind, request.hinted, request.uri, request.range, request.sequence, request.part);
+        }
+        scheduler
+    }
+
+    /// Progress so far, to persist for [`restore`][Self::restore].
+    pub fn state(&self) -> FetchState {
+        let mut in_flight: Vec<&InFlight> = self.in_flight.values().collect();
+        in_flight.sort_by_key(|x| x.request.id);
+        let pending = in_flight.into_iter().map(|x| x.request.clone()).chain(self.queue.iter().cloned()).collect();
+        let mut requested: Vec<(String, Option<u64>, u64)> =
+            self.scheduled.iter().map(|((uri, offset), sequence)| (uri.clone(), *offset, *sequence)).collect();
+        requested.sort_unstable();
+        FetchState { pending, requested }
+    }
+
+    pub fn add_observer(&mut self, observer: impl FetchObserver + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// Queues what [`MediaPlaylist::prefetch_plan`] needs to buffer `buffer_target` from
+    /// `from_sequence`, with each map ahead of the first media needing it, then the playlist's
+    /// preload hints. Call again after every reload; anything already scheduled is skipped, and
+    /// what the playlist no longer lists is forgotten.
+    ///
+    /// When the plan starts within the trailing parts, parts before the first INDEPENDENT one
+    /// are skipped since playback can't start from them, all of them if none is yet. Returns the
+    /// number of requests queued.
+    pub fn schedule(&mut self, playlist: &MediaPlaylist, from_sequence: u64, buffer_target: Duration) -> usize {
+        let queued = self.queue.len();
+        self.prune(playlist);
+        let plan = playlist.prefetch_plan(buffer_target, from_sequence);
+        let parts = playlist.trailing_parts();
+        let first_playable = if plan.first().is_some_and(|x| x.part.is_some()) {
+            plan.iter().position(|x| x.part.is_some_and(|part| parts[part].independent())).unwrap_or(plan.len())
+        } else {
+            0
+        };
+        for request in &plan[first_playable..] {
+            if let Some(map) = request.map {
+                self.push_map(map, request.sequence);
+            }
+            let range = match request.byte_range {
+                None => RequestRange::Whole,
+                Some(byte_range) => match byte_range.offset {
+                    Some(offset) => RequestRange::Bytes { offset, length: Some(byte_range.length) },
+                    //the offset can't be inferred, so there is no range to ask the server for
+                    None => continue,
+                },
+            };
+            let kind = if request.part.is_some() { FetchKind::Part } else { FetchKind::Segment };
+            self.push(kind, false, request.uri.clone(), range, request.sequence, request.part);
+        }
+
+        let next_sequence = playlist.media_sequence() + playlist.skipped_segments() + playlist.segments().len() as u64;
+        for hint in playlist.preload_hints() {
+            match hint.hint_type() {
+                PreloadHintType::Map => {
+                    self.push(FetchKind::Map, true, hint.uri().clone(), hint_range(hint), next_sequence, None);
+                }
+                PreloadHintType::Part => {
+                    let part = Some(parts.len());
+                    self.push(FetchKind::Part, true, hint.uri().clone(), hint_range(hint), next_sequence, part);
+                }
+            }
+        }
+        self.queue.len() - queued
+    }
+
+    /// The next request to issue: a hinted one whenever there is one, otherwise the next in
+    /// playback order if fewer than `max_in_flight` are in flight.
+    pub fn next_request(&mut self, now: Instant) -> Option<FetchRequest> {
+        let position = self.queue.iter().position(|x| x.hinted).or_else(|| {
+            let busy = self.in_flight.values().filter(|x| !x.request.hinted).count();
+            Some(0).filter(|_| busy < self.max_in_flight && !self.queue.is_empty())
+        })?;
+        let request = self.queue.remove(position)?;
+        for observer in &mut self.observers {
+            observer.request_started(&request, now);
+        }
+        self.in_flight.insert(request.id, InFlight { request: request.clone(), started: now, first_byte: None });
+        Some(request)
+    }
+
+    /// Records when the response to request `id` started arriving.
+    pub fn first_byte(&mut self, id: u64, now: Instant) {
+        if let Some(in_flight) = self.in_flight.get_mut(&id) {
+            in_flight.first_byte.get_or_insert(now);
+        }
+    }
+
+    /// Records that request `id` finished with `bytes` of body, freeing its connection.
+    pub fn finished(&mut self, id: u64, bytes: u64, now: Instant) -> Option<RequestTiming> {
+        let in_flight = self.in_flight.remove(&id)?;
+        let timing = RequestTiming {
+            request: in_flight.request,
+            bytes,
+            started: in_flight.started,
+            first_byte: in_flight.first_byte,
+            finished: now,
+        };
+        for observer in &mut self.observers {
+            observer.request_finished(&timing);
+        }
+        Some(timing)
+    }
+
+    /// Records that request `id` failed with the HTTP `status`, if there was a response. The
+    /// resource can be scheduled again.
+    pub fn failed(&mut self, id: u64, status: Option<u16>, now: Instant) {
+        let Some(in_flight) = self.in_flight.remove(&id) else {
+            return;
+        };
+        for observer in &mut self.observers {
+            observer.request_failed(&in_flight.request, status, now);
+        }
+        self.scheduled.remove(&schedule_key(&in_flight.request.uri, in_flight.request.range));
+    }
+
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Forgets what was scheduled for segments which have left the playlist, so following a live
+    /// stream doesn't grow the set without bound.
+    fn prune(&mut self, playlist: &MediaPlaylist) {
+        let mut listed: HashSet<&str> = HashSet::new();
+        for segment in playlist.segments() {
+            listed.insert(segment.url().as_str());
+            listed.extend(segment.map().map(|x| x.uri()));
+        }
+        listed.extend(playlist.trailing_parts().iter().map(|x| x.uri().as_str()));
+        listed.extend(playlist.preload_hints().iter().map(|x| x.uri().as_str()));
+        let first_sequence = playlist.media_sequence();
+        self.scheduled.retain(|(uri, _), sequence| *sequence >= first_sequence && listed.contains(uri.as_str()));
+    }
+
+    fn push_map(&mut self, map: &SegmentMap, sequence: u64) {
+        //a map without an offset starts at the beginning of the resource
+        let range = match map.byte_range() {
+            Some(byte_range) => RequestRange::Bytes {
+                offset: byte_range.offset.unwrap_or_default(),
+                length: Some(byte_range.length),
+            },
+            None => RequestRange::Whole,
+        };
+        self.push(FetchKind::Map, false, SegmentUri::new(map.uri()), range, sequence, None);
+    }
+
+    fn push(
+        &mut self,
+        kind: FetchKind,
+        hinted: bool,
+        uri: SegmentUri,
+        range: RequestRange,
+        sequence: u64,
+        part: Option<usize>,
+    ) {
+        //a map stays scheduled for as long as the media needing it is
+        let key = schedule_key(&uri, range);
+        if let Some(scheduled) = self.scheduled.get_mut(&key) {
+            *scheduled = (*scheduled).max(sequence);
+            return;
+        }
+        self.scheduled.insert(key, sequence);
+        self.queue.push_back(FetchRequest { id: self.next_id, kind, hinted, uri, range, sequence, part });
+        self.next_id += 1;
+    }
+}
+
+impl core::fmt::Debug for FetchScheduler {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FetchScheduler")
+            .field("max_in_flight", &self.max_in_flight)
+            .field("queue", &self.queue)
+            .field("in_flight", &self.in_flight)
+            .field("observers", &self.observers.len())
+            .finish_non_exhaustive()
+    }
+}
+
+fn hint_range(hint: &PreloadHint) -> RequestRange {
+    if hint.is_sub_range() {
+        RequestRange::Bytes { offset: hint.byte_range_start(), length: hint.byte_range_length() }
+    } else {
+        RequestRange::Whole
+    }
+}
+
+/// What identifies the same bytes across reloads: a hint and the part it becomes share the URI
+/// and start, but only the part knows the length.
+fn schedule_key(uri: &SegmentUri, range: RequestRange) -> (String, Option<u64>) {
+    let offset = match range {
+        RequestRange::Whole => None,
+        RequestRange::Bytes { offset, .. } => Some(offset),
+    };
+    (uri.to_string(), offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    const LOW_LATENCY: &str = indoc::indoc! {r#"
+        #EXTM3U
+        #EXT-X-VERSION:9
+        #EXT-X-TARGETDURATION:4
+        #EXT-X-MEDIA-SEQUENCE:100
+        #EXT-X-PART-INF:PART-TARGET=1.0
+        #EXT-X-MAP:URI="init.mp4"
+        #EXTINF:4.0,
+        100.mp4
+        #EXT-X-PART:DURATION=1.0,URI="101.mp4",BYTERANGE="800@0"
+        #EXT-X-PART:DURATION=1.0,URI="101.mp4",INDEPENDENT=YES,BYTERANGE="900"
+        #EXT-X-PRELOAD-HINT:TYPE=PART,URI="101.mp4",BYTERANGE-START=1700
+    "#};
+
+    #[derive(Default)]
+    struct Recorder(Arc<Mutex<Vec<(u64, Duration)>>>);
+
+    impl FetchObserver for Recorder {
+        fn request_finished(&mut self, timing: &RequestTiming) {
+            self.0.lock().unwrap().push((timing.request.id, timing.transfer_duration()));
+        }
+    }
+
+    fn summary(request: &FetchRequest) -> (FetchKind, bool, String, Option<String>) {
+        (request.kind, request.hinted, request.uri.to_string(), request.range.to_http_range_header())
+    }
+
+    #[test]
+    fn schedules_hints_ahead_of_pool() {
+        let playlist = MediaPlaylist::parse_ext_m3u(LOW_LATENCY).unwrap();
+        let mut scheduler = FetchScheduler::new(1);
+        let finished = Arc::new(Mutex::new(Vec::new()));
+        scheduler.add_observer(Recorder(finished.clone()));
+
+        //joining at the parts skips the first, which playback can't start from
+        assert_eq!(scheduler.schedule(&playlist, 101, Duration::from_secs(10)), 3);
+        let now = Instant::now();
+        let hint = scheduler.next_request(now).unwrap();
+        assert_eq!(summary(&hint), (FetchKind::Part, true, "101.mp4".to_string(), Some("bytes=1700-".to_string())));
+        let map = scheduler.next_request(now).unwrap();
+        assert_eq!(summary(&map), (FetchKind::Map, false, "init.mp4".to_string(), None));
+        assert_eq!(scheduler.next_request(now), None);
+
+        scheduler.first_byte(map.id, now + Duration::from_millis(100));
+        let timing = scheduler.finished(map.id, 1000, now + Duration::from_millis(300)).unwrap();
+        assert_eq!(timing.transfer_duration(), Duration::from_millis(200));
+        let part = scheduler.next_request(now).unwrap();
+        assert_eq!(summary(&part), (FetchKind::Part, false, "101.mp4".to_string(), Some("bytes=800-1699".to_string())));
+        assert_eq!(*finished.lock().unwrap(), vec![(map.id, Duration::from_millis(200))]);
+
+        //once the hinted part is listed it isn't requested again
+        let reloaded = LOW_LATENCY.replace(
+            r#"#EXT-X-PRELOAD-HINT:TYPE=PART,URI="101.mp4",BYTERANGE-START=1700"#,
+            "#EXT-X-PART:DURATION=1.0,URI=\"101.mp4\",BYTERANGE=\"700\"\n\
+             #EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"101.mp4\",BYTERANGE-START=2400",
+        );
+        let reloaded = MediaPlaylist::parse_ext_m3u(&reloaded).unwrap();
+        assert_eq!(scheduler.schedule(&reloaded, 101, Duration::from_secs(10)), 1);
+        assert_eq!(scheduler.next_request(now).map(|x| x.range.to_http_range_header()), Some(Some("bytes=2400-".to_string())));
+        //a restarted scheduler carries on with what was pending, without requesting anything twice
+        let state = scheduler.state();
+        let pending: Vec<_> = state.pending.iter().map(summary).collect();
+        assert_eq!(pending.len(), 3);
+        let mut restored = FetchScheduler::restore(1, state);
+        assert_eq!(restored.state().pending.iter().map(summary).collect::<Vec<_>>(), pending);
+        assert_eq!(restored.schedule(&reloaded, 101, Duration::from_secs(10)), 0);
+        assert_eq!(restored.next_request(now).map(|x| summary(&x)), Some(pending[1].clone()), "hints still go first");
+    }
+
+    #[test]
+    fn waits_for_an_independent_part() {
+        let dependent = LOW_LATENCY.replace(",INDEPENDENT=YES", "");
+        let playlist = MediaPlaylist::parse_ext_m3u(&dependent).unwrap();
+        let mut scheduler = FetchScheduler::new(1);
+        //only the hint is requested, since playback can't start from either part
+        assert_eq!(scheduler.schedule(&playlist, 101, Duration::from_secs(10)), 1);
+        let hint = scheduler.next_request(Instant::now()).unwrap();
+        assert!(hint.hinted);
+    }
+
+    #[test]
+    fn forgets_what_leaves_the_playlist() {
+        let mut scheduler = FetchScheduler::new(4);
+        let now = Instant::now();
+        let mut maps = 0;
+        for sequence in 0..500u64 {
+            let segments: String = (sequence..sequence + 3).map(|x| format!("#EXTINF:4.0,\n{}.mp4\n", x)).collect();
+            let file = format!(
+                "#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-MEDIA-SEQUENCE:{}\n#EXT-X-MAP:URI=\"init.mp4\"\n{}\
+                 #EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"{}.mp4\"\n",
+                sequence,
+                segments,
+                sequence + 3
+            );
+            let playlist = MediaPlaylist::parse_ext_m3u(&file).unwrap();
+            scheduler.schedule(&playlist, sequence, Duration::from_secs(60));
+            while let Some(request) = scheduler.next_request(now) {
+                maps += usize::from(request.kind == FetchKind::Map);
+                scheduler.finished(request.id, 1000, now);
+            }
+            //the three segments, the hint and the map
+            let scheduled = scheduler.scheduled.len();
+            assert!(scheduled <= 5, "{} scheduled after {} reloads", scheduled, sequence);
+        }
+        assert_eq!(maps, 1, "the map is still needed, so it isn't requested again");
+        assert_eq!(scheduler.state().requested.len(), 5);
+    }
+}
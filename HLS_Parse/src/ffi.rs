@@ -0,0 +1,639 @@
+//! C bindings, enabled with the `ffi` feature. The header is generated with
+//! `cbindgen --config cbindgen.toml --output include/hls_parsing.h`.
+//!
+//! Playlists are opaque handles owned by the caller and released with
+//! [`hls_media_playlist_free`] or [`hls_master_playlist_free`]. Segment, key, map and variant
+//! handles and strings borrowed from a playlist stay valid until the playlist is freed. Strings
+//! are not NUL-terminated, their length is returned through an out parameter instead, and
+//! optional strings are NULL when absent.
+
+use core::ffi::c_char;
+use core::ptr;
+use std::ffi::CString;
+use std::time::UNIX_EPOCH;
+
+use anyhow::Result;
+
+use crate::{ByteRange, EncryptionKey, MasterPlaylist, MediaPlaylist, MediaSegment, SegmentMap, VariantStream};
+
+/// A [`ByteRange`], with `has_offset` false when the range starts right after the previous one.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HlsByteRange {
+    pub length: u64,
+    pub offset: u64,
+    pub has_offset: bool,
+}
+
+/// Boxes a parsed playlist, or stores the error message in `error` and returns NULL.
+unsafe fn into_handle<T>(result: Result<T>, error: *mut *mut c_char) -> *mut T {
+    match result {
+        Ok(playlist) => Box::into_raw(Box::new(playlist)),
+        Err(parse_error) => {
+            if !error.is_null() {
+                // Messages never contain NUL, but don't trust that with a panic across FFI.
+                let message = parse_error.to_string().replace('\0', " ");
+                *error = CString::new(message).map_or(ptr::null_mut(), CString::into_raw);
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+unsafe fn borrow_str(string: &str, len: *mut usize) -> *const u8 {
+    *len = string.len();
+    string.as_ptr()
+}
+
+unsafe fn borrow_optional_str(string: Option<&str>, len: *mut usize) -> *const u8 {
+    match string {
+        Some(string) => borrow_str(string, len),
+        None => {
+            *len = 0;
+            ptr::null()
+        }
+    }
+}
+
+/// Stores `byte_range` in `out` and returns whether there was one.
+unsafe fn write_byte_range(byte_range: Option<ByteRange>, out: *mut HlsByteRange) -> bool {
+    let Some(byte_range) = byte_range else { return false };
+    *out = HlsByteRange {
+        length: byte_range.length,
+        offset: byte_range.offset.unwrap_or(0),
+        has_offset: byte_range.offset.is_some(),
+    };
+    true
+}
+
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    if data.is_null() { &[][..] } else { core::slice::from_raw_parts(data, len) }
+}
+
+/// Parses `len` bytes of UTF-8 at `data` into a new playlist handle.
+///
+/// On failure returns NULL and, if `error` is not NULL, stores a NUL-terminated message there
+/// which must be released with [`hls_string_free`].
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, and `error` must be NULL or point to writable
+/// storage for a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hls_media_playlist_parse(
+    data: *const u8,
+    len: usize,
+    error: *mut *mut c_char,
+) -> *mut MediaPlaylist {
+    into_handle(MediaPlaylist::parse_ext_m3u_bytes(bytes(data, len)), error)
+}
+
+/// Releases a playlist returned by [`hls_media_playlist_parse`]. NULL is ignored.
+///
+/// # Safety
+///
+/// `playlist` must be NULL or a handle which hasn't been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn hls_media_playlist_free(playlist: *mut MediaPlaylist) {
+    if !playlist.is_null() {
+        drop(Box::from_raw(playlist));
+    }
+}
+
+/// Releases an error message returned through [`hls_media_playlist_parse`] or
+/// [`hls_master_playlist_parse`]. NULL is ignored.
+///
+/// # Safety
+///
+/// `string` must be NULL or a message which hasn't been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn hls_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// See [`MediaPlaylist::version`].
+///
+/// # Safety
+///
+/// `playlist` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn hls_media_playlist_version(playlist: *const MediaPlaylist) -> u64 {
+    (*playlist).version()
+}
+
+/// See [`MediaPlaylist::target_duration`], in seconds.
+///
+/// # Safety
+///
+/// `playlist` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn hls_media_playlist_target_duration(playlist: *const MediaPlaylist) -> f64 {
+    (*playlist).target_duration().as_secs_f64()
+}
+
+/// See [`MediaPlaylist::ended`].
+///
+/// # Safety
+///
+/// `playlist` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn hls_media_playlist_ended(playlist: *const MediaPlaylist) -> bool {
+    (*playlist).ended()
+}
+
+/// Number of segments, for use with [`hls_media_playlist_segment`].
+///
+/// # Safety
+///
+/// `playlist` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn hls_media_playlist_segment_count(playlist: *const MediaPlaylist) -> usize {
+    (*playlist).segments().len()
+}
+
+/// Borrows the segment at `index`, or returns NULL if it is out of range.
+///
+/// # Safety
+///
+/// `playlist` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn hls_media_playlist_segment(
+    playlist: *const MediaPlaylist,
+    index: usize,
+) -> *const MediaSegment {
+    (*playlist).segments().get(index).map_or(ptr::null(), |segment| segment as *const _)
+}
+
+/// See [`MediaSegment::duration`], in seconds.
+///
+/// # Safety
+///
+/// `segment` must be a handle borrowed from a live playlist.
+#[no_mangle]
+pub unsafe extern "C" fn hls_media_segment_duration(segment: *const MediaSegment) -> f64 {
+    (*segment).duration().as_secs_f64()
+}
+
+/// See [`MediaSegment::url`]. Returns the start of the UTF-8 bytes and stores their count in
+/// `len`.
+///
+/// # Safety
+///
+/// `segment` must be a handle borrowed from a live playlist and `len` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn hls_media_segment_url(
+    segment: *const MediaSegment,
+    len: *mut usize,
+) -> *const u8 {
+    borrow_str((*segment).url().as_str(), len)
+}
+
+/// See [`MediaSegment::byte_range`]. Returns false, leaving `byte_range` untouched, if the
+/// segment is a whole resource.
+///
+/// # Safety
+///
+/// `segment` must be a handle borrowed from a live playlist and `byte_range` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn hls_media_segment_byte_range(
+    segment: *const MediaSegment,
+    byte_range: *mut HlsByteRange,
+) -> bool {
+    write_byte_range((*segment).byte_range(), byte_range)
+}
+
+/// See [`MediaSegment::discontinuity`].
+///
+/// # Safety
+///
+/// `segment` must be a handle borrowed from a live playlist.
+#[no_mangle]
+pub unsafe extern "C" fn hls_media_segment_discontinuity(segment: *const MediaSegment) -> bool {
+    (*segment).discontinuity()
+}
+
+/// See [`MediaSegment::gap`].
+///
+/// # Safety
+///
+/// `segment` must be a handle borrowed from a live playlist.
+#[no_mangle]
+pub unsafe extern "C" fn hls_media_segment_gap(segment: *const MediaSegment) -> bool {
+    (*segment).gap()
+}
+
+/// See [`MediaSegment::program_date_time`]. Stores the seconds since the Unix epoch and the
+/// offset from UTC it was written with, or returns false if the segment has no date.
+///
+/// # Safety
+///
+/// `segment` must be a handle borrowed from a live playlist, and `seconds` and `offset_minutes`
+/// must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn hls_media_segment_program_date_time(
+    segment: *const MediaSegment,
+    seconds: *mut f64,
+    offset_minutes: *mut i16,
+) -> bool {
+    let Some(date) = (*segment).program_date_time() else { return false };
+    *seconds = match date.to_system_time().duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs_f64(),
+        Err(error) => -error.duration().as_secs_f64(),
+    };
+    *offset_minutes = date.offset_minutes();
+    true
+}
+
+/// Number of keys applying to the segment, for use with [`hls_media_segment_key`].
+///
+/// # Safety
+///
+/// `segment` must be a handle borrowed from a live playlist.
+#[no_mangle]
+pub unsafe extern "C" fn hls_media_segment_key_count(segment: *const MediaSegment) -> usize {
+    (*segment).keys().len()
+}
+
+/// Borrows the key at `index`, or returns NULL if it is out of range.
+///
+/// # Safety
+///
+/// `segment` must be a handle borrowed from a live playlist.
+#[no_mangle]
+pub unsafe extern "C" fn hls_media_segment_key(
+    segment: *const MediaSegment,
+    index: usize,
+) -> *const EncryptionKey {
+    (*segment).keys().get(index).map_or(ptr::null(), |key| key as *const _)
+}
+
+/// See [`EncryptionKey::method`], as written in the playlist, e.g. `AES-128`.
+///
+/// # Safety
+///
+/// `key` must be a handle borrowed from a live playlist and `len` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn hls_encryption_key_method(key: *const EncryptionKey, len: *mut usize) -> *const u8 {
+    borrow_str((*key).method().as_str(), len)
+}
+
+/// See [`EncryptionKey::uri`].
+///
+/// # Safety
+///
+/// `key` must be a handle borrowed from a live playlist and `len` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn hls_encryption_key_uri(key: *const EncryptionKey, len: *mut usize) -> *const u8 {
+    borrow_optional_str((*key).uri(), len)
+}
+
+/// See [`EncryptionKey::iv`].
+///
+/// # Safety
+///
+/// `key` must be a handle borrowed from a live playlist and `len` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn hls_encryption_key_iv(key: *const EncryptionKey, len: *mut usize) -> *const u8 {
+    borrow_optional_str((*key).iv(), len)
+}
+
+/// See [`EncryptionKey::key_format`].
+///
+/// # Safety
+///
+/// `key` must be a handle borrowed from a live playlist and `len` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn hls_encryption_key_format(key: *const EncryptionKey, len: *mut usize) -> *const u8 {
+    borrow_str((*key).key_format(), len)
+}
+
+/// See [`MediaSegment::map`]. Returns NULL if the segment has no media initialization section.
+///
+/// # Safety
+///
+/// `segment` must be a handle borrowed from a live playlist.
+#[no_mangle]
+pub unsafe extern "C" fn hls_media_segment_map(segment: *const MediaSegment) -> *const SegmentMap {
+    (*segment).map().map_or(ptr::null(), |map| map as *const _)
+}
+
+/// See [`SegmentMap::uri`].
+///
+/// # Safety
+///
+/// `map` must be a handle borrowed from a live playlist and `len` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn hls_segment_map_uri(map: *const SegmentMap, len: *mut usize) -> *const u8 {
+    borrow_str((*map).uri(), len)
+}
+
+/// See [`SegmentMap::byte_range`], like [`hls_media_segment_byte_range`].
+///
+/// # Safety
+///
+/// `map` must be a handle borrowed from a live playlist and `byte_range` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn hls_segment_map_byte_range(map: *const SegmentMap, byte_range: *mut HlsByteRange) -> bool {
+    write_byte_range((*map).byte_range(), byte_range)
+}
+
+/// Parses `len` bytes of UTF-8 at `data` into a new master playlist handle, reporting errors like
+/// [`hls_media_playlist_parse`].
+///
+/// # Safety
+///
+/// As for [`hls_media_playlist_parse`].
+#[no_mangle]
+pub unsafe extern "C" fn hls_master_playlist_parse(
+    data: *const u8,
+    len: usize,
+    error: *mut *mut c_char,
+) -> *mut MasterPlaylist {
+    into_handle(MasterPlaylist::parse_ext_m3u_bytes(bytes(data, len)), error)
+}
+
+/// Releases a playlist returned by [`hls_master_playlist_parse`]. NULL is ignored.
+///
+/// # Safety
+///
+/// `playlist` must be NULL or a handle which hasn't been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn hls_master_playlist_free(playlist: *mut MasterPlaylist) {
+    if !playlist.is_null() {
+        drop(Box::from_raw(playlist));
+    }
+}
+
+/// See [`MasterPlaylist::version`].
+///
+/// # Safety
+///
+/// `playlist` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn hls_master_playlist_version(playlist: *const MasterPlaylist) -> u64 {
+    (*playlist).version()
+}
+
+/// Number of variant streams, for use with [`hls_master_playlist_variant`].
+///
+/// # Safety
+///
+/// `playlist` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn hls_master_playlist_variant_count(playlist: *const MasterPlaylist) -> usize {
+    (*playlist).variants().len()
+}
+
+/// Borrows the variant stream at `index`, or returns NULL if it is out of range.
+///
+/// # Safety
+///
+/// `playlist` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn hls_master_playlist_variant(
+    playlist: *const MasterPlaylist,
+    index: usize,
+) -> *const VariantStream {
+    (*playlist).variants().get(index).map_or(ptr::null(), |variant| variant as *const _)
+}
+
+/// See [`VariantStream::uri`].
+///
+/// # Safety
+///
+/// `variant` must be a handle borrowed from a live playlist and `len` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn hls_variant_stream_uri(variant: *const VariantStream, len: *mut usize) -> *const u8 {
+    borrow_str((*variant).uri(), len)
+}
+
+/// See [`VariantStream::bandwidth`].
+///
+/// # Safety
+///
+/// `variant` must be a handle borrowed from a live playlist.
+#[no_mangle]
+pub unsafe extern "C" fn hls_variant_stream_bandwidth(variant: *const VariantStream) -> u64 {
+    (*variant).bandwidth()
+}
+
+/// See [`VariantStream::average_bandwidth`]. Returns false if the playlist doesn't state it.
+///
+/// # Safety
+///
+/// `variant` must be a handle borrowed from a live playlist and `bandwidth` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn hls_variant_stream_average_bandwidth(
+    variant: *const VariantStream,
+    bandwidth: *mut u64,
+) -> bool {
+    let Some(average) = (*variant).average_bandwidth() else { return false };
+    *bandwidth = average;
+    true
+}
+
+/// See [`VariantStream::codecs`].
+///
+/// # Safety
+///
+/// `variant` must be a handle borrowed from a live playlist and `len` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn hls_variant_stream_codecs(variant: *const VariantStream, len: *mut usize) -> *const u8 {
+    borrow_optional_str((*variant).codecs(), len)
+}
+
+/// See [`VariantStream::resolution`]. Returns false if the playlist doesn't state it.
+///
+/// # Safety
+///
+/// `variant` must be a handle borrowed from a live playlist, and `width` and `height` must be
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn hls_variant_stream_resolution(
+    variant: *const VariantStream,
+    width: *mut u64,
+    height: *mut u64,
+) -> bool {
+    let Some(resolution) = (*variant).resolution() else { return false };
+    *width = resolution.width;
+    *height = resolution.height;
+    true
+}
+
+/// See [`VariantStream::frame_rate`]. Returns false if the playlist doesn't state it.
+///
+/// # Safety
+///
+/// `variant` must be a handle borrowed from a live playlist and `frame_rate` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn hls_variant_stream_frame_rate(variant: *const VariantStream, frame_rate: *mut f64) -> bool {
+    let Some(rate) = (*variant).frame_rate() else { return false };
+    *frame_rate = rate;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::ffi::CStr;
+
+    #[test]
+    fn iterates_segments() {
+        let file = indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-VERSION:3
+            #EXT-X-TARGETDURATION:10
+            #EXTINF:9.5,
+            first.ts
+            #EXTINF:4,
+            second.ts
+            #EXT-X-ENDLIST
+        "};
+        unsafe {
+            let playlist = hls_media_playlist_parse(file.as_ptr(), file.len(), ptr::null_mut());
+            assert!(!playlist.is_null());
+            assert_eq!(hls_media_playlist_version(playlist), 3);
+            assert_eq!(hls_media_playlist_target_duration(playlist), 10.0);
+            assert!(hls_media_playlist_ended(playlist));
+            assert_eq!(hls_media_playlist_segment_count(playlist), 2);
+
+            let segment = hls_media_playlist_segment(playlist, 1);
+            assert_eq!(hls_media_segment_duration(segment), 4.0);
+            let mut len = 0;
+            let url = hls_media_segment_url(segment, &mut len);
+            assert_eq!(core::slice::from_raw_parts(url, len), b"second.ts");
+
+            assert!(hls_media_playlist_segment(playlist, 2).is_null());
+            hls_media_playlist_free(playlist);
+        }
+    }
+
+    #[test]
+    fn exposes_segment_attributes() {
+        let file = indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-VERSION:5
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-MAP:URI=\"init.mp4\",BYTERANGE=\"720@0\"
+            #EXT-X-KEY:METHOD=AES-128,URI=\"key.bin\",IV=0x1
+            #EXT-X-PROGRAM-DATE-TIME:2010-02-19T14:54:23.5+08:00
+            #EXTINF:9.5,
+            #EXT-X-BYTERANGE:1000@720
+            media.mp4
+            #EXT-X-DISCONTINUITY
+            #EXTINF:4,
+            #EXT-X-BYTERANGE:500
+            media.mp4
+            #EXT-X-ENDLIST
+        "};
+        unsafe {
+            let playlist = hls_media_playlist_parse(file.as_ptr(), file.len(), ptr::null_mut());
+            assert!(!playlist.is_null());
+            let first = hls_media_playlist_segment(playlist, 0);
+            let second = hls_media_playlist_segment(playlist, 1);
+            let mut len = 0;
+            let string = |data: *const u8, len: usize| core::slice::from_raw_parts(data, len).to_vec();
+
+            let mut byte_range = HlsByteRange::default();
+            assert!(hls_media_segment_byte_range(first, &mut byte_range));
+            assert_eq!(byte_range, HlsByteRange { length: 1000, offset: 720, has_offset: true });
+            assert!(hls_media_segment_byte_range(second, &mut byte_range));
+            assert_eq!(byte_range, HlsByteRange { length: 500, offset: 0, has_offset: false });
+            assert!(!hls_media_segment_discontinuity(first));
+            assert!(hls_media_segment_discontinuity(second));
+            assert!(!hls_media_segment_gap(first));
+
+            let (mut seconds, mut offset_minutes) = (0.0, 0);
+            assert!(hls_media_segment_program_date_time(first, &mut seconds, &mut offset_minutes));
+            assert_eq!((seconds, offset_minutes), (1_266_562_463.5, 480));
+
+            assert_eq!(hls_media_segment_key_count(first), 1);
+            let key = hls_media_segment_key(second, 0);
+            assert_eq!(string(hls_encryption_key_method(key, &mut len), len), b"AES-128");
+            assert_eq!(string(hls_encryption_key_uri(key, &mut len), len), b"key.bin");
+            assert_eq!(string(hls_encryption_key_iv(key, &mut len), len), b"0x1");
+            assert_eq!(string(hls_encryption_key_format(key, &mut len), len), b"identity");
+            assert!(hls_media_segment_key(second, 1).is_null());
+
+            let map = hls_media_segment_map(first);
+            assert_eq!(string(hls_segment_map_uri(map, &mut len), len), b"init.mp4");
+            assert!(hls_segment_map_byte_range(map, &mut byte_range));
+            assert_eq!(byte_range, HlsByteRange { length: 720, offset: 0, has_offset: true });
+            hls_media_playlist_free(playlist);
+
+            let file = "#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:9.5,\nfirst.ts\n";
+            let playlist = hls_media_playlist_parse(file.as_ptr(), file.len(), ptr::null_mut());
+            let segment = hls_media_playlist_segment(playlist, 0);
+            assert!(!hls_media_segment_byte_range(segment, &mut byte_range));
+            assert!(!hls_media_segment_program_date_time(segment, &mut seconds, &mut offset_minutes));
+            assert_eq!(hls_media_segment_key_count(segment), 0);
+            assert!(hls_media_segment_map(segment).is_null());
+            hls_media_playlist_free(playlist);
+        }
+    }
+
+    #[test]
+    fn iterates_variants() {
+        let file = indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-VERSION:4
+            #EXT-X-STREAM-INF:BANDWIDTH=1280,AVERAGE-BANDWIDTH=1000,CODECS=\"avc1\",RESOLUTION=640x360,FRAME-RATE=30
+            low.m3u8
+            #EXT-X-STREAM-INF:BANDWIDTH=65000
+            audio.m3u8
+        "};
+        unsafe {
+            let playlist = hls_master_playlist_parse(file.as_ptr(), file.len(), ptr::null_mut());
+            assert!(!playlist.is_null());
+            assert_eq!(hls_master_playlist_version(playlist), 4);
+            assert_eq!(hls_master_playlist_variant_count(playlist), 2);
+
+            let low = hls_master_playlist_variant(playlist, 0);
+            let mut len = 0;
+            let uri = hls_variant_stream_uri(low, &mut len);
+            assert_eq!(core::slice::from_raw_parts(uri, len), b"low.m3u8");
+            assert_eq!(hls_variant_stream_bandwidth(low), 1280);
+            let mut average = 0;
+            assert!(hls_variant_stream_average_bandwidth(low, &mut average));
+            assert_eq!(average, 1000);
+            let codecs = hls_variant_stream_codecs(low, &mut len);
+            assert_eq!(core::slice::from_raw_parts(codecs, len), b"avc1");
+            let (mut width, mut height) = (0, 0);
+            assert!(hls_variant_stream_resolution(low, &mut width, &mut height));
+            assert_eq!((width, height), (640, 360));
+            let mut frame_rate = 0.0;
+            assert!(hls_variant_stream_frame_rate(low, &mut frame_rate));
+            assert_eq!(frame_rate, 30.0);
+
+            let audio = hls_master_playlist_variant(playlist, 1);
+            assert!(!hls_variant_stream_average_bandwidth(audio, &mut average));
+            assert!(hls_variant_stream_codecs(audio, &mut len).is_null());
+            assert_eq!(len, 0);
+            assert!(!hls_variant_stream_resolution(audio, &mut width, &mut height));
+            assert!(!hls_variant_stream_frame_rate(audio, &mut frame_rate));
+            assert!(hls_master_playlist_variant(playlist, 2).is_null());
+            hls_master_playlist_free(playlist);
+
+            let mut error = ptr::null_mut();
+            let file = "#EXTM3U\n#EXT-X-STREAM-INF:CODECS=\"avc1\"\nlow.m3u8\n";
+            assert!(hls_master_playlist_parse(file.as_ptr(), file.len(), &mut error).is_null());
+            assert!(!error.is_null());
+            hls_string_free(error);
+        }
+    }
+
+    #[test]
+    fn reports_errors() {
+        let file = "#EXTM3U\n#EXTINF:9.5,\nfirst.ts\n";
+        unsafe {
+            let mut error = ptr::null_mut();
+            let playlist = hls_media_playlist_parse(file.as_ptr(), file.len(), &mut error);
+            assert!(playlist.is_null());
+            assert_eq!(CStr::from_ptr(error).to_str(), Ok("Duration tag not found"));
+            hls_string_free(error);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Loading playlists from the local file system, e.g. to test against a packager's output
+//! without serving it.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::{MasterPlaylist, MediaPlaylist, SegmentUri};
+
+/// A master playlist read from disk with every media playlist it refers to, from
+/// [`LoadedMaster::from_path`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadedMaster {
+    pub master: MasterPlaylist,
+
+    /// Media playlists by the URI the master refers to them with, in the order first referred
+    /// to: variants, then I-frame variants, then renditions.
+    pub media: Vec<(String, MediaPlaylist)>,
+}
+
+impl LoadedMaster {
+    /// Reads the master playlist at `path` with [`MasterPlaylist::from_path`], then every media
+    /// playlist it refers to with [`MediaPlaylist::from_path`]. Fails if any can't be read or
+    /// parsed, including those with a remote URL.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let master = MasterPlaylist::from_path(path)?;
+        let variants = master.variants().iter().chain(master.i_frame_variants()).map(|x| x.uri());
+        let mut media: Vec<(String, MediaPlaylist)> = Vec::new();
+        for uri in variants.chain(master.renditions().iter().filter_map(|x| x.uri())) {
+            if media.iter().all(|(existing, _)| existing != uri) {
+                media.push((uri.to_string(), MediaPlaylist::from_path(uri)?));
+            }
+        }
+        Ok(Self { master, media })
+    }
+
+    /// The media playlist the master refers to as `uri`.
+    pub fn media(&self, uri: &str) -> Option<&MediaPlaylist> {
+        self.media.iter().find(|(existing, _)| existing == uri).map(|(_, playlist)| playlist)
+    }
+}
+
+impl MediaPlaylist {
+    /// Reads and parses the media playlist at `path`, then resolves relative URIs against its
+    /// directory with [`map_urls`][Self::map_urls], so they can be opened from the current one.
+    /// URLs with a scheme are left alone.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut playlist = Self::parse_ext_m3u_bytes(&read(path)?)
+            .with_context(|| format!("Could not parse {}", path.display()))?;
+        playlist.map_urls(|uri| resolve(path, uri));
+        Ok(playlist)
+    }
+}
+
+impl MasterPlaylist {
+    /// Like [`MediaPlaylist::from_path`], for the URIs of variants, I-frame variants and
+    /// renditions.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut playlist = Self::parse_ext_m3u_bytes(&read(path)?)
+            .with_context(|| format!("Could not parse {}", path.display()))?;
+        playlist.map_urls(|uri| resolve(path, uri));
+        Ok(playlist)
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).with_context(|| format!("Could not read {}", path.display()))
+}
+
+/// `uri` relative to the directory of the playlist at `path`.
+fn resolve(path: &Path, uri: &str) -> String {
+    if SegmentUri::new(uri).is_absolute() {
+        return uri.to_string();
+    }
+    let directory = path.parent().unwrap_or(Path::new(""));
+    directory.join(uri).to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_playlist_tree() {
+        let directory = std::env::temp_dir().join(format!("hls-files-{}", std::process::id()));
+        fs::create_dir_all(directory.join("video")).unwrap();
+        fs::write(directory.join("master.m3u8"), indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",NAME="English",DEFAULT=YES,URI="video/index.m3u8"
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000,AUDIO="aac"
+            video/index.m3u8
+        "#})
+        .unwrap();
+        fs::write(directory.join("video/index.m3u8"), indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-TARGETDURATION:4
+            #EXT-X-KEY:METHOD=AES-128,URI="https://keys.example.com/1"
+            #EXTINF:4,
+            0.ts
+            #EXT-X-ENDLIST
+        "#})
+        .unwrap();
+
+        let loaded = LoadedMaster::from_path(directory.join("master.m3u8"));
+        fs::remove_dir_all(&directory).unwrap();
+        let loaded = loaded.unwrap();
+        let uri = directory.join("video/index.m3u8").to_string_lossy().into_owned();
+        assert_eq!(loaded.master.variants()[0].uri(), uri);
+        assert_eq!(loaded.media.len(), 1);
+        let media = loaded.media(&uri).unwrap();
+        assert_eq!(media.segments()[0].url().as_str(), directory.join("video/0.ts").to_string_lossy());
+        assert_eq!(media.segments()[0].keys()[0].uri(), Some("https://keys.example.com/1"));
+        assert!(MediaPlaylist::from_path(directory.join("missing.m3u8")).is_err());
+    }
+}
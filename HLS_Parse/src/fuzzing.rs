@@ -0,0 +1,284 @@
+//! Random but valid playlists for property tests and fuzz targets. Playlists are generated as
+//! `ext-m3u` data and parsed, so every value is one parsing can produce, without diagnostics
+//! errors, and writing it out and parsing it again gives the same value.
+
+use core::fmt::Write;
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::events::{
+    ALLOW_CACHE_TAG, BYTERANGE_TAG, DISCONTINUITY_SEQUENCE_TAG, DISCONTINUITY_TAG, DURATION_TAG, ENDLIST_TAG,
+    HEADER_TAG, I_FRAMES_ONLY_TAG, KEY_TAG, MAP_TAG, MEDIA_SEQUENCE_TAG, MEDIA_TAG, PLAYLIST_TYPE_TAG,
+    PROGRAM_DATE_TIME_TAG, SEGMENT_TAG, SKIP_TAG, STREAM_INF_TAG, VERSION_TAG,
+};
+use crate::{MasterPlaylist, MediaPlaylist, ProgramDateTime};
+
+const URL_PREFIXES: [&str; 4] = ["", "media/", "/live/", "https://cdn.example.com/vod/"];
+const TITLES: [&str; 4] = ["Intro", "Part 2, continued", "Ad break", "Übersicht"];
+const LANGUAGES: [&str; 6] = ["en", "en-US", "fr-CA", "es-419", "zh-Hans", "de"];
+const VIDEO_CODECS: [&str; 4] = ["avc1.4d401f", "avc1.640028", "hvc1.2.4.L123.B0", "av01.0.08M.08"];
+const AUDIO_CODECS: [&str; 3] = ["mp4a.40.2", "ac-3", "ec-3"];
+const RESOLUTIONS: [(u32, u32); 4] = [(640, 360), (1280, 720), (1920, 1080), (3840, 2160)];
+const FRAME_RATES: [&str; 5] = ["23.976", "25.000", "29.970", "50.000", "59.940"];
+const CHANNELS: [&str; 4] = ["1", "2", "6", "16/JOC"];
+
+impl<'a> Arbitrary<'a> for MediaPlaylist {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let file = media_playlist(u)?;
+        Ok(MediaPlaylist::parse_ext_m3u(&file).unwrap_or_else(|error| panic!("{:#} in generated playlist\n{}", error, file)))
+    }
+}
+
+impl<'a> Arbitrary<'a> for MasterPlaylist {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let file = master_playlist(u)?;
+        Ok(MasterPlaylist::parse_ext_m3u(&file).unwrap_or_else(|error| panic!("{:#} in generated playlist\n{}", error, file)))
+    }
+}
+
+fn media_playlist(u: &mut Unstructured) -> Result<String> {
+    let skipped_segments: u64 = if u.ratio(1, 8)? { u.int_in_range(1..=100)? } else { 0 };
+    //high enough for every tag generated below, so there are no version diagnostics
+    let version: u64 = if skipped_segments > 0 { 9 } else { u.int_in_range(6..=9)? };
+    let i_frames_only = u.ratio(1, 8)?;
+
+    let mut segments = String::new();
+    let mut target_duration = 1;
+    let mut offset = 0;
+    //an implicit offset is only valid after a sub-range of the same resource
+    let mut previous_range_url: Option<String> = None;
+    for index in 0..u.int_in_range(0..=24)? {
+        if u.ratio(1, 8)? {
+            writeln!(segments, "#{}", DISCONTINUITY_TAG).unwrap();
+        }
+        if u.ratio(1, 6)? {
+            let key = match u.int_in_range(0..=3)? {
+                0 => "METHOD=NONE".to_string(),
+                1 => format!("METHOD=AES-128,URI=\"keys/{}.key\",IV=0x{:032X}", index, u.arbitrary::<u128>()?),
+                2 => format!(
+                    "METHOD=SAMPLE-AES,URI=\"skd://{}\",KEYFORMAT=\"com.apple.streamingkeydelivery\",KEYFORMATVERSIONS=\"1\"",
+                    index
+                ),
+                _ => format!(
+                    "METHOD=SAMPLE-AES,URI=\"data:text/plain;base64,{}\",KEYFORMAT=\"urn:uuid:edef8ba9-79d6-4ace-a3c8-27dcd51d21ed\"",
+                    index
+                ),
+            };
+            writeln!(segments, "#{}:{}", KEY_TAG, key).unwrap();
+        }
+        if u.ratio(1, 6)? {
+            let byte_range = if u.ratio(1, 2)? { format!(",BYTERANGE=\"{}@0\"", u.int_in_range(1..=4096)?) } else { String::new() };
+            writeln!(segments, "#{}:URI=\"init{}.mp4\"{}", MAP_TAG, index, byte_range).unwrap();
+        }
+        if u.ratio(1, 4)? {
+            //between 1970 and 2100, to the millisecond
+            let millis = u.int_in_range(0..=4_102_444_800_000)?;
+            let date_time = ProgramDateTime::from_system_time(std::time::UNIX_EPOCH + core::time::Duration::from_millis(millis));
+            writeln!(segments, "#{}:{}", PROGRAM_DATE_TIME_TAG, date_time).unwrap();
+        }
+        let mut url = format!("{}segment{}.ts", u.choose(&URL_PREFIXES)?, index);
+        let has_byte_range = u.ratio(1, 3)?;
+        if has_byte_range {
+            let length = u.int_in_range(1..=1_000_000)?;
+            let implicit_offset = u.ratio(1, 2)?;
+            match previous_range_url.take().filter(|_| implicit_offset) {
+                Some(previous) => {
+                    writeln!(segments, "#{}:{}", BYTERANGE_TAG, length).unwrap();
+                    url = previous;
+                }
+                None => writeln!(segments, "#{}:{}@{}", BYTERANGE_TAG, length, offset).unwrap(),
+            }
+            offset += length;
+        }
+        let scale = u.int_in_range(0..=3)?;
+        let mantissa: u64 = u.int_in_range(1..=12 * 10u64.pow(scale))?;
+        let duration = mantissa as f64 / 10f64.powi(scale as i32);
+        target_duration = target_duration.max(duration.round() as u64);
+        let title = if u.ratio(1, 3)? { *u.choose(&TITLES)? } else { "" };
+        writeln!(segments, "#{}:{:.*},{}", SEGMENT_TAG, scale as usize, duration, title).unwrap();
+        writeln!(segments, "{}", url).unwrap();
+        previous_range_url = Some(url).filter(|_| has_byte_range);
+    }
+
+    let mut file = format!("#{}\n#{}:{}\n#{}:{}\n", HEADER_TAG, VERSION_TAG, version, DURATION_TAG, target_duration);
+    if u.ratio(1, 2)? {
+        writeln!(file, "#{}:{}", MEDIA_SEQUENCE_TAG, u.int_in_range(1..=u32::MAX)?).unwrap();
+    }
+    if u.ratio(1, 4)? {
+        writeln!(file, "#{}:{}", DISCONTINUITY_SEQUENCE_TAG, u.int_in_range(1..=u16::MAX)?).unwrap();
+    }
+    //deprecated, only a warning before version 7
+    if version < 7 && u.ratio(1, 8)? {
+        writeln!(file, "#{}:{}", ALLOW_CACHE_TAG, if u.arbitrary()? { "YES" } else { "NO" }).unwrap();
+    }
+    if u.ratio(1, 4)? {
+        writeln!(file, "#{}:{}", PLAYLIST_TYPE_TAG, *u.choose(&["EVENT", "VOD"])?).unwrap();
+    }
+    if i_frames_only {
+        writeln!(file, "#{}", I_FRAMES_ONLY_TAG).unwrap();
+    }
+    if skipped_segments > 0 {
+        writeln!(file, "#{}:SKIPPED-SEGMENTS={}", SKIP_TAG, skipped_segments).unwrap();
+    }
+    file.push_str(&segments);
+    if u.arbitrary()? {
+        writeln!(file, "#{}", ENDLIST_TAG).unwrap();
+    }
+    Ok(file)
+}
+
+fn master_playlist(u: &mut Unstructured) -> Result<String> {
+    let mut file = format!("#{}\n", HEADER_TAG);
+    if u.arbitrary()? {
+        writeln!(file, "#{}:{}", VERSION_TAG, u.int_in_range(1..=9)?).unwrap();
+    }
+
+    let mut audio_groups = Vec::new();
+    for group in 0..u.int_in_range(0..=2)? {
+        let group_id = format!("audio{}", group);
+        for index in 0..u.int_in_range(1..=3)? {
+            let mut rendition = format!("TYPE=AUDIO,GROUP-ID=\"{}\",NAME=\"Audio {}\"", group_id, index);
+            if u.arbitrary()? {
+                write!(rendition, ",LANGUAGE=\"{}\"", u.choose(&LANGUAGES)?).unwrap();
+            }
+            rendition.push_str(&selection(u, index == 0, false)?);
+            if u.arbitrary()? {
+                write!(rendition, ",CHANNELS=\"{}\"", u.choose(&CHANNELS)?).unwrap();
+            }
+            //without a URI the audio is in the variant's own segments
+            if index > 0 || u.ratio(3, 4)? {
+                write!(rendition, ",URI=\"audio/{}/{}.m3u8\"", group, index).unwrap();
+            }
+            writeln!(file, "#{}:{}", MEDIA_TAG, rendition).unwrap();
+        }
+        audio_groups.push(group_id);
+    }
+
+    let subtitles = u.arbitrary::<bool>()?;
+    if subtitles {
+        for index in 0..u.int_in_range(1..=3)? {
+            let mut rendition = format!("TYPE=SUBTITLES,GROUP-ID=\"subs\",NAME=\"Subtitles {}\"", index);
+            write!(rendition, ",LANGUAGE=\"{}\"", u.choose(&LANGUAGES)?).unwrap();
+            rendition.push_str(&selection(u, index == 0, true)?);
+            if u.ratio(1, 4)? {
+                rendition.push_str(",CHARACTERISTICS=\"public.accessibility.transcribes-spoken-dialog\"");
+            }
+            write!(rendition, ",URI=\"subs/{}.m3u8\"", index).unwrap();
+            writeln!(file, "#{}:{}", MEDIA_TAG, rendition).unwrap();
+        }
+    }
+
+    let closed_captions = u.ratio(1, 3)?;
+    if closed_captions {
+        for channel in 1..=u.int_in_range(1..=4)? {
+            let mut rendition = format!("TYPE=CLOSED-CAPTIONS,GROUP-ID=\"cc\",NAME=\"CC{}\",INSTREAM-ID=\"CC{}\"", channel, channel);
+            if u.arbitrary()? {
+                write!(rendition, ",LANGUAGE=\"{}\"", u.choose(&LANGUAGES)?).unwrap();
+            }
+            rendition.push_str(&selection(u, channel == 1, false)?);
+            writeln!(file, "#{}:{}", MEDIA_TAG, rendition).unwrap();
+        }
+    }
+
+    //NONE must be on every variant or none of them
+    let no_closed_captions = !closed_captions && u.ratio(1, 4)?;
+    for index in 0..u.int_in_range(1..=8)? {
+        let bandwidth: u32 = u.int_in_range(64_000..=20_000_000)?;
+        let mut variant = format!("BANDWIDTH={}", bandwidth);
+        if u.arbitrary()? {
+            write!(variant, ",AVERAGE-BANDWIDTH={}", bandwidth / 4 * 3).unwrap();
+        }
+        let audio_group = if audio_groups.is_empty() { None } else { Some(u.choose(&audio_groups)?) };
+        if u.arbitrary()? {
+            let audio_codec = if audio_group.is_some() { format!(",{}", u.choose(&AUDIO_CODECS)?) } else { String::new() };
+            write!(variant, ",CODECS=\"{}{}\"", u.choose(&VIDEO_CODECS)?, audio_codec).unwrap();
+        }
+        if u.ratio(1, 8)? {
+            variant.push_str(",SUPPLEMENTAL-CODECS=\"dvh1.08.07/db4h\"");
+        }
+        if u.arbitrary()? {
+            let (width, height) = u.choose(&RESOLUTIONS)?;
+            write!(variant, ",RESOLUTION={}x{}", width, height).unwrap();
+        }
+        if u.arbitrary()? {
+            write!(variant, ",FRAME-RATE={}", u.choose(&FRAME_RATES)?).unwrap();
+        }
+        if u.ratio(1, 4)? {
+            write!(variant, ",HDCP-LEVEL={}", u.choose(&["NONE", "TYPE-0", "TYPE-1"])?).unwrap();
+        }
+        if u.ratio(1, 4)? {
+            write!(variant, ",VIDEO-RANGE={}", u.choose(&["SDR", "HLG", "PQ"])?).unwrap();
+        }
+        if let Some(group_id) = audio_group {
+            write!(variant, ",AUDIO=\"{}\"", group_id).unwrap();
+        }
+        if subtitles && u.arbitrary()? {
+            variant.push_str(",SUBTITLES=\"subs\"");
+        }
+        if closed_captions && u.arbitrary()? {
+            variant.push_str(",CLOSED-CAPTIONS=\"cc\"");
+        } else if no_closed_captions {
+            variant.push_str(",CLOSED-CAPTIONS=NONE");
+        }
+        writeln!(file, "#{}:{}", STREAM_INF_TAG, variant).unwrap();
+        writeln!(file, "{}video/{}.m3u8", u.choose(&URL_PREFIXES)?, index).unwrap();
+    }
+    Ok(file)
+}
+
+/// DEFAULT, AUTOSELECT and FORCED attributes. Only the first rendition of a group may be the
+/// default, which requires AUTOSELECT.
+fn selection(u: &mut Unstructured, first: bool, forced: bool) -> Result<String> {
+    let mut attributes = String::new();
+    let default = first && u.arbitrary()?;
+    if default {
+        attributes.push_str(",DEFAULT=YES");
+    }
+    if default || u.arbitrary()? {
+        attributes.push_str(",AUTOSELECT=YES");
+    }
+    if forced && u.ratio(1, 4)? {
+        attributes.push_str(",FORCED=YES");
+    }
+    Ok(attributes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Severity;
+
+    /// Deterministic bytes for [`Unstructured`], from a xorshift generator.
+    fn random_bytes(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn media_playlists_round_trip() {
+        for seed in 0..500 {
+            let bytes = random_bytes(seed, 4096);
+            let playlist = MediaPlaylist::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+            assert!(playlist.diagnostics().iter().all(|x| x.severity != Severity::Error), "{:?}", playlist.diagnostics());
+            let written = playlist.to_string();
+            assert_eq!(MediaPlaylist::parse_ext_m3u(&written).unwrap(), playlist, "{}", written);
+        }
+    }
+
+    #[test]
+    fn master_playlists_round_trip() {
+        for seed in 0..500 {
+            let bytes = random_bytes(seed, 4096);
+            let playlist = MasterPlaylist::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+            let written = playlist.to_string();
+            assert_eq!(MasterPlaylist::parse_ext_m3u(&written).unwrap(), playlist, "{}", written);
+        }
+    }
+}
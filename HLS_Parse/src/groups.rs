@@ -0,0 +1,110 @@
+//! Splitting a media playlist at its discontinuities, e.g. to feed each period to its own MSE
+//! SourceBuffer or ffmpeg concat demuxer input.
+
+use core::time::Duration;
+
+use crate::{EncryptionKey, MediaPlaylist, SegmentContext, SegmentMap};
+
+/// Consecutive segments sharing a discontinuity sequence number, and so timestamps and encoding
+/// parameters, from [`MediaPlaylist::discontinuity_groups`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscontinuityGroup<'a> {
+    pub discontinuity_sequence: u64,
+
+    /// Media initialization section in effect at the start of the group. A later EXT-X-MAP in
+    /// the same group shows up in the `map` of its segments.
+    pub map: Option<&'a SegmentMap>,
+
+    /// Keys in effect at the start of the group, which may still rotate within it.
+    pub keys: &'a [EncryptionKey],
+
+    /// Time from the start of the first listed segment to the start of the group.
+    pub start: Duration,
+
+    pub segments: Vec<SegmentContext<'a>>,
+}
+
+impl DiscontinuityGroup<'_> {
+    /// Sum of the durations of the segments.
+    pub fn duration(&self) -> Duration {
+        self.segments.iter().map(|x| x.segment.duration()).sum()
+    }
+}
+
+impl MediaPlaylist {
+    /// The segments split into groups at each EXT-X-DISCONTINUITY, in playback order. Empty if
+    /// there are no segments.
+    pub fn discontinuity_groups(&self) -> Vec<DiscontinuityGroup<'_>> {
+        let mut groups: Vec<DiscontinuityGroup> = Vec::new();
+        for context in self.iter_segments() {
+            match groups.last_mut() {
+                Some(group) if group.discontinuity_sequence == context.discontinuity_sequence => {
+                    group.segments.push(context);
+                }
+                _ => groups.push(DiscontinuityGroup {
+                    discontinuity_sequence: context.discontinuity_sequence,
+                    map: context.map,
+                    keys: context.keys,
+                    start: context.start,
+                    segments: vec![context],
+                }),
+            }
+        }
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_by_discontinuity() {
+        let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-VERSION:6
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-DISCONTINUITY-SEQUENCE:7
+            #EXT-X-MAP:URI="main-init.mp4"
+            #EXT-X-KEY:METHOD=AES-128,URI="1.key"
+            #EXTINF:10,
+            main1.mp4
+            #EXT-X-KEY:METHOD=AES-128,URI="2.key"
+            #EXTINF:10,
+            main2.mp4
+            #EXT-X-DISCONTINUITY
+            #EXT-X-MAP:URI="ad-init.mp4"
+            #EXT-X-KEY:METHOD=NONE
+            #EXTINF:5,
+            ad1.mp4
+            #EXTINF:5,
+            ad2.mp4
+            #EXT-X-DISCONTINUITY
+            #EXT-X-MAP:URI="main-init.mp4"
+            #EXT-X-KEY:METHOD=AES-128,URI="3.key"
+            #EXTINF:10,
+            main3.mp4
+        "#})
+        .unwrap();
+        let groups = playlist.discontinuity_groups();
+        let summary: Vec<_> = groups
+            .iter()
+            .map(|x| (x.discontinuity_sequence, x.map.unwrap().uri(), x.start, x.duration(), x.segments.len()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (7, "main-init.mp4", Duration::ZERO, Duration::from_secs(20), 2),
+                (8, "ad-init.mp4", Duration::from_secs(20), Duration::from_secs(10), 2),
+                (9, "main-init.mp4", Duration::from_secs(30), Duration::from_secs(10), 1),
+            ]
+        );
+        assert_eq!(groups[0].keys[0].uri(), Some("1.key"));
+        assert_eq!(groups[0].segments[1].keys[0].uri(), Some("2.key"));
+        assert!(groups[1].keys.is_empty());
+        assert_eq!(groups[2].segments[0].sequence, 4);
+
+        let empty = MediaPlaylist::parse_ext_m3u("#EXTM3U\n#EXT-X-TARGETDURATION:10\n").unwrap();
+        assert!(empty.discontinuity_groups().is_empty());
+    }
+}
@@ -0,0 +1,106 @@
+//! What makes two listings of a segment, e.g. in successive reloads of a live playlist, the same
+//! segment. The bytes fetched depend only on the URI and byte range, so the EXTINF duration,
+//! which encoders may round differently between reloads, and the title don't count.
+
+use core::fmt;
+
+use crate::{ByteRange, MediaSegment, SegmentContext, SegmentUri};
+
+/// Identifies a segment across playlist reloads, for dedupe sets and caches keyed by segment.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SegmentId {
+    pub sequence: u64,
+    pub uri: SegmentUri,
+
+    /// Offset and length of the segment's bytes, `None` for the whole resource. The offset is
+    /// `None` where it is implicit and can't be inferred, so it never collides with an explicit
+    /// one.
+    pub byte_range: Option<(Option<u64>, u64)>,
+}
+
+impl MediaSegment {
+    /// The segment's identity at media sequence number `sequence`. An EXT-X-BYTERANGE without
+    /// an offset continues the previous segment's, which only the playlist knows, so prefer
+    /// [`SegmentContext::identity`] for those.
+    pub fn identity(&self, sequence: u64) -> SegmentId {
+        SegmentId { sequence, uri: self.url().clone(), byte_range: self.byte_range().map(to_range) }
+    }
+}
+
+impl SegmentContext<'_> {
+    /// The segment's identity, with an implicit byte range offset filled in.
+    pub fn identity(&self) -> SegmentId {
+        let uri = self.segment.url().clone();
+        SegmentId { sequence: self.sequence, uri, byte_range: self.byte_range.map(to_range) }
+    }
+}
+
+fn to_range(byte_range: ByteRange) -> (Option<u64>, u64) {
+    (byte_range.offset, byte_range.length)
+}
+
+impl fmt::Display for SegmentId {
+    /// Formats the ID as `sequence:uri`, with `@offset-length` for a byte range, or `@length`
+    /// if the offset isn't known.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.sequence, self.uri)?;
+        match self.byte_range {
+            Some((Some(offset), length)) => write!(f, "@{}-{}", offset, length),
+            Some((None, length)) => write!(f, "@{}", length),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{MediaPlaylist, ParseOptions};
+
+    #[test]
+    fn ignores_duration_precision() {
+        let first = MediaPlaylist::parse_ext_m3u(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-VERSION:4
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-MEDIA-SEQUENCE:5
+            #EXTINF:10,
+            #EXT-X-BYTERANGE:1000@0
+            main.ts
+            #EXTINF:9.5,
+            #EXT-X-BYTERANGE:1000
+            main.ts
+        "})
+        .unwrap();
+        let reload = first.to_string().replace("#EXTINF:10,", "#EXTINF:9.97667,First");
+        let reload = MediaPlaylist::parse_ext_m3u(&reload).unwrap();
+        assert_ne!(first.segments()[0], reload.segments()[0]);
+
+        let seen: HashSet<_> = first.iter_segments().map(|x| x.identity()).collect();
+        assert!(reload.iter_segments().all(|x| seen.contains(&x.identity())));
+        let second = reload.iter_segments().nth(1).unwrap().identity();
+        assert_eq!(second.to_string(), "6:main.ts@1000-1000");
+        assert_ne!(reload.segments()[1].identity(6), second);
+
+        //after the whole resource the offset can't be inferred, which isn't the same as 0
+        let lenient = ParseOptions { lenient: true, ..ParseOptions::default() };
+        let file = indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-VERSION:4
+            #EXT-X-TARGETDURATION:10
+            #EXTINF:10,
+            main.ts
+            #EXTINF:10,
+            #EXT-X-BYTERANGE:1000
+            main.ts
+        "};
+        let unknown = MediaPlaylist::parse_with_options(file, &lenient).unwrap();
+        let implicit = unknown.iter_segments().nth(1).unwrap().identity();
+        assert_eq!(implicit.to_string(), "1:main.ts@1000");
+        assert_eq!(implicit, unknown.segments()[1].identity(1));
+        let explicit = file.replace("#EXT-X-BYTERANGE:1000", "#EXT-X-BYTERANGE:1000@0");
+        let explicit = MediaPlaylist::parse_ext_m3u(&explicit).unwrap();
+        assert_ne!(explicit.iter_segments().nth(1).unwrap().identity(), implicit);
+    }
+}
@@ -0,0 +1,201 @@
+//! HLS interstitials, date ranges of class `com.apple.hls.interstitial` asking players to play
+//! other content, e.g. ads, at a point of the primary timeline. See
+//! <https://developer.apple.com/streaming/GettingStartedWithHLSInterstitials.pdf>.
+
+use core::time::Duration;
+
+use anyhow::Result;
+
+use crate::{DateRange, MediaPlaylist, ProgramDateTime, SegmentDuration};
+
+/// CLASS of the date ranges describing interstitials.
+pub const INTERSTITIAL_CLASS: &str = "com.apple.hls.interstitial";
+
+/// What to play for an interstitial.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum InterstitialAsset {
+    /// A single asset, from X-ASSET-URI, usually a multivariant playlist.
+    Uri(String),
+
+    /// A JSON list of assets to play back to back, from X-ASSET-LIST.
+    List(String),
+}
+
+/// An interstitial, from an EXT-X-DATERANGE tag with the interstitial CLASS.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Interstitial {
+    pub id: String,
+    pub start_date: ProgramDateTime,
+    pub asset: InterstitialAsset,
+
+    /// How far the primary content moves on while the interstitial plays, from
+    /// X-RESUME-OFFSET. `None` means the duration of the interstitial.
+    pub resume_offset: Option<Duration>,
+
+    /// Longest the interstitial may play, from X-PLAYOUT-LIMIT.
+    pub playout_limit: Option<Duration>,
+
+    /// Whether to play before the primary content starts, after it ends, or only the first
+    /// time the position is reached, from the CUE attribute.
+    pub pre: bool,
+    pub post: bool,
+    pub once: bool,
+
+    /// DURATION of the range, or else its PLANNED-DURATION.
+    pub duration: Option<Duration>,
+}
+
+impl Interstitial {
+    /// Reads the interstitial attributes of `date_range`, `None` if it isn't of the interstitial
+    /// CLASS. Errors if it has neither or both of X-ASSET-URI and X-ASSET-LIST.
+    pub fn from_date_range(date_range: &DateRange) -> Result<Option<Self>> {
+        if date_range.class() != Some(INTERSTITIAL_CLASS) {
+            return Ok(None);
+        }
+        let id = date_range.id();
+        let asset = match (date_range.client_attribute("X-ASSET-URI"), date_range.client_attribute("X-ASSET-LIST")) {
+            (Some(uri), None) => InterstitialAsset::Uri(uri.to_string()),
+            (None, Some(list)) => InterstitialAsset::List(list.to_string()),
+            (Some(_), Some(_)) => return Err(anyhow::anyhow!("Interstitial {} has X-ASSET-URI and X-ASSET-LIST", id)),
+            (None, None) => return Err(anyhow::anyhow!("Interstitial {} has no X-ASSET-URI or X-ASSET-LIST", id)),
+        };
+        let seconds = |name: &str| -> Result<Option<Duration>> {
+            let Some(value) = date_range.client_attribute(name) else {
+                return Ok(None);
+            };
+            let duration: SegmentDuration =
+                value.parse().map_err(|_| anyhow::anyhow!("Invalid {} {} of interstitial {}", name, value, id))?;
+            Ok(Some(duration.as_duration()))
+        };
+
+        let (mut pre, mut post, mut once) = (false, false, false);
+        for cue in date_range.cue().into_iter().flat_map(|x| x.split(',')) {
+            match cue {
+                "PRE" => pre = true,
+                "POST" => post = true,
+                "ONCE" => once = true,
+                other => return Err(anyhow::anyhow!("Unknown CUE {} of interstitial {}", other, id)),
+            }
+        }
+        if pre && post {
+            return Err(anyhow::anyhow!("Interstitial {} has both PRE and POST cues", id));
+        }
+
+        Ok(Some(Self {
+            id: id.to_string(),
+            start_date: date_range.start_date(),
+            asset,
+            resume_offset: seconds("X-RESUME-OFFSET")?,
+            playout_limit: seconds("X-PLAYOUT-LIMIT")?,
+            pre,
+            post,
+            once,
+            duration: date_range.duration().or(date_range.planned_duration()),
+        }))
+    }
+}
+
+/// Where an interstitial plays on the primary timeline, from
+/// [`MediaPlaylist::interstitial_schedule`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScheduledInterstitial {
+    pub interstitial: Interstitial,
+
+    /// Time from the start of the first listed segment at which the interstitial plays.
+    pub position: Duration,
+
+    /// Where primary playback resumes afterwards: the position plus the resume offset, or plus
+    /// the interstitial's duration if it has no offset. Just the position if neither is known,
+    /// and for POST cues, after which there's nothing to resume.
+    pub resume_at: Duration,
+}
+
+impl MediaPlaylist {
+    /// The interstitials announced by the playlist's date ranges, in the order of their first
+    /// tags. Tags with the same ID are one range, later ones adding attributes.
+    pub fn interstitials(&self) -> Result<Vec<Interstitial>> {
+        let mut interstitials = Vec::new();
+        for date_range in &self.merged_date_ranges() {
+            interstitials.extend(Interstitial::from_date_range(date_range)?);
+        }
+        Ok(interstitials)
+    }
+
+    /// The interstitials with their positions on the primary timeline, in playback order: PRE
+    /// cues at the start, POST cues at the end, and others where their START-DATE falls, going
+    /// by the program date times of the segments. Those starting before the first dated
+    /// segment, e.g. because a live playlist has moved past them, are left out.
+    pub fn interstitial_schedule(&self) -> Result<Vec<ScheduledInterstitial>> {
+        let end = self.iter_segments().last().map(|x| x.start + x.segment.duration()).unwrap_or_default();
+        let mut schedule = Vec::new();
+        for interstitial in self.interstitials()? {
+            let position = if interstitial.pre {
+                Some(Duration::ZERO)
+            } else if interstitial.post {
+                Some(end)
+            } else {
+                //from the last dated segment starting at or before it, which may be before a gap
+                self.iter_segments()
+                    .filter_map(|x| {
+                        let offset = interstitial.start_date.duration_since(&x.program_date_time?)?;
+                        Some(x.start + offset)
+                    })
+                    .last()
+            };
+            let Some(position) = position else {
+                continue;
+            };
+            let resume_at = match interstitial.resume_offset.or(interstitial.duration) {
+                Some(offset) if !interstitial.post => position + offset,
+                _ => position,
+            };
+            schedule.push(ScheduledInterstitial { interstitial, position, resume_at });
+        }
+        schedule.sort_by_key(|x| x.position);
+        Ok(schedule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedules_interstitials() {
+        let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-DATERANGE:ID="pre",CLASS="com.apple.hls.interstitial",START-DATE="2024-03-01T12:00:00Z",CUE="PRE",X-ASSET-URI="pre.m3u8"
+            #EXT-X-PROGRAM-DATE-TIME:2024-03-01T12:00:00Z
+            #EXTINF:10,
+            0.ts
+            #EXT-X-DATERANGE:ID="mid",CLASS="com.apple.hls.interstitial",START-DATE="2024-03-01T12:00:15Z",X-ASSET-LIST="ads.json"
+            #EXT-X-DATERANGE:ID="splice",START-DATE="2024-03-01T12:00:15Z",DURATION=5
+            #EXTINF:10,
+            1.ts
+            #EXTINF:10,
+            2.ts
+            #EXT-X-DATERANGE:ID="mid",START-DATE="2024-03-01T12:00:15Z",X-RESUME-OFFSET=0,X-PLAYOUT-LIMIT=30.5
+            #EXT-X-DATERANGE:ID="post",CLASS="com.apple.hls.interstitial",START-DATE="2024-03-01T12:00:00Z",CUE="POST,ONCE",DURATION=20,X-ASSET-URI="post.m3u8"
+        "#})
+        .unwrap();
+        let interstitials = playlist.interstitials().unwrap();
+        assert_eq!(interstitials.iter().map(|x| x.id.as_str()).collect::<Vec<_>>(), ["pre", "mid", "post"]);
+        let mid = &interstitials[1];
+        assert_eq!(mid.asset, InterstitialAsset::List("ads.json".to_string()));
+        assert_eq!((mid.resume_offset, mid.playout_limit), (Some(Duration::ZERO), Some(Duration::from_millis(30_500))));
+        assert!(interstitials[2].post && interstitials[2].once);
+
+        let schedule = playlist.interstitial_schedule().unwrap();
+        let positions: Vec<_> =
+            schedule.iter().map(|x| (x.interstitial.id.as_str(), x.position, x.resume_at)).collect();
+        assert_eq!(positions, [
+            ("pre", Duration::ZERO, Duration::ZERO),
+            ("mid", Duration::from_secs(15), Duration::from_secs(15)),
+            ("post", Duration::from_secs(30), Duration::from_secs(30)),
+        ]);
+
+        let invalid = r#"ID="a",CLASS="com.apple.hls.interstitial",START-DATE="2024-03-01T12:00:00Z""#;
+        assert!(Interstitial::from_date_range(&DateRange::parse(invalid).unwrap()).is_err());
+    }
+}
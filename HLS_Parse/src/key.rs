@@ -0,0 +1,295 @@
+//! Media segment encryption. See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2.4>.
+
+use core::fmt;
+use core::ops::Range;
+
+use anyhow::Result;
+
+use crate::attributes::{AttributeList, RawAttributes};
+use crate::MediaPlaylist;
+
+/// How media segments are encrypted, from the METHOD attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyMethod {
+    /// Segments are not encrypted.
+    None,
+    /// Whole segments are encrypted with AES-128 in CBC mode.
+    Aes128,
+    /// Media samples are encrypted individually, e.g. with FairPlay.
+    SampleAes,
+}
+
+/// Information from an EXT-X-KEY tag, which applies to every following media segment until the
+/// next EXT-X-KEY tag.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EncryptionKey {
+    method: KeyMethod,
+
+    /// Where to obtain the key. Required unless the method is NONE.
+    uri: Option<String>,
+
+    /// Hexadecimal initialization vector, including the `0x` prefix.
+    iv: Option<String>,
+
+    key_format: Option<String>,
+
+    key_format_versions: Option<String>,
+
+    /// The attribute list as parsed.
+    raw: RawAttributes,
+}
+
+impl KeyMethod {
+    /// Name of the method as it appears in the playlist.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyMethod::None => "NONE",
+            KeyMethod::Aes128 => "AES-128",
+            KeyMethod::SampleAes => "SAMPLE-AES",
+        }
+    }
+}
+
+impl fmt::Display for KeyMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for EncryptionKey {
+    /// Formats the key as the attribute list of an EXT-X-KEY tag.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "METHOD={}", self.method)?;
+        if let Some(uri) = &self.uri {
+            write!(f, ",URI=\"{}\"", uri)?;
+        }
+        if let Some(iv) = &self.iv {
+            write!(f, ",IV={}", iv)?;
+        }
+        if let Some(key_format) = &self.key_format {
+            write!(f, ",KEYFORMAT=\"{}\"", key_format)?;
+        }
+        if let Some(key_format_versions) = &self.key_format_versions {
+            write!(f, ",KEYFORMATVERSIONS=\"{}\"", key_format_versions)?;
+        }
+        Ok(())
+    }
+}
+
+impl EncryptionKey {
+    /// Parses the attribute list of an EXT-X-KEY tag.
+    pub(crate) fn parse(attribute_list: &str) -> Result<Self> {
+        let attributes = AttributeList::parse(attribute_list)?;
+        let method = match attributes.get("METHOD") {
+            Some("NONE") => KeyMethod::None,
+            Some("AES-128") => KeyMethod::Aes128,
+            Some("SAMPLE-AES") => KeyMethod::SampleAes,
+            Some(other) => return Err(anyhow::anyhow!("Unknown key method {}", other)),
+            None => return Err(anyhow::Error::msg("Key is missing METHOD attribute")),
+        };
+        let uri = attributes.quoted_string("URI")?.map(str::to_string);
+        let iv = attributes.get("IV").map(str::to_string);
+        if let Some(iv) = &iv {
+            let digits = iv.strip_prefix("0x").or_else(|| iv.strip_prefix("0X")).unwrap_or_default();
+            if digits.is_empty() || !digits.bytes().all(|x| x.is_ascii_hexdigit()) {
+                return Err(anyhow::anyhow!("Key IV {} is not a hexadecimal sequence", iv));
+            }
+        }
+        let key_format = attributes.quoted_string("KEYFORMAT")?.map(str::to_string);
+        let key_format_versions = attributes.quoted_string("KEYFORMATVERSIONS")?.map(str::to_string);
+
+        match (method, &uri) {
+            (KeyMethod::None, Some(_)) => Err(anyhow::Error::msg("Key with METHOD=NONE must not have a URI")),
+            (KeyMethod::None, None) if iv.is_some() || key_format.is_some() || key_format_versions.is_some() => {
+                Err(anyhow::Error::msg("Key with METHOD=NONE must not have other attributes"))
+            }
+            (KeyMethod::Aes128 | KeyMethod::SampleAes, None) => Err(anyhow::anyhow!("{} key is missing URI", method)),
+            _ => Ok(Self { method, uri, iv, key_format, key_format_versions, raw: RawAttributes::new(attribute_list) }),
+        }
+    }
+
+    /// Parses the attribute list of an EXT-X-SESSION-KEY tag, which is an EXT-X-KEY attribute list
+    /// whose method can't be NONE.
+    pub(crate) fn parse_session(attribute_list: &str) -> Result<Self> {
+        let key = Self::parse(attribute_list)?;
+        if key.method == KeyMethod::None {
+            return Err(anyhow::Error::msg("Session key must not have METHOD=NONE"));
+        }
+        Ok(key)
+    }
+
+    /// Raw value of the attribute `name` as parsed, including ones the crate doesn't model, see
+    /// [`RawAttributes`][crate::RawAttributes].
+    pub fn raw_attribute(&self, name: &str) -> Option<&str> {
+        self.raw.get(name)
+    }
+
+    /// Every attribute of the EXT-X-KEY tag as parsed.
+    pub fn raw_attributes(&self) -> &RawAttributes {
+        &self.raw
+    }
+
+    pub(crate) fn set_uri(&mut self, uri: Option<String>) {
+        self.uri = uri;
+    }
+
+    /// Updates the keys in effect for the following segments with this key's EXT-X-KEY tag: it
+    /// replaces the key with the same KEYFORMAT, or is added after the others, while method NONE
+    /// removes every key.
+    pub(crate) fn apply_to(self, keys: &mut Vec<EncryptionKey>) {
+        if self.method == KeyMethod::None {
+            keys.clear();
+        } else if let Some(existing) = keys.iter_mut().find(|x| x.key_format() == self.key_format()) {
+            *existing = self;
+        } else {
+            keys.push(self);
+        }
+    }
+
+    /// How segments are encrypted.
+    pub fn method(&self) -> KeyMethod {
+        self.method
+    }
+
+    /// Where to obtain the key, relative to the playlist unless absolute.
+    pub fn uri(&self) -> Option<&str> {
+        self.uri.as_deref()
+    }
+
+    /// Initialization vector as written, including the `0x` prefix.
+    pub fn iv(&self) -> Option<&str> {
+        self.iv.as_deref()
+    }
+
+    /// How the key is represented, `identity` if absent.
+    pub fn key_format(&self) -> &str {
+        self.key_format.as_deref().unwrap_or("identity")
+    }
+
+    /// Versions of [`key_format`][Self::key_format] the key is compatible with, as written.
+    pub fn key_format_versions(&self) -> Option<&str> {
+        self.key_format_versions.as_deref()
+    }
+}
+
+impl MediaPlaylist {
+    /// Each key in effect, with the media sequence numbers of the consecutive segments it
+    /// decrypts, ordered by the first of them. Unencrypted segments aren't included. License
+    /// requests for upcoming keys can be made from this before the segments needing them are
+    /// reached.
+    pub fn key_rotations(&self) -> Vec<(Range<u64>, &EncryptionKey)> {
+        let mut rotations: Vec<(Range<u64>, &EncryptionKey)> = Vec::new();
+        for context in self.iter_segments() {
+            for key in context.keys {
+                let current = rotations.iter_mut().rev().find(|(sequences, last)| sequences.end == context.sequence && *last == key);
+                match current {
+                    Some((sequences, _)) => sequences.end += 1,
+                    None => rotations.push((context.sequence..context.sequence + 1, key)),
+                }
+            }
+        }
+        rotations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MediaSegment;
+
+    #[test]
+    fn parses_aes_key() {
+        let key = EncryptionKey::parse(r#"METHOD=AES-128,URI="keys/1.key",IV=0x0123456789abcdef"#)
+            .expect("should parse");
+        assert_eq!(key.method(), KeyMethod::Aes128);
+        assert_eq!(key.uri(), Some("keys/1.key"));
+        assert_eq!(key.iv(), Some("0x0123456789abcdef"));
+        assert_eq!(key.key_format(), "identity");
+    }
+
+    #[test]
+    fn validates_attributes() {
+        assert!(EncryptionKey::parse("METHOD=NONE").is_ok());
+        assert!(EncryptionKey::parse(r#"METHOD=NONE,URI="a.key""#).is_err());
+        assert!(EncryptionKey::parse("METHOD=AES-128").is_err());
+        assert!(EncryptionKey::parse(r#"METHOD=AES-256,URI="a.key""#).is_err());
+        assert!(EncryptionKey::parse(r#"METHOD=AES-128,URI="a.key",IV=1234"#).is_err());
+    }
+
+    #[test]
+    fn displays_as_attribute_list() {
+        let attributes = r#"METHOD=SAMPLE-AES,URI="skd://id",KEYFORMAT="com.apple.streamingkeydelivery",KEYFORMATVERSIONS="1""#;
+        assert_eq!(EncryptionKey::parse(attributes).unwrap().to_string(), attributes);
+    }
+
+    #[test]
+    fn keeps_a_key_per_format() {
+        let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-VERSION:5
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-KEY:METHOD=SAMPLE-AES,URI="skd://1",KEYFORMAT="com.apple.streamingkeydelivery",KEYFORMATVERSIONS="1"
+            #EXT-X-KEY:METHOD=SAMPLE-AES,URI="data:text/plain;base64,AAAA",KEYFORMAT="urn:uuid:edef8ba9-79d6-4ace-a3c8-27dcd51d21ed"
+            #EXTINF:10,
+            1.mp4
+            #EXT-X-KEY:METHOD=SAMPLE-AES,URI="skd://2",KEYFORMAT="com.apple.streamingkeydelivery",KEYFORMATVERSIONS="1"
+            #EXTINF:10,
+            2.mp4
+            #EXT-X-KEY:METHOD=NONE
+            #EXTINF:10,
+            3.mp4
+        "#})
+        .unwrap();
+        let segments = playlist.segments();
+        fn uris(segment: &MediaSegment) -> Vec<&str> {
+            segment.keys().iter().filter_map(EncryptionKey::uri).collect()
+        }
+        assert_eq!(uris(&segments[0]), vec!["skd://1", "data:text/plain;base64,AAAA"]);
+        assert_eq!(uris(&segments[1]), vec!["skd://2", "data:text/plain;base64,AAAA"]);
+        assert!(segments[2].keys().is_empty());
+        let fair_play = segments[1].keys_for_format("com.apple.streamingkeydelivery").unwrap();
+        assert_eq!(fair_play.uri(), Some("skd://2"));
+        assert_eq!(segments[1].keys_for_format("identity"), None);
+
+        //only the FairPlay key is written again for the second segment
+        let written = playlist.to_string();
+        assert_eq!(written.matches("#EXT-X-KEY").count(), 4);
+        assert_eq!(MediaPlaylist::parse_ext_m3u(&written).unwrap(), playlist);
+
+        let rotations: Vec<(Range<u64>, Option<&str>)> =
+            playlist.key_rotations().into_iter().map(|(sequences, key)| (sequences, key.uri())).collect();
+        assert_eq!(
+            rotations,
+            vec![(0..1, Some("skd://1")), (0..2, Some("data:text/plain;base64,AAAA")), (1..2, Some("skd://2"))]
+        );
+    }
+
+    #[test]
+    fn lists_key_rotations() {
+        let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-MEDIA-SEQUENCE:100
+            #EXTINF:10,
+            clear.ts
+            #EXT-X-KEY:METHOD=AES-128,URI="1.key"
+            #EXTINF:10,
+            1.ts
+            #EXTINF:10,
+            2.ts
+            #EXT-X-KEY:METHOD=AES-128,URI="2.key"
+            #EXTINF:10,
+            3.ts
+            #EXT-X-KEY:METHOD=NONE
+            #EXTINF:10,
+            4.ts
+            #EXT-X-KEY:METHOD=AES-128,URI="2.key"
+            #EXTINF:10,
+            5.ts
+        "#})
+        .unwrap();
+        let rotations: Vec<(Range<u64>, Option<&str>)> =
+            playlist.key_rotations().into_iter().map(|(sequences, key)| (sequences, key.uri())).collect();
+        assert_eq!(rotations, vec![(101..103, Some("1.key")), (103..104, Some("2.key")), (105..106, Some("2.key"))]);
+    }
+}
@@ -0,0 +1,119 @@
+//! Checking the bit rate ladder of a master playlist: the variants should step up in bandwidth as
+//! they step up in resolution, and each rung should be distinct.
+
+use crate::diagnostics::Diagnostic;
+use crate::master_playlist::missing_groups;
+use crate::{MasterPlaylist, VariantStream};
+
+impl MasterPlaylist {
+    /// Checks that AVERAGE-BANDWIDTH doesn't exceed BANDWIDTH, that every group a variant names
+    /// exists, that no two variants other than backups of each other (see
+    /// [`variant_groups`][Self::variant_groups]) have the same RESOLUTION, CODECS and VIDEO-RANGE,
+    /// and that a higher resolution never comes with a lower BANDWIDTH than another variant with
+    /// the same codecs.
+    pub fn validate_ladder(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        for variant in self.variants().iter().chain(self.i_frame_variants()) {
+            if let Some(average) = variant.average_bandwidth().filter(|x| *x > variant.bandwidth()) {
+                diagnostics.push(Diagnostic::error(None, format!(
+                    "Variant {} has AVERAGE-BANDWIDTH {} above its BANDWIDTH {}",
+                    variant.uri(), average, variant.bandwidth()
+                )));
+            }
+            for (media_type, group_id) in missing_groups(variant, self.renditions()) {
+                diagnostics.push(Diagnostic::error(None, format!(
+                    "Variant {} refers to unknown {} group {}",
+                    variant.uri(), media_type, group_id
+                )));
+            }
+        }
+
+        //backups repeat a rung on purpose, so only look at one variant of each
+        let rungs: Vec<&VariantStream> = self.variant_groups().into_iter().map(|x| x[0]).collect();
+        for (index, variant) in rungs.iter().enumerate() {
+            if let Some(earlier) = rungs[..index].iter().find(|x| same_rung(x, variant)) {
+                diagnostics.push(Diagnostic::warning(None, format!(
+                    "Variant {} has the same RESOLUTION and CODECS as variant {}",
+                    variant.uri(), earlier.uri()
+                )));
+            }
+        }
+
+        let mut ladders: Vec<Vec<&VariantStream>> = Vec::new();
+        for variant in rungs.into_iter().filter(|x| x.resolution().is_some()) {
+            match ladders.iter_mut().find(|ladder| same_encoding(ladder[0], variant)) {
+                Some(ladder) => ladder.push(variant),
+                None => ladders.push(vec![variant]),
+            }
+        }
+        for ladder in &mut ladders {
+            ladder.sort_by_key(|x| (x.resolution().map(|x| x.width * x.height), x.bandwidth()));
+            for window in ladder.windows(2) {
+                let [lower, higher] = window else {
+                    continue;
+                };
+                if higher.bandwidth() < lower.bandwidth() {
+                    diagnostics.push(Diagnostic::warning(None, format!(
+                        "Variant {} has a higher RESOLUTION than variant {} but a lower BANDWIDTH",
+                        higher.uri(), lower.uri()
+                    )));
+                }
+            }
+        }
+        diagnostics
+    }
+}
+
+fn same_encoding(a: &VariantStream, b: &VariantStream) -> bool {
+    a.codecs() == b.codecs() && a.video_range() == b.video_range()
+}
+
+fn same_rung(a: &VariantStream, b: &VariantStream) -> bool {
+    same_encoding(a, b) && a.resolution() == b.resolution()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_good_ladder() {
+        let playlist = MasterPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-STREAM-INF:BANDWIDTH=2560000,AVERAGE-BANDWIDTH=2000000,CODECS="avc1.4d401f",RESOLUTION=1280x720
+            a/high.m3u8
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000,CODECS="avc1.4d401e",RESOLUTION=640x360
+            a/low.m3u8
+            #EXT-X-STREAM-INF:BANDWIDTH=2560000,AVERAGE-BANDWIDTH=2000000,CODECS="avc1.4d401f",RESOLUTION=1280x720
+            b/high.m3u8
+            #EXT-X-STREAM-INF:BANDWIDTH=1000000,CODECS="hvc1.2.4.L93.B0",RESOLUTION=1280x720
+            hevc.m3u8
+        "#})
+        .unwrap();
+        assert_eq!(playlist.validate_ladder(), vec![]);
+    }
+
+    #[test]
+    fn reports_ladder_problems() {
+        let playlist = MasterPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000,AVERAGE-BANDWIDTH=1500000,CODECS="avc1.4d401f",RESOLUTION=640x360
+            low.m3u8
+            #EXT-X-STREAM-INF:BANDWIDTH=1000000,CODECS="avc1.4d401f",RESOLUTION=1280x720
+            high.m3u8
+            #EXT-X-STREAM-INF:BANDWIDTH=1300000,CODECS="avc1.4d401f",RESOLUTION=640x360
+            low2.m3u8
+        "#})
+        .unwrap();
+        let messages: Vec<String> = playlist.validate_ladder().into_iter().map(|x| x.message).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Variant low.m3u8 has AVERAGE-BANDWIDTH 1500000 above its BANDWIDTH 1280000",
+                "Variant low2.m3u8 has the same RESOLUTION and CODECS as variant low.m3u8",
+                "Variant high.m3u8 has a higher RESOLUTION than variant low2.m3u8 but a lower BANDWIDTH",
+            ]
+        );
+    }
+}
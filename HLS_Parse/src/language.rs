@@ -0,0 +1,192 @@
+//! Choosing renditions by language and accessibility characteristics. See
+//! <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.4.1>.
+
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::str::FromStr;
+
+use crate::{MasterPlaylist, MediaType, Rendition};
+
+/// An [RFC 5646][bcp47] language tag from a LANGUAGE or ASSOC-LANGUAGE attribute, e.g. `en-US`.
+/// Tags compare case-insensitively, but display as written.
+///
+/// [bcp47]: https://datatracker.ietf.org/doc/html/rfc5646
+#[derive(Debug, Clone, Eq)]
+pub struct LanguageTag {
+    tag: String,
+}
+
+impl LanguageTag {
+    /// The tag as written.
+    pub fn as_str(&self) -> &str {
+        &self.tag
+    }
+
+    /// The primary language subtag, e.g. `en` for `en-US`.
+    pub fn primary_language(&self) -> &str {
+        self.subtags().next().unwrap_or_default()
+    }
+
+    /// The script subtag if there is one, e.g. `Hant` for `zh-Hant-TW`.
+    pub fn script(&self) -> Option<&str> {
+        self.subtags()
+            .skip(1)
+            .take_while(|x| x.len() > 1)
+            .find(|x| x.len() == 4 && x.bytes().all(|x| x.is_ascii_alphabetic()))
+    }
+
+    /// The region subtag if there is one, e.g. `TW` for `zh-Hant-TW`, or `419` for `es-419`.
+    pub fn region(&self) -> Option<&str> {
+        self.subtags().skip(1).take_while(|x| x.len() > 1).find(|x| {
+            (x.len() == 2 && x.bytes().all(|x| x.is_ascii_alphabetic()))
+                || (x.len() == 3 && x.bytes().all(|x| x.is_ascii_digit()))
+        })
+    }
+
+    /// Whether this tag falls within `range`, using [RFC 4647 basic filtering][filtering]: `en`
+    /// matches `en` and `en-US` but not `eng`.
+    ///
+    /// [filtering]: https://datatracker.ietf.org/doc/html/rfc4647#section-3.3.1
+    pub fn matches(&self, range: &LanguageTag) -> bool {
+        let (tag, range) = (self.tag.as_bytes(), range.tag.as_bytes());
+        tag.len() >= range.len()
+            && tag[..range.len()].eq_ignore_ascii_case(range)
+            && (tag.len() == range.len() || tag[range.len()] == b'-')
+    }
+
+    fn subtags(&self) -> impl Iterator<Item = &str> {
+        self.tag.split('-')
+    }
+}
+
+impl MasterPlaylist {
+    /// Renditions of the given type whose LANGUAGE matches `language`, in playlist order. See
+    /// [`LanguageTag::matches`].
+    pub fn renditions_in_language(&self, media_type: MediaType, language: &LanguageTag) -> Vec<&Rendition> {
+        self.renditions()
+            .iter()
+            .filter(|x| x.media_type() == media_type && x.language().is_some_and(|x| x.matches(language)))
+            .collect()
+    }
+
+    /// Audio renditions in the given language, e.g. `en` for both `en-US` and `en-GB` tracks.
+    pub fn audio_renditions(&self, language: &LanguageTag) -> Vec<&Rendition> {
+        self.renditions_in_language(MediaType::Audio, language)
+    }
+
+    /// Renditions with the given Uniform Type Identifier in CHARACTERISTICS, in playlist order,
+    /// e.g. `public.accessibility.describes-video` for audio description.
+    pub fn renditions_with_characteristic(&self, characteristic: &str) -> Vec<&Rendition> {
+        self.renditions().iter().filter(|x| x.has_characteristic(characteristic)).collect()
+    }
+}
+
+impl Rendition {
+    /// Whether CHARACTERISTICS includes the given Uniform Type Identifier.
+    pub fn has_characteristic(&self, characteristic: &str) -> bool {
+        self.characteristics().iter().any(|x| x == characteristic)
+    }
+}
+
+impl PartialEq for LanguageTag {
+    fn eq(&self, other: &Self) -> bool {
+        self.tag.eq_ignore_ascii_case(&other.tag)
+    }
+}
+
+impl Hash for LanguageTag {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for byte in self.tag.bytes() {
+            state.write_u8(byte.to_ascii_lowercase());
+        }
+    }
+}
+
+impl FromStr for LanguageTag {
+    type Err = anyhow::Error;
+
+    /// Checks the general shape of the tag, subtags of 1 to 8 letters and digits starting with a
+    /// letter, without consulting the subtag registry.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let valid = value.split('-').enumerate().all(|(index, subtag)| {
+            (1..=8).contains(&subtag.len())
+                && subtag.bytes().all(|x| x.is_ascii_alphanumeric())
+                && (index > 0 || subtag.bytes().all(|x| x.is_ascii_alphabetic()))
+        });
+        if !valid {
+            return Err(anyhow::anyhow!("Invalid language tag {}", value));
+        }
+        Ok(Self { tag: value.to_string() })
+    }
+}
+
+impl fmt::Display for LanguageTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(value: &str) -> LanguageTag {
+        value.parse().expect("test tag should parse")
+    }
+
+    #[test]
+    fn parses_subtags() {
+        let chinese = tag("zh-Hant-TW");
+        assert_eq!(chinese.primary_language(), "zh");
+        assert_eq!(chinese.script(), Some("Hant"));
+        assert_eq!(chinese.region(), Some("TW"));
+        assert_eq!(tag("es-419").region(), Some("419"));
+        assert_eq!(tag("en").region(), None);
+        assert_eq!(tag("en-x-US").region(), None);
+        assert_eq!(tag("EN-us"), tag("en-US"));
+        assert_eq!(tag("EN-us").to_string(), "EN-us");
+
+        for invalid in ["", "en_US", "en-", "1en", "en-toolongsubtag"] {
+            assert!(invalid.parse::<LanguageTag>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn matches_language_ranges() {
+        assert!(tag("en-US").matches(&tag("en")));
+        assert!(tag("en").matches(&tag("EN")));
+        assert!(!tag("en").matches(&tag("en-US")));
+        assert!(!tag("eng").matches(&tag("en")));
+    }
+
+    #[test]
+    fn filters_renditions() {
+        let playlist = MasterPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",LANGUAGE="en-US",NAME="English",URI="en-us.m3u8"
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",LANGUAGE="en-GB",NAME="English (UK)",URI="en-gb.m3u8"
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",LANGUAGE="en",NAME="English AD",CHARACTERISTICS="public.accessibility.describes-video",URI="en-ad.m3u8"
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",LANGUAGE="fr",NAME="Français",URI="fr.m3u8"
+            #EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID="subs",LANGUAGE="en",NAME="English",CHARACTERISTICS="public.accessibility.transcribes-spoken-dialog,public.accessibility.describes-music-and-sound",URI="en-sdh.m3u8"
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000,AUDIO="aac",SUBTITLES="subs"
+            low.m3u8
+        "#})
+        .expect("should parse");
+        fn uris(renditions: Vec<&Rendition>) -> Vec<&str> {
+            renditions.into_iter().filter_map(Rendition::uri).collect()
+        }
+
+        assert_eq!(uris(playlist.audio_renditions(&tag("en"))), vec!["en-us.m3u8", "en-gb.m3u8", "en-ad.m3u8"]);
+        assert_eq!(uris(playlist.audio_renditions(&tag("en-gb"))), vec!["en-gb.m3u8"]);
+        assert_eq!(uris(playlist.renditions_in_language(MediaType::Subtitles, &tag("fr"))), Vec::<&str>::new());
+        assert_eq!(
+            uris(playlist.renditions_with_characteristic("public.accessibility.describes-video")),
+            vec!["en-ad.m3u8"]
+        );
+        assert_eq!(
+            uris(playlist.renditions_with_characteristic("public.accessibility.describes-music-and-sound")),
+            vec!["en-sdh.m3u8"]
+        );
+        assert_eq!(playlist.renditions()[4].characteristics().len(), 2);
+    }
+}
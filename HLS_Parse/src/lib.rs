@@ -2,10 +2,162 @@
 //!
 //! This library follows [this specification][spec] to parse [ext-m3u][m3u] formatted data.
 //!
+//! # Features
+//!
+//! - `arbitrary`: random but valid [`MediaPlaylist`] and [`MasterPlaylist`] values from
+//!   [`arbitrary::Arbitrary`], for property tests and fuzzing.
+//! - `checksum`: SHA-256 checksum tags per segment with [`ChecksumHandler`], verified on download
+//!   by [`ChecksumSink`].
+//! - `cli`: the `hls` binary, with `validate` (see [`conformance`]), `info` (with `--json` for
+//!   the [`report`]) and `segments` subcommands.
+//! - `dash`: conversion to and from static MPEG-DASH manifests with [`MasterPlaylist::to_mpd`]
+//!   and [`MasterPlaylist::from_mpd`].
+//! - `ffi`: C bindings in [`ffi`], with a header in `include/hls_parsing.h`.
+//! - `rayon`: multithreaded parsing of very large playlists with
+//!   [`MediaPlaylist::parse_parallel`], and of many at once with [`conformance::validate_batch`].
+//! - `reqwest`: [`PlaylistTransport`] for `reqwest::Client`, to reload live playlists with
+//!   [`LiveFollower::fetch`].
+//! - `serde`: `Serialize` for the [`report`] of a media playlist and for diagnostics.
+//! - `tokio`: incremental parsing from an `AsyncBufRead` with [`MediaPlaylist::parse_async`].
+//! - `tracing`: spans and events from parsing, validation and [`LiveFollower`], e.g. to find out
+//!   why a playlist was rejected in production.
+//! - `wasm-bindgen`: exports `parseMediaPlaylist` and `parseMasterPlaylist` to JavaScript when
+//!   built for `wasm32-unknown-unknown`.
+//!
 //! [m3u]: https://en.wikipedia.org/wiki/M3U#Extended_M3U
 //! [spec]: https://datatracker.ietf.org/doc/html/rfc8216#section-4
 //! [wiki]: https://en.wikipedia.org/wiki/HTTP_Live_Streaming
 
+mod anonymize;
+mod assemble;
+mod attributes;
+mod byte_range;
+pub mod cache;
+mod capabilities;
+mod captions;
+mod channels;
+#[cfg(feature = "checksum")]
+mod checksum;
+mod compare;
+pub mod conformance;
+mod consistency;
+mod container;
+mod context;
+mod cues;
+#[cfg(feature = "dash")]
+mod dash;
+#[cfg(feature = "dash")]
+mod dash_import;
+mod date_range;
+mod date_time;
+mod delta;
+mod drift;
+pub mod diagnostics;
+mod duration;
+mod encoding;
+pub mod events;
+mod extensions;
+mod fetch;
+mod failover;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod files;
+#[cfg(feature = "arbitrary")]
+mod fuzzing;
+mod groups;
+mod identity;
+mod interstitials;
+mod key;
+mod ladder;
+mod language;
+mod live;
+mod map;
+mod master_playlist;
 mod media_playlist;
+mod normalize;
+mod options;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod part;
+mod playlist_type;
+mod prefetch;
+mod preload;
+mod push;
+mod rendition;
+mod rendition_report;
+pub mod report;
+mod segment_tags;
+mod selection;
+mod server_control;
+mod session;
+mod session_data;
+mod sink;
+mod source;
+mod splice;
+mod stats;
+mod subtitles;
+pub mod testing;
+mod throughput;
+mod transport;
+mod uri;
+mod uri_policy;
+mod urls;
+mod variables;
+mod variant;
+mod writer;
+#[cfg(feature = "wasm-bindgen")]
+mod wasm;
 
+pub use assemble::PlaylistHints;
+pub use attributes::RawAttributes;
+pub use byte_range::{ByteRange, SegmentResource};
+pub use capabilities::Capabilities;
+pub use captions::{ClosedCaptions, InstreamId};
+pub use channels::Channels;
+#[cfg(feature = "checksum")]
+pub use checksum::{ChecksumHandler, ChecksumSink, SegmentChecksum, CHECKSUM_TAG};
+pub use compare::PlaylistChange;
+pub use container::{Container, PackedAudio};
+pub use context::SegmentContext;
+pub use cues::Cue;
+#[cfg(feature = "dash")]
+pub use dash_import::MpdImport;
+pub use date_range::DateRange;
+pub use date_time::ProgramDateTime;
+pub use drift::DateTimeMismatch;
+pub use duration::SegmentDuration;
+pub use extensions::{CustomTag, Extensions, TagExtensions, TagHandler, TagHandlers};
+pub use fetch::{FetchKind, FetchObserver, FetchRequest, FetchScheduler, FetchState, RequestRange, RequestTiming};
+pub use files::LoadedMaster;
+pub use groups::DiscontinuityGroup;
+pub use identity::SegmentId;
+pub use interstitials::{Interstitial, InterstitialAsset, ScheduledInterstitial, INTERSTITIAL_CLASS};
+pub use key::{EncryptionKey, KeyMethod};
+pub use language::LanguageTag;
+pub use live::{FollowOptions, FollowerEvent, FollowerState, LiveFollower, RetryPolicy};
+pub use map::SegmentMap;
+pub use master_playlist::MasterPlaylist;
 pub use media_playlist::{MediaPlaylist, MediaSegment};
+pub use options::{Limit, LimitExceeded, ParseLimits, ParseOptions, SpecVersion};
+pub use part::PartialSegment;
+pub use playlist_type::{PlaylistTransition, PlaylistType};
+pub use prefetch::PrefetchRequest;
+pub use preload::{PreloadHint, PreloadHintType};
+pub use push::PushParser;
+pub use rendition::{MediaType, Rendition};
+pub use rendition_report::RenditionReport;
+pub use segment_tags::SegmentTag;
+pub use selection::{ResolvedSelection, SelectionPreferences};
+pub use server_control::{LiveEdge, ServerControl};
+pub use session::{Session, SessionEvent};
+pub use session_data::{SessionData, SessionDataFormat};
+pub use sink::{ConcatenatedTsSink, DirectorySink, Fmp4Sink, SegmentSink};
+pub use splice::{SpliceCues, SpliceOptions};
+pub use stats::PlaylistStats;
+pub use subtitles::{SubtitleWindow, TimestampMap};
+pub use throughput::{EwmaEstimator, ThroughputSink};
+pub use transport::{FetchedReload, PlaylistTransport, TransportResponse, Validators};
+pub use uri::SegmentUri;
+pub use uri_policy::{UriKind, UriPolicy, UriValidator};
+pub use variant::{HdcpLevel, Resolution, SupplementalCodec, VariantStream, VideoRange};
+pub use writer::{AttributeQuoting, LineEnding, VersionTag, WriteOptions};
@@ -0,0 +1,647 @@
+//! Following a live media playlist as it is reloaded. [`LiveFollower`] does no I/O: callers fetch
+//! the playlist however they like, report each reload or failed request, and are told which
+//! segments are new and how long to wait before trying again. See
+//! <https://datatracker.ietf.org/doc/html/rfc8216#section-6.3.4>.
+
+use core::hash::{BuildHasher, Hasher};
+use core::time::Duration;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::time::Instant;
+
+use anyhow::Result;
+
+use crate::{ByteRange, MediaPlaylist, MediaSegment, ParseOptions, PartialSegment, PlaylistTransition, Validators};
+
+/// How failed playlist reloads and segment requests are retried.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts before giving up, including the first.
+    pub max_attempts: u32,
+
+    /// Wait before the first retry, doubled for each retry after it.
+    pub initial_backoff: Duration,
+
+    /// Longest wait between attempts.
+    pub max_backoff: Duration,
+
+    /// Fraction of each wait which is randomized, from `0.0` for none to `1.0` for anywhere
+    /// between no wait and the full wait, so clients of the same server don't retry in lockstep.
+    pub jitter: f64,
+
+    /// Retry a 404 for a segment listed by the latest reload, which CDNs return while the
+    /// segment is still propagating.
+    pub retry_new_segment_not_found: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+            jitter: 0.5,
+            retry_new_segment_not_found: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before retry number `retry`, counting from 1, or `None` once attempts are exhausted.
+    /// `random` is a sample from `0.0..1.0` used for jitter.
+    pub fn backoff(&self, retry: u32, random: f64) -> Option<Duration> {
+        if retry == 0 || retry >= self.max_attempts {
+            return None;
+        }
+        let backoff = self.initial_backoff.saturating_mul(2u32.saturating_pow(retry - 1)).min(self.max_backoff);
+        Some(backoff.mul_f64(1.0 - self.jitter.clamp(0.0, 1.0) * random.clamp(0.0, 1.0)))
+    }
+
+    /// Whether a request which failed with the given HTTP status, `None` for a network error, is
+    /// worth retrying. Timeouts, rate limiting and server errors are, and so are 404s for segments
+    /// `just_advertised` by the latest reload if [`retry_new_segment_not_found`] is set.
+    ///
+    /// [`retry_new_segment_not_found`]: Self::retry_new_segment_not_found
+    pub fn is_retryable(&self, status: Option<u16>, just_advertised: bool) -> bool {
+        match status {
+            None | Some(408 | 429 | 500..=599) => true,
+            Some(404) => just_advertised && self.retry_new_segment_not_found,
+            Some(_) => false,
+        }
+    }
+}
+
+/// Passed to [`LiveFollower::new`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FollowOptions {
+    pub retry: RetryPolicy,
+
+    /// Report [`FollowerEvent::Stalled`] after this many target durations without new segments.
+    pub stall_after: u32,
+}
+
+impl Default for FollowOptions {
+    fn default() -> Self {
+        Self { retry: RetryPolicy::default(), stall_after: 3 }
+    }
+}
+
+/// What a reload revealed, from [`LiveFollower::reload`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum FollowerEvent {
+    /// A segment no earlier reload listed, in media sequence order. An implicit byte range offset
+    /// is filled in where it can be inferred, even from a segment only an earlier reload listed.
+    Segment { sequence: u64, segment: Box<MediaSegment> },
+
+    /// A part of the segment being produced which no earlier reload listed, so low-latency clients
+    /// can start on it before the segment is complete. `index` counts from its first part.
+    Part { sequence: u64, index: usize, part: Box<PartialSegment> },
+
+    /// The playlist changed its EXT-X-PLAYLIST-TYPE or gained an EXT-X-ENDLIST tag, e.g. an
+    /// EVENT playlist becoming VOD. Reported before the segments of the reload, with any
+    /// segments the previous reload listed which were mutated rather than appended to.
+    Transition(PlaylistTransition),
+
+    /// The playlist gained an EXT-X-ENDLIST tag, so it needs no more reloads.
+    Ended,
+
+    /// No new segments have appeared for [`FollowOptions::stall_after`] target durations.
+    /// Reported once per stall, and `since` is the time since the last new segment appeared.
+    Stalled { next_sequence: u64, since: Duration },
+}
+
+/// Progress of a [`LiveFollower`], to persist and hand to [`LiveFollower::restore`] so a
+/// restarted archiver reports neither segments it already had nor gaps. With the `serde`
+/// feature it can be serialized in any format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FollowerState {
+    /// Media sequence of the first segment not yet reported.
+    pub next_sequence: Option<u64>,
+
+    pub ended: bool,
+
+    /// Failed attempts for segments still being retried, by media sequence.
+    pub segment_failures: Vec<(u64, u32)>,
+
+    /// The latest reload as written by `Display`, which may be a delta update, so a type
+    /// change or mutation across the restart is still reported.
+    pub previous: Option<String>,
+
+    /// Validators of the latest response from each URL reloaded with
+    /// [`fetch`][LiveFollower::fetch], so the first reload after the restart can be conditional.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub validators: Vec<(String, Validators)>,
+
+    /// Media sequence of the segment being produced and index of the latest of its parts
+    /// reported.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub last_part: Option<(u64, usize)>,
+
+    /// URI and end offset of the byte range of the latest segment reported, for the offset of a
+    /// segment continuing it after it has left the playlist.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pending_range: Option<(String, u64)>,
+}
+
+/// Tracks a live media playlist across reloads.
+#[derive(Debug)]
+pub struct LiveFollower {
+    options: FollowOptions,
+    target_duration: Duration,
+
+    /// Media sequence of the first segment not yet reported, `None` before the first reload.
+    next_sequence: Option<u64>,
+
+    /// First media sequence reported by the latest reload.
+    first_new_sequence: u64,
+
+    /// Whether the latest reload had new segments, which decides the reload interval.
+    changed: bool,
+
+    /// When new segments last appeared.
+    last_change: Option<Instant>,
+    stalled: bool,
+    ended: bool,
+    reload_failures: u32,
+    segment_failures: HashMap<u64, u32>,
+
+    /// The latest reload, to check the next one against.
+    previous: Option<MediaPlaylist>,
+
+    /// Validators of the latest response by URL, sent back by [`fetch`][Self::fetch].
+    pub(crate) validators: HashMap<String, Validators>,
+
+    /// Media sequence and index of the latest part reported.
+    last_part: Option<(u64, usize)>,
+
+    /// URI and end offset of the latest segment reported, if it was a sub-range.
+    pending_range: Option<(String, u64)>,
+
+    /// When the latest reload was received, to trace the interval between reloads.
+    #[cfg(feature = "tracing")]
+    last_reload: Option<Instant>,
+
+    /// State of the xorshift generator used for jitter.
+    random: u64,
+}
+
+impl LiveFollower {
+    pub fn new(options: FollowOptions) -> Self {
+        Self {
+            options,
+            target_duration: Duration::ZERO,
+            next_sequence: None,
+            first_new_sequence: 0,
+            changed: false,
+            last_change: None,
+            stalled: false,
+            ended: false,
+            reload_failures: 0,
+            segment_failures: HashMap::new(),
+            previous: None,
+            validators: HashMap::new(),
+            last_part: None,
+            pending_range: None,
+            #[cfg(feature = "tracing")]
+            last_reload: None,
+            random: RandomState::new().build_hasher().finish() | 1,
+        }
+    }
+
+    /// A follower carrying on from `state`, from [`state`][Self::state] of an earlier one. The
+    /// time since new segments last appeared starts again at the first reload.
+    pub fn restore(options: FollowOptions, state: FollowerState) -> Result<Self> {
+        //a sliding window may start with a byte range continuing a segment which has left it
+        let lenient = ParseOptions { lenient: true, ..ParseOptions::default() };
+        let previous = match &state.previous {
+            Some(previous) => Some(
+                MediaPlaylist::parse_with_options(previous, &lenient)
+                    .map_err(|error| error.context("Invalid previous playlist"))?,
+            ),
+            None => None,
+        };
+        Ok(Self {
+            next_sequence: state.next_sequence,
+            first_new_sequence: state.next_sequence.unwrap_or_default(),
+            ended: state.ended,
+            segment_failures: state.segment_failures.into_iter().collect(),
+            previous,
+            validators: state.validators.into_iter().collect(),
+            last_part: state.last_part,
+            pending_range: state.pending_range,
+            ..Self::new(options)
+        })
+    }
+
+    /// Progress so far, to persist for [`restore`][Self::restore].
+    pub fn state(&self) -> FollowerState {
+        let mut segment_failures: Vec<(u64, u32)> = self.segment_failures.iter().map(|(x, y)| (*x, *y)).collect();
+        segment_failures.sort_unstable();
+        let mut validators: Vec<(String, Validators)> = self.validators.clone().into_iter().collect();
+        validators.sort_unstable_by(|x, y| x.0.cmp(&y.0));
+        FollowerState {
+            next_sequence: self.next_sequence,
+            ended: self.ended,
+            segment_failures,
+            previous: self.previous.as_ref().map(|x| x.to_string()),
+            validators,
+            last_part: self.last_part,
+            pending_range: self.pending_range.clone(),
+        }
+    }
+
+    /// Reports a successful reload received at `now`, returning the segments added since the
+    /// previous one and any change in state.
+    pub fn reload(&mut self, playlist: &MediaPlaylist, now: Instant) -> Vec<FollowerEvent> {
+        let mut events = Vec::new();
+        self.reload_failures = 0;
+        self.target_duration = playlist.target_duration();
+        if let Some(transition) = self.previous.as_ref().and_then(|x| playlist.transition_from(x)) {
+            #[cfg(feature = "tracing")]
+            if !transition.is_append_only() {
+                tracing::warn!(mutated = ?transition.mutated, "Playlist changed type and mutated segments");
+            }
+            events.push(FollowerEvent::Transition(transition));
+        }
+        self.previous = Some(playlist.clone());
+
+        let first = playlist.media_sequence() + playlist.skipped_segments();
+        let next = self.next_sequence.unwrap_or(first);
+        //the segment before the first listed may have been reported by an earlier reload
+        let mut end = self.pending_range.as_ref().filter(|_| first == next).map(|(uri, end)| (uri.as_str(), *end));
+        let mut pending_range = None;
+        for (offset, segment) in playlist.segments().iter().enumerate() {
+            let sequence = first + offset as u64;
+            let byte_range = segment.byte_range().map(|byte_range| ByteRange {
+                offset: byte_range.offset.or(end.filter(|x| x.0 == segment.url().as_str()).map(|x| x.1)),
+                length: byte_range.length,
+            });
+            end = byte_range.and_then(|x| x.end_offset()).map(|x| (segment.url().as_str(), x));
+            if sequence >= next {
+                let mut segment = segment.clone();
+                segment.set_byte_range(byte_range);
+                events.push(FollowerEvent::Segment { sequence, segment: Box::new(segment) });
+                pending_range = Some(end.map(|(uri, end)| (uri.to_string(), end)));
+            }
+        }
+        if let Some(pending_range) = pending_range {
+            self.pending_range = pending_range;
+        }
+        self.segment_failures.retain(|sequence, _| *sequence >= first);
+
+        self.changed = !events.is_empty();
+        #[cfg(feature = "tracing")]
+        {
+            tracing::debug!(
+                media_sequence = first,
+                new_segments = events.len(),
+                since_previous_reload = ?self.last_reload.map(|x| now.saturating_duration_since(x)),
+                ended = playlist.ended(),
+                "Reloaded live playlist"
+            );
+            self.last_reload = Some(now);
+        }
+        if self.changed || self.last_change.is_none() {
+            self.first_new_sequence = next;
+            self.next_sequence = Some(first + playlist.segments().len() as u64);
+            self.last_change = Some(now);
+            self.stalled = false;
+        }
+
+        let producing = first + playlist.segments().len() as u64;
+        if producing >= next {
+            let from = match self.last_part {
+                Some((sequence, index)) if sequence == producing => index + 1,
+                _ => 0,
+            };
+            for (index, part) in playlist.trailing_parts().iter().enumerate().skip(from) {
+                events.push(FollowerEvent::Part { sequence: producing, index, part: Box::new(part.clone()) });
+                self.last_part = Some((producing, index));
+            }
+        }
+        if playlist.ended() && !self.ended {
+            self.ended = true;
+            events.push(FollowerEvent::Ended);
+        }
+
+        events.extend(self.stall(now, first));
+        events
+    }
+
+    /// Reports a reload at `now` which found the playlist as it was, e.g. a 304 response to a
+    /// conditional request, without it having to be parsed again. Like a reload with no new
+    /// segments, it can only report a stall.
+    pub fn reload_unchanged(&mut self, now: Instant) -> Vec<FollowerEvent> {
+        self.reload_failures = 0;
+        self.changed = false;
+        #[cfg(feature = "tracing")]
+        {
+            let since_previous_reload = self.last_reload.map(|x| now.saturating_duration_since(x));
+            tracing::debug!(?since_previous_reload, "Live playlist unchanged");
+            self.last_reload = Some(now);
+        }
+        self.stall(now, self.first_new_sequence).into_iter().collect()
+    }
+
+    /// [`FollowerEvent::Stalled`] the first time new segments have been missing for too long.
+    fn stall(&mut self, now: Instant, first: u64) -> Option<FollowerEvent> {
+        let since = self.last_change.map_or(Duration::ZERO, |x| now.saturating_duration_since(x));
+        if self.ended || self.stalled || since < self.target_duration * self.options.stall_after {
+            return None;
+        }
+        self.stalled = true;
+        #[cfg(feature = "tracing")]
+        tracing::warn!(?since, "Live playlist stalled, no new segments");
+        Some(FollowerEvent::Stalled { next_sequence: self.next_sequence.unwrap_or(first), since })
+    }
+
+    /// How long to wait before the next reload: the target duration after a reload with new
+    /// segments, and half of it otherwise.
+    pub fn reload_delay(&self) -> Duration {
+        if self.changed {
+            self.target_duration
+        } else {
+            self.target_duration / 2
+        }
+    }
+
+    /// Whether the playlist has ended, so it needs no more reloads.
+    pub fn ended(&self) -> bool {
+        self.ended
+    }
+
+    /// Reports a failed reload, returning how long to wait before retrying, or an error if the
+    /// failure isn't retryable or attempts are exhausted.
+    pub fn reload_failed(&mut self, status: Option<u16>) -> Result<Duration> {
+        self.reload_failures += 1;
+        let retry = self.reload_failures;
+        let delay = self.retry_delay(status, false, retry).map_err(|error| error.context("Playlist reload failed"));
+        #[cfg(feature = "tracing")]
+        match &delay {
+            Ok(delay) => tracing::info!(status, retry, ?delay, "Retrying playlist reload"),
+            Err(error) => tracing::warn!(status, retry, "{:#}", error),
+        }
+        delay
+    }
+
+    /// Reports a failed request for the segment with the given media sequence, returning how
+    /// long to wait before retrying, or an error if the failure isn't retryable or attempts are
+    /// exhausted.
+    pub fn segment_failed(&mut self, sequence: u64, status: Option<u16>) -> Result<Duration> {
+        let just_advertised = sequence >= self.first_new_sequence;
+        let failures = self.segment_failures.entry(sequence).or_default();
+        *failures += 1;
+        let retry = *failures;
+        let delay = self
+            .retry_delay(status, just_advertised, retry)
+            .map_err(|error| error.context(format!("Segment {} failed", sequence)));
+        #[cfg(feature = "tracing")]
+        match &delay {
+            Ok(delay) => tracing::info!(sequence, status, retry, ?delay, "Retrying segment"),
+            Err(error) => tracing::warn!(sequence, status, retry, "{:#}", error),
+        }
+        delay
+    }
+
+    fn retry_delay(&mut self, status: Option<u16>, just_advertised: bool, retry: u32) -> Result<Duration> {
+        let retry_policy = &self.options.retry;
+        if !retry_policy.is_retryable(status, just_advertised) {
+            return Err(match status {
+                Some(status) => anyhow::anyhow!("Status {} is not retryable", status),
+                None => anyhow::Error::msg("Not retryable"),
+            });
+        }
+        let random = self.next_random();
+        self.options
+            .retry
+            .backoff(retry, random)
+            .ok_or_else(|| anyhow::anyhow!("Gave up after {} attempts", retry))
+    }
+
+    fn next_random(&mut self) -> f64 {
+        //xorshift64, plenty for spreading out retries
+        self.random ^= self.random << 13;
+        self.random ^= self.random >> 7;
+        self.random ^= self.random << 17;
+        (self.random >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PlaylistType;
+
+    fn live_playlist(media_sequence: u64, segments: u64, ended: bool) -> MediaPlaylist {
+        let mut file = format!("#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-MEDIA-SEQUENCE:{}\n", media_sequence);
+        for sequence in media_sequence..media_sequence + segments {
+            file.push_str(&format!("#EXTINF:4,\n{}.ts\n", sequence));
+        }
+        if ended {
+            file.push_str("#EXT-X-ENDLIST\n");
+        }
+        MediaPlaylist::parse_ext_m3u(&file).expect("test playlist should parse")
+    }
+
+    fn sequences(events: &[FollowerEvent]) -> Vec<u64> {
+        events
+            .iter()
+            .filter_map(|x| match x {
+                FollowerEvent::Segment { sequence, .. } => Some(*sequence),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn no_jitter() -> FollowOptions {
+        FollowOptions { retry: RetryPolicy { jitter: 0.0, ..RetryPolicy::default() }, ..FollowOptions::default() }
+    }
+
+    #[test]
+    fn reports_new_segments() {
+        let start = Instant::now();
+        let mut follower = LiveFollower::new(no_jitter());
+        assert_eq!(sequences(&follower.reload(&live_playlist(10, 3, false), start)), vec![10, 11, 12]);
+        assert_eq!(follower.reload_delay(), Duration::from_secs(4));
+
+        let events = follower.reload(&live_playlist(11, 3, false), start + Duration::from_secs(4));
+        assert_eq!(sequences(&events), vec![13]);
+
+        assert!(follower.reload(&live_playlist(11, 3, false), start + Duration::from_secs(6)).is_empty());
+        assert_eq!(follower.reload_delay(), Duration::from_secs(2));
+
+        let events = follower.reload(&live_playlist(12, 3, true), start + Duration::from_secs(8));
+        assert_eq!(sequences(&events), vec![14]);
+        assert_eq!(events.last(), Some(&FollowerEvent::Ended));
+        assert!(follower.ended());
+    }
+
+    #[test]
+    fn reports_transitions() {
+        let start = Instant::now();
+        let mut follower = LiveFollower::new(no_jitter());
+        let mut event = live_playlist(10, 2, false);
+        event.set_playlist_type(Some(PlaylistType::Event));
+        follower.reload(&event, start);
+
+        let mut vod = live_playlist(10, 3, true);
+        vod.set_playlist_type(Some(PlaylistType::Vod));
+        let events = follower.reload(&vod, start + Duration::from_secs(4));
+        let transition = PlaylistTransition {
+            from: Some(PlaylistType::Event),
+            to: Some(PlaylistType::Vod),
+            ended: true,
+            mutated: vec![],
+        };
+        assert_eq!(events.first(), Some(&FollowerEvent::Transition(transition)));
+        assert_eq!(sequences(&events), vec![12]);
+        assert_eq!(events.last(), Some(&FollowerEvent::Ended));
+    }
+
+    #[test]
+    fn resumes_from_state() {
+        let start = Instant::now();
+        let mut follower = LiveFollower::new(no_jitter());
+        follower.reload(&live_playlist(10, 3, false), start);
+        follower.segment_failed(12, Some(503)).unwrap();
+        let state = follower.state();
+        assert_eq!((state.next_sequence, state.segment_failures.as_slice()), (Some(13), &[(12, 1)][..]));
+
+        let mut restored = LiveFollower::restore(no_jitter(), state.clone()).unwrap();
+        assert_eq!(restored.state(), state);
+        assert_eq!(sequences(&restored.reload(&live_playlist(11, 3, false), start)), vec![13]);
+        assert_eq!(restored.segment_failed(12, Some(503)).unwrap(), Duration::from_secs(1));
+
+        let mut event = live_playlist(11, 3, false);
+        event.set_playlist_type(Some(PlaylistType::Event));
+        assert!(matches!(restored.reload(&event, start).first(), Some(FollowerEvent::Transition(_))));
+    }
+
+    #[test]
+    fn reports_stall_once() {
+        let start = Instant::now();
+        let mut follower = LiveFollower::new(no_jitter());
+        let playlist = live_playlist(10, 3, false);
+        follower.reload(&playlist, start);
+        assert!(follower.reload(&playlist, start + Duration::from_secs(11)).is_empty());
+        assert_eq!(
+            follower.reload(&playlist, start + Duration::from_secs(12)),
+            vec![FollowerEvent::Stalled { next_sequence: 13, since: Duration::from_secs(12) }]
+        );
+        assert!(follower.reload(&playlist, start + Duration::from_secs(14)).is_empty());
+        assert_eq!(sequences(&follower.reload(&live_playlist(11, 3, false), start + Duration::from_secs(16))), vec![13]);
+    }
+
+    #[test]
+    fn backs_off_exponentially() {
+        let policy = RetryPolicy { jitter: 0.0, max_backoff: Duration::from_secs(1), ..RetryPolicy::default() };
+        let delays: Vec<Option<Duration>> = (1..=4).map(|retry| policy.backoff(retry, 0.5)).collect();
+        assert_eq!(
+            delays,
+            vec![Some(Duration::from_millis(500)), Some(Duration::from_secs(1)), Some(Duration::from_secs(1)), None]
+        );
+        let jittered = RetryPolicy { jitter: 0.5, ..policy };
+        assert_eq!(jittered.backoff(1, 0.5), Some(Duration::from_millis(375)));
+    }
+
+    #[test]
+    fn gives_up_on_reloads() {
+        let mut follower = LiveFollower::new(no_jitter());
+        assert_eq!(follower.reload_failed(Some(503)).unwrap(), Duration::from_millis(500));
+        assert_eq!(follower.reload_failed(None).unwrap(), Duration::from_secs(1));
+        assert_eq!(follower.reload_failed(None).unwrap(), Duration::from_secs(2));
+        let error = follower.reload_failed(None).expect_err("attempts are exhausted");
+        assert_eq!(format!("{:#}", error), "Playlist reload failed: Gave up after 4 attempts");
+
+        follower.reload(&live_playlist(10, 3, false), Instant::now());
+        assert!(follower.reload_failed(Some(503)).is_ok());
+        let error = follower.reload_failed(Some(403)).expect_err("403 is not retryable");
+        assert_eq!(format!("{:#}", error), "Playlist reload failed: Status 403 is not retryable");
+    }
+
+    #[test]
+    fn retries_missing_new_segments() {
+        let start = Instant::now();
+        let mut follower = LiveFollower::new(no_jitter());
+        follower.reload(&live_playlist(10, 3, false), start);
+        follower.reload(&live_playlist(11, 3, false), start + Duration::from_secs(4));
+        assert_eq!(follower.segment_failed(13, Some(404)).unwrap(), Duration::from_millis(500));
+        assert!(follower.segment_failed(12, Some(404)).is_err());
+
+        let strict = FollowOptions {
+            retry: RetryPolicy { retry_new_segment_not_found: false, ..RetryPolicy::default() },
+            ..FollowOptions::default()
+        };
+        let mut follower = LiveFollower::new(strict);
+        follower.reload(&live_playlist(10, 3, false), start);
+        assert!(follower.segment_failed(12, Some(404)).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn restores_parts_and_byte_ranges() {
+        let low_latency = |media_sequence: u64, segments: &str, parts: usize| {
+            let mut file = format!(
+                "#EXTM3U\n#EXT-X-VERSION:9\n#EXT-X-TARGETDURATION:4\n#EXT-X-MEDIA-SEQUENCE:{}\n\
+                 #EXT-X-PART-INF:PART-TARGET=1.0\n{}",
+                media_sequence, segments
+            );
+            for part in 0..parts {
+                file.push_str(&format!("#EXT-X-PART:DURATION=1.0,URI=\"part{}.mp4\"\n", part));
+            }
+            //strict parsing rejects an implicit offset in the first segment, which servers still write
+            let lenient = crate::ParseOptions { lenient: true, ..Default::default() };
+            MediaPlaylist::parse_with_options(&file, &lenient).expect("test playlist should parse")
+        };
+        let parts = |events: &[FollowerEvent]| -> Vec<(u64, usize)> {
+            events
+                .iter()
+                .filter_map(|x| match x {
+                    FollowerEvent::Part { sequence, index, .. } => Some((*sequence, *index)),
+                    _ => None,
+                })
+                .collect()
+        };
+        let offsets = |events: &[FollowerEvent]| -> Vec<Option<u64>> {
+            events
+                .iter()
+                .filter_map(|x| match x {
+                    FollowerEvent::Segment { segment, .. } => Some(segment.byte_range().and_then(|x| x.offset)),
+                    _ => None,
+                })
+                .collect()
+        };
+        let start = Instant::now();
+        let mut follower = LiveFollower::new(no_jitter());
+        let segments = "#EXTINF:4,\n#EXT-X-BYTERANGE:1000@0\nmain.mp4\n#EXTINF:4,\n#EXT-X-BYTERANGE:1000\nmain.mp4\n";
+        let events = follower.reload(&low_latency(10, segments, 2), start);
+        assert_eq!((sequences(&events), parts(&events)), (vec![10, 11], vec![(12, 0), (12, 1)]));
+
+        let state = follower.state();
+        assert_eq!(state.last_part, Some((12, 1)));
+        assert_eq!(state.pending_range, Some(("main.mp4".to_string(), 2000)));
+        let json = serde_json::to_string(&state).unwrap();
+        let restored: FollowerState = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, state);
+        let mut follower = LiveFollower::restore(no_jitter(), restored).unwrap();
+        assert_eq!(parts(&follower.reload(&low_latency(10, segments, 3), start)), vec![(12, 2)]);
+
+        //the segment the parts became continues one which has left the playlist
+        let events = follower.reload(&low_latency(12, "#EXTINF:4,\n#EXT-X-BYTERANGE:1000\nmain.mp4\n", 1), start);
+        assert_eq!(parts(&events), vec![(13, 0)]);
+        assert_eq!(offsets(&events), vec![Some(2000)]);
+
+        //and a follower restored from that window carries on from it
+        let mut follower = LiveFollower::restore(no_jitter(), follower.state()).unwrap();
+        let events = follower.reload(&low_latency(13, "#EXTINF:4,\n#EXT-X-BYTERANGE:1000\nmain.mp4\n", 1), start);
+        assert_eq!((sequences(&events), parts(&events)), (vec![13], vec![(14, 0)]));
+        assert_eq!(offsets(&events), vec![Some(3000)]);
+
+        let older: serde_json::Value = serde_json::json!({
+            "next_sequence": 13, "ended": false, "segment_failures": [], "previous": null
+        });
+        let older: FollowerState = serde_json::from_value(older).unwrap();
+        assert_eq!((older.last_part, older.pending_range), (None, None));
+    }
+}
@@ -0,0 +1,89 @@
+//! Media initialization sections. See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2.5>.
+
+use core::fmt;
+
+use anyhow::Result;
+
+use crate::attributes::{AttributeList, RawAttributes};
+use crate::ByteRange;
+
+/// Information from an EXT-X-MAP tag: where to get the data needed to parse the following media
+/// segments, e.g. the `moov` box of fragmented MP4. Applies to every following segment until the
+/// next EXT-X-MAP tag.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SegmentMap {
+    uri: String,
+
+    /// Sub-range of the resource holding the initialization section. Unlike for segments, the
+    /// offset is never implicit.
+    byte_range: Option<ByteRange>,
+
+    /// The attribute list as parsed.
+    raw: RawAttributes,
+}
+
+impl SegmentMap {
+    pub fn new(uri: impl Into<String>, byte_range: Option<ByteRange>) -> Self {
+        Self { uri: uri.into(), byte_range, raw: RawAttributes::default() }
+    }
+
+    /// Parses the attribute list of an EXT-X-MAP tag.
+    pub(crate) fn parse(attribute_list: &str) -> Result<Self> {
+        let attributes = AttributeList::parse(attribute_list)?;
+        let Some(uri) = attributes.quoted_string("URI")? else {
+            return Err(anyhow::Error::msg("Map is missing URI attribute"));
+        };
+        let byte_range = attributes.quoted_string("BYTERANGE")?.map(str::parse::<ByteRange>).transpose()?;
+        Ok(Self { uri: uri.to_string(), byte_range, raw: RawAttributes::new(attribute_list) })
+    }
+
+    /// Raw value of the attribute `name` as parsed, including ones the crate doesn't model, see
+    /// [`RawAttributes`][crate::RawAttributes].
+    pub fn raw_attribute(&self, name: &str) -> Option<&str> {
+        self.raw.get(name)
+    }
+
+    /// Every attribute of the EXT-X-MAP tag as parsed.
+    pub fn raw_attributes(&self) -> &RawAttributes {
+        &self.raw
+    }
+
+    pub(crate) fn set_uri(&mut self, uri: impl Into<String>) {
+        self.uri = uri.into();
+    }
+
+    /// Where to get the initialization section, relative to the playlist unless absolute.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    pub fn byte_range(&self) -> Option<ByteRange> {
+        self.byte_range
+    }
+}
+
+impl fmt::Display for SegmentMap {
+    /// Formats the map as the attribute list of an EXT-X-MAP tag.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "URI=\"{}\"", self.uri)?;
+        if let Some(byte_range) = &self.byte_range {
+            write!(f, ",BYTERANGE=\"{}\"", byte_range)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_map() {
+        let map = SegmentMap::parse(r#"URI="init.mp4",BYTERANGE="720@0""#).expect("should parse");
+        assert_eq!(map, SegmentMap::new("init.mp4", Some(ByteRange { length: 720, offset: Some(0) })));
+        assert_eq!(map.to_string(), r#"URI="init.mp4",BYTERANGE="720@0""#);
+        assert_eq!(SegmentMap::parse(r#"URI="init.mp4""#).unwrap().byte_range(), None);
+        assert!(SegmentMap::parse(r#"BYTERANGE="720@0""#).is_err());
+        assert!(SegmentMap::parse(r#"URI="init.mp4",BYTERANGE="seven""#).is_err());
+    }
+}
@@ -0,0 +1,449 @@
+//! Utilites for parsing master playlists, which list the variant streams and renditions of a
+//! presentation.
+
+use anyhow::Result;
+
+use crate::events::{self, Event, HEADER_TAG, STREAM_INF_TAG};
+use crate::variables::Variables;
+use crate::{ClosedCaptions, EncryptionKey, MediaType, ParseOptions, Rendition, SessionData, SpecVersion, VariantStream};
+
+/// Storage for HLS Master Playlist data. Can be constructed from `ext-m3u` data using
+/// [`parse_ext_m3u`][MasterPlaylist::parse_ext_m3u].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MasterPlaylist {
+    /// Version of playlist for compatibility. See
+    /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.1.2>.
+    version: u64,
+
+    /// From EXT-X-STREAM-INF tags, in playlist order. See
+    /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.4.2>.
+    variants: Vec<VariantStream>,
+
+    /// From EXT-X-MEDIA tags, in playlist order. See
+    /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.4.1>.
+    renditions: Vec<Rendition>,
+
+    /// From EXT-X-I-FRAME-STREAM-INF tags, in playlist order. See
+    /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.4.3>.
+    i_frame_variants: Vec<VariantStream>,
+
+    /// From EXT-X-SESSION-DATA tags, in playlist order. See
+    /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.4.4>.
+    session_data: Vec<SessionData>,
+
+    /// From EXT-X-SESSION-KEY tags, in playlist order. See
+    /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.4.5>.
+    session_keys: Vec<EncryptionKey>,
+}
+
+impl MasterPlaylist {
+    /// Parses the given file into a [`MasterPlaylist`], returning an error if the file does not
+    /// adhere to the specification.
+    pub fn parse_ext_m3u(file: &str) -> Result<Self> {
+        Self::parse_with_options(file, &ParseOptions::default())
+    }
+
+    /// Like [`parse_ext_m3u`][Self::parse_ext_m3u], following [`ParseOptions::spec`]. Tags
+    /// outside that version are errors, since there are no diagnostics to report fixes in, and
+    /// the other options are for media playlists.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(bytes = file.len())))]
+    pub fn parse_with_options(file: &str, options: &ParseOptions) -> Result<Self> {
+        let mut parser = Parser { spec: options.spec, ..Parser::default() };
+        let result = file.lines().try_for_each(|line| parser.line(line)).and_then(|()| parser.finish());
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(playlist) => tracing::debug!(
+                variants = playlist.variants.len(),
+                renditions = playlist.renditions.len(),
+                "Parsed master playlist"
+            ),
+            Err(error) => tracing::warn!("Master playlist failed to parse: {:#}", error),
+        }
+        result
+    }
+
+    /// Version of the playlist, `0` if there was no version tag.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Variant streams in playlist order.
+    pub fn variants(&self) -> &[VariantStream] {
+        &self.variants
+    }
+
+    /// Renditions in playlist order.
+    pub fn renditions(&self) -> &[Rendition] {
+        &self.renditions
+    }
+
+    /// I-frame playlists for trick play, in playlist order. Their attributes describe the
+    /// I-frames only, so AUDIO, SUBTITLES and CLOSED-CAPTIONS are never set.
+    pub fn i_frame_variants(&self) -> &[VariantStream] {
+        &self.i_frame_variants
+    }
+
+    /// Session data in playlist order.
+    pub fn session_data(&self) -> &[SessionData] {
+        &self.session_data
+    }
+
+    /// Keys of the media playlists, so clients can load them before playback starts.
+    pub fn session_keys(&self) -> &[EncryptionKey] {
+        &self.session_keys
+    }
+
+    pub(crate) fn variants_mut(&mut self) -> &mut Vec<VariantStream> {
+        &mut self.variants
+    }
+
+    pub(crate) fn renditions_mut(&mut self) -> &mut Vec<Rendition> {
+        &mut self.renditions
+    }
+
+    pub(crate) fn i_frame_variants_mut(&mut self) -> &mut Vec<VariantStream> {
+        &mut self.i_frame_variants
+    }
+
+    pub(crate) fn session_data_mut(&mut self) -> &mut Vec<SessionData> {
+        &mut self.session_data
+    }
+
+    pub(crate) fn session_keys_mut(&mut self) -> &mut Vec<EncryptionKey> {
+        &mut self.session_keys
+    }
+
+    /// Renditions of the given type in the given group.
+    pub fn rendition_group(&self, media_type: MediaType, group_id: &str) -> Vec<&Rendition> {
+        self.renditions.iter().filter(|x| x.media_type() == media_type && x.group_id() == group_id).collect()
+    }
+
+    /// The first variant with the given STABLE-VARIANT-ID, to find a variant again after the
+    /// master playlist was reloaded and its URIs changed.
+    pub fn variant_by_stable_id(&self, stable_variant_id: &str) -> Option<&VariantStream> {
+        self.variants.iter().find(|x| x.stable_variant_id() == Some(stable_variant_id))
+    }
+
+    /// The first rendition with the given STABLE-RENDITION-ID, like
+    /// [`variant_by_stable_id`][Self::variant_by_stable_id].
+    pub fn rendition_by_stable_id(&self, stable_rendition_id: &str) -> Option<&Rendition> {
+        self.renditions.iter().find(|x| x.stable_rendition_id() == Some(stable_rendition_id))
+    }
+}
+
+/// Incremental [`MasterPlaylist`] parser, fed one line at a time.
+#[derive(Debug, Default)]
+struct Parser {
+    line_number: usize,
+    spec: SpecVersion,
+    variables: Variables,
+    version: Option<u64>,
+    variants: Vec<VariantStream>,
+    renditions: Vec<Rendition>,
+    i_frame_variants: Vec<VariantStream>,
+    session_data: Vec<SessionData>,
+    session_keys: Vec<EncryptionKey>,
+
+    /// Line number of the EXT-X-STREAM-INF waiting for its URI, with the variant.
+    pending_variant: Option<(usize, VariantStream)>,
+}
+
+impl Parser {
+    fn line(&mut self, line: &str) -> Result<()> {
+        self.line_number += 1;
+        let line_number = self.line_number;
+
+        //RFC8216 4.3.1.1 requirement
+        if line_number == 1 && line != format!("#{HEADER_TAG}") {
+            return Err(anyhow::Error::msg("Input doesn't start with EXTM3U tag"));
+        }
+        //RFC8216bis 4.3, references are replaced before the line is parsed
+        let line = match self.spec {
+            SpecVersion::Rfc8216bis => self.variables.substitute(line)?,
+            SpecVersion::Rfc8216 => line.into(),
+        };
+        let Some(event) = events::parse_line(&line) else {
+            return Ok(());
+        };
+        let event = event?;
+        if self.spec == SpecVersion::Rfc8216 {
+            if let Some(tag) = event.rfc8216bis_tag() {
+                return Err(anyhow::anyhow!("{} tag is not part of RFC 8216 at line {}", tag, line_number));
+            }
+            if let Event::Version(version @ 8..) = event {
+                let message = format!("EXT-X-VERSION {} is not part of RFC 8216 at line {}", version, line_number);
+                return Err(anyhow::Error::msg(message));
+            }
+        }
+        match event {
+            Event::Header => {
+                if line_number > 1 {
+                    return Err(anyhow::anyhow!("Unexpected {} tag at line {}", HEADER_TAG, line_number));
+                }
+            }
+            //RFC8216 4.3.1.2 requirements
+            Event::Version(version) => {
+                if self.version.is_some() {
+                    return Err(anyhow::Error::msg("Playlist contains more than 1 version tag"));
+                }
+                self.version = Some(version);
+            }
+            //RFC8216 4.3.4.2 requirements
+            Event::StreamInf(variant) => {
+                if let Some((variant_line, _)) = self.pending_variant {
+                    return Err(anyhow::anyhow!("{} without URI at line {}", STREAM_INF_TAG, variant_line));
+                }
+                self.pending_variant = Some((line_number, variant));
+            }
+            Event::Media(rendition) => self.renditions.push(rendition),
+            Event::Define { name, value } => self.variables.define(name, value)?,
+            Event::IFrameStreamInf(variant) => self.i_frame_variants.push(variant),
+            Event::SessionData(data) => self.session_data.push(data),
+            Event::SessionKey(key) => self.session_keys.push(key),
+            Event::Uri(uri) => {
+                let Some((_, mut variant)) = self.pending_variant.take() else {
+                    return Err(anyhow::anyhow!("URI without {} at line {}", STREAM_INF_TAG, line_number));
+                };
+                variant.set_uri(uri);
+                self.variants.push(variant);
+            }
+            //RFC8216 4.1, a playlist is either a media or a master playlist
+            Event::TargetDuration(_)
+            | Event::MediaSequence(_)
+            | Event::AllowCache(_)
+            | Event::PlaylistType(_)
+            | Event::ExtInf { .. }
+            | Event::ByteRange(_)
+            | Event::Discontinuity
+            | Event::Key(_)
+            | Event::EndList
+            | Event::Skip(_)
+            | Event::IFramesOnly
+            | Event::Map(_)
+            | Event::ProgramDateTime(_)
+            | Event::DateRange(_)
+            | Event::DiscontinuitySequence(_)
+            | Event::Gap
+            | Event::Part(_)
+            | Event::PartInf(_)
+            | Event::PreloadHint(_)
+            | Event::RenditionReport(_) => {
+                return Err(anyhow::anyhow!(
+                    "Media playlist tag {} in master playlist at line {}",
+                    events::tag_name(&line),
+                    line_number
+                ));
+            }
+            Event::Unknown { .. } | Event::Comment(_) => {
+                //unsupported tags and comments are ignored
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<MasterPlaylist> {
+        if self.line_number == 0 {
+            return Err(anyhow::Error::msg("Input contains no data"));
+        }
+        if let Some((variant_line, _)) = self.pending_variant {
+            return Err(anyhow::anyhow!("{} without URI at line {}", STREAM_INF_TAG, variant_line));
+        }
+
+        for variant in self.variants.iter().chain(&self.i_frame_variants) {
+            if let Some(&(media_type, group_id)) = missing_groups(variant, &self.renditions).first() {
+                return Err(anyhow::anyhow!(
+                    "Variant {} refers to unknown {} group {}",
+                    variant.uri(), media_type, group_id
+                ));
+            }
+        }
+
+        //RFC8216 4.3.4.4 and 4.3.4.5 requirements
+        for (index, data) in self.session_data.iter().enumerate() {
+            let same = |x: &SessionData| x.data_id() == data.data_id() && x.language() == data.language();
+            if self.session_data[..index].iter().any(same) {
+                return Err(anyhow::anyhow!("Session data {} appears more than once for its language", data.data_id()));
+            }
+        }
+        for (index, key) in self.session_keys.iter().enumerate() {
+            if self.session_keys[..index].contains(key) {
+                return Err(anyhow::anyhow!("Session key {} appears more than once", key));
+            }
+        }
+
+        //RFC8216 4.3.4.2, NONE is all or nothing
+        if self.variants.iter().any(|x| x.closed_captions() == Some(&ClosedCaptions::None)) {
+            if let Some(variant) = self.variants.iter().find(|x| x.closed_captions() != Some(&ClosedCaptions::None)) {
+                return Err(anyhow::anyhow!(
+                    "Variant {} must have CLOSED-CAPTIONS=NONE since other variants do",
+                    variant.uri()
+                ));
+            }
+        }
+
+        Ok(MasterPlaylist {
+            version: self.version.unwrap_or(0),
+            variants: self.variants,
+            renditions: self.renditions,
+            i_frame_variants: self.i_frame_variants,
+            session_data: self.session_data,
+            session_keys: self.session_keys,
+        })
+    }
+}
+
+/// Groups named by `variant` which no rendition of the matching type is in. RFC8216 4.3.4.2
+/// requires them to exist.
+pub(crate) fn missing_groups<'a>(variant: &'a VariantStream, renditions: &[Rendition]) -> Vec<(MediaType, &'a str)> {
+    let closed_captions = match variant.closed_captions() {
+        Some(ClosedCaptions::Group(group_id)) => Some(group_id.as_str()),
+        Some(ClosedCaptions::None) | None => None,
+    };
+    let groups = [
+        (MediaType::Audio, variant.audio()),
+        (MediaType::Video, variant.video()),
+        (MediaType::Subtitles, variant.subtitles()),
+        (MediaType::ClosedCaptions, closed_captions),
+    ];
+    groups
+        .into_iter()
+        .filter_map(|(media_type, group_id)| Some((media_type, group_id?)))
+        .filter(|&(media_type, group_id)| {
+            !renditions.iter().any(|x| x.media_type() == media_type && x.group_id() == group_id)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MediaPlaylist, Resolution};
+
+    const MASTER: &str = indoc::indoc! {r#"
+        #EXTM3U
+        #EXT-X-VERSION:4
+        #EXT-X-INDEPENDENT-SEGMENTS
+        #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",LANGUAGE="en",NAME="English",DEFAULT=YES,AUTOSELECT=YES,URI="audio/en.m3u8"
+        #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",LANGUAGE="de",NAME="Deutsch",URI="audio/de.m3u8"
+        #EXT-X-STREAM-INF:BANDWIDTH=1280000,CODECS="avc1.4d401e,mp4a.40.2",RESOLUTION=640x360,AUDIO="aac"
+        low/index.m3u8
+        # higher quality
+        #EXT-X-STREAM-INF:BANDWIDTH=2560000,CODECS="avc1.4d401f,mp4a.40.2",RESOLUTION=1280x720,AUDIO="aac"
+        high/index.m3u8
+    "#};
+
+    fn parse_error(file: &str) -> String {
+        MasterPlaylist::parse_ext_m3u(file).expect_err("playlist should not parse").to_string()
+    }
+
+    #[test]
+    fn parses_variants_and_renditions() {
+        let playlist = MasterPlaylist::parse_ext_m3u(MASTER).expect("should parse");
+        assert_eq!(playlist.version(), 4);
+        let uris: Vec<&str> = playlist.variants().iter().map(VariantStream::uri).collect();
+        assert_eq!(uris, vec!["low/index.m3u8", "high/index.m3u8"]);
+        assert_eq!(playlist.variants()[1].resolution(), Some(Resolution { width: 1280, height: 720 }));
+        let names: Vec<&str> = playlist.rendition_group(MediaType::Audio, "aac").iter().map(|x| x.name()).collect();
+        assert_eq!(names, vec!["English", "Deutsch"]);
+        assert!(playlist.rendition_group(MediaType::Subtitles, "aac").is_empty());
+    }
+
+    #[test]
+    fn rejects_stream_inf_without_uri() {
+        let error = parse_error(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000
+            #EXT-X-STREAM-INF:BANDWIDTH=2560000
+            high.m3u8
+        "});
+        assert_eq!(error, "EXT-X-STREAM-INF without URI at line 2");
+        let error = parse_error("#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1280000\n");
+        assert_eq!(error, "EXT-X-STREAM-INF without URI at line 2");
+        let error = parse_error("#EXTM3U\nlow.m3u8\n");
+        assert_eq!(error, "URI without EXT-X-STREAM-INF at line 2");
+    }
+
+    #[test]
+    fn rejects_media_playlist_tags() {
+        let error = parse_error("#EXTM3U\n#EXT-X-TARGETDURATION:10\n");
+        assert_eq!(error, "Media playlist tag EXT-X-TARGETDURATION in master playlist at line 2");
+    }
+
+    #[test]
+    fn rejects_unknown_groups() {
+        let error = parse_error("#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1280000,SUBTITLES=\"subs\"\nlow.m3u8\n");
+        assert_eq!(error, "Variant low.m3u8 refers to unknown SUBTITLES group subs");
+    }
+
+    #[test]
+    fn reports_bad_attributes() {
+        let error = MasterPlaylist::parse_ext_m3u("#EXTM3U\n#EXT-X-STREAM-INF:RESOLUTION=1x1\nlow.m3u8\n")
+            .expect_err("playlist should not parse");
+        assert_eq!(format!("{:#}", error), "Stream tag found, but could not parse: Stream is missing BANDWIDTH attribute");
+    }
+
+    #[test]
+    fn finds_variants_and_renditions_by_stable_id() {
+        let file = indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",NAME="English",STABLE-RENDITION-ID="audio-en",URI="en.m3u8?v=2"
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000,AUDIO="aac",STABLE-VARIANT-ID="720p"
+            low.m3u8?v=2
+        "#};
+        let playlist = MasterPlaylist::parse_ext_m3u(file).unwrap();
+        assert_eq!(playlist.variant_by_stable_id("720p").map(VariantStream::uri), Some("low.m3u8?v=2"));
+        assert_eq!(playlist.rendition_by_stable_id("audio-en").and_then(Rendition::uri), Some("en.m3u8?v=2"));
+        assert!(playlist.variant_by_stable_id("audio-en").is_none());
+        assert!(playlist.rendition_by_stable_id("720p").is_none());
+        assert_eq!(playlist.to_string(), file);
+    }
+
+    #[test]
+    fn parses_i_frame_variants() {
+        let file = indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000,RESOLUTION=640x360
+            low.m3u8
+            #EXT-X-I-FRAME-STREAM-INF:BANDWIDTH=86000,CODECS="avc1.4d401e",RESOLUTION=640x360,URI="low/iframes.m3u8"
+        "#};
+        let playlist = MasterPlaylist::parse_ext_m3u(file).unwrap();
+        assert_eq!(playlist.variants().len(), 1);
+        let uris: Vec<&str> = playlist.i_frame_variants().iter().map(VariantStream::uri).collect();
+        assert_eq!(uris, vec!["low/iframes.m3u8"]);
+        assert_eq!(playlist.to_string(), file);
+
+        let error = parse_error("#EXTM3U\n#EXT-X-I-FRAME-STREAM-INF:BANDWIDTH=1,VIDEO=\"v\",URI=\"i.m3u8\"\n");
+        assert_eq!(error, "Variant i.m3u8 refers to unknown VIDEO group v");
+    }
+
+    #[test]
+    fn parses_session_data_and_keys() {
+        let file = indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-SESSION-DATA:DATA-ID="com.example.title",VALUE="Title",LANGUAGE="en"
+            #EXT-X-SESSION-DATA:DATA-ID="com.example.title",VALUE="Titel",LANGUAGE="de"
+            #EXT-X-SESSION-KEY:METHOD=SAMPLE-AES,URI="skd://1",KEYFORMAT="com.apple.streamingkeydelivery"
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000
+            low.m3u8
+        "#};
+        let playlist = MasterPlaylist::parse_ext_m3u(file).unwrap();
+        let values: Vec<Option<&str>> = playlist.session_data().iter().map(SessionData::value).collect();
+        assert_eq!(values, vec![Some("Title"), Some("Titel")]);
+        assert_eq!(playlist.session_keys()[0].uri(), Some("skd://1"));
+        assert_eq!(playlist.to_string(), file);
+
+        let data = "#EXT-X-SESSION-DATA:DATA-ID=\"a\",VALUE=\"x\"\n";
+        let error = parse_error(&format!("#EXTM3U\n{}{}", data, data));
+        assert_eq!(error, "Session data a appears more than once for its language");
+        let key = "#EXT-X-SESSION-KEY:METHOD=AES-128,URI=\"1.key\"\n";
+        let error = parse_error(&format!("#EXTM3U\n{}{}", key, key));
+        assert_eq!(error, "Session key METHOD=AES-128,URI=\"1.key\" appears more than once");
+        let error = MasterPlaylist::parse_ext_m3u("#EXTM3U\n#EXT-X-SESSION-KEY:METHOD=NONE\n").unwrap_err();
+        assert_eq!(
+            format!("{:#}", error),
+            "Session key tag found, but could not parse: Session key must not have METHOD=NONE"
+        );
+        let error = MediaPlaylist::parse_ext_m3u(&format!("#EXTM3U\n#EXT-X-TARGETDURATION:10\n{}", data)).unwrap_err();
+        assert_eq!(error.to_string(), "Master playlist tag EXT-X-SESSION-DATA in media playlist");
+    }
+}
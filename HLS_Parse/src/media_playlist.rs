@@ -1,19 +1,29 @@
 //! Utilites for parsing media playlists (i.e. not master playlists).
 
 use core::time::Duration;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
 
 use anyhow::Result;
 
-/// RFC8216, Section 4 tag constants
-static HEADER_TAG: &'static str = "#EXTM3U";
-static VERSION_TAG: &'static str = "#EXT-X-VERSION";
-static VERSION_PRE: &'static str = "#EXT-X-VERSION:";
-static ENDLIST_TAG: &'static str = "#EXT-X-ENDLIST";
-static DURATION_TAG: &'static str = "#EXT-X-TARGETDURATION";
-static DURATION_PRE: &'static str = "#EXT-X-TARGETDURATION:";
-static SEGMENT_TAG: &'static str = "#EXTINF";
-static SEGMENT_PRE: &'static str = "#EXTINF:";
-static BYTERANGE_TAG: &'static str = "#EXT-X-BYTERANGE";
+use crate::attributes;
+use crate::diagnostics::{Diagnostic, ParseError, ParseNotes};
+use crate::extensions::{CustomTag, Extensions, TagExtensions, TagHandlers};
+use crate::events::{
+    self, Event, ATTRIBUTE_LIST_TAGS, BYTERANGE_TAG, DATERANGE_TAG, DISCONTINUITY_TAG, ENDLIST_TAG, GAP_TAG,
+    HEADER_TAG, I_FRAME_STREAM_INF_TAG, MEDIA_TAG, PROGRAM_DATE_TIME_TAG, SEGMENT_TAG, SESSION_DATA_TAG,
+    SESSION_KEY_TAG, STREAM_INF_TAG,
+};
+use crate::options::Limit;
+use crate::segment_tags::{TagOrder, TagSlot};
+use crate::source::{Source, SourceRecorder};
+use crate::variables::Variables;
+use crate::writer::PlaylistTag;
+use crate::{
+    ByteRange, Cue, DateRange, EncryptionKey, ParseLimits, ParseOptions, PartialSegment, PlaylistType, PreloadHint,
+    ProgramDateTime, RenditionReport, SegmentDuration, SegmentMap, SegmentUri, SpecVersion,
+};
 
 /// Storage for HLS Media Playlist data. Can be constructed from `ext-m3u` data using
 /// [`parse_ext_m3u`][MediaPlaylist::parse_ext_m3u].
@@ -29,9 +39,60 @@ pub struct MediaPlaylist {
     /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.3.1>.
     target_duration: Duration,
 
+    /// Sequence number of the first segment. See
+    /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.3.2>.
+    media_sequence: u64,
+
+    /// Discontinuity sequence number of the first segment. See
+    /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.3.3>.
+    discontinuity_sequence: u64,
+
+    /// Legacy caching permission, honored by some caches and proxies. See
+    /// <https://datatracker.ietf.org/doc/html/draft-pantos-http-live-streaming-13#section-3.4.5>.
+    allow_cache: Option<bool>,
+
+    /// How the server may change the playlist, `None` for a live playlist which may change in
+    /// any way. See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.3.5>.
+    playlist_type: Option<PlaylistType>,
+
     /// Version of playlist for compatibility. See
     /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.1.2>.
     version: u64,
+
+    /// Segments left out before the first one, if this is a playlist delta update. See
+    /// <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.5.2>.
+    skipped_segments: u64,
+
+    /// Whether each segment is a single I-frame, for trick play. See
+    /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.3.6>.
+    i_frames_only: bool,
+
+    /// Duration no part can exceed, if the playlist has low-latency parts. See
+    /// <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.3.7>.
+    part_target: Option<SegmentDuration>,
+
+    /// Parts of the segment being produced, listed after the last complete segment.
+    trailing_parts: Vec<PartialSegment>,
+
+    /// Date ranges listed after the last segment, e.g. an upcoming ad break.
+    trailing_date_ranges: Vec<DateRange>,
+
+    /// Resources the server is about to publish, at most one of each type. See
+    /// <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.5.3>.
+    preload_hints: Vec<PreloadHint>,
+
+    /// Progress of other renditions of the stream. See
+    /// <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.5.4>.
+    rendition_reports: Vec<RenditionReport>,
+
+    /// Original lines if parsed with [`ParseOptions::preserve_source`], otherwise empty.
+    source: Source,
+
+    /// Fixes made to the input with [`ParseOptions::lenient`].
+    parse_notes: ParseNotes,
+
+    /// Data from [`ParseOptions::tag_handlers`] about the whole playlist.
+    extensions: Extensions,
 }
 
 /// A media segment contains information to actually load the presentation. See [the
@@ -41,109 +102,1275 @@ pub struct MediaPlaylist {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MediaSegment {
     /// From the #EXTINF tag. See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2.1>.
-    duration: Duration,
+    duration: SegmentDuration,
 
-    /// Relative URL of media segment. See
+    /// Whether the segment had no EXTINF tag, so the duration was estimated in lenient mode.
+    duration_estimated: bool,
+
+    /// URL of the media segment, usually relative to the playlist. See
     /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2> and
     /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.1>.
-    url: String,
+    url: SegmentUri,
+
+    /// Human-readable title from the #EXTINF tag, if not empty.
+    title: Option<String>,
+
+    /// Keys from the most recent EXT-X-KEY tag of each KEYFORMAT since the last one with method
+    /// NONE, in the order the formats first appeared. See
+    /// <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.4.4>.
+    /// Shared by the segments a parsed tag applies to.
+    keys: Arc<[EncryptionKey]>,
+
+    /// Sub-range of the resource at the URL. See
+    /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2.2>.
+    byte_range: Option<ByteRange>,
+
+    /// Whether an EXT-X-DISCONTINUITY tag precedes the segment. See
+    /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2.3>.
+    discontinuity: bool,
+
+    /// Media initialization section from the most recent EXT-X-MAP tag. See
+    /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2.5>. Shared like the keys.
+    map: Option<Arc<SegmentMap>>,
+
+    /// Date and time of the first sample, from an EXT-X-PROGRAM-DATE-TIME tag preceding the
+    /// segment. See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2.6>.
+    program_date_time: Option<ProgramDateTime>,
+
+    /// Whether an EXT-X-GAP tag marks the segment as unavailable. See
+    /// <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.4.7>.
+    gap: bool,
+
+    /// Parts of the segment from the EXT-X-PART tags preceding it, in order.
+    parts: Vec<PartialSegment>,
+
+    /// Date ranges from the EXT-X-DATERANGE tags preceding the segment, in order. Their dates
+    /// needn't fall within the segment.
+    date_ranges: Vec<DateRange>,
+
+    /// SCTE-35 cues of the date ranges overlapping the segment, found once parsing is done.
+    cues: Vec<Cue>,
+
+    /// Order of the tags preceding the segment, for [`MediaSegment::tags`].
+    tag_order: TagOrder,
+
+    /// Data from [`ParseOptions::tag_handlers`] about the tags preceding the segment.
+    extensions: Extensions,
 }
 
 impl MediaPlaylist {
     /// Parses the given file into a [`MediaPlaylist`], returning an error if the file does not
     /// adhere to the specification.
     pub fn parse_ext_m3u(_file: &str) -> Result<Self> {
-        let lines: Vec<&str> = _file.lines().collect();
-        
-        //return error if our input contains no data
-        if lines.len() == 0 { return Err(anyhow::Error::msg("Input contains no data")); }
-        
-        //RFC8216 4.3.1.1 requirement
-        if lines[0] != HEADER_TAG { return Err(anyhow::Error::msg("Input doesn't start with EXTM3U tag")); }
-        
-        //RFC8216 4.3.1.2 requirements
-        let mut file_version: u64 = 0;
-        if lines.iter().filter(|x| x.starts_with(VERSION_TAG)).count() > 1 {
-            return Err(anyhow::Error::msg("Playlist contains more than 1 version tag"));
-        }
-        let version_line: Option<&str> = lines.iter().find(|x| x.starts_with(VERSION_TAG)).copied();
-        if version_line != None
-        {
-            let version_string: Option<&str> = version_line.unwrap().strip_prefix(VERSION_PRE);
-            if version_string != None {
-                let parsed_version = version_string.unwrap().parse::<u64>();
-                match parsed_version {
-                    Ok(..) => file_version = parsed_version.unwrap(),
-                    Err(..) => return Err(anyhow::Error::msg("Version tag found, but could not parse"))
-                }
-            }
-            else { return Err(anyhow::Error::msg("Version tag found, but could not parse")); }
-        }
-        else {
-            //no version tag, a rigorous check makes sure we only have V1 tags 
-        }
-        
-        //RFC8216 4.3.3.1 requirements
-        let mut file_duration: Duration = Duration::new(0, 0);
-        if lines.iter().filter(|x| x.starts_with(DURATION_TAG)).count() > 1 {
-            return Err(anyhow::Error::msg("Playlist contains more than 1 duration tag"));
-        }
-        let duration_line: Option<&str> = lines.iter().find(|x| x.starts_with(DURATION_TAG)).copied();
-        if duration_line != None
-        {
-            let duration_string: Option<&str> = duration_line.unwrap().strip_prefix(DURATION_PRE);
-            if duration_string != None {
-                let parsed_duration = duration_string.unwrap().parse::<u64>();
-                match parsed_duration {
-                    Ok(..) => file_duration = Duration::new(parsed_duration.unwrap(), 0),
-                    Err(..) => return Err(anyhow::Error::msg("Duration tag found, but could not parse"))
-                }
-            }
-        }
-        else {
-            return Err(anyhow::Error::msg("Duration tag not found"));
+        Self::parse_with_options(_file, &ParseOptions::default())
+    }
+
+    /// Like [`parse_ext_m3u`][Self::parse_ext_m3u], with control over how parsing is done.
+    pub fn parse_with_options(file: &str, options: &ParseOptions) -> Result<Self> {
+        Self::parse_with_line(file, options).map_err(|(_, error)| error)
+    }
+
+    /// Like [`parse_with_options`][Self::parse_with_options], but parsing carries on past a line
+    /// with an error as if it wasn't there, so every problem in the file is reported at once.
+    /// Nothing after a missing header is checked, since it can't be trusted to be a playlist.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(bytes = file.len())))]
+    pub fn parse_all_errors(file: &str, options: &ParseOptions) -> Result<Self, Vec<ParseError>> {
+        let mut parser = Parser::new(options);
+        parser.set_input_len(file.len());
+        let mut errors = Vec::new();
+        for line in file.lines() {
+            if let Err(error) = parser.line(line) {
+                errors.push(ParseError { line: Some(parser.line_number), error });
+                if parser.missing_header.is_some() {
+                    break;
+                }
+            }
+        }
+        let result = if parser.missing_header.is_some() && !errors.is_empty() {
+            Err(errors)
+        } else {
+            match parser.finish() {
+                Ok(playlist) if errors.is_empty() => Ok(playlist),
+                Ok(_) => Err(errors),
+                Err(error) => {
+                    errors.push(ParseError { line: None, error });
+                    Err(errors)
+                }
+            }
+        };
+        #[cfg(feature = "tracing")]
+        if let Err(errors) = &result {
+            for error in errors {
+                tracing::warn!(line = error.line, "Media playlist failed to parse: {:#}", error.error);
+            }
+        }
+        result
+    }
+
+    /// Like [`parse_with_options`][Self::parse_with_options], but errors come with the line they
+    /// were found on, if any.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(bytes = file.len())))]
+    pub(crate) fn parse_with_line(
+        file: &str,
+        options: &ParseOptions,
+    ) -> Result<Self, (Option<usize>, anyhow::Error)> {
+        let mut parser = Parser::new(options);
+        parser.set_input_len(file.len());
+        let result = match file.lines().try_for_each(|line| parser.line(line)) {
+            Ok(()) => parser.finish().map_err(|error| (None, error)),
+            Err(error) => Err((Some(parser.line_number), error)),
+        };
+        #[cfg(feature = "tracing")]
+        if let Err((line, error)) = &result {
+            tracing::warn!(line, "Media playlist failed to parse: {:#}", error);
+        }
+        result
+    }
+
+    /// Parses a [`MediaPlaylist`] from the reader line by line as data arrives, so errors are
+    /// reported before the whole body has been received.
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub async fn parse_async(reader: impl tokio::io::AsyncBufRead + Unpin) -> Result<Self> {
+        use tokio::io::AsyncBufReadExt;
+
+        let mut parser = Parser::new(&ParseOptions::default());
+        let mut lines = reader.lines();
+        let result = loop {
+            match lines.next_line().await {
+                Ok(Some(line)) => {
+                    if let Err(error) = parser.line(&line) {
+                        break Err(error);
+                    }
+                }
+                Ok(None) => break parser.finish(),
+                Err(error) => break Err(error.into()),
+            }
+        };
+        #[cfg(feature = "tracing")]
+        if let Err(error) = &result {
+            tracing::warn!("Media playlist failed to parse: {:#}", error);
+        }
+        result
+    }
+
+    /// Whether the playlist has an ENDLIST tag, i.e. no more segments will be added.
+    pub fn ended(&self) -> bool {
+        self.ended
+    }
+
+    /// Media segments in playback order.
+    pub fn segments(&self) -> &[MediaSegment] {
+        &self.segments
+    }
+
+    /// Duration that no media segment can exceed.
+    pub fn target_duration(&self) -> Duration {
+        self.target_duration
+    }
+
+    /// Sequence number of the first segment, `0` if there was no media sequence tag.
+    pub fn media_sequence(&self) -> u64 {
+        self.media_sequence
+    }
+
+    /// Discontinuity sequence number of the first segment, `0` if there was no discontinuity
+    /// sequence tag. Each segment with a discontinuity starts the next number.
+    pub fn discontinuity_sequence(&self) -> u64 {
+        self.discontinuity_sequence
+    }
+
+    /// Value of the EXT-X-ALLOW-CACHE tag, if present. The tag was removed in protocol version 7,
+    /// so newer clients ignore it.
+    pub fn allow_cache(&self) -> Option<bool> {
+        self.allow_cache
+    }
+
+    /// Value of the EXT-X-PLAYLIST-TYPE tag, if present.
+    pub fn playlist_type(&self) -> Option<PlaylistType> {
+        self.playlist_type
+    }
+
+    /// Compatibility version of the playlist, `0` if there was no version tag.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Number of segments replaced by an EXT-X-SKIP tag, `0` unless this is a delta update. The
+    /// first segment in [`segments`][Self::segments] has media sequence
+    /// `media_sequence + skipped_segments`.
+    pub fn skipped_segments(&self) -> u64 {
+        self.skipped_segments
+    }
+
+    /// Whether the playlist has an EXT-X-I-FRAMES-ONLY tag, i.e. each segment is a single
+    /// I-frame and its duration lasts until the next I-frame.
+    pub fn i_frames_only(&self) -> bool {
+        self.i_frames_only
+    }
+
+    /// PART-TARGET of the EXT-X-PART-INF tag, if the playlist has one.
+    pub fn part_target(&self) -> Option<Duration> {
+        self.part_target.map(|x| x.as_duration())
+    }
+
+    /// Parts of the segment which is still being produced, i.e. listed after the last complete
+    /// segment. Empty unless this is a low-latency playlist.
+    pub fn trailing_parts(&self) -> &[PartialSegment] {
+        &self.trailing_parts
+    }
+
+    /// Resources the server is about to publish, which low-latency clients request ahead of
+    /// time, see [`FetchScheduler`][crate::FetchScheduler].
+    pub fn preload_hints(&self) -> &[PreloadHint] {
+        &self.preload_hints
+    }
+
+    /// Date ranges whose tags follow the last segment.
+    pub fn trailing_date_ranges(&self) -> &[DateRange] {
+        &self.trailing_date_ranges
+    }
+
+    /// Every date range in the playlist, in the order of their tags.
+    pub fn date_ranges(&self) -> impl Iterator<Item = &DateRange> + '_ {
+        self.segments.iter().flat_map(|x| &x.date_ranges).chain(&self.trailing_date_ranges)
+    }
+
+    /// The last segment and part of other renditions, as of when this playlist was produced.
+    pub fn rendition_reports(&self) -> &[RenditionReport] {
+        &self.rendition_reports
+    }
+
+    /// Data [`ParseOptions::tag_handlers`] stored about the playlist.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    pub(crate) fn exact_part_target(&self) -> Option<SegmentDuration> {
+        self.part_target
+    }
+
+    pub(crate) fn set_skipped_segments(&mut self, skipped_segments: u64) {
+        self.skipped_segments = skipped_segments;
+    }
+
+    /// Forgets the original lines, for changes too large to merge into them.
+    pub(crate) fn discard_source(&mut self) {
+        self.source = Source::default();
+    }
+
+    /// Mutable access to the segments, for editing the playlist. The target duration isn't
+    /// raised for segments added this way, unlike with [`push_segment`][Self::push_segment], so
+    /// check the result with [`enforce_target_duration`][Self::enforce_target_duration].
+    pub fn segments_mut(&mut self) -> &mut Vec<MediaSegment> {
+        &mut self.segments
+    }
+
+    /// Appends a segment, raising the target duration if the segment would exceed it.
+    pub fn push_segment(&mut self, segment: MediaSegment) {
+        self.insert_segment(self.segments.len(), segment);
+    }
+
+    /// Inserts a segment at `index`, raising the target duration if the segment would exceed it.
+    /// Panics if `index` is greater than the number of segments.
+    pub fn insert_segment(&mut self, index: usize, segment: MediaSegment) {
+        self.target_duration = self.target_duration.max(segment.required_target_duration());
+        self.segments.insert(index, segment);
+    }
+
+    /// Smallest target duration all segments fit in, per
+    /// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.3.1>: the longest EXTINF
+    /// duration rounded to the nearest second.
+    pub fn required_target_duration(&self) -> Duration {
+        self.segments.iter().map(MediaSegment::required_target_duration).max().unwrap_or_default()
+    }
+
+    /// Lowest compatibility version the playlist's tags and attributes are allowed in, per
+    /// <https://datatracker.ietf.org/doc/html/rfc8216#section-7>.
+    pub fn required_version(&self) -> u64 {
+        let segments = &self.segments;
+        let mut version = 1;
+        if segments.iter().any(|x| x.keys.iter().any(|key| key.iv().is_some())) {
+            version = 2;
+        }
+        if segments.iter().any(|x| x.duration.as_duration().subsec_nanos() != 0) {
+            version = 3;
+        }
+        if self.i_frames_only || segments.iter().any(|x| x.byte_range.is_some()) {
+            version = 4;
+        }
+        if segments.iter().any(|x| x.map.is_some()) {
+            version = version.max(if self.i_frames_only { 5 } else { 6 });
+        }
+        if self.skipped_segments > 0 {
+            version = 9;
+        }
+        version
+    }
+
+    /// Checks that every segment fits in the target duration, returning an error naming each one
+    /// that doesn't. Raise the target duration to
+    /// [`required_target_duration`][Self::required_target_duration] to fix it.
+    pub fn enforce_target_duration(&self) -> Result<()> {
+        let violations: Vec<String> = self.target_duration_violations().collect();
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow::Error::msg(violations.join("; ")))
+        }
+    }
+
+    /// RFC8216 4.3.3.1, EXTINF durations rounded to the nearest integer must not exceed the
+    /// target duration.
+    fn target_duration_violations(&self) -> impl Iterator<Item = String> + '_ {
+        self.segments.iter().enumerate().filter(|(_, x)| x.exceeds_target_duration(self.target_duration)).map(
+            |(index, segment)| {
+                format!(
+                    "Segment {} ({}) duration {}s exceeds target duration {}s",
+                    index + 1, segment.url, segment.duration.as_secs_f64(), self.target_duration.as_secs_f64()
+                )
+            },
+        )
+    }
+
+    pub fn set_ended(&mut self, ended: bool) {
+        self.ended = ended;
+    }
+
+    /// Sets the target duration, which is always a whole number of seconds.
+    pub fn set_target_duration(&mut self, seconds: u64) {
+        self.target_duration = Duration::from_secs(seconds);
+    }
+
+    pub fn set_media_sequence(&mut self, media_sequence: u64) {
+        self.media_sequence = media_sequence;
+    }
+
+    pub fn set_discontinuity_sequence(&mut self, discontinuity_sequence: u64) {
+        self.discontinuity_sequence = discontinuity_sequence;
+    }
+
+    pub fn set_allow_cache(&mut self, allow_cache: Option<bool>) {
+        self.allow_cache = allow_cache;
+    }
+
+    pub fn set_playlist_type(&mut self, playlist_type: Option<PlaylistType>) {
+        self.playlist_type = playlist_type;
+    }
+
+    pub fn set_i_frames_only(&mut self, i_frames_only: bool) {
+        self.i_frames_only = i_frames_only;
+    }
+
+    pub fn set_part_target(&mut self, part_target: Option<SegmentDuration>) {
+        self.part_target = part_target;
+    }
+
+    pub fn set_trailing_parts(&mut self, trailing_parts: Vec<PartialSegment>) {
+        self.trailing_parts = trailing_parts;
+    }
+
+    pub fn set_trailing_date_ranges(&mut self, trailing_date_ranges: Vec<DateRange>) {
+        self.trailing_date_ranges = trailing_date_ranges;
+    }
+
+    pub fn set_preload_hints(&mut self, preload_hints: Vec<PreloadHint>) {
+        self.preload_hints = preload_hints;
+    }
+
+    pub fn set_rendition_reports(&mut self, rendition_reports: Vec<RenditionReport>) {
+        self.rendition_reports = rendition_reports;
+    }
+
+    /// Sets the compatibility version, `0` to leave out the version tag.
+    pub fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
+
+    pub(crate) fn source(&self) -> Option<&Source> {
+        Some(&self.source).filter(|x| !x.is_empty())
+    }
+
+    pub(crate) fn source_mut(&mut self) -> &mut Source {
+        &mut self.source
+    }
+
+    /// Checks the parsed playlist for problems which don't prevent parsing, such as segments
+    /// exceeding the target duration or tags requiring a newer version. Fixes made to the input by
+    /// [`ParseOptions::lenient`] come first.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = self.parse_notes.0.clone();
+        let version = self.version.max(1);
+
+        diagnostics.extend(self.target_duration_violations().map(|x| Diagnostic::error(None, x)));
+
+        //the same bytes listed twice is almost always a packager bug. Ranges whose offset can't
+        //be resolved aren't comparable, so they are skipped
+        let mut seen: HashMap<(&str, Option<ByteRange>), usize> = HashMap::new();
+        for (index, (segment, byte_range)) in self.segments.iter().zip(self.resolved_byte_ranges()).enumerate() {
+            if byte_range.is_some_and(|x| x.offset.is_none()) {
+                continue;
+            }
+            if let Some(first) = seen.insert((segment.url.as_str(), byte_range), index) {
+                diagnostics.push(Diagnostic::warning(None, format!(
+                    "Segment {} ({}) duplicates segment {}",
+                    index + 1, segment.url, first + 1
+                )));
+            }
+        }
+
+        //RFC8216 4.3.2.7, date ranges are placed on the timeline by the program date times
+        if self.date_ranges().next().is_some() && self.segments.iter().all(|x| x.program_date_time.is_none()) {
+            diagnostics.push(Diagnostic::error(None, "EXT-X-DATERANGE requires an EXT-X-PROGRAM-DATE-TIME tag"));
+        }
+
+        //RFC8216 7, protocol version compatibility
+        if self.allow_cache.is_some() {
+            let message = "EXT-X-ALLOW-CACHE is deprecated and was removed in protocol version 7";
+            diagnostics.push(if version >= 7 {
+                Diagnostic::error(None, message)
+            } else {
+                Diagnostic::warning(None, message)
+            });
+        }
+        if version < 3 && self.segments.iter().any(|x| x.duration.as_duration().subsec_nanos() != 0) {
+            diagnostics.push(Diagnostic::error(None, format!(
+                "Floating-point EXTINF durations require version 3, playlist is version {}",
+                version
+            )));
+        }
+        if version < 4 && self.segments.iter().any(|x| x.byte_range.is_some()) {
+            diagnostics.push(Diagnostic::error(None, format!(
+                "EXT-X-BYTERANGE requires version 4, playlist is version {}",
+                version
+            )));
+        }
+        if version < 4 && self.i_frames_only {
+            diagnostics.push(Diagnostic::error(None, format!(
+                "EXT-X-I-FRAMES-ONLY requires version 4, playlist is version {}",
+                version
+            )));
+        }
+        //RFC8216 4.3.2.5, only I-frame playlists could use EXT-X-MAP before version 6
+        let map_version = if self.i_frames_only { 5 } else { 6 };
+        if version < map_version && self.segments.iter().any(|x| x.map.is_some()) {
+            diagnostics.push(Diagnostic::error(None, format!(
+                "EXT-X-MAP requires version {}, playlist is version {}",
+                map_version, version
+            )));
+        }
+        if version < 2 && self.segments.iter().any(|x| x.keys.iter().any(|key| key.iv().is_some())) {
+            diagnostics.push(Diagnostic::error(None, format!(
+                "EXT-X-KEY IV attribute requires version 2, playlist is version {}",
+                version
+            )));
+        }
+        //rfc8216bis 4.4.3.7, parts need a target and must fit in it
+        let mut parts = self.segments.iter().flat_map(|x| &x.parts).chain(&self.trailing_parts).peekable();
+        match self.part_target {
+            None if parts.peek().is_some() => {
+                diagnostics.push(Diagnostic::error(None, "EXT-X-PART requires an EXT-X-PART-INF tag"));
+            }
+            Some(part_target) => {
+                for part in parts.filter(|x| x.duration() > part_target.as_duration()) {
+                    diagnostics.push(Diagnostic::error(None, format!(
+                        "Part {} duration {}s exceeds part target {}s",
+                        part.uri(), part.duration().as_secs_f64(), part_target.as_secs_f64()
+                    )));
+                }
+            }
+            None => {}
+        }
+        if version < 9 && self.skipped_segments > 0 {
+            diagnostics.push(Diagnostic::error(None, format!(
+                "EXT-X-SKIP requires version 9, playlist is version {}",
+                version
+            )));
+        }
+        #[cfg(feature = "tracing")]
+        crate::diagnostics::trace(&diagnostics);
+        diagnostics
+    }
+}
+
+impl MediaSegment {
+    /// Creates an unencrypted segment covering the whole resource at `url`.
+    pub fn new(duration: impl Into<SegmentDuration>, url: impl Into<SegmentUri>) -> Self {
+        Self {
+            duration: duration.into(),
+            duration_estimated: false,
+            url: url.into(),
+            title: None,
+            keys: Arc::default(),
+            byte_range: None,
+            discontinuity: false,
+            map: None,
+            program_date_time: None,
+            gap: false,
+            parts: Vec::new(),
+            date_ranges: Vec::new(),
+            cues: Vec::new(),
+            tag_order: TagOrder::default(),
+            extensions: Extensions::default(),
+        }
+    }
+
+    /// Duration of the segment from its EXTINF tag.
+    pub fn duration(&self) -> Duration {
+        self.duration.as_duration()
+    }
+
+    /// Duration of the segment exactly as written in its EXTINF tag.
+    pub fn exact_duration(&self) -> SegmentDuration {
+        self.duration
+    }
+
+    /// Whether the segment had no EXTINF tag and [`ParseOptions::lenient`] estimated its
+    /// duration, from the program date times around it or else the target duration.
+    pub fn duration_estimated(&self) -> bool {
+        self.duration_estimated
+    }
+
+    /// URL of the segment, relative to the playlist unless absolute.
+    pub fn url(&self) -> &SegmentUri {
+        &self.url
+    }
+
+    /// Title from the EXTINF tag, if it has one.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Keys the segment is encrypted with, one for each KEYFORMAT a client may support, e.g.
+    /// FairPlay and Widevine. Empty if the segment isn't encrypted.
+    pub fn keys(&self) -> &[EncryptionKey] {
+        &self.keys
+    }
+
+    /// The key for the given KEYFORMAT, `identity` for keys fetched directly from their URI.
+    pub fn keys_for_format(&self, key_format: &str) -> Option<&EncryptionKey> {
+        self.keys.iter().find(|x| x.key_format() == key_format)
+    }
+
+    /// Sub-range of the resource to load, if not the whole resource.
+    pub fn byte_range(&self) -> Option<ByteRange> {
+        self.byte_range
+    }
+
+    /// Whether there is a discontinuity (e.g. in encoding or timestamps) between the previous
+    /// segment and this one.
+    pub fn discontinuity(&self) -> bool {
+        self.discontinuity
+    }
+
+    /// Media initialization section needed to parse the segment, if any.
+    pub fn map(&self) -> Option<&SegmentMap> {
+        self.map.as_deref()
+    }
+
+    /// Date and time of the segment's first sample, if a tag gives it explicitly. See
+    /// [`MediaPlaylist::iter_segments`] for times carried forward from earlier segments.
+    pub fn program_date_time(&self) -> Option<ProgramDateTime> {
+        self.program_date_time
+    }
+
+    /// Whether the segment is unavailable, so clients must not load it but still count its
+    /// duration.
+    pub fn gap(&self) -> bool {
+        self.gap
+    }
+
+    /// Parts of the segment which low-latency clients can load before it is complete.
+    pub fn parts(&self) -> &[PartialSegment] {
+        &self.parts
+    }
+
+    /// Date ranges whose tags precede the segment.
+    pub fn date_ranges(&self) -> &[DateRange] {
+        &self.date_ranges
+    }
+
+    /// SCTE-35 cues of the playlist's date ranges overlapping the segment, e.g. to tell whether
+    /// it falls inside an ad avail. Found when the playlist is parsed, so not updated when the
+    /// playlist is modified.
+    pub fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    /// Data [`ParseOptions::tag_handlers`] stored about the tags preceding the segment.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// Sets the duration, from either a [`Duration`] or an exact [`SegmentDuration`].
+    pub fn set_duration(&mut self, duration: impl Into<SegmentDuration>) {
+        self.duration = duration.into();
+    }
+
+    pub fn set_duration_estimated(&mut self, duration_estimated: bool) {
+        self.duration_estimated = duration_estimated;
+    }
+
+    pub fn set_url(&mut self, url: impl Into<SegmentUri>) {
+        self.url = url.into();
+    }
+
+    /// Sets the title, with an empty title treated as none.
+    pub fn set_title(&mut self, title: Option<String>) {
+        self.title = title.filter(|x| !x.is_empty());
+    }
+
+    /// Sets the keys, which must have different KEYFORMATs to be written out as they are.
+    pub fn set_keys(&mut self, keys: Vec<EncryptionKey>) {
+        self.keys = keys.into();
+    }
+
+    pub fn set_byte_range(&mut self, byte_range: Option<ByteRange>) {
+        self.byte_range = byte_range;
+    }
+
+    pub fn set_discontinuity(&mut self, discontinuity: bool) {
+        self.discontinuity = discontinuity;
+    }
+
+    pub fn set_map(&mut self, map: Option<SegmentMap>) {
+        self.map = map.map(Arc::new);
+    }
+
+    /// The keys as shared with the other segments they apply to.
+    pub(crate) fn shared_keys(&self) -> &Arc<[EncryptionKey]> {
+        &self.keys
+    }
+
+    pub(crate) fn set_shared_keys(&mut self, keys: Arc<[EncryptionKey]>) {
+        self.keys = keys;
+    }
+
+    /// The map as shared with the other segments it applies to.
+    pub(crate) fn shared_map(&self) -> Option<&Arc<SegmentMap>> {
+        self.map.as_ref()
+    }
+
+    pub(crate) fn set_shared_map(&mut self, map: Option<Arc<SegmentMap>>) {
+        self.map = map;
+    }
+
+    pub fn set_program_date_time(&mut self, program_date_time: Option<ProgramDateTime>) {
+        self.program_date_time = program_date_time;
+    }
+
+    pub fn set_gap(&mut self, gap: bool) {
+        self.gap = gap;
+    }
+
+    pub fn set_parts(&mut self, parts: Vec<PartialSegment>) {
+        self.parts = parts;
+    }
+
+    pub fn set_date_ranges(&mut self, date_ranges: Vec<DateRange>) {
+        self.date_ranges = date_ranges;
+    }
+
+    pub(crate) fn tag_order(&self) -> &[TagSlot] {
+        &self.tag_order.0
+    }
+
+    pub(crate) fn set_tag_order(&mut self, tag_order: Vec<TagSlot>) {
+        self.tag_order = TagOrder(tag_order);
+    }
+
+    /// Whether the duration, rounded to the nearest integer, is longer than the target.
+    pub(crate) fn exceeds_target_duration(&self, target_duration: Duration) -> bool {
+        self.required_target_duration() > target_duration
+    }
+
+    /// Target duration the segment fits in, its duration rounded to the nearest second.
+    pub(crate) fn required_target_duration(&self) -> Duration {
+        Duration::from_secs(self.duration.as_secs_f64().round() as u64)
+    }
+}
+
+/// Most segments reserved up front from the length of the second one, since a few short
+/// segments ahead of a long tail of something else would otherwise reserve far more than the
+/// input holds. A day of 6 second segments fits.
+const MAX_RESERVED_SEGMENTS: usize = 16_384;
+
+/// Incremental [`MediaPlaylist`] parser, fed one line at a time.
+#[derive(Debug, Default)]
+pub(crate) struct Parser {
+    /// Set with [`ParseOptions::preserve_source`].
+    source: Option<SourceRecorder>,
+
+    /// [`ParseOptions::lenient`], with the fixes made to the input so far.
+    lenient: bool,
+    fixes: Vec<Diagnostic>,
+
+    limits: ParseLimits,
+    spec: SpecVersion,
+    line_number: usize,
+
+    /// Set while the first line wasn't the header, along with the first segment tag seen since.
+    missing_header: Option<Option<(usize, &'static str)>>,
+
+    variables: Variables,
+    version: Option<u64>,
+    target_duration: Option<Duration>,
+    media_sequence: Option<u64>,
+    discontinuity_sequence: Option<u64>,
+    allow_cache: Option<bool>,
+    playlist_type: Option<PlaylistType>,
+    skipped_segments: Option<u64>,
+    i_frames_only: bool,
+    part_target: Option<SegmentDuration>,
+    ended: bool,
+
+    /// Number of segments before the EXT-X-ENDLIST tag, which no segment should follow.
+    segments_before_end: Option<usize>,
+    segments: Vec<MediaSegment>,
+
+    /// Bytes of the input, if known, and of the lines read so far, with where the first segment
+    /// ended, to size `segments` from the length of the second.
+    input_len: usize,
+    read_len: usize,
+    first_segment_end: usize,
+
+    keys: Arc<[EncryptionKey]>,
+    byte_range: Option<ByteRange>,
+    discontinuity: bool,
+    map: Option<Arc<SegmentMap>>,
+    program_date_time: Option<ProgramDateTime>,
+    gap: bool,
+    parts: Vec<PartialSegment>,
+    date_ranges: Vec<DateRange>,
+    tag_order: Vec<TagSlot>,
+    preload_hints: Vec<PreloadHint>,
+    rendition_reports: Vec<RenditionReport>,
+
+    /// Line number of the EXTINF (and any other segment tag) waiting for its URI, with the
+    /// duration and title.
+    pending_segment: Option<(usize, SegmentDuration, Option<String>)>,
+    pending_tag: Option<(usize, &'static str)>,
+
+    /// Line numbers of the URIs of segments without an EXTINF, accepted in lenient mode.
+    estimated: Vec<usize>,
+
+    /// [`ParseOptions::tag_handlers`], with what they stored for the playlist and the next segment.
+    tag_handlers: TagHandlers,
+    extensions: Extensions,
+    segment_extensions: Extensions,
+}
+
+impl Parser {
+    pub(crate) fn new(options: &ParseOptions) -> Self {
+        Self {
+            source: options.preserve_source.then(SourceRecorder::default),
+            lenient: options.lenient,
+            limits: options.limits,
+            spec: options.spec,
+            tag_handlers: options.tag_handlers.clone(),
+            ..Self::default()
         }
+    }
+
+    /// The length of the input, so the segment list of a large playlist can be sized up front
+    /// rather than moved each time it outgrows its allocation.
+    pub(crate) fn set_input_len(&mut self, len: usize) {
+        self.input_len = len;
+    }
 
-        //RFC8216 4.3.2 requirements
-        let mut file_segments: Vec<MediaSegment> = Vec::<MediaSegment>::new();        
-        let mut new_segment: bool = false;
-        let mut segment_duration = Duration::new(0,0);
-        for line in lines.iter() {
-            if line.starts_with(SEGMENT_TAG){
-                new_segment = true;
-                let info_string: Option<&str> = line.strip_prefix(SEGMENT_PRE);
-                if info_string != None {
-                    let info: Vec<&str> = info_string.unwrap().split(",").collect();
-                    let parsed_duration = info[0].parse::<f32>();
-                    match parsed_duration {
-                        Ok(..) => {
-                            segment_duration = Duration::from_secs_f32(parsed_duration.unwrap())
-                        },
-                        Err(..) => return Err(anyhow::Error::msg("Segment tag found, but could not parse duration"))
+    pub(crate) fn line(&mut self, raw: &str) -> Result<()> {
+        let (line, fixes) = self.normalize(raw);
+        self.tokenized_line(raw, &line, fixes, events::parse_line(&line))
+    }
+
+    /// The line as the tokenizer expects it, with any fixes made in lenient mode.
+    pub(crate) fn normalize<'a>(&self, raw: &'a str) -> (Cow<'a, str>, Vec<String>) {
+        if self.lenient {
+            events::normalize(raw)
+        } else {
+            (Cow::Borrowed(raw), Vec::new())
+        }
+    }
+
+    /// The rest of [`line`][Self::line]. Normalizing and tokenizing don't depend on earlier lines,
+    /// so they can be done up front, see [`MediaPlaylist::parse_parallel`].
+    pub(crate) fn tokenized_line(
+        &mut self,
+        raw: &str,
+        line: &str,
+        fixes: Vec<String>,
+        event: Option<Result<Event<'_>>>,
+    ) -> Result<()> {
+        self.line_number += 1;
+        self.read_len += raw.len() + 1;
+        let line_number = self.line_number;
+        self.limits.check(Limit::Lines, line_number)?;
+        self.limits.check(Limit::LineLength, raw.len())?;
+        //all of them are EXT-X- tags, which rules out the EXTINF and URI lines cheaply
+        let attribute_list = line.starts_with("#EXT-X-") && ATTRIBUTE_LIST_TAGS.contains(&events::tag_name(line));
+        if self.limits.max_attributes.is_some() && attribute_list {
+            let list = line.split_once(':').map(|(_, list)| list).unwrap_or_default();
+            self.limits.check(Limit::Attributes, attributes::count(list))?;
+        }
+        self.fixes.extend(fixes.into_iter().map(|x| Diagnostic::warning(Some(line_number), x)));
+
+        //RFC8216 4.3.1.1 requirement
+        if line_number == 1 && line != format!("#{HEADER_TAG}") {
+            self.missing_header = Some(None);
+        }
+        if let Some(first_segment_tag) = &mut self.missing_header {
+            //segment tags ahead of a late header are a more useful thing to report
+            match events::tag_name(line) {
+                HEADER_TAG if line_number > 1 => {
+                    if let Some((tag_line, tag)) = first_segment_tag {
+                        return Err(anyhow::anyhow!(
+                            "{} before #{} header at line {}",
+                            tag, HEADER_TAG, tag_line
+                        ));
                     }
+                    return Err(anyhow::Error::msg("Input doesn't start with EXTM3U tag"));
+                }
+                SEGMENT_TAG if first_segment_tag.is_none() => {
+                    *first_segment_tag = Some((line_number, SEGMENT_TAG));
+                }
+                BYTERANGE_TAG if first_segment_tag.is_none() => {
+                    *first_segment_tag = Some((line_number, BYTERANGE_TAG));
+                }
+                DISCONTINUITY_TAG if first_segment_tag.is_none() => {
+                    *first_segment_tag = Some((line_number, DISCONTINUITY_TAG));
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        //RFC8216bis 4.3, references are replaced before the line is parsed
+        let mut substituted = Cow::Borrowed(line);
+        let mut event = event;
+        if self.spec == SpecVersion::Rfc8216bis && line.contains("{$") {
+            substituted = self.variables.substitute(line)?;
+            if substituted != line {
+                event = events::parse_line(&substituted);
+            }
+        }
+        //unwrapped in one step, since events are large and each move copies them
+        let event = match event {
+            Some(Ok(event)) => event,
+            Some(Err(error)) => return Err(error),
+            None => {
+                if let Some(source) = &mut self.source {
+                    source.verbatim(raw);
+                }
+                return Ok(());
+            }
+        };
+        if self.spec == SpecVersion::Rfc8216 {
+            if let Some(tag) = event.rfc8216bis_tag() {
+                self.violation(format!("{} tag is not part of RFC 8216", tag))?;
+            }
+            if let Event::Version(version @ 8..) = event {
+                self.violation(format!("EXT-X-VERSION {} is not part of RFC 8216", version))?;
+            }
+            if matches!(&event, Event::DateRange(x) if x.cue().is_some()) {
+                self.violation(format!("CUE attribute of {} is not part of RFC 8216", DATERANGE_TAG))?;
+            }
+        }
+        //tags the model doesn't cover belong to the next segment once the first one has started
+        let slot = TagSlot::from_event(&event, &substituted).filter(|x| {
+            let started = !self.segments.is_empty() || !self.tag_order.is_empty() || self.pending_segment.is_some();
+            !matches!(x, TagSlot::Custom(_)) || (started && !self.ended)
+        });
+        let segment_custom = matches!(slot, Some(TagSlot::Custom(_)));
+        if let Some(slot) = slot {
+            self.tag_order.push(slot);
+        }
+        if let Some(source) = &mut self.source {
+            match &event {
+                Event::Version(version) => source.tag(PlaylistTag::Version(*version), raw),
+                Event::TargetDuration(duration) => {
+                    source.tag(PlaylistTag::TargetDuration(Duration::from_secs(*duration)), raw)
+                }
+                Event::MediaSequence(sequence) => source.tag(PlaylistTag::MediaSequence(*sequence), raw),
+                Event::DiscontinuitySequence(sequence) => {
+                    source.tag(PlaylistTag::DiscontinuitySequence(*sequence), raw)
+                }
+                Event::AllowCache(allow_cache) => source.tag(PlaylistTag::AllowCache(*allow_cache), raw),
+                Event::PlaylistType(playlist_type) => source.tag(PlaylistTag::PlaylistType(*playlist_type), raw),
+                Event::Skip(skipped_segments) => source.tag(PlaylistTag::Skip(*skipped_segments), raw),
+                Event::IFramesOnly => source.tag(PlaylistTag::IFramesOnly, raw),
+                Event::EndList => source.tag(PlaylistTag::EndList, raw),
+                Event::PartInf(part_target) => source.tag(PlaylistTag::PartInf(*part_target), raw),
+                Event::ExtInf { .. }
+                | Event::ByteRange(_)
+                | Event::Discontinuity
+                | Event::Key(_)
+                | Event::Map(_)
+                | Event::ProgramDateTime(_)
+                | Event::DateRange(_)
+                | Event::Gap
+                | Event::Part(_)
+                | Event::PreloadHint(_)
+                | Event::RenditionReport(_) => source.segment_tag(raw),
+                Event::Unknown { .. } if segment_custom => source.segment_tag(raw),
+                //recorded once the segment is complete
+                Event::Uri(_) => {}
+                Event::Header
+                | Event::Media(_)
+                | Event::StreamInf(_)
+                | Event::IFrameStreamInf(_)
+                | Event::SessionData(_)
+                | Event::SessionKey(_)
+                | Event::Define { .. }
+                | Event::Unknown { .. }
+                | Event::Comment(_) => source.verbatim(raw),
+            }
+        }
+        match event {
+            //RFC8216 4.3.1.2 requirements
+            Event::Version(version) => {
+                if self.version.is_some() {
+                    return self.violation("Playlist contains more than 1 version tag");
+                }
+                self.version = Some(version);
+            }
+            //RFC8216 4.3.3.1 requirements
+            Event::TargetDuration(duration) => {
+                if self.target_duration.is_some() {
+                    return self.violation("Playlist contains more than 1 duration tag");
+                }
+                self.target_duration = Some(Duration::new(duration, 0));
+            }
+            //RFC8216 4.3.3.2 requirements
+            Event::MediaSequence(sequence) => {
+                if self.media_sequence.is_some() {
+                    return self.violation("Playlist contains more than 1 media sequence tag");
+                }
+                if self.started() {
+                    self.violation("Media sequence tag must appear before the first segment")?;
+                }
+                self.media_sequence = Some(sequence);
+            }
+            //RFC8216 4.3.3.3 requirements
+            Event::DiscontinuitySequence(sequence) => {
+                if self.discontinuity_sequence.is_some() {
+                    return self.violation("Playlist contains more than 1 discontinuity sequence tag");
+                }
+                if self.started() {
+                    self.violation("Discontinuity sequence tag must appear before the first segment")?;
+                }
+                self.discontinuity_sequence = Some(sequence);
+            }
+            Event::AllowCache(allow_cache) => {
+                if self.allow_cache.is_some() {
+                    return self.violation("Playlist contains more than 1 allow cache tag");
+                }
+                self.allow_cache = Some(allow_cache);
+            }
+            //RFC8216 4.3.3.5 requirements
+            Event::PlaylistType(playlist_type) => {
+                if self.playlist_type.is_some() {
+                    return self.violation("Playlist contains more than 1 playlist type tag");
+                }
+                self.playlist_type = Some(playlist_type);
+            }
+            Event::Skip(skipped_segments) => {
+                if self.skipped_segments.is_some() {
+                    return self.violation("Playlist contains more than 1 skip tag");
+                }
+                if self.started() {
+                    self.violation("Skip tag must appear before the first segment")?;
+                }
+                self.skipped_segments = Some(skipped_segments);
+            }
+            //RFC8216 4.3.2 requirements
+            Event::ExtInf { duration, title } => {
+                if let Some((segment_line, ..)) = self.pending_segment {
+                    return Err(anyhow::anyhow!("EXTINF without URI at line {}", segment_line));
                 }
+                let title = Some(title).filter(|x| !x.is_empty()).map(str::to_string);
+                self.pending_segment = Some((line_number, duration, title));
             }
-            else if line.starts_with(BYTERANGE_TAG)
-            {
-                //ignore for now
+            Event::ByteRange(byte_range) => {
+                self.byte_range = Some(byte_range);
+                self.pending_tag = Some((line_number, BYTERANGE_TAG));
             }
-            else if line.starts_with(ENDLIST_TAG)
-            {
-                //ignore
+            Event::Discontinuity => {
+                self.discontinuity = true;
+                self.pending_tag = Some((line_number, DISCONTINUITY_TAG));
             }
-            else if new_segment {
+            Event::Key(key) => {
+                let mut keys = self.keys.to_vec();
+                key.apply_to(&mut keys);
+                self.keys = keys.into();
+            }
+            Event::Map(map) => self.map = Some(Arc::new(map)),
+            Event::ProgramDateTime(date_time) => {
+                self.program_date_time = Some(date_time);
+                self.pending_tag = Some((line_number, PROGRAM_DATE_TIME_TAG));
+            }
+            Event::DateRange(date_range) => self.date_ranges.push(date_range),
+            Event::Gap => {
+                self.gap = true;
+                self.pending_tag = Some((line_number, GAP_TAG));
+            }
+            //parts may come after the last segment, so they don't need a URI
+            Event::Part(part) => self.parts.push(part),
+            Event::PreloadHint(hint) => {
+                if self.preload_hints.iter().any(|x| x.hint_type() == hint.hint_type()) {
+                    return self.violation(format!(
+                        "Playlist contains more than 1 preload hint of type {}",
+                        hint.hint_type().as_str()
+                    ));
+                }
+                self.preload_hints.push(hint);
+            }
+            Event::RenditionReport(report) => self.rendition_reports.push(report),
+            Event::PartInf(part_target) => {
+                if self.part_target.is_some() {
+                    return self.violation("Playlist contains more than 1 part information tag");
+                }
+                self.part_target = Some(part_target);
+            }
+            Event::IFramesOnly => {
+                if self.i_frames_only {
+                    return self.violation("Playlist contains more than 1 I-frames only tag");
+                }
+                self.i_frames_only = true;
+            }
+            //RFC8216 4.3.3.4, the playlist ends here
+            Event::EndList => {
+                if self.ended {
+                    return self.violation("Playlist contains more than 1 end list tag");
+                }
+                self.ended = true;
+                self.segments_before_end = Some(self.segments.len() + usize::from(self.pending_segment.is_some()));
+            }
+            Event::Uri(url) => {
                 //we have a url!
-                file_segments.push(MediaSegment { duration: segment_duration, url: line.to_string() });
-                new_segment = false;
+                let (duration, title, duration_estimated) = match self.pending_segment.take() {
+                    Some((_, duration, title)) => (duration, title, false),
+                    //the duration is estimated once the segments around it are known
+                    None if self.lenient => {
+                        self.estimated.push(line_number);
+                        (SegmentDuration::ZERO, None, true)
+                    }
+                    None => return Err(anyhow::anyhow!("URI without EXTINF at line {}", line_number)),
+                };
+                self.limits.check(Limit::Segments, self.segments.len() + 1)?;
+                let segment = MediaSegment {
+                    duration,
+                    duration_estimated,
+                    url: SegmentUri::new(url),
+                    title,
+                    keys: self.keys.clone(),
+                    byte_range: self.byte_range.take(),
+                    discontinuity: core::mem::take(&mut self.discontinuity),
+                    map: self.map.clone(),
+                    program_date_time: self.program_date_time.take(),
+                    gap: core::mem::take(&mut self.gap),
+                    parts: core::mem::take(&mut self.parts),
+                    date_ranges: core::mem::take(&mut self.date_ranges),
+                    cues: Vec::new(),
+                    tag_order: TagOrder(core::mem::take(&mut self.tag_order)),
+                    extensions: core::mem::take(&mut self.segment_extensions),
+                };
+                if let Some(source) = &mut self.source {
+                    source.segment(self.segments.len(), &segment, raw);
+                }
+                //RFC8216 4.3.2.2, an implicit offset continues the previous segment's sub-range. After
+                //a delta update's skipped segments there is nothing to check it against
+                let continues_previous = || match self.segments.last() {
+                    Some(previous) => previous.url == segment.url && previous.byte_range.is_some(),
+                    None => self.skipped_segments.is_some(),
+                };
+                if segment.byte_range.is_some_and(|x| x.offset.is_none()) && !continues_previous() {
+                    self.violation(format!(
+                        "Byte range of segment at line {} has no offset, but the previous segment isn't a sub-range of {}",
+                        line_number, segment.url
+                    ))?;
+                }
+                if self.segments_before_end == Some(self.segments.len()) {
+                    self.violation(format!("Segment at line {} follows the {} tag", line_number, ENDLIST_TAG))?;
+                }
+                //the header comes before the first segment, so the second is the one to go by
+                match self.segments.len() {
+                    0 => self.first_segment_end = self.read_len,
+                    1 => {
+                        let segment_len = (self.read_len - self.first_segment_end).max(1);
+                        let estimate = 1 + self.input_len.saturating_sub(self.read_len) / segment_len;
+                        let room = self.limits.max_segments.unwrap_or(usize::MAX).min(MAX_RESERVED_SEGMENTS);
+                        self.segments.reserve(estimate.min(room.saturating_sub(1)));
+                    }
+                    _ => {}
+                }
+                self.segments.push(segment);
+                self.pending_tag = None;
+            }
+            Event::Header => {
+                if line_number > 1 {
+                    return Err(anyhow::anyhow!("Unexpected {} tag at line {}", HEADER_TAG, line_number));
+                }
+            }
+            Event::Define { name, value } => self.variables.define(name, value)?,
+            //RFC8216 4.1, a playlist is either a media or a master playlist
+            Event::Media(_) => {
+                return Err(anyhow::anyhow!("Master playlist tag {} in media playlist", MEDIA_TAG));
+            }
+            Event::StreamInf(_) => {
+                return Err(anyhow::anyhow!("Master playlist tag {} in media playlist", STREAM_INF_TAG));
+            }
+            Event::IFrameStreamInf(_) => {
+                return Err(anyhow::anyhow!("Master playlist tag {} in media playlist", I_FRAME_STREAM_INF_TAG));
+            }
+            Event::SessionData(_) => {
+                return Err(anyhow::anyhow!("Master playlist tag {} in media playlist", SESSION_DATA_TAG));
+            }
+            Event::SessionKey(_) => {
+                return Err(anyhow::anyhow!("Master playlist tag {} in media playlist", SESSION_KEY_TAG));
+            }
+            Event::Unknown { name, value } if !self.tag_handlers.is_empty() => {
+                let tag = CustomTag { name, value, line: line_number, next_segment: self.segments.len() };
+                let mut extensions =
+                    TagExtensions { playlist: &mut self.extensions, segment: &mut self.segment_extensions };
+                self.tag_handlers
+                    .handle(&tag, &mut extensions)
+                    .map_err(|error| error.context(format!("{} tag at line {} not handled", name, line_number)))?;
+            }
+            Event::Unknown { .. } | Event::Comment(_) => {
+                //unsupported tags and comments are ignored
+                #[cfg(feature = "tracing")]
+                if line.starts_with("#EXT") {
+                    tracing::trace!(line = line_number, "Ignoring unsupported tag {}", events::tag_name(line));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Fills in the durations of segments without an EXTINF: the time until the next segment's
+    /// EXT-X-PROGRAM-DATE-TIME if both dates are known, otherwise the target duration.
+    fn estimate_durations(&mut self, target_duration: Duration) {
+        let mut lines = self.estimated.iter();
+        let mut next_date_time: Option<ProgramDateTime> = None;
+        for index in 0..self.segments.len() {
+            let segment = &self.segments[index];
+            let date_time = match segment.program_date_time {
+                Some(date_time) => Some(date_time),
+                None if segment.discontinuity => None,
+                None => next_date_time,
+            };
+            if segment.duration_estimated {
+                let next = self.segments.get(index + 1).filter(|x| !x.discontinuity).and_then(|x| x.program_date_time);
+                let duration = date_time
+                    .zip(next)
+                    .and_then(|(start, next)| next.duration_since(&start))
+                    .filter(|x| !x.is_zero())
+                    .unwrap_or(target_duration);
+                let segment = &mut self.segments[index];
+                segment.duration = duration.into();
+                self.fixes.push(Diagnostic::warning(lines.next().copied(), format!(
+                    "Segment {} has no EXTINF, duration estimated as {}s",
+                    segment.url, duration.as_secs_f64()
+                )));
             }
+            next_date_time = date_time.and_then(|x| x.checked_add(self.segments[index].duration()));
         }
+        self.fixes.sort_by_key(|x| x.line);
+    }
+
+    /// Whether a segment has started, after which tags describing the first one are too late.
+    fn started(&self) -> bool {
+        !self.segments.is_empty() || self.pending_segment.is_some()
+    }
+
+    /// Fails with `message`, or in lenient mode reports it as a warning about the current line
+    /// and carries on.
+    fn violation(&mut self, message: impl Into<String>) -> Result<()> {
+        if !self.lenient {
+            return Err(anyhow::Error::msg(message.into()));
+        }
+        self.fixes.push(Diagnostic::warning(Some(self.line_number), message));
+        Ok(())
+    }
+
+    pub(crate) fn limits(&self) -> &ParseLimits {
+        &self.limits
+    }
 
-        Ok(Self {
-            ended: lines.iter().any(|x| x == &ENDLIST_TAG),
-            segments: file_segments,
-            target_duration: file_duration,
-            version: file_version
-        })
+    /// Segments completed so far, i.e. whose URI line has been seen.
+    pub(crate) fn segments(&self) -> &[MediaSegment] {
+        &self.segments
+    }
+
+    pub(crate) fn finish(mut self) -> Result<MediaPlaylist> {
+        //return error if our input contains no data
+        if self.line_number == 0 {
+            return Err(anyhow::Error::msg("Input contains no data"));
+        }
+        if self.missing_header.is_some() {
+            return Err(anyhow::Error::msg("Input doesn't start with EXTM3U tag"));
+        }
+        if let Some((segment_line, ..)) = self.pending_segment {
+            return Err(anyhow::anyhow!("EXTINF without URI at line {}", segment_line));
+        }
+        if let Some((tag_line, tag)) = self.pending_tag {
+            return Err(anyhow::anyhow!("{} without URI at line {}", tag, tag_line));
+        }
+        let Some(target_duration) = self.target_duration else {
+            return Err(anyhow::Error::msg("Duration tag not found"));
+        };
+        if !self.estimated.is_empty() {
+            self.estimate_durations(target_duration);
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            lines = self.line_number,
+            segments = self.segments.len(),
+            discontinuities = self.segments.iter().filter(|x| x.discontinuity).count(),
+            encrypted = self.segments.iter().filter(|x| !x.keys.is_empty()).count(),
+            byte_ranges = self.segments.iter().filter(|x| x.byte_range.is_some()).count(),
+            program_date_times = self.segments.iter().filter(|x| x.program_date_time.is_some()).count(),
+            fixes = self.fixes.len(),
+            "Parsed media playlist"
+        );
+        let mut playlist = MediaPlaylist {
+            ended: self.ended,
+            segments: self.segments,
+            target_duration,
+            media_sequence: self.media_sequence.unwrap_or(0),
+            discontinuity_sequence: self.discontinuity_sequence.unwrap_or(0),
+            allow_cache: self.allow_cache,
+            playlist_type: self.playlist_type,
+            //no version tag means version 1, a rigorous check makes sure we only have V1 tags
+            version: self.version.unwrap_or(0),
+            skipped_segments: self.skipped_segments.unwrap_or(0),
+            i_frames_only: self.i_frames_only,
+            part_target: self.part_target,
+            source: Source::default(),
+            trailing_parts: self.parts,
+            trailing_date_ranges: self.date_ranges,
+            preload_hints: self.preload_hints,
+            rendition_reports: self.rendition_reports,
+            parse_notes: ParseNotes(self.fixes),
+            extensions: self.extensions,
+        };
+        let cues = playlist.segment_cues();
+        for (segment, cues) in playlist.segments.iter_mut().zip(cues) {
+            segment.cues = cues;
+        }
+        if let Some(source) = self.source {
+            playlist.source = source.finish(&playlist);
+        }
+        Ok(playlist)
     }
 }
 
@@ -151,45 +1378,45 @@ impl MediaPlaylist {
 mod tests {
     use super::*;
 
+    const BIG_BUCK_BUNNY: &str = indoc::indoc! {"
+        #EXTM3U
+        #EXT-X-VERSION:4
+        #EXT-X-ALLOW-CACHE:NO
+        #EXT-X-TARGETDURATION:20
+        #EXT-X-MEDIA-SEQUENCE:1
+        #EXT-X-PROGRAM-DATE-TIME:2015-08-25T01:59:23.708+00:00
+        #EXTINF:12.166,
+        #EXT-X-BYTERANGE:1430680@4048392
+        segment_1440468394459_1440468394459_1.ts
+        #EXTINF:13.292,
+        #EXT-X-BYTERANGE:840360@5479072
+        segment_1440468394459_1440468394459_1.ts
+        #EXTINF:10.500,
+        #EXT-X-BYTERANGE:1009184@6319432
+        segment_1440468394459_1440468394459_1.ts
+        #EXTINF:11.417,
+        #EXT-X-BYTERANGE:806332@0
+        segment_1440468394459_1440468394459_2.ts
+        #EXTINF:12.459,
+        #EXT-X-BYTERANGE:701616@806332
+        segment_1440468394459_1440468394459_2.ts
+        #EXTINF:14.000,
+        #EXT-X-BYTERANGE:931352@1507948
+        segment_1440468394459_1440468394459_2.ts
+        #EXTINF:19.292,
+        #EXT-X-BYTERANGE:1593676@2439300
+        segment_1440468394459_1440468394459_2.ts
+        #EXTINF:7.834,
+        #EXT-X-BYTERANGE:657812@4032976
+        segment_1440468394459_1440468394459_2.ts
+        #EXT-X-ENDLIST
+    "};
+
     mod big_buck_bunny {
         use super::*;
 
         // Helper because this playlist is valid and should parse correctly.
         fn big_buck_bunny() -> MediaPlaylist {
-            const BIG_BUCK_BUNNY: &str = indoc::indoc! {"
-                #EXTM3U
-                #EXT-X-VERSION:4
-                #EXT-X-ALLOW-CACHE:NO
-                #EXT-X-TARGETDURATION:20
-                #EXT-X-MEDIA-SEQUENCE:1
-                #EXT-X-PROGRAM-DATE-TIME:2015-08-25T01:59:23.708+00:00
-                #EXTINF:12.166,
-                #EXT-X-BYTERANGE:1430680@4048392
-                segment_1440468394459_1440468394459_1.ts
-                #EXTINF:13.292,
-                #EXT-X-BYTERANGE:840360@5479072
-                segment_1440468394459_1440468394459_1.ts
-                #EXTINF:10.500,
-                #EXT-X-BYTERANGE:1009184@6319432
-                segment_1440468394459_1440468394459_1.ts
-                #EXTINF:11.417,
-                #EXT-X-BYTERANGE:806332@0
-                segment_1440468394459_1440468394459_2.ts
-                #EXTINF:12.459,
-                #EXT-X-BYTERANGE:701616@806332
-                segment_1440468394459_1440468394459_2.ts
-                #EXTINF:14.000,
-                #EXT-X-BYTERANGE:931352@1507948
-                segment_1440468394459_1440468394459_2.ts
-                #EXTINF:19.292,
-                #EXT-X-BYTERANGE:1593676@2439300
-                segment_1440468394459_1440468394459_2.ts
-                #EXTINF:7.834,
-                #EXT-X-BYTERANGE:657812@4032976
-                segment_1440468394459_1440468394459_2.ts
-                #EXT-X-ENDLIST
-            "};
-
             MediaPlaylist::parse_ext_m3u(BIG_BUCK_BUNNY).expect("Big Buck Bunny should parse")
         }
 
@@ -207,6 +1434,18 @@ mod tests {
             assert_eq!(playlist.target_duration, Duration::from_secs(20));
         }
 
+        #[test]
+        fn parses_allow_cache() {
+            let playlist = big_buck_bunny();
+            assert_eq!(playlist.allow_cache, Some(false));
+        }
+
+        #[test]
+        fn parses_media_sequence() {
+            let playlist = big_buck_bunny();
+            assert_eq!(playlist.media_sequence, 1);
+        }
+
         //#[ignore = "uncomment when ready"]
         #[test]
         fn parses_end_tag() {
@@ -220,44 +1459,613 @@ mod tests {
             let playlist = big_buck_bunny();
             let expected = vec![
                 MediaSegment {
-                    duration: Duration::from_secs_f32(12.166),
-                    url: "segment_1440468394459_1440468394459_1.ts".to_string(),
+                    duration_estimated: false,
+                    duration: SegmentDuration::from_millis(12166),
+                    url: "segment_1440468394459_1440468394459_1.ts".into(),
+                    title: None,
+                    keys: Arc::default(),
+                    byte_range: Some(ByteRange { length: 1430680, offset: Some(4048392) }),
+                    discontinuity: false,
+                    map: None,
+                    gap: false,
+                    parts: Vec::new(),
+                    program_date_time: Some("2015-08-25T01:59:23.708+00:00".parse().unwrap()),
+                    date_ranges: Vec::new(),
+                    cues: Vec::new(),
+                    tag_order: TagOrder(vec![TagSlot::ProgramDateTime, TagSlot::ByteRange]),
+                    extensions: Extensions::default(),
                 },
                 MediaSegment {
-                    duration: Duration::from_secs_f32(13.292),
-                    url: "segment_1440468394459_1440468394459_1.ts".to_string(),
+                    duration_estimated: false,
+                    duration: SegmentDuration::from_millis(13292),
+                    url: "segment_1440468394459_1440468394459_1.ts".into(),
+                    title: None,
+                    keys: Arc::default(),
+                    byte_range: Some(ByteRange { length: 840360, offset: Some(5479072) }),
+                    discontinuity: false,
+                    map: None,
+                    gap: false,
+                    parts: Vec::new(),
+                    program_date_time: None,
+                    date_ranges: Vec::new(),
+                    cues: Vec::new(),
+                    tag_order: TagOrder(vec![TagSlot::ByteRange]),
+                    extensions: Extensions::default(),
                 },
                 MediaSegment {
-                    duration: Duration::from_secs_f32(10.500),
-                    url: "segment_1440468394459_1440468394459_1.ts".to_string(),
+                    duration_estimated: false,
+                    duration: SegmentDuration::from_millis(10500),
+                    url: "segment_1440468394459_1440468394459_1.ts".into(),
+                    title: None,
+                    keys: Arc::default(),
+                    byte_range: Some(ByteRange { length: 1009184, offset: Some(6319432) }),
+                    discontinuity: false,
+                    map: None,
+                    gap: false,
+                    parts: Vec::new(),
+                    program_date_time: None,
+                    date_ranges: Vec::new(),
+                    cues: Vec::new(),
+                    tag_order: TagOrder(vec![TagSlot::ByteRange]),
+                    extensions: Extensions::default(),
                 },
                 MediaSegment {
-                    duration: Duration::from_secs_f32(11.417),
-                    url: "segment_1440468394459_1440468394459_2.ts".to_string(),
+                    duration_estimated: false,
+                    duration: SegmentDuration::from_millis(11417),
+                    url: "segment_1440468394459_1440468394459_2.ts".into(),
+                    title: None,
+                    keys: Arc::default(),
+                    byte_range: Some(ByteRange { length: 806332, offset: Some(0) }),
+                    discontinuity: false,
+                    map: None,
+                    gap: false,
+                    parts: Vec::new(),
+                    program_date_time: None,
+                    date_ranges: Vec::new(),
+                    cues: Vec::new(),
+                    tag_order: TagOrder(vec![TagSlot::ByteRange]),
+                    extensions: Extensions::default(),
                 },
                 MediaSegment {
-                    duration: Duration::from_secs_f32(12.459),
-                    url: "segment_1440468394459_1440468394459_2.ts".to_string(),
+                    duration_estimated: false,
+                    duration: SegmentDuration::from_millis(12459),
+                    url: "segment_1440468394459_1440468394459_2.ts".into(),
+                    title: None,
+                    keys: Arc::default(),
+                    byte_range: Some(ByteRange { length: 701616, offset: Some(806332) }),
+                    discontinuity: false,
+                    map: None,
+                    gap: false,
+                    parts: Vec::new(),
+                    program_date_time: None,
+                    date_ranges: Vec::new(),
+                    cues: Vec::new(),
+                    tag_order: TagOrder(vec![TagSlot::ByteRange]),
+                    extensions: Extensions::default(),
                 },
                 MediaSegment {
-                    duration: Duration::from_secs_f32(14.000),
-                    url: "segment_1440468394459_1440468394459_2.ts".to_string(),
+                    duration_estimated: false,
+                    duration: SegmentDuration::from_millis(14000),
+                    url: "segment_1440468394459_1440468394459_2.ts".into(),
+                    title: None,
+                    keys: Arc::default(),
+                    byte_range: Some(ByteRange { length: 931352, offset: Some(1507948) }),
+                    discontinuity: false,
+                    map: None,
+                    gap: false,
+                    parts: Vec::new(),
+                    program_date_time: None,
+                    date_ranges: Vec::new(),
+                    cues: Vec::new(),
+                    tag_order: TagOrder(vec![TagSlot::ByteRange]),
+                    extensions: Extensions::default(),
                 },
                 MediaSegment {
-                    duration: Duration::from_secs_f32(19.292),
-                    url: "segment_1440468394459_1440468394459_2.ts".to_string(),
+                    duration_estimated: false,
+                    duration: SegmentDuration::from_millis(19292),
+                    url: "segment_1440468394459_1440468394459_2.ts".into(),
+                    title: None,
+                    keys: Arc::default(),
+                    byte_range: Some(ByteRange { length: 1593676, offset: Some(2439300) }),
+                    discontinuity: false,
+                    map: None,
+                    gap: false,
+                    parts: Vec::new(),
+                    program_date_time: None,
+                    date_ranges: Vec::new(),
+                    cues: Vec::new(),
+                    tag_order: TagOrder(vec![TagSlot::ByteRange]),
+                    extensions: Extensions::default(),
                 },
                 MediaSegment {
-                    duration: Duration::from_secs_f32(7.834),
-                    url: "segment_1440468394459_1440468394459_2.ts".to_string(),
+                    duration_estimated: false,
+                    duration: SegmentDuration::from_millis(7834),
+                    url: "segment_1440468394459_1440468394459_2.ts".into(),
+                    title: None,
+                    keys: Arc::default(),
+                    byte_range: Some(ByteRange { length: 657812, offset: Some(4032976) }),
+                    discontinuity: false,
+                    map: None,
+                    gap: false,
+                    parts: Vec::new(),
+                    program_date_time: None,
+                    date_ranges: Vec::new(),
+                    cues: Vec::new(),
+                    tag_order: TagOrder(vec![TagSlot::ByteRange]),
+                    extensions: Extensions::default(),
                 },
             ];
 
             // Slightly easier to read failures if we go one at a time.
             assert_eq!(playlist.segments.len(), expected.len());
             for (actual, expected) in playlist.segments.into_iter().zip(expected) {
+                //only the order of custom tags is compared with the segments
+                assert_eq!(actual.tag_order(), expected.tag_order());
                 assert_eq!(actual, expected);
             }
         }
     }
+
+    mod structure {
+        use super::*;
+
+        fn parse_error(file: &str) -> String {
+            MediaPlaylist::parse_ext_m3u(file).expect_err("playlist should not parse").to_string()
+        }
+
+        #[test]
+        fn rejects_trailing_extinf() {
+            let error = parse_error(indoc::indoc! {"
+                #EXTM3U
+                #EXT-X-TARGETDURATION:10
+                #EXTINF:9.009,
+                first.ts
+                #EXTINF:9.009,
+            "});
+            assert_eq!(error, "EXTINF without URI at line 5");
+        }
+
+        #[test]
+        fn rejects_consecutive_extinf() {
+            let error = parse_error(indoc::indoc! {"
+                #EXTM3U
+                #EXT-X-TARGETDURATION:10
+                #EXTINF:9.009,
+                #EXTINF:9.009,
+                second.ts
+            "});
+            assert_eq!(error, "EXTINF without URI at line 3");
+        }
+
+        #[test]
+        fn rejects_trailing_byterange() {
+            let error = parse_error(indoc::indoc! {"
+                #EXTM3U
+                #EXT-X-TARGETDURATION:10
+                #EXTINF:9.009,
+                first.ts
+                #EXT-X-BYTERANGE:1000@0
+                #EXT-X-ENDLIST
+            "});
+            assert_eq!(error, "EXT-X-BYTERANGE without URI at line 5");
+        }
+
+        #[test]
+        fn rejects_trailing_discontinuity() {
+            let error = parse_error(indoc::indoc! {"
+                #EXTM3U
+                #EXT-X-TARGETDURATION:10
+                #EXTINF:9.009,
+                first.ts
+                #EXT-X-DISCONTINUITY
+            "});
+            assert_eq!(error, "EXT-X-DISCONTINUITY without URI at line 5");
+        }
+
+        #[test]
+        fn rejects_uri_without_extinf() {
+            let error = parse_error(indoc::indoc! {"
+                #EXTM3U
+                #EXT-X-TARGETDURATION:10
+                #EXT-X-BYTERANGE:1000@0
+                first.ts
+            "});
+            assert_eq!(error, "URI without EXTINF at line 4");
+        }
+
+        #[test]
+        fn rejects_segment_tags_before_header() {
+            let error = parse_error(indoc::indoc! {"
+                #EXTINF:9.009,
+                #EXTM3U
+                #EXT-X-TARGETDURATION:10
+                first.ts
+            "});
+            assert_eq!(error, "EXTINF before #EXTM3U header at line 1");
+        }
+
+        #[test]
+        fn rejects_duplicate_playlist_tags() {
+            let error = parse_error(indoc::indoc! {"
+                #EXTM3U
+                #EXT-X-VERSION:3
+                #EXT-X-TARGETDURATION:10
+                #EXT-X-VERSION:4
+            "});
+            assert_eq!(error, "Playlist contains more than 1 version tag");
+        }
+
+        #[test]
+        fn enforces_tag_placement_unless_lenient() {
+            let file = indoc::indoc! {"
+                #EXTM3U
+                #EXT-X-TARGETDURATION:10
+                #EXTINF:9,
+                first.ts
+                #EXT-X-ENDLIST
+                #EXT-X-TARGETDURATION:6
+                #EXTINF:9,
+                second.ts
+                #EXT-X-ENDLIST
+            "};
+            assert_eq!(parse_error(file), "Playlist contains more than 1 duration tag");
+            let options = ParseOptions { lenient: true, preserve_source: true, ..ParseOptions::default() };
+            let playlist = MediaPlaylist::parse_with_options(file, &options).expect("should parse leniently");
+            assert_eq!(playlist.target_duration, Duration::from_secs(10));
+            assert_eq!(playlist.segments.len(), 2);
+            let lines: Vec<Option<usize>> = playlist.diagnostics().iter().map(|x| x.line).collect();
+            assert_eq!(lines, vec![Some(6), Some(8), Some(9)]);
+            assert_eq!(
+                playlist.write(&crate::WriteOptions::default()),
+                indoc::indoc! {"
+                    #EXTM3U
+                    #EXT-X-TARGETDURATION:10
+                    #EXTINF:9,
+                    first.ts
+                    #EXTINF:9,
+                    second.ts
+                    #EXT-X-ENDLIST
+                "}
+            );
+        }
+
+        #[test]
+        fn requires_target_duration_after_segments() {
+            let error = parse_error(indoc::indoc! {"
+                #EXTM3U
+                #EXTINF:9.009,
+                first.ts
+                #EXT-X-ENDLIST
+            "});
+            assert_eq!(error, "Duration tag not found");
+        }
+
+        #[test]
+        fn normalizes_sloppy_tags_when_lenient() {
+            let sloppy = indoc::indoc! {r#"
+                #EXTM3U
+                #ext-x-targetduration:10
+                #EXT-X-KEY: METHOD=AES-128, URI="1.key"
+                #extinf: 9,
+                  first.ts
+            "#};
+            assert_eq!(parse_error(sloppy), "Key tag found, but could not parse");
+            let lenient = ParseOptions { lenient: true, ..ParseOptions::default() };
+            let playlist = MediaPlaylist::parse_with_options(sloppy, &lenient).expect("should parse leniently");
+            assert_eq!(playlist.target_duration, Duration::from_secs(10));
+            assert_eq!(playlist.segments[0].keys().first().and_then(EncryptionKey::uri), Some("1.key"));
+            assert_eq!(playlist.segments[0].url, "first.ts");
+            let lines: Vec<Option<usize>> = playlist.diagnostics().iter().map(|x| x.line).collect();
+            assert_eq!(lines, vec![Some(2), Some(3), Some(4), Some(4), Some(5)]);
+        }
+
+        #[test]
+        fn estimates_missing_extinf_when_lenient() {
+            let file = indoc::indoc! {"
+                #EXTM3U
+                #EXT-X-VERSION:3
+                #EXT-X-TARGETDURATION:6
+                #EXT-X-PROGRAM-DATE-TIME:2024-03-01T12:00:00Z
+                #EXTINF:6,
+                first.ts
+                second.ts
+                #EXT-X-PROGRAM-DATE-TIME:2024-03-01T12:00:10.5Z
+                #EXTINF:6,
+                third.ts
+                fourth.ts
+            "};
+            assert_eq!(parse_error(file), "URI without EXTINF at line 7");
+            let lenient = ParseOptions { lenient: true, ..ParseOptions::default() };
+            let playlist = MediaPlaylist::parse_with_options(file, &lenient).expect("should parse leniently");
+            let durations: Vec<(Duration, bool)> =
+                playlist.segments.iter().map(|x| (x.duration(), x.duration_estimated())).collect();
+            assert_eq!(
+                durations,
+                vec![
+                    (Duration::from_secs(6), false),
+                    (Duration::from_millis(4500), true),
+                    (Duration::from_secs(6), false),
+                    (Duration::from_secs(6), true),
+                ]
+            );
+            assert_eq!(
+                playlist.diagnostics(),
+                vec![
+                    Diagnostic::warning(Some(7), "Segment second.ts has no EXTINF, duration estimated as 4.5s"),
+                    Diagnostic::warning(Some(11), "Segment fourth.ts has no EXTINF, duration estimated as 6s"),
+                ]
+            );
+        }
+
+        #[test]
+        fn rejects_master_playlist() {
+            let error = parse_error(indoc::indoc! {"
+                #EXTM3U
+                #EXT-X-STREAM-INF:BANDWIDTH=1280000
+                low.m3u8
+            "});
+            assert_eq!(error, "Master playlist tag EXT-X-STREAM-INF in media playlist");
+        }
+
+        #[test]
+        fn collects_all_errors() {
+            let options = ParseOptions::default();
+            let errors = MediaPlaylist::parse_all_errors(
+                indoc::indoc! {"
+                    #EXTM3U
+                    #EXT-X-VERSION:three
+                    #EXTINF:9.009,
+                    first.ts
+                    second.ts
+                    #EXT-X-STREAM-INF:BANDWIDTH=1280000
+                    #EXTINF:9.009,
+                "},
+                &options,
+            )
+            .unwrap_err();
+            let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+            assert_eq!(
+                errors,
+                vec![
+                    "line 2: Version tag found, but could not parse",
+                    "line 5: URI without EXTINF at line 5",
+                    "line 6: Master playlist tag EXT-X-STREAM-INF in media playlist",
+                    "EXTINF without URI at line 7",
+                ]
+            );
+
+            let errors = MediaPlaylist::parse_all_errors("#EXTINF:10,\n#EXTM3U\n#EXT-X-VERSION:x\n", &options);
+            assert_eq!(errors.unwrap_err().len(), 1);
+            assert!(MediaPlaylist::parse_all_errors(BIG_BUCK_BUNNY, &options).is_ok());
+        }
+
+        #[test]
+        fn ignores_comments_and_blank_lines() {
+            let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {"
+                #EXTM3U
+                #EXT-X-TARGETDURATION:10
+                # a comment between segments
+
+                #EXTINF:9.009,
+                # another comment
+                first.ts
+            "})
+            .expect("comments and blank lines are allowed");
+            assert_eq!(playlist.segments.len(), 1);
+            assert_eq!(playlist.segments[0].url, "first.ts");
+        }
+
+        #[test]
+        fn sizes_segment_list_from_second_segment() {
+            //titles naming the tag don't count as segments
+            let segments = "#EXTINF:4,#EXTINF\n0.ts\n".repeat(100);
+            let file = format!("#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-VERSION:3\n{}#EXT-X-ENDLIST\n", segments);
+            let mut parser = Parser::new(&ParseOptions::default());
+            parser.set_input_len(file.len());
+            file.lines().try_for_each(|line| parser.line(line)).unwrap();
+            assert_eq!(parser.segments.capacity(), 100);
+
+            let limits = ParseLimits { max_segments: Some(100), ..ParseLimits::default() };
+            let mut parser = Parser::new(&ParseOptions { limits, ..ParseOptions::default() });
+            parser.set_input_len(file.len() * 10);
+            file.lines().try_for_each(|line| parser.line(line)).unwrap();
+            assert_eq!(parser.segments.capacity(), 100);
+
+            //two short segments ahead of a long comment can't reserve a segment per few bytes
+            let file = format!("#EXTM3U\n#EXT-X-TARGETDURATION:4\nx\ny\n#{}\n", "x".repeat(1 << 20));
+            let lenient = ParseOptions { lenient: true, ..ParseOptions::default() };
+            let mut parser = Parser::new(&lenient);
+            parser.set_input_len(file.len());
+            file.lines().try_for_each(|line| parser.line(line)).unwrap();
+            assert_eq!(parser.segments.capacity(), MAX_RESERVED_SEGMENTS);
+        }
+    }
+
+    mod keys {
+        use super::*;
+
+        #[test]
+        fn applies_key_until_next_key_tag() {
+            let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+                #EXTM3U
+                #EXT-X-TARGETDURATION:10
+                #EXTINF:9.5,
+                clear.ts
+                #EXT-X-KEY:METHOD=AES-128,URI="1.key"
+                #EXTINF:9.5,
+                first.ts
+                #EXTINF:9.5,
+                second.ts
+                #EXT-X-KEY:METHOD=NONE
+                #EXTINF:9.5,
+                clear_again.ts
+            "#})
+            .expect("should parse");
+            let uris: Vec<Option<&str>> = playlist
+                .segments
+                .iter()
+                .map(|x| x.keys().first().and_then(EncryptionKey::uri))
+                .collect();
+            assert_eq!(uris, vec![None, Some("1.key"), Some("1.key"), None]);
+        }
+
+        #[test]
+        fn rejects_invalid_key() {
+            let error = MediaPlaylist::parse_ext_m3u(indoc::indoc! {"
+                #EXTM3U
+                #EXT-X-TARGETDURATION:10
+                #EXT-X-KEY:METHOD=AES-128
+            "})
+            .expect_err("key without URI should fail");
+            assert_eq!(format!("{:#}", error), "Key tag found, but could not parse: AES-128 key is missing URI");
+        }
+    }
+
+    mod diagnostics {
+        use super::*;
+
+        #[test]
+        fn big_buck_bunny_only_uses_deprecated_tag() {
+            let playlist = MediaPlaylist::parse_ext_m3u(BIG_BUCK_BUNNY).unwrap();
+            assert_eq!(
+                playlist.diagnostics(),
+                vec![Diagnostic::warning(None, "EXT-X-ALLOW-CACHE is deprecated and was removed in protocol version 7")]
+            );
+        }
+
+        #[test]
+        fn rejects_allow_cache_in_version_7() {
+            let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {"
+                #EXTM3U
+                #EXT-X-VERSION:7
+                #EXT-X-TARGETDURATION:10
+                #EXT-X-ALLOW-CACHE:YES
+            "})
+            .unwrap();
+            assert_eq!(playlist.allow_cache(), Some(true));
+            assert_eq!(
+                playlist.diagnostics(),
+                vec![Diagnostic::error(None, "EXT-X-ALLOW-CACHE is deprecated and was removed in protocol version 7")]
+            );
+        }
+
+        #[test]
+        fn reports_segments_exceeding_target_duration() {
+            let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {"
+                #EXTM3U
+                #EXT-X-VERSION:3
+                #EXT-X-TARGETDURATION:10
+                #EXTINF:10.4,
+                rounds_down.ts
+                #EXTINF:10.5,
+                rounds_up.ts
+            "})
+            .unwrap();
+            assert_eq!(
+                playlist.diagnostics(),
+                vec![Diagnostic::error(None, "Segment 2 (rounds_up.ts) duration 10.5s exceeds target duration 10s")]
+            );
+        }
+
+        #[test]
+        fn added_segments_raise_target_duration() {
+            let mut playlist = MediaPlaylist::parse_ext_m3u("#EXTM3U\n#EXT-X-TARGETDURATION:6\n").unwrap();
+            playlist.push_segment(MediaSegment::new(Duration::from_millis(6400), "rounds_down.ts"));
+            assert_eq!(playlist.target_duration(), Duration::from_secs(6));
+            playlist.insert_segment(0, MediaSegment::new(Duration::from_millis(7500), "rounds_up.ts"));
+            assert_eq!(playlist.target_duration(), Duration::from_secs(8));
+            assert_eq!(playlist.segments()[0].url(), "rounds_up.ts");
+            playlist.enforce_target_duration().unwrap();
+
+            //edits through segments_mut aren't checked until asked
+            playlist.segments_mut().push(MediaSegment::new(Duration::from_secs(9), "long.ts"));
+            playlist.segments_mut().push(MediaSegment::new(Duration::from_secs(10), "longer.ts"));
+            assert_eq!(
+                playlist.enforce_target_duration().unwrap_err().to_string(),
+                "Segment 3 (long.ts) duration 9s exceeds target duration 8s; \
+                 Segment 4 (longer.ts) duration 10s exceeds target duration 8s"
+            );
+            assert_eq!(playlist.required_target_duration(), Duration::from_secs(10));
+        }
+
+        #[test]
+        fn reports_duplicate_segments() {
+            let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {"
+                #EXTM3U
+                #EXT-X-VERSION:4
+                #EXT-X-TARGETDURATION:10
+                #EXTINF:9,
+                #EXT-X-BYTERANGE:100@0
+                main.ts
+                #EXTINF:9,
+                #EXT-X-BYTERANGE:100
+                main.ts
+                #EXTINF:9,
+                #EXT-X-BYTERANGE:100@0
+                main.ts
+                #EXTINF:9,
+                #EXT-X-BYTERANGE:100@100
+                main.ts
+                #EXTINF:9,
+                whole.ts
+                #EXTINF:9,
+                whole.ts
+            "})
+            .unwrap();
+            assert_eq!(
+                playlist.diagnostics(),
+                vec![
+                    Diagnostic::warning(None, "Segment 3 (main.ts) duplicates segment 1"),
+                    Diagnostic::warning(None, "Segment 4 (main.ts) duplicates segment 2"),
+                    Diagnostic::warning(None, "Segment 6 (whole.ts) duplicates segment 5"),
+                ]
+            );
+        }
+
+        #[test]
+        fn reports_version_requirements() {
+            let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+                #EXTM3U
+                #EXT-X-TARGETDURATION:10
+                #EXT-X-KEY:METHOD=AES-128,URI="1.key",IV=0x01
+                #EXTINF:9.5,
+                first.ts
+            "#})
+            .unwrap();
+            assert_eq!(
+                playlist.diagnostics(),
+                vec![
+                    Diagnostic::error(None, "Floating-point EXTINF durations require version 3, playlist is version 1"),
+                    Diagnostic::error(None, "EXT-X-KEY IV attribute requires version 2, playlist is version 1"),
+                ]
+            );
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    mod parse_async {
+        use super::*;
+
+        #[tokio::test]
+        async fn matches_parse_ext_m3u() {
+            let playlist = MediaPlaylist::parse_async(BIG_BUCK_BUNNY.as_bytes())
+                .await
+                .expect("Big Buck Bunny should parse");
+            assert_eq!(playlist, MediaPlaylist::parse_ext_m3u(BIG_BUCK_BUNNY).unwrap());
+        }
+
+        #[tokio::test]
+        async fn reports_errors_before_end_of_input() {
+            use tokio::io::AsyncWriteExt;
+
+            let (mut writer, reader) = tokio::io::duplex(64);
+            writer.write_all(b"#EXTM3U\n#EXT-X-VERSION:4\n#EXT-X-VERSION:5\n").await.unwrap();
+            // The writer is kept open, so this only finishes if the parser doesn't wait for EOF.
+            let error = MediaPlaylist::parse_async(tokio::io::BufReader::new(reader))
+                .await
+                .expect_err("duplicate version should fail");
+            assert_eq!(error.to_string(), "Playlist contains more than 1 version tag");
+            drop(writer);
+        }
+    }
 }
@@ -0,0 +1,95 @@
+//! Canonical forms of playlists, so equivalent playlists serialize to the same bytes, e.g. for
+//! caches keyed on the manifest.
+
+use crate::uri::remove_url_dot_segments;
+use crate::{ByteRange, MasterPlaylist, MediaPlaylist};
+
+impl MediaPlaylist {
+    /// Rewrites the playlist into its canonical form: comments, unknown tags and the original
+    /// formatting are dropped, so attributes are written in a fixed order, `.` and `..` segments
+    /// are removed from URLs, and byte range offsets which a segment would get implicitly from
+    /// the one before it are left out.
+    pub fn normalize(&mut self) {
+        self.discard_source();
+        self.map_urls(remove_url_dot_segments);
+
+        let resolved = self.resolved_byte_ranges();
+        let mut previous: Option<(String, Option<ByteRange>)> = None;
+        for (segment, byte_range) in self.segments_mut().iter_mut().zip(resolved) {
+            let implicit = previous.as_ref().is_some_and(|(url, previous_range)| {
+                let previous_end = previous_range.and_then(|x| x.end_offset());
+                url == segment.url().as_str() && byte_range.is_some_and(|x| x.offset.is_some() && x.offset == previous_end)
+            });
+            if implicit {
+                segment.set_byte_range(byte_range.map(|x| ByteRange { offset: None, ..x }));
+            }
+            previous = Some((segment.url().to_string(), byte_range));
+        }
+    }
+}
+
+impl MasterPlaylist {
+    /// Rewrites the playlist into its canonical form, with `.` and `..` segments removed from
+    /// URIs. Comments and unknown tags are never kept, and attributes are always written in a
+    /// fixed order.
+    pub fn normalize(&mut self) {
+        self.map_urls(remove_url_dot_segments);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseOptions;
+
+    #[test]
+    fn normalizes_media_playlist() {
+        let file = indoc::indoc! {r#"
+            #EXTM3U
+            # from the packager
+            #EXT-X-VERSION:4
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-KEY:URI="./keys/../1.key",METHOD=AES-128
+            #EXTINF:10,
+            #EXT-X-BYTERANGE:100@0
+            ./main.ts
+            #EXTINF:10,
+            #EXT-X-BYTERANGE:100@100
+            main.ts
+            #EXTINF:10,
+            #EXT-X-BYTERANGE:100@500
+            main.ts
+        "#};
+        let mut playlist =
+            MediaPlaylist::parse_with_options(file, &ParseOptions { preserve_source: true, ..ParseOptions::default() })
+                .unwrap();
+        playlist.normalize();
+        let expected = indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-VERSION:4
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-KEY:METHOD=AES-128,URI="1.key"
+            #EXT-X-BYTERANGE:100@0
+            #EXTINF:10,
+            main.ts
+            #EXT-X-BYTERANGE:100
+            #EXTINF:10,
+            main.ts
+            #EXT-X-BYTERANGE:100@500
+            #EXTINF:10,
+            main.ts
+        "#};
+        assert_eq!(playlist.to_string(), expected);
+        let mut renormalized = MediaPlaylist::parse_ext_m3u(expected).unwrap();
+        renormalized.normalize();
+        assert_eq!(renormalized.to_string(), expected);
+    }
+
+    #[test]
+    fn normalizes_master_playlist() {
+        let mut playlist =
+            MasterPlaylist::parse_ext_m3u("#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1\nvideo/./../low.m3u8\n").unwrap();
+        playlist.normalize();
+        assert_eq!(playlist.variants()[0].uri(), "low.m3u8");
+    }
+}
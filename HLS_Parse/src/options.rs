@@ -0,0 +1,197 @@
+//! Options controlling how playlists are parsed.
+
+use core::fmt;
+
+use anyhow::Result;
+
+use crate::extensions::TagHandlers;
+
+/// Passed to [`MediaPlaylist::parse_with_options`][crate::MediaPlaylist::parse_with_options],
+/// and for its [`spec`][Self::spec] to
+/// [`MasterPlaylist::parse_with_options`][crate::MasterPlaylist::parse_with_options]. The
+/// default matches [`parse_ext_m3u`][crate::MediaPlaylist::parse_ext_m3u].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Record the original lines, including comments and unknown tags, so
+    /// [`MediaPlaylist::write`][crate::MediaPlaylist::write] can reproduce them exactly except
+    /// where the model was modified. Costs a copy of the input.
+    pub preserve_source: bool,
+
+    /// Accept tags written in the wrong case (`#extinf:`) and whitespace around tag values and
+    /// attributes (`#EXT-X-KEY: METHOD=NONE`), which some encoders produce, as well as EXTINF
+    /// durations with a comma as the decimal separator (`#EXTINF:12,166,`) and segment URIs
+    /// without an EXTINF tag, whose duration is then estimated (see
+    /// [`MediaSegment::duration_estimated`][crate::MediaSegment::duration_estimated]). Repeated
+    /// playlist tags are ignored after the first, segments after EXT-X-ENDLIST are kept (though
+    /// written before it), and so are byte ranges whose offset can't be inferred. Every fix is
+    /// reported as a warning from [`MediaPlaylist::diagnostics`][crate::MediaPlaylist::diagnostics].
+    pub lenient: bool,
+
+    /// Called with every tag of a media playlist the parser doesn't model, see [`TagHandler`][crate::TagHandler].
+    pub tag_handlers: TagHandlers,
+
+    /// Bounds on the size of the input, none by default.
+    pub limits: ParseLimits,
+
+    /// Which version of the specification the playlist must follow.
+    pub spec: SpecVersion,
+}
+
+/// A version of the HLS specification, for [`ParseOptions::spec`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SpecVersion {
+    /// <https://datatracker.ietf.org/doc/html/rfc8216>, up to EXT-X-VERSION 7. Tags added since,
+    /// e.g. for low-latency streaming, are violations, and so is the CUE attribute of
+    /// EXT-X-DATERANGE.
+    Rfc8216,
+
+    /// <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis>, up to EXT-X-VERSION
+    /// 11, with EXT-X-DEFINE variables substituted into later lines.
+    #[default]
+    Rfc8216bis,
+}
+
+/// Bounds on the size of a playlist, for services parsing manifests they can't trust. Parsing
+/// fails with [`LimitExceeded`] as soon as the input goes past one, before the rest is read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ParseLimits {
+    pub max_lines: Option<usize>,
+    pub max_segments: Option<usize>,
+
+    /// In bytes, without the line ending.
+    pub max_line_length: Option<usize>,
+
+    /// Of any one tag with an attribute list.
+    pub max_attributes: Option<usize>,
+}
+
+impl ParseLimits {
+    /// Limits no real playlist comes near, e.g. a 24 hour DVR window of 1 second segments with
+    /// a few tags each.
+    pub fn untrusted() -> Self {
+        Self {
+            max_lines: Some(1_000_000),
+            max_segments: Some(200_000),
+            max_line_length: Some(64 * 1024),
+            max_attributes: Some(64),
+        }
+    }
+
+    /// Fails if `value` is over the limit.
+    pub(crate) fn check(&self, limit: Limit, value: usize) -> Result<()> {
+        let max = match limit {
+            Limit::Lines => self.max_lines,
+            Limit::Segments => self.max_segments,
+            Limit::LineLength => self.max_line_length,
+            Limit::Attributes => self.max_attributes,
+        };
+        match max {
+            Some(max) if value > max => Err(LimitExceeded { limit, max }.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// One of the [`ParseLimits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Limit {
+    Lines,
+    Segments,
+    LineLength,
+    Attributes,
+}
+
+/// The error when a playlist goes past one of the [`ParseLimits`], which can be told apart from
+/// other parse errors with [`anyhow::Error::downcast_ref`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LimitExceeded {
+    pub limit: Limit,
+    pub max: usize,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.limit {
+            Limit::Lines => write!(f, "Playlist has more than {} lines", self.max),
+            Limit::Segments => write!(f, "Playlist has more than {} segments", self.max),
+            Limit::LineLength => write!(f, "Line is longer than {} bytes", self.max),
+            Limit::Attributes => write!(f, "Tag has more than {} attributes", self.max),
+        }
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MasterPlaylist, MediaPlaylist, PushParser};
+
+    fn exceeded(file: &str, limits: ParseLimits) -> Option<Limit> {
+        let options = ParseOptions { limits, ..ParseOptions::default() };
+        let error = MediaPlaylist::parse_with_options(file, &options).err()?;
+        Some(error.downcast_ref::<LimitExceeded>()?.limit)
+    }
+
+    #[test]
+    fn enforces_limits() {
+        let file = concat!(
+            "#EXTM3U\n#EXT-X-TARGETDURATION:4\n",
+            "#EXT-X-KEY:METHOD=AES-128,URI=\"a,b\",IV=0x1\n#EXTINF:4,\n0.ts\n#EXTINF:4,\n1.ts\n"
+        );
+        assert_eq!(exceeded(file, ParseLimits::untrusted()), None);
+        assert_eq!(exceeded(file, ParseLimits { max_lines: Some(6), ..ParseLimits::default() }), Some(Limit::Lines));
+        let max_segments = ParseLimits { max_segments: Some(1), ..ParseLimits::default() };
+        assert_eq!(exceeded(file, max_segments), Some(Limit::Segments));
+        let max_line_length = ParseLimits { max_line_length: Some(40), ..ParseLimits::default() };
+        assert_eq!(exceeded(file, max_line_length), Some(Limit::LineLength));
+        let max_attributes = ParseLimits { max_attributes: Some(2), ..ParseLimits::default() };
+        assert_eq!(exceeded(file, max_attributes), Some(Limit::Attributes));
+        assert_eq!(exceeded(file, ParseLimits { max_attributes: Some(3), ..ParseLimits::default() }), None);
+
+        let options = ParseOptions { limits: max_line_length, ..ParseOptions::default() };
+        let mut parser = PushParser::with_options(&options);
+        assert!(parser.push(&[b'#'; 41]).unwrap_err().downcast_ref::<LimitExceeded>().is_some());
+    }
+
+    #[test]
+    fn follows_spec_version() {
+        let file = indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-VERSION:8
+            #EXT-X-TARGETDURATION:4
+            #EXT-X-DEFINE:NAME="host",VALUE="cdn.example.com"
+            #EXTINF:4,
+            https://{$host}/0.ts
+            #EXT-X-GAP
+            #EXTINF:4,
+            1.ts
+        "#};
+        let playlist = MediaPlaylist::parse_ext_m3u(file).unwrap();
+        assert_eq!(playlist.segments()[0].url().as_str(), "https://cdn.example.com/0.ts");
+        assert!(playlist.segments()[1].gap());
+
+        let rfc8216 = ParseOptions { spec: SpecVersion::Rfc8216, ..ParseOptions::default() };
+        assert!(MediaPlaylist::parse_with_options(file, &rfc8216).is_err());
+        let lenient = ParseOptions { lenient: true, ..rfc8216.clone() };
+        let playlist = MediaPlaylist::parse_with_options(file, &lenient).unwrap();
+        assert_eq!(playlist.segments()[0].url().as_str(), "https://{$host}/0.ts");
+        assert_eq!(playlist.diagnostics().len(), 3, "EXT-X-VERSION:8, EXT-X-DEFINE and EXT-X-GAP");
+        assert!(MediaPlaylist::parse_ext_m3u(&file.replace("{$host}", "{$other}")).is_err());
+
+        let master = indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-VERSION:7
+            #EXT-X-DEFINE:NAME="host",VALUE="cdn.example.com"
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000
+            https://{$host}/low.m3u8
+        "#};
+        let playlist = MasterPlaylist::parse_ext_m3u(master).unwrap();
+        assert_eq!(playlist.variants()[0].uri(), "https://cdn.example.com/low.m3u8");
+        let error = MasterPlaylist::parse_with_options(master, &rfc8216).unwrap_err();
+        assert_eq!(error.to_string(), "EXT-X-DEFINE tag is not part of RFC 8216 at line 3");
+        let master = master.replace("VERSION:7", "VERSION:8");
+        let error = MasterPlaylist::parse_with_options(&master, &rfc8216).unwrap_err();
+        assert_eq!(error.to_string(), "EXT-X-VERSION 8 is not part of RFC 8216 at line 2");
+    }
+}
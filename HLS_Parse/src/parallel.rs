@@ -0,0 +1,116 @@
+//! Parsing very large playlists, such as 24 hour DVR windows, on the rayon thread pool.
+
+use std::borrow::Cow;
+
+use anyhow::Result;
+use rayon::prelude::*;
+
+use crate::events;
+use crate::media_playlist::Parser;
+use crate::{MediaPlaylist, ParseOptions};
+
+/// Smallest part of the file worth handing to another thread, in bytes.
+const MIN_CHUNK_BYTES: usize = 128 * 1024;
+
+impl MediaPlaylist {
+    /// Like [`parse_with_options`][Self::parse_with_options], with the lines normalized and
+    /// tokenized in parallel chunks which end at segment URIs. Only the tags which carry state
+    /// from one segment to the next are then handled in order, so the result and any error are the
+    /// same as parsing on one thread.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(bytes = file.len(), chunks)))]
+    pub fn parse_parallel(file: &str, options: &ParseOptions) -> Result<Self> {
+        let mut parser = Parser::new(options);
+        parser.set_input_len(file.len());
+        let chunks = chunks(file, (file.len() / rayon::current_num_threads()).max(MIN_CHUNK_BYTES));
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("chunks", chunks.len());
+        let normalized: Vec<Vec<(Cow<str>, Vec<String>)>> =
+            chunks.par_iter().map(|chunk| chunk.lines().map(|line| parser.normalize(line)).collect()).collect();
+        let tokenized: Vec<Vec<_>> = normalized
+            .par_iter()
+            .map(|chunk| chunk.iter().map(|(line, _)| events::parse_line(line)).collect::<Vec<_>>())
+            .collect();
+
+        let result = chunks
+            .iter()
+            .flat_map(|x| x.lines())
+            .zip(normalized.iter().flatten())
+            .zip(tokenized.into_iter().flatten())
+            .try_for_each(|((raw, (line, fixes)), event)| parser.tokenized_line(raw, line, fixes.clone(), event))
+            .and_then(|()| parser.finish());
+        #[cfg(feature = "tracing")]
+        if let Err(error) = &result {
+            tracing::warn!("Media playlist failed to parse: {:#}", error);
+        }
+        result
+    }
+}
+
+/// Splits the file into chunks of at least `size` bytes, each ending with the line break after a
+/// segment URI apart from the last, so their lines are the file's lines.
+fn chunks(file: &str, size: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = file;
+    while rest.len() > size {
+        //a line break is never part of a multi-byte character
+        let Some(line_end) = rest.as_bytes()[size..].iter().position(|x| *x == b'\n') else {
+            break;
+        };
+        let mut end = size + line_end + 1;
+        let uri_end = rest[end..].split_inclusive('\n').find_map(|line| {
+            end += line.len();
+            (is_uri(line) && line.ends_with('\n')).then_some(end)
+        });
+        let Some(end) = uri_end else {
+            break;
+        };
+        let (chunk, next) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = next;
+    }
+    chunks.push(rest);
+    chunks
+}
+
+fn is_uri(line: &str) -> bool {
+    let line = line.trim();
+    !line.is_empty() && !line.starts_with('#')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playlist(segments: usize) -> String {
+        let mut file = String::from("#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:10\n");
+        for index in 0..segments {
+            if index % 1000 == 0 {
+                file.push_str(&format!("#EXT-X-KEY:METHOD=AES-128,URI=\"{index}.key\"\n"));
+            }
+            file.push_str(&format!("#EXTINF:9.{},\nsegment{index}.ts\n", index % 10));
+        }
+        file + "#EXT-X-ENDLIST\n"
+    }
+
+    #[test]
+    fn splits_at_segment_uris() {
+        let file = "#EXTM3U\n#EXTINF:10,\n1.ts\r\n#EXTINF:10,\n2.ts\n#EXT-X-ENDLIST\n";
+        assert_eq!(chunks(file, 1), vec!["#EXTM3U\n#EXTINF:10,\n1.ts\r\n", "#EXTINF:10,\n2.ts\n", "#EXT-X-ENDLIST\n"]);
+        assert_eq!(chunks(file, 64), vec![file]);
+    }
+
+    #[test]
+    fn matches_sequential_parsing() {
+        let file = playlist(10_000);
+        for options in [ParseOptions::default(), ParseOptions { lenient: true, ..ParseOptions::default() }] {
+            let parallel = MediaPlaylist::parse_parallel(&file, &options).unwrap();
+            assert_eq!(parallel, MediaPlaylist::parse_with_options(&file, &options).unwrap());
+            assert_eq!(parallel.segments()[9_999].keys()[0].uri(), Some("9000.key"));
+        }
+
+        let broken = file.replacen("segment1234.ts\n", "", 1);
+        let parallel = MediaPlaylist::parse_parallel(&broken, &ParseOptions::default()).unwrap_err();
+        let sequential = MediaPlaylist::parse_ext_m3u(&broken).unwrap_err();
+        assert_eq!(parallel.to_string(), sequential.to_string());
+    }
+}
@@ -0,0 +1,155 @@
+//! Partial segments of low-latency playlists. See
+//! <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.4.9>.
+
+use core::fmt;
+use core::time::Duration;
+
+use anyhow::Result;
+
+use crate::attributes::{AttributeList, RawAttributes};
+use crate::{ByteRange, SegmentDuration, SegmentUri};
+
+/// A part of a media segment from an EXT-X-PART tag, which clients can load before the whole
+/// segment is available.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PartialSegment {
+    duration: SegmentDuration,
+
+    /// URI of the part, usually relative to the playlist.
+    uri: SegmentUri,
+
+    /// Whether the part starts with an independent frame, e.g. an IDR, so playback can start
+    /// there.
+    independent: bool,
+
+    /// Sub-range of the resource at the URI. The offset is implicit if the previous part has the
+    /// same URI.
+    byte_range: Option<ByteRange>,
+
+    /// Whether the part is unavailable and mustn't be loaded.
+    gap: bool,
+
+    /// The attribute list as parsed.
+    raw: RawAttributes,
+}
+
+impl PartialSegment {
+    pub fn new(duration: impl Into<SegmentDuration>, uri: impl Into<SegmentUri>) -> Self {
+        let (duration, uri) = (duration.into(), uri.into());
+        Self { duration, uri, independent: false, byte_range: None, gap: false, raw: RawAttributes::default() }
+    }
+
+    /// Parses the attribute list of an EXT-X-PART tag.
+    pub(crate) fn parse(attribute_list: &str) -> Result<Self> {
+        let attributes = AttributeList::parse(attribute_list)?;
+        let Some(duration) = attributes.get("DURATION") else {
+            return Err(anyhow::Error::msg("Part is missing DURATION attribute"));
+        };
+        let duration = duration.parse::<SegmentDuration>()?;
+        let Some(uri) = attributes.quoted_string("URI")? else {
+            return Err(anyhow::Error::msg("Part is missing URI attribute"));
+        };
+        let byte_range = attributes.quoted_string("BYTERANGE")?.map(str::parse::<ByteRange>).transpose()?;
+        let flag = |name: &str| match attributes.get(name) {
+            Some("YES") => Ok(true),
+            Some(other) => Err(anyhow::anyhow!("Invalid {} {}", name, other)),
+            None => Ok(false),
+        };
+        Ok(Self {
+            duration,
+            uri: SegmentUri::new(uri),
+            independent: flag("INDEPENDENT")?,
+            byte_range,
+            gap: flag("GAP")?,
+            raw: RawAttributes::new(attribute_list),
+        })
+    }
+
+    /// Raw value of the attribute `name` as parsed, including ones the crate doesn't model, see
+    /// [`RawAttributes`][crate::RawAttributes].
+    pub fn raw_attribute(&self, name: &str) -> Option<&str> {
+        self.raw.get(name)
+    }
+
+    /// Every attribute of the EXT-X-PART tag as parsed.
+    pub fn raw_attributes(&self) -> &RawAttributes {
+        &self.raw
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration.as_duration()
+    }
+
+    /// The duration as written in the DURATION attribute.
+    pub fn exact_duration(&self) -> SegmentDuration {
+        self.duration
+    }
+
+    pub fn uri(&self) -> &SegmentUri {
+        &self.uri
+    }
+
+    pub fn independent(&self) -> bool {
+        self.independent
+    }
+
+    pub fn byte_range(&self) -> Option<ByteRange> {
+        self.byte_range
+    }
+
+    pub fn gap(&self) -> bool {
+        self.gap
+    }
+
+    pub fn set_uri(&mut self, uri: impl Into<SegmentUri>) {
+        self.uri = uri.into();
+    }
+
+    pub fn set_independent(&mut self, independent: bool) {
+        self.independent = independent;
+    }
+
+    pub fn set_byte_range(&mut self, byte_range: Option<ByteRange>) {
+        self.byte_range = byte_range;
+    }
+
+    pub fn set_gap(&mut self, gap: bool) {
+        self.gap = gap;
+    }
+}
+
+impl fmt::Display for PartialSegment {
+    /// Formats the part as the attribute list of an EXT-X-PART tag.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DURATION={},URI=\"{}\"", self.duration, self.uri)?;
+        if self.independent {
+            f.write_str(",INDEPENDENT=YES")?;
+        }
+        if let Some(byte_range) = &self.byte_range {
+            write!(f, ",BYTERANGE=\"{}\"", byte_range)?;
+        }
+        if self.gap {
+            f.write_str(",GAP=YES")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_parts() {
+        let part = PartialSegment::parse(r#"DURATION=0.33334,URI="part1.0.mp4",INDEPENDENT=YES,BYTERANGE="1000@0""#).unwrap();
+        assert_eq!(part.duration(), Duration::from_micros(333_340));
+        assert_eq!(part.uri(), "part1.0.mp4");
+        assert!(part.independent() && !part.gap());
+        assert_eq!(part.byte_range(), Some(ByteRange { length: 1000, offset: Some(0) }));
+        assert_eq!(part.to_string(), r#"DURATION=0.33334,URI="part1.0.mp4",INDEPENDENT=YES,BYTERANGE="1000@0""#);
+
+        assert!(PartialSegment::parse(r#"URI="part1.0.mp4""#).is_err());
+        assert!(PartialSegment::parse("DURATION=1").is_err());
+        assert!(PartialSegment::parse(r#"DURATION=1,URI="a.mp4",GAP=NO"#).is_err());
+    }
+}
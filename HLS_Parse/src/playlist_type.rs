@@ -0,0 +1,142 @@
+//! The EXT-X-PLAYLIST-TYPE tag, and how a media playlist may change kind between reloads, e.g.
+//! an EVENT playlist becoming VOD once the event is over. See
+//! <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.3.5>.
+
+use core::fmt;
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::compare;
+use crate::MediaPlaylist;
+
+/// What a server promises about how a media playlist changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlaylistType {
+    /// Segments are only ever appended.
+    Event,
+
+    /// The playlist never changes.
+    Vod,
+}
+
+impl PlaylistType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PlaylistType::Event => "EVENT",
+            PlaylistType::Vod => "VOD",
+        }
+    }
+
+    /// Parses the value of an EXT-X-PLAYLIST-TYPE tag.
+    pub(crate) fn parse(value: &str) -> Result<Self> {
+        match value {
+            "EVENT" => Ok(PlaylistType::Event),
+            "VOD" => Ok(PlaylistType::Vod),
+            _ => Err(anyhow::anyhow!("Unknown playlist type {}", value)),
+        }
+    }
+}
+
+impl fmt::Display for PlaylistType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A reload changing the playlist type or adding an EXT-X-ENDLIST tag, from
+/// [`MediaPlaylist::transition_from`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlaylistTransition {
+    /// Type before and after, `None` for a live playlist without the tag.
+    pub from: Option<PlaylistType>,
+    pub to: Option<PlaylistType>,
+
+    /// Whether the playlist gained an EXT-X-ENDLIST tag.
+    pub ended: bool,
+
+    /// Media sequence numbers of the earlier playlist's segments which the later one changed,
+    /// or removed even though the earlier type promised not to. Live playlists without a type
+    /// may remove segments from the front.
+    pub mutated: Vec<u64>,
+}
+
+impl PlaylistTransition {
+    /// Whether segments were only appended, as they should have been.
+    pub fn is_append_only(&self) -> bool {
+        self.mutated.is_empty()
+    }
+}
+
+impl MediaPlaylist {
+    /// How the playlist changed kind since `previous`, an earlier load of it, `None` if it has
+    /// the same type and neither or both have ended.
+    pub fn transition_from(&self, previous: &MediaPlaylist) -> Option<PlaylistTransition> {
+        let (from, to) = (previous.playlist_type(), self.playlist_type());
+        let ended = self.ended() && !previous.ended();
+        if from == to && !ended {
+            return None;
+        }
+
+        let current: HashMap<u64, _> = self.iter_segments().map(|x| (x.sequence, x)).collect();
+        let mut mutated = Vec::new();
+        for before in previous.iter_segments() {
+            let changed = match current.get(&before.sequence) {
+                Some(after) => {
+                    !compare::same_segment(before.segment, before.byte_range, after.segment, after.byte_range)
+                }
+                None if before.sequence < self.media_sequence() => from.is_some(),
+                //skipped by a delta update, so it can't have changed
+                None if before.sequence < self.media_sequence() + self.skipped_segments() => false,
+                None => true,
+            };
+            if changed {
+                mutated.push(before.sequence);
+            }
+        }
+        Some(PlaylistTransition { from, to, ended, mutated })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playlist(playlist_type: &str, first: u64, segments: &[&str], ended: bool) -> MediaPlaylist {
+        let mut file = format!("#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-MEDIA-SEQUENCE:{}\n{}", first, playlist_type);
+        for segment in segments {
+            file.push_str(&format!("#EXTINF:4,\n{}\n", segment));
+        }
+        if ended {
+            file.push_str("#EXT-X-ENDLIST\n");
+        }
+        MediaPlaylist::parse_ext_m3u(&file).unwrap()
+    }
+
+    #[test]
+    fn detects_transitions() {
+        let event = playlist("#EXT-X-PLAYLIST-TYPE:EVENT\n", 0, &["0.ts", "1.ts"], false);
+        assert_eq!(event.playlist_type(), Some(PlaylistType::Event));
+        assert_eq!(playlist("", 0, &["0.ts", "1.ts", "2.ts"], false).transition_from(&event).unwrap().to, None);
+        assert_eq!(event.transition_from(&event), None);
+
+        let vod = playlist("#EXT-X-PLAYLIST-TYPE:VOD\n", 0, &["0.ts", "1.ts", "2.ts"], true);
+        let transition = vod.transition_from(&event).unwrap();
+        assert_eq!(transition, PlaylistTransition {
+            from: Some(PlaylistType::Event),
+            to: Some(PlaylistType::Vod),
+            ended: true,
+            mutated: vec![],
+        });
+
+        let event = playlist("#EXT-X-PLAYLIST-TYPE:EVENT\n", 0, &["0.ts", "1.ts", "2.ts"], false);
+        let rewritten = playlist("#EXT-X-PLAYLIST-TYPE:VOD\n", 1, &["1.ts", "edited.ts"], true);
+        assert_eq!(rewritten.transition_from(&event).unwrap().mutated, vec![0, 2]);
+
+        //live playlists slide, so losing the first segment isn't a mutation
+        let live = playlist("", 0, &["0.ts", "1.ts"], false);
+        let ended = playlist("", 1, &["1.ts", "2.ts"], true);
+        assert!(ended.transition_from(&live).unwrap().is_append_only());
+        assert!(MediaPlaylist::parse_ext_m3u("#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-PLAYLIST-TYPE:LIVE\n").is_err());
+    }
+}
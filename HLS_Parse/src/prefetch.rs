@@ -0,0 +1,181 @@
+//! Planning the requests needed to fill a playback buffer, e.g. for a player starting up or
+//! catching up after a seek.
+
+use core::time::Duration;
+
+use crate::{ByteRange, EncryptionKey, MediaPlaylist, PartialSegment, SegmentMap, SegmentUri};
+
+/// A resource to load, from [`MediaPlaylist::prefetch_plan`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrefetchRequest<'a> {
+    /// Media sequence number of the segment, or of the segment being produced for a trailing
+    /// part.
+    pub sequence: u64,
+
+    /// Index of the part within the trailing parts, `None` for a whole segment.
+    pub part: Option<usize>,
+
+    pub uri: &'a SegmentUri,
+
+    /// Byte range with an implicit offset filled in, `None` for the whole resource. The offset
+    /// is still `None` if it can't be inferred.
+    pub byte_range: Option<ByteRange>,
+
+    /// Media initialization section needed to parse the media, if any.
+    pub map: Option<&'a SegmentMap>,
+
+    /// Keys the media is encrypted with, one per KEYFORMAT. Empty if it isn't encrypted.
+    pub keys: &'a [EncryptionKey],
+
+    /// Time from the start of the first listed segment to the start of the media.
+    pub start: Duration,
+
+    pub duration: Duration,
+}
+
+impl MediaPlaylist {
+    /// The segments to load, in order, to buffer `buffer_target` of media starting at the segment
+    /// with sequence number `from_sequence`. Once the complete segments run out, the trailing
+    /// parts of the segment being produced are requested one by one.
+    ///
+    /// Gap segments and parts are skipped since they can't be loaded, but their duration still
+    /// counts towards the buffer, as a player plays through them. The plan is empty if
+    /// `from_sequence` is neither listed nor the segment being produced.
+    pub fn prefetch_plan(&self, buffer_target: Duration, from_sequence: u64) -> Vec<PrefetchRequest<'_>> {
+        let first_sequence = self.media_sequence() + self.skipped_segments();
+        let next_sequence = first_sequence + self.segments().len() as u64;
+        let mut plan = Vec::new();
+        if !(first_sequence..=next_sequence).contains(&from_sequence) {
+            return plan;
+        }
+
+        let mut buffered = Duration::ZERO;
+        for context in self.iter_segments().filter(|x| x.sequence >= from_sequence) {
+            if buffered >= buffer_target {
+                return plan;
+            }
+            buffered += context.segment.duration();
+            if context.segment.gap() {
+                continue;
+            }
+            plan.push(PrefetchRequest {
+                sequence: context.sequence,
+                part: None,
+                uri: context.segment.url(),
+                byte_range: context.byte_range,
+                map: context.map,
+                keys: context.keys,
+                start: context.start,
+                duration: context.segment.duration(),
+            });
+        }
+
+        //the parts are all there is of the segment being produced, so they follow the last segment
+        let last = self.segments().last();
+        let mut start: Duration = self.segments().iter().map(|x| x.duration()).sum();
+        let parts = self.trailing_parts();
+        for (index, (part, byte_range)) in parts.iter().zip(resolved_part_ranges(parts)).enumerate() {
+            if buffered >= buffer_target {
+                break;
+            }
+            buffered += part.duration();
+            if !part.gap() {
+                plan.push(PrefetchRequest {
+                    sequence: next_sequence,
+                    part: Some(index),
+                    uri: part.uri(),
+                    byte_range,
+                    map: last.and_then(|x| x.map()),
+                    keys: last.map(|x| x.keys()).unwrap_or_default(),
+                    start,
+                    duration: part.duration(),
+                });
+            }
+            start += part.duration();
+        }
+        plan
+    }
+}
+
+/// Byte range of every part, with implicit offsets filled in from the previous part when it is a
+/// sub-range of the same resource, like [`MediaPlaylist::resolved_byte_ranges`].
+pub(crate) fn resolved_part_ranges(parts: &[PartialSegment]) -> Vec<Option<ByteRange>> {
+    let mut resolved: Vec<Option<ByteRange>> = Vec::with_capacity(parts.len());
+    for (index, part) in parts.iter().enumerate() {
+        let byte_range = part.byte_range().map(|byte_range| match byte_range.offset {
+            Some(_) => byte_range,
+            None => {
+                let previous_end = index
+                    .checked_sub(1)
+                    .filter(|previous| parts[*previous].uri() == part.uri())
+                    .and_then(|previous| resolved[previous])
+                    .and_then(|previous| previous.end_offset());
+                ByteRange { length: byte_range.length, offset: previous_end }
+            }
+        });
+        resolved.push(byte_range);
+    }
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOW_LATENCY: &str = indoc::indoc! {r#"
+        #EXTM3U
+        #EXT-X-VERSION:9
+        #EXT-X-TARGETDURATION:4
+        #EXT-X-MEDIA-SEQUENCE:100
+        #EXT-X-PART-INF:PART-TARGET=1.0
+        #EXT-X-MAP:URI="init.mp4"
+        #EXTINF:4.0,
+        100.mp4
+        #EXT-X-GAP
+        #EXTINF:4.0,
+        101.mp4
+        #EXT-X-PART:DURATION=1.0,URI="102.mp4",INDEPENDENT=YES,BYTERANGE="1000@0"
+        #EXT-X-PART:DURATION=1.0,URI="102.mp4",BYTERANGE="1000"
+        #EXTINF:2.0,
+        102.mp4
+        #EXT-X-PART:DURATION=1.0,URI="103.mp4",INDEPENDENT=YES,BYTERANGE="800@0"
+        #EXT-X-PART:DURATION=1.0,URI="103.mp4",BYTERANGE="900"
+        #EXT-X-PART:DURATION=1.0,URI="103.gap.mp4",GAP=YES
+        #EXT-X-PART:DURATION=1.0,URI="103.mp4",BYTERANGE="700"
+    "#};
+
+    fn summary(plan: &[PrefetchRequest]) -> Vec<(u64, Option<usize>, String, Option<ByteRange>)> {
+        plan.iter().map(|x| (x.sequence, x.part, x.uri.to_string(), x.byte_range)).collect()
+    }
+
+    #[test]
+    fn plans_segments_then_parts() {
+        let playlist = MediaPlaylist::parse_ext_m3u(LOW_LATENCY).unwrap();
+        assert_eq!(playlist.to_string(), LOW_LATENCY);
+        assert_eq!(playlist.diagnostics(), vec![]);
+
+        let plan = playlist.prefetch_plan(Duration::from_secs(8), 100);
+        assert_eq!(summary(&plan), vec![(100, None, "100.mp4".to_string(), None)]);
+        assert_eq!(plan[0].map.unwrap().uri(), "init.mp4");
+
+        let plan = playlist.prefetch_plan(Duration::from_secs(8), 101);
+        assert_eq!(
+            summary(&plan),
+            vec![
+                (102, None, "102.mp4".to_string(), None),
+                (103, Some(0), "103.mp4".to_string(), Some(ByteRange { length: 800, offset: Some(0) })),
+                (103, Some(1), "103.mp4".to_string(), Some(ByteRange { length: 900, offset: Some(800) })),
+            ]
+        );
+        assert_eq!(plan[2].start, Duration::from_secs(11));
+
+        //the gap part counts towards the buffer, so the last part is still needed for 4s
+        let plan = playlist.prefetch_plan(Duration::from_secs(4), 103);
+        assert_eq!(plan.len(), 3);
+        assert_eq!(plan[2].byte_range, Some(ByteRange { length: 700, offset: None }));
+        assert_eq!(plan[2].start, Duration::from_secs(13));
+
+        assert!(playlist.prefetch_plan(Duration::from_secs(4), 99).is_empty());
+        assert!(playlist.prefetch_plan(Duration::from_secs(4), 104).is_empty());
+    }
+}
@@ -0,0 +1,153 @@
+//! Resources a low-latency server is about to publish. See
+//! <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.5.3>.
+
+use core::fmt;
+
+use anyhow::Result;
+
+use crate::attributes::{AttributeList, RawAttributes};
+use crate::SegmentUri;
+
+/// What an EXT-X-PRELOAD-HINT points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PreloadHintType {
+    /// The next partial segment.
+    Part,
+
+    /// The media initialization section of the next segment.
+    Map,
+}
+
+impl PreloadHintType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PreloadHintType::Part => "PART",
+            PreloadHintType::Map => "MAP",
+        }
+    }
+}
+
+/// A resource from an EXT-X-PRELOAD-HINT tag, which clients should request before it's listed so
+/// the server can send it as soon as it's produced.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PreloadHint {
+    hint_type: PreloadHintType,
+    uri: SegmentUri,
+
+    /// Offset of the first byte of the resource at the URI, 0 if the whole resource is hinted.
+    byte_range_start: u64,
+
+    /// Number of bytes from the start, `None` for everything up to the end of the resource, which
+    /// may not be known yet.
+    byte_range_length: Option<u64>,
+
+    /// The attribute list as parsed.
+    raw: RawAttributes,
+}
+
+impl PreloadHint {
+    pub fn new(hint_type: PreloadHintType, uri: impl Into<SegmentUri>) -> Self {
+        Self { hint_type, uri: uri.into(), byte_range_start: 0, byte_range_length: None, raw: RawAttributes::default() }
+    }
+
+    /// Parses the attribute list of an EXT-X-PRELOAD-HINT tag.
+    pub(crate) fn parse(attribute_list: &str) -> Result<Self> {
+        let attributes = AttributeList::parse(attribute_list)?;
+        let hint_type = match attributes.get("TYPE") {
+            Some("PART") => PreloadHintType::Part,
+            Some("MAP") => PreloadHintType::Map,
+            Some(other) => return Err(anyhow::anyhow!("Unknown preload hint type {}", other)),
+            None => return Err(anyhow::Error::msg("Preload hint is missing TYPE attribute")),
+        };
+        let Some(uri) = attributes.quoted_string("URI")? else {
+            return Err(anyhow::Error::msg("Preload hint is missing URI attribute"));
+        };
+        let integer = |name: &str| {
+            attributes
+                .get(name)
+                .map(|x| x.parse::<u64>().map_err(|_| anyhow::anyhow!("Invalid {} {}", name, x)))
+                .transpose()
+        };
+        Ok(Self {
+            hint_type,
+            uri: SegmentUri::new(uri),
+            byte_range_start: integer("BYTERANGE-START")?.unwrap_or_default(),
+            byte_range_length: integer("BYTERANGE-LENGTH")?,
+            raw: RawAttributes::new(attribute_list),
+        })
+    }
+
+    /// Raw value of the attribute `name` as parsed, including ones the crate doesn't model, see
+    /// [`RawAttributes`][crate::RawAttributes].
+    pub fn raw_attribute(&self, name: &str) -> Option<&str> {
+        self.raw.get(name)
+    }
+
+    /// Every attribute of the EXT-X-PRELOAD-HINT tag as parsed.
+    pub fn raw_attributes(&self) -> &RawAttributes {
+        &self.raw
+    }
+
+    pub fn hint_type(&self) -> PreloadHintType {
+        self.hint_type
+    }
+
+    pub fn uri(&self) -> &SegmentUri {
+        &self.uri
+    }
+
+    pub fn byte_range_start(&self) -> u64 {
+        self.byte_range_start
+    }
+
+    pub fn byte_range_length(&self) -> Option<u64> {
+        self.byte_range_length
+    }
+
+    /// Whether the hint covers only part of the resource.
+    pub fn is_sub_range(&self) -> bool {
+        self.byte_range_start > 0 || self.byte_range_length.is_some()
+    }
+
+    pub fn set_uri(&mut self, uri: impl Into<SegmentUri>) {
+        self.uri = uri.into();
+    }
+
+    pub fn set_byte_range(&mut self, start: u64, length: Option<u64>) {
+        self.byte_range_start = start;
+        self.byte_range_length = length;
+    }
+}
+
+impl fmt::Display for PreloadHint {
+    /// Formats the hint as the attribute list of an EXT-X-PRELOAD-HINT tag.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TYPE={},URI=\"{}\"", self.hint_type.as_str(), self.uri)?;
+        if self.byte_range_start > 0 {
+            write!(f, ",BYTERANGE-START={}", self.byte_range_start)?;
+        }
+        if let Some(length) = self.byte_range_length {
+            write!(f, ",BYTERANGE-LENGTH={}", length)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_preload_hints() {
+        let hint = PreloadHint::parse(r#"TYPE=PART,URI="2.1.mp4",BYTERANGE-START=1000"#).unwrap();
+        assert_eq!(hint.hint_type(), PreloadHintType::Part);
+        assert_eq!(hint.uri(), "2.1.mp4");
+        assert_eq!((hint.byte_range_start(), hint.byte_range_length()), (1000, None));
+        assert_eq!(hint.to_string(), r#"TYPE=PART,URI="2.1.mp4",BYTERANGE-START=1000"#);
+
+        let hint = PreloadHint::parse(r#"TYPE=MAP,URI="init.mp4""#).unwrap();
+        assert!(!hint.is_sub_range());
+        assert!(PreloadHint::parse(r#"TYPE=SEGMENT,URI="3.mp4""#).is_err());
+        assert!(PreloadHint::parse(r#"TYPE=PART,URI="3.mp4",BYTERANGE-LENGTH=-1"#).is_err());
+    }
+}
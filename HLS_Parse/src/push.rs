@@ -0,0 +1,150 @@
+//! Parsing a media playlist from chunks of bytes as they arrive, for proxies that rewrite
+//! playlists while the response is still coming in.
+
+use anyhow::Result;
+
+use crate::encoding::{self, UTF8_BOM};
+use crate::media_playlist::Parser;
+use crate::options::Limit;
+use crate::{MediaPlaylist, MediaSegment, ParseOptions};
+
+/// Incremental [`MediaPlaylist`] parser. Chunks may split lines, and even UTF-8 characters,
+/// anywhere. Once [`push`][Self::push] has returned an error, every later call does too.
+#[derive(Debug)]
+pub struct PushParser {
+    parser: Parser,
+
+    /// Bytes of the line that hasn't been completed by a newline yet.
+    buffer: Vec<u8>,
+
+    /// Offset of the start of the buffer in the input, for error messages.
+    offset: usize,
+
+    lines: usize,
+
+    /// Number of segments already returned by `push`.
+    returned: usize,
+
+    failed: bool,
+}
+
+impl PushParser {
+    pub fn new() -> Self {
+        Self::with_options(&ParseOptions::default())
+    }
+
+    pub fn with_options(options: &ParseOptions) -> Self {
+        Self { parser: Parser::new(options), buffer: Vec::new(), offset: 0, lines: 0, returned: 0, failed: false }
+    }
+
+    /// Parses every line completed by `bytes`, returning the segments whose URI was among them,
+    /// in playlist order. Parsing errors are reported as soon as the offending line is complete.
+    pub fn push(&mut self, bytes: &[u8]) -> Result<Vec<MediaSegment>> {
+        if self.failed {
+            return Err(anyhow::Error::msg("Playlist already failed to parse"));
+        }
+        self.buffer.extend_from_slice(bytes);
+        let result = self.complete_lines();
+        self.failed = result.is_err();
+        result
+    }
+
+    /// Parses the last line, if it had no newline, and returns the whole playlist, including the
+    /// segments [`push`][Self::push] already returned.
+    pub fn finish(mut self) -> Result<MediaPlaylist> {
+        if self.failed {
+            return Err(anyhow::Error::msg("Playlist already failed to parse"));
+        }
+        if !self.buffer.is_empty() {
+            let line = core::mem::take(&mut self.buffer);
+            feed(&mut self.parser, &line, self.offset, self.lines)?;
+        }
+        self.parser.finish()
+    }
+
+    fn complete_lines(&mut self) -> Result<Vec<MediaSegment>> {
+        let mut start = 0;
+        while let Some(length) = self.buffer[start..].iter().position(|x| *x == b'\n') {
+            let line = &self.buffer[start..start + length];
+            //like str::lines, a CR before the newline isn't part of the line
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            feed(&mut self.parser, line, self.offset + start, self.lines)?;
+            self.lines += 1;
+            start += length + 1;
+        }
+        self.buffer.drain(..start);
+        self.offset += start;
+        //a line without a newline may be arbitrarily long
+        self.parser.limits().check(Limit::LineLength, self.buffer.len())?;
+
+        let segments = self.parser.segments()[self.returned..].to_vec();
+        self.returned += segments.len();
+        Ok(segments)
+    }
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decodes one line starting at `offset` in the input, after `lines` others, and parses it.
+fn feed(parser: &mut Parser, line: &[u8], offset: usize, lines: usize) -> Result<()> {
+    let (line, offset) = if lines == 0 {
+        encoding::check_byte_order_mark(line)?;
+        let skipped = if line.starts_with(UTF8_BOM) { UTF8_BOM.len() } else { 0 };
+        (&line[skipped..], offset + skipped)
+    } else {
+        (line, offset)
+    };
+    let line = core::str::from_utf8(line).map_err(|error| {
+        anyhow::anyhow!("Invalid UTF-8 at byte offset {} (line {})", offset + error.valid_up_to(), lines + 1)
+    })?;
+    parser.line(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYLIST: &str = "\u{FEFF}#EXTM3U\r\n#EXT-X-TARGETDURATION:10\n#EXTINF:9.5,Ünïcode\nfirst.ts\n#EXTINF:10,\nsecond.ts";
+
+    fn urls(segments: &[MediaSegment]) -> Vec<&str> {
+        segments.iter().map(|x| x.url().as_str()).collect()
+    }
+
+    #[test]
+    fn returns_segments_as_they_complete() {
+        let bytes = PLAYLIST.as_bytes();
+        //split in the middle of the Ü, and of the first URI
+        let title = PLAYLIST.find('Ü').unwrap() + 1;
+        let uri = PLAYLIST.find("first").unwrap() + 3;
+        let mut parser = PushParser::new();
+        assert!(parser.push(&bytes[..title]).unwrap().is_empty());
+        assert!(parser.push(&bytes[title..uri]).unwrap().is_empty());
+        let segments = parser.push(&bytes[uri..]).unwrap();
+        assert_eq!(urls(&segments), vec!["first.ts"]);
+        assert_eq!(segments[0].title(), Some("Ünïcode"));
+
+        //the last line has no newline, so it only counts once the input ends
+        let playlist = parser.finish().unwrap();
+        assert_eq!(urls(playlist.segments()), vec!["first.ts", "second.ts"]);
+        assert_eq!(playlist, MediaPlaylist::parse_ext_m3u_bytes(bytes).unwrap());
+    }
+
+    #[test]
+    fn reports_errors_early() {
+        let mut parser = PushParser::new();
+        parser.push(b"#EXTM3U\n#EXT-X-TARGETDURATION:10\n").unwrap();
+        let error = parser.push(b"#EXTINF:10,\n#EXTINF:10,\nmore").unwrap_err();
+        assert_eq!(error.to_string(), "EXTINF without URI at line 3");
+        assert!(parser.push(b".ts\n").is_err());
+        assert!(parser.finish().is_err());
+
+        let mut parser = PushParser::new();
+        let error = parser.push(b"#EXTM3U\n#EXTINF:10,\xFF\n").unwrap_err();
+        assert_eq!(error.to_string(), "Invalid UTF-8 at byte offset 19 (line 2)");
+        assert!(PushParser::new().push(b"\xFF\xFE#\0E\0\n\0").is_err());
+    }
+}
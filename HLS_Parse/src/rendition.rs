@@ -0,0 +1,343 @@
+//! Alternative renditions of a master playlist. See
+//! <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.4.1>.
+
+use core::fmt;
+
+use anyhow::Result;
+
+use crate::attributes::{AttributeList, AttributeWriter, RawAttributes};
+use crate::{Channels, InstreamId, LanguageTag};
+
+/// The kind of media in a rendition, from the TYPE attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MediaType {
+    Audio,
+    Video,
+    Subtitles,
+    /// Captions carried inside the video, so the rendition has no URI of its own.
+    ClosedCaptions,
+}
+
+/// One rendition from an EXT-X-MEDIA tag. Variants refer to renditions by their group.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rendition {
+    media_type: MediaType,
+
+    /// Media playlist of the rendition. When absent, the rendition is included in the media
+    /// playlist of the variant.
+    uri: Option<String>,
+
+    group_id: String,
+
+    /// Primary language.
+    language: Option<LanguageTag>,
+
+    /// Associated language, e.g. of a spoken rendition of a written one.
+    assoc_language: Option<LanguageTag>,
+
+    /// Human-readable description, unique within the group.
+    name: String,
+
+    /// Whether clients should play this rendition when the user hasn't chosen one.
+    default: bool,
+
+    /// Whether clients may choose this rendition without explicit user preference.
+    autoselect: bool,
+
+    /// Whether subtitles contain content the user should see regardless of preference.
+    forced: bool,
+
+    /// Uniform Type Identifiers describing the rendition, e.g. for accessibility.
+    characteristics: Vec<String>,
+
+    /// Audio channel layout.
+    channels: Option<Channels>,
+
+    /// Caption service within the video, for CLOSED-CAPTIONS renditions.
+    instream_id: Option<InstreamId>,
+
+    /// Identifier of the rendition that stays the same across reloads of the master playlist,
+    /// even if its URI changes.
+    stable_rendition_id: Option<String>,
+
+    /// The attribute list as parsed.
+    raw: RawAttributes,
+}
+
+/// Values of the TYPE attribute.
+const MEDIA_TYPES: &[&str] = &["AUDIO", "VIDEO", "SUBTITLES", "CLOSED-CAPTIONS"];
+
+/// Values of YES/NO attributes.
+const BOOLEANS: &[&str] = &["YES", "NO"];
+
+impl MediaType {
+    /// Value of the TYPE attribute.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MediaType::Audio => "AUDIO",
+            MediaType::Video => "VIDEO",
+            MediaType::Subtitles => "SUBTITLES",
+            MediaType::ClosedCaptions => "CLOSED-CAPTIONS",
+        }
+    }
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Rendition {
+    /// Parses the attribute list of an EXT-X-MEDIA tag.
+    pub(crate) fn parse(attribute_list: &str) -> Result<Self> {
+        let attributes = AttributeList::parse(attribute_list)?;
+        let media_type = match attributes.get("TYPE") {
+            Some("AUDIO") => MediaType::Audio,
+            Some("VIDEO") => MediaType::Video,
+            Some("SUBTITLES") => MediaType::Subtitles,
+            Some("CLOSED-CAPTIONS") => MediaType::ClosedCaptions,
+            Some(other) => return Err(anyhow::anyhow!("Unknown media type {}", other)),
+            None => return Err(anyhow::Error::msg("Media is missing TYPE attribute")),
+        };
+        let Some(group_id) = attributes.quoted_string("GROUP-ID")? else {
+            return Err(anyhow::Error::msg("Media is missing GROUP-ID attribute"));
+        };
+        let Some(name) = attributes.quoted_string("NAME")? else {
+            return Err(anyhow::Error::msg("Media is missing NAME attribute"));
+        };
+        let uri = attributes.quoted_string("URI")?.map(str::to_string);
+        let default = parse_boolean(&attributes, "DEFAULT")?;
+        let autoselect = parse_boolean(&attributes, "AUTOSELECT")?;
+        let forced = parse_boolean(&attributes, "FORCED")?;
+        let channels = match attributes.quoted_string("CHANNELS")? {
+            Some(value) => Some(value.parse::<Channels>()?),
+            None => None,
+        };
+
+        let language = match attributes.quoted_string("LANGUAGE")? {
+            Some(value) => Some(value.parse::<LanguageTag>()?),
+            None => None,
+        };
+        let assoc_language = match attributes.quoted_string("ASSOC-LANGUAGE")? {
+            Some(value) => Some(value.parse::<LanguageTag>()?),
+            None => None,
+        };
+        let characteristics = match attributes.quoted_string("CHARACTERISTICS")? {
+            Some(value) if !value.is_empty() => value.split(',').map(str::to_string).collect(),
+            _ => Vec::new(),
+        };
+        let instream_id = match attributes.quoted_string("INSTREAM-ID")? {
+            Some(value) => Some(value.parse::<InstreamId>()?),
+            None => None,
+        };
+
+        if media_type == MediaType::ClosedCaptions && uri.is_some() {
+            return Err(anyhow::Error::msg("CLOSED-CAPTIONS media must not have a URI"));
+        }
+        match (media_type, instream_id) {
+            (MediaType::ClosedCaptions, None) => {
+                return Err(anyhow::Error::msg("CLOSED-CAPTIONS media is missing INSTREAM-ID attribute"));
+            }
+            (MediaType::Audio | MediaType::Video | MediaType::Subtitles, Some(_)) => {
+                return Err(anyhow::Error::msg("INSTREAM-ID is only allowed for CLOSED-CAPTIONS media"));
+            }
+            _ => {}
+        }
+        if default && attributes.get("AUTOSELECT").is_some() && !autoselect {
+            return Err(anyhow::Error::msg("Media with DEFAULT=YES must have AUTOSELECT=YES"));
+        }
+        if attributes.get("FORCED").is_some() && media_type != MediaType::Subtitles {
+            return Err(anyhow::Error::msg("FORCED is only allowed for SUBTITLES media"));
+        }
+
+        Ok(Self {
+            media_type,
+            uri,
+            group_id: group_id.to_string(),
+            language,
+            assoc_language,
+            name: name.to_string(),
+            default,
+            autoselect,
+            forced,
+            characteristics,
+            channels,
+            instream_id,
+            stable_rendition_id: attributes.stable_id("STABLE-RENDITION-ID")?.map(str::to_string),
+            raw: RawAttributes::new(attribute_list),
+        })
+    }
+
+    pub(crate) fn set_uri(&mut self, uri: Option<String>) {
+        self.uri = uri;
+    }
+
+    /// Raw value of the attribute `name` as parsed, including ones the crate doesn't model, see
+    /// [`RawAttributes`][crate::RawAttributes].
+    pub fn raw_attribute(&self, name: &str) -> Option<&str> {
+        self.raw.get(name)
+    }
+
+    /// Every attribute of the EXT-X-MEDIA tag as parsed.
+    pub fn raw_attributes(&self) -> &RawAttributes {
+        &self.raw
+    }
+
+    pub fn media_type(&self) -> MediaType {
+        self.media_type
+    }
+
+    /// Media playlist of the rendition, `None` if it's part of the variant's own playlist.
+    pub fn uri(&self) -> Option<&str> {
+        self.uri.as_deref()
+    }
+
+    /// Group the rendition belongs to, as referred to by variants.
+    pub fn group_id(&self) -> &str {
+        &self.group_id
+    }
+
+    /// Primary language, e.g. `en-US`.
+    pub fn language(&self) -> Option<&LanguageTag> {
+        self.language.as_ref()
+    }
+
+    /// Associated language, e.g. the spoken language of a sign language video rendition.
+    pub fn assoc_language(&self) -> Option<&LanguageTag> {
+        self.assoc_language.as_ref()
+    }
+
+    /// Human-readable description.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.default
+    }
+
+    pub fn is_autoselect(&self) -> bool {
+        self.autoselect
+    }
+
+    pub fn is_forced(&self) -> bool {
+        self.forced
+    }
+
+    /// Uniform Type Identifiers from CHARACTERISTICS, e.g.
+    /// `public.accessibility.transcribes-spoken-dialog`.
+    pub fn characteristics(&self) -> &[String] {
+        &self.characteristics
+    }
+
+    /// Caption service within the video, set for every CLOSED-CAPTIONS rendition.
+    pub fn instream_id(&self) -> Option<InstreamId> {
+        self.instream_id
+    }
+
+    /// Audio channel layout, if the playlist states it.
+    pub fn channels(&self) -> Option<&Channels> {
+        self.channels.as_ref()
+    }
+
+    /// Identifier from STABLE-RENDITION-ID, for matching the rendition across master playlist
+    /// updates.
+    pub fn stable_rendition_id(&self) -> Option<&str> {
+        self.stable_rendition_id.as_deref()
+    }
+}
+
+impl Rendition {
+    /// The attribute list of the rendition's EXT-X-MEDIA tag.
+    pub(crate) fn attributes(&self) -> AttributeWriter {
+        let mut list = AttributeWriter::default();
+        list.enumerated("TYPE", self.media_type.as_str(), MEDIA_TYPES).quoted("GROUP-ID", &self.group_id);
+        if let Some(language) = &self.language {
+            list.quoted("LANGUAGE", language.as_str());
+        }
+        if let Some(assoc_language) = &self.assoc_language {
+            list.quoted("ASSOC-LANGUAGE", assoc_language.as_str());
+        }
+        list.quoted("NAME", &self.name);
+        for (name, value) in [("DEFAULT", self.default), ("AUTOSELECT", self.autoselect), ("FORCED", self.forced)] {
+            if value {
+                list.enumerated(name, "YES", BOOLEANS);
+            }
+        }
+        if let Some(instream_id) = self.instream_id {
+            let value = instream_id.to_string();
+            list.check(value.parse::<InstreamId>().map(drop)).quoted("INSTREAM-ID", &value);
+        }
+        if !self.characteristics.is_empty() {
+            list.quoted_list("CHARACTERISTICS", &self.characteristics);
+        }
+        if let Some(channels) = &self.channels {
+            list.quoted("CHANNELS", &channels.to_string());
+        }
+        if let Some(stable_rendition_id) = &self.stable_rendition_id {
+            list.quoted("STABLE-RENDITION-ID", stable_rendition_id);
+        }
+        if let Some(uri) = &self.uri {
+            list.quoted("URI", uri);
+        }
+        list
+    }
+}
+
+impl fmt::Display for Rendition {
+    /// Formats the rendition as the attribute list of an EXT-X-MEDIA tag.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.attributes().as_str())
+    }
+}
+
+/// Value of an enumerated YES/NO attribute, `false` when absent.
+fn parse_boolean(attributes: &AttributeList, name: &str) -> Result<bool> {
+    match attributes.get(name) {
+        None | Some("NO") => Ok(false),
+        Some("YES") => Ok(true),
+        Some(other) => Err(anyhow::anyhow!("Attribute {} should be YES or NO, not {}", name, other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_audio_rendition() {
+        let rendition = Rendition::parse(
+            r#"TYPE=AUDIO,GROUP-ID="aac",LANGUAGE="en",NAME="English",DEFAULT=YES,AUTOSELECT=YES,URI="audio/en.m3u8""#,
+        )
+        .expect("should parse");
+        assert_eq!(rendition.media_type(), MediaType::Audio);
+        assert_eq!(rendition.group_id(), "aac");
+        assert_eq!(rendition.name(), "English");
+        assert_eq!(rendition.language().map(LanguageTag::as_str), Some("en"));
+        assert_eq!(rendition.uri(), Some("audio/en.m3u8"));
+        assert!(rendition.is_default());
+        assert!(rendition.is_autoselect());
+        assert!(!rendition.is_forced());
+    }
+
+    #[test]
+    fn validates_attributes() {
+        assert!(Rendition::parse(r#"TYPE=CLOSED-CAPTIONS,GROUP-ID="cc",NAME="English",INSTREAM-ID="CC1""#).is_ok());
+        assert!(Rendition::parse(r#"TYPE=CLOSED-CAPTIONS,GROUP-ID="cc",NAME="English""#).is_err());
+        assert!(
+            Rendition::parse(r#"TYPE=CLOSED-CAPTIONS,GROUP-ID="cc",NAME="English",INSTREAM-ID="CC1",URI="cc.m3u8""#)
+                .is_err()
+        );
+        assert!(Rendition::parse(r#"TYPE=SUBTITLES,GROUP-ID="subs",NAME="English",INSTREAM-ID="CC1""#).is_err());
+        assert!(Rendition::parse(r#"TYPE=AUDIO,NAME="English""#).is_err());
+        assert!(Rendition::parse(r#"TYPE=AUDIO,GROUP-ID="aac""#).is_err());
+        assert!(Rendition::parse(r#"TYPE=DATA,GROUP-ID="aac",NAME="English""#).is_err());
+        assert!(Rendition::parse(r#"TYPE=AUDIO,GROUP-ID="aac",NAME="English",DEFAULT=YES,AUTOSELECT=NO"#).is_err());
+        assert!(Rendition::parse(r#"TYPE=AUDIO,GROUP-ID="aac",NAME="English",FORCED=NO"#).is_err());
+        assert!(Rendition::parse(r#"TYPE=AUDIO,GROUP-ID="aac",NAME="English",DEFAULT=maybe"#).is_err());
+        assert!(Rendition::parse(r#"TYPE=AUDIO,GROUP-ID="aac",NAME="English",LANGUAGE="en_US""#).is_err());
+        assert!(Rendition::parse(r#"TYPE=AUDIO,GROUP-ID="aac",NAME="English",STABLE-RENDITION-ID=en"#).is_err());
+        assert!(Rendition::parse(r#"TYPE=AUDIO,GROUP-ID="aac",NAME="English",STABLE-RENDITION-ID="en!""#).is_err());
+    }
+}
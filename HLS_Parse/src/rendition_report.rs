@@ -0,0 +1,102 @@
+//! How far other renditions of a low-latency stream have got, so clients switching to or
+//! following them can skip a reload. See
+//! <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.5.4>.
+
+use core::fmt;
+
+use anyhow::Result;
+
+use crate::attributes::{AttributeList, RawAttributes};
+
+/// The last segment and part of another media playlist, from an EXT-X-RENDITION-REPORT tag.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RenditionReport {
+    /// URI of the other media playlist, relative to this one.
+    uri: String,
+
+    /// Media sequence number of its last segment.
+    last_msn: Option<u64>,
+
+    /// Index of its last part within the segment being produced.
+    last_part: Option<u64>,
+
+    /// The attribute list as parsed.
+    raw: RawAttributes,
+}
+
+impl RenditionReport {
+    pub fn new(uri: impl Into<String>, last_msn: Option<u64>, last_part: Option<u64>) -> Self {
+        Self { uri: uri.into(), last_msn, last_part, raw: RawAttributes::default() }
+    }
+
+    /// Parses the attribute list of an EXT-X-RENDITION-REPORT tag.
+    pub(crate) fn parse(attribute_list: &str) -> Result<Self> {
+        let attributes = AttributeList::parse(attribute_list)?;
+        let Some(uri) = attributes.quoted_string("URI")? else {
+            return Err(anyhow::Error::msg("Rendition report is missing URI attribute"));
+        };
+        let integer = |name: &str| {
+            attributes
+                .get(name)
+                .map(|x| x.parse::<u64>().map_err(|_| anyhow::anyhow!("Invalid {} {}", name, x)))
+                .transpose()
+        };
+        let (last_msn, last_part) = (integer("LAST-MSN")?, integer("LAST-PART")?);
+        Ok(Self { uri: uri.to_string(), last_msn, last_part, raw: RawAttributes::new(attribute_list) })
+    }
+
+    /// Raw value of the attribute `name` as parsed, including ones the crate doesn't model, see
+    /// [`RawAttributes`][crate::RawAttributes].
+    pub fn raw_attribute(&self, name: &str) -> Option<&str> {
+        self.raw.get(name)
+    }
+
+    /// Every attribute of the EXT-X-RENDITION-REPORT tag as parsed.
+    pub fn raw_attributes(&self) -> &RawAttributes {
+        &self.raw
+    }
+
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    pub fn last_msn(&self) -> Option<u64> {
+        self.last_msn
+    }
+
+    pub fn last_part(&self) -> Option<u64> {
+        self.last_part
+    }
+
+    pub fn set_uri(&mut self, uri: impl Into<String>) {
+        self.uri = uri.into();
+    }
+}
+
+impl fmt::Display for RenditionReport {
+    /// Formats the report as the attribute list of an EXT-X-RENDITION-REPORT tag.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "URI=\"{}\"", self.uri)?;
+        if let Some(last_msn) = self.last_msn {
+            write!(f, ",LAST-MSN={}", last_msn)?;
+        }
+        if let Some(last_part) = self.last_part {
+            write!(f, ",LAST-PART={}", last_part)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rendition_reports() {
+        let report = RenditionReport::parse(r#"URI="../audio/en.m3u8",LAST-MSN=273,LAST-PART=2"#).unwrap();
+        assert_eq!((report.uri(), report.last_msn(), report.last_part()), ("../audio/en.m3u8", Some(273), Some(2)));
+        assert_eq!(report.to_string(), r#"URI="../audio/en.m3u8",LAST-MSN=273,LAST-PART=2"#);
+        assert!(RenditionReport::parse("LAST-MSN=273").is_err());
+        assert!(RenditionReport::parse(r#"URI="en.m3u8",LAST-MSN=next"#).is_err());
+    }
+}
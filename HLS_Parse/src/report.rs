@@ -0,0 +1,208 @@
+//! A flattened summary of a media playlist for machine consumption, e.g. for CI pipelines to
+//! assert on manifest properties. With the `serde` feature every type here is
+//! [`Serialize`][serde::Serialize], so the report can be written as JSON.
+//!
+//! Durations are in seconds, and the state carried over from earlier tags (keys, maps, implicit
+//! byte range offsets) is resolved for each segment.
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::diagnostics::Diagnostic;
+use crate::prefetch::resolved_part_ranges;
+use crate::{ByteRange, EncryptionKey, MediaPlaylist, PartialSegment, SegmentMap, SegmentUri};
+
+/// Returned by [`MediaPlaylist::to_report`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PlaylistReport {
+    /// Compatibility version, `0` if the playlist has no version tag.
+    pub version: u64,
+    pub target_duration: f64,
+    pub part_target: Option<f64>,
+    pub media_sequence: u64,
+    pub discontinuity_sequence: u64,
+    pub ended: bool,
+    pub i_frames_only: bool,
+
+    /// Sum of the segment durations.
+    pub duration: f64,
+
+    pub segments: Vec<SegmentReport>,
+
+    /// Parts of the segment being produced, see [`MediaPlaylist::trailing_parts`].
+    pub trailing_parts: Vec<PartReport>,
+
+    /// See [`MediaPlaylist::diagnostics`].
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SegmentReport {
+    pub sequence: u64,
+    pub discontinuity_sequence: u64,
+    pub url: String,
+    pub title: Option<String>,
+    pub duration: f64,
+
+    /// Time from the start of the first listed segment.
+    pub start: f64,
+
+    /// With an implicit offset filled in where it can be.
+    pub byte_range: Option<ByteRange>,
+
+    /// Explicit or carried forward, as written in EXT-X-PROGRAM-DATE-TIME.
+    pub program_date_time: Option<String>,
+
+    pub discontinuity: bool,
+    pub gap: bool,
+    pub map: Option<MapReport>,
+    pub keys: Vec<KeyReport>,
+    pub parts: Vec<PartReport>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct MapReport {
+    pub uri: String,
+    pub byte_range: Option<ByteRange>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct KeyReport {
+    pub method: String,
+    pub uri: Option<String>,
+    pub key_format: String,
+    pub iv: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PartReport {
+    pub uri: String,
+    pub duration: f64,
+
+    /// With an implicit offset filled in where it can be.
+    pub byte_range: Option<ByteRange>,
+
+    pub independent: bool,
+    pub gap: bool,
+}
+
+impl MediaPlaylist {
+    /// Summarizes the playlist, with every URL resolved against `base_url` if given, usually the
+    /// URL the playlist was fetched from.
+    pub fn to_report(&self, base_url: Option<&str>) -> PlaylistReport {
+        let resolve = |uri: &str| match base_url {
+            Some(base_url) => SegmentUri::new(uri).resolve(base_url).into_string(),
+            None => uri.to_string(),
+        };
+        let segments = self
+            .iter_segments()
+            .map(|context| SegmentReport {
+                sequence: context.sequence,
+                discontinuity_sequence: context.discontinuity_sequence,
+                url: resolve(context.segment.url().as_str()),
+                title: context.segment.title().map(str::to_string),
+                duration: context.segment.duration().as_secs_f64(),
+                start: context.start.as_secs_f64(),
+                byte_range: context.byte_range,
+                program_date_time: context.program_date_time.map(|x| x.to_string()),
+                discontinuity: context.segment.discontinuity(),
+                gap: context.segment.gap(),
+                map: context.map.map(|x| map_report(x, &resolve)),
+                keys: context.keys.iter().map(|x| key_report(x, &resolve)).collect(),
+                parts: part_reports(context.segment.parts(), &resolve),
+            })
+            .collect();
+        PlaylistReport {
+            version: self.version(),
+            target_duration: self.target_duration().as_secs_f64(),
+            part_target: self.part_target().map(|x| x.as_secs_f64()),
+            media_sequence: self.media_sequence(),
+            discontinuity_sequence: self.discontinuity_sequence(),
+            ended: self.ended(),
+            i_frames_only: self.i_frames_only(),
+            duration: self.segments().iter().map(|x| x.duration().as_secs_f64()).sum(),
+            segments,
+            trailing_parts: part_reports(self.trailing_parts(), &resolve),
+            diagnostics: self.diagnostics(),
+        }
+    }
+}
+
+fn map_report(map: &SegmentMap, resolve: &dyn Fn(&str) -> String) -> MapReport {
+    MapReport { uri: resolve(map.uri()), byte_range: map.byte_range() }
+}
+
+fn key_report(key: &EncryptionKey, resolve: &dyn Fn(&str) -> String) -> KeyReport {
+    KeyReport {
+        method: key.method().as_str().to_string(),
+        //key URIs like skd:// are absolute, so they stay as they are
+        uri: key.uri().map(resolve),
+        key_format: key.key_format().to_string(),
+        iv: key.iv().map(str::to_string),
+    }
+}
+
+fn part_reports(parts: &[PartialSegment], resolve: &dyn Fn(&str) -> String) -> Vec<PartReport> {
+    parts
+        .iter()
+        .zip(resolved_part_ranges(parts))
+        .map(|(part, byte_range)| PartReport {
+            uri: resolve(part.uri().as_str()),
+            duration: part.duration().as_secs_f64(),
+            byte_range,
+            independent: part.independent(),
+            gap: part.gap(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYLIST: &str = indoc::indoc! {r#"
+        #EXTM3U
+        #EXT-X-VERSION:6
+        #EXT-X-TARGETDURATION:4
+        #EXT-X-MAP:URI="init.mp4"
+        #EXT-X-KEY:METHOD=AES-128,URI="https://keys.example.com/1.key",IV=0x01
+        #EXT-X-PROGRAM-DATE-TIME:2024-03-01T12:00:00.000Z
+        #EXTINF:4,
+        #EXT-X-BYTERANGE:1000@0
+        main.mp4
+        #EXTINF:4,
+        #EXT-X-BYTERANGE:1000
+        main.mp4
+    "#};
+
+    #[test]
+    fn reports_resolved_segments() {
+        let playlist = MediaPlaylist::parse_ext_m3u(PLAYLIST).unwrap();
+        let report = playlist.to_report(Some("https://cdn.example.com/live/index.m3u8"));
+        assert_eq!(report.duration, 8.0);
+        assert!(report.diagnostics.is_empty());
+        let segment = &report.segments[1];
+        assert_eq!(segment.url, "https://cdn.example.com/live/main.mp4");
+        assert_eq!(segment.start, 4.0);
+        assert_eq!(segment.byte_range, Some(ByteRange { length: 1000, offset: Some(1000) }));
+        assert_eq!(segment.program_date_time.as_deref(), Some("2024-03-01T12:00:04.000Z"));
+        assert_eq!(segment.map.as_ref().map(|x| x.uri.as_str()), Some("https://cdn.example.com/live/init.mp4"));
+        assert_eq!(segment.keys[0].uri.as_deref(), Some("https://keys.example.com/1.key"));
+        assert_eq!(playlist.to_report(None).segments[0].url, "main.mp4");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializes_to_json() {
+        let report = MediaPlaylist::parse_ext_m3u(PLAYLIST).unwrap().to_report(None);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["segments"][1]["byte_range"], serde_json::json!({ "length": 1000, "offset": 1000 }));
+        assert_eq!(json["segments"][0]["keys"][0]["method"], "AES-128");
+        assert_eq!(json["diagnostics"], serde_json::json!([]));
+    }
+}
@@ -0,0 +1,298 @@
+//! The tags preceding each segment in the order they appeared, so tools can insert or remove tags
+//! around a segment and write the playlist back with everything else where it was.
+
+use crate::events::Event;
+use crate::source;
+use crate::{ByteRange, DateRange, EncryptionKey, MediaSegment, PartialSegment, ProgramDateTime, SegmentMap};
+
+/// A tag preceding a segment, from [`MediaSegment::tags`]. EXTINF isn't one, since it always
+/// comes last.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SegmentTag {
+    Discontinuity,
+    Key(EncryptionKey),
+    Map(SegmentMap),
+    ProgramDateTime(ProgramDateTime),
+    DateRange(DateRange),
+    ByteRange(ByteRange),
+    Gap,
+    Part(PartialSegment),
+
+    /// A tag the model doesn't cover, as the whole line, e.g. `#EXT-X-CUE-OUT:30`.
+    Custom(String),
+}
+
+/// Where a segment's tag goes, with the values of modeled tags left to the segment's fields so
+/// changes through its setters still apply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum TagSlot {
+    Discontinuity,
+    Key,
+    Map,
+    ProgramDateTime,
+    DateRange,
+    ByteRange,
+    Gap,
+    Part,
+    Custom(String),
+}
+
+/// Order of tags where a segment's tags don't place them, e.g. for segments built in code.
+static DEFAULT_ORDER: [TagSlot; 8] = [
+    TagSlot::Discontinuity,
+    TagSlot::Key,
+    TagSlot::Map,
+    TagSlot::Part,
+    TagSlot::ProgramDateTime,
+    TagSlot::DateRange,
+    TagSlot::ByteRange,
+    TagSlot::Gap,
+];
+
+impl TagSlot {
+    /// The slot of a segment tag, with `line` kept for those the model doesn't cover.
+    pub(crate) fn from_event(event: &Event<'_>, line: &str) -> Option<Self> {
+        Some(match event {
+            Event::Discontinuity => TagSlot::Discontinuity,
+            Event::Key(_) => TagSlot::Key,
+            Event::Map(_) => TagSlot::Map,
+            Event::ProgramDateTime(_) => TagSlot::ProgramDateTime,
+            Event::DateRange(_) => TagSlot::DateRange,
+            Event::ByteRange(_) => TagSlot::ByteRange,
+            Event::Gap => TagSlot::Gap,
+            Event::Part(_) => TagSlot::Part,
+            Event::Unknown { .. } => TagSlot::Custom(line.to_string()),
+            _ => return None,
+        })
+    }
+}
+
+/// Slots of a segment's tags. Where modeled tags were isn't part of the model, so segments
+/// compare equal whatever their order, but custom tags only live here and compare in order.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TagOrder(pub(crate) Vec<TagSlot>);
+
+impl PartialEq for TagOrder {
+    fn eq(&self, other: &Self) -> bool {
+        custom_lines(&self.0).eq(custom_lines(&other.0))
+    }
+}
+
+/// The lines of the custom tags among `slots`, in order.
+pub(crate) fn custom_lines(slots: &[TagSlot]) -> impl Iterator<Item = &str> {
+    slots.iter().filter_map(|x| match x {
+        TagSlot::Custom(line) => Some(line.as_str()),
+        _ => None,
+    })
+}
+
+impl Eq for TagOrder {}
+
+impl MediaSegment {
+    /// The tags preceding the segment, in the order they were parsed or last set with
+    /// [`set_tags`][Self::set_tags], followed by any others the segment needs in the default
+    /// order. EXT-X-KEY and EXT-X-MAP entries are the keys and map in effect, which an earlier
+    /// segment's tags may have set, and are only written where they change.
+    pub fn tags(&self) -> Vec<SegmentTag> {
+        let (mut keys, mut parts) = (self.keys().iter(), self.parts().iter());
+        let mut date_ranges = self.date_ranges().iter();
+        let mut tags = Vec::new();
+        for (slot, rest) in self.tag_slots() {
+            let count = if rest { usize::MAX } else { 1 };
+            match slot {
+                TagSlot::Discontinuity => tags.extend(self.discontinuity().then_some(SegmentTag::Discontinuity)),
+                TagSlot::Key => tags.extend(keys.by_ref().take(count).cloned().map(SegmentTag::Key)),
+                TagSlot::Map => tags.extend(self.map().cloned().map(SegmentTag::Map)),
+                TagSlot::ProgramDateTime => tags.extend(self.program_date_time().map(SegmentTag::ProgramDateTime)),
+                TagSlot::DateRange => {
+                    tags.extend(date_ranges.by_ref().take(count).cloned().map(SegmentTag::DateRange));
+                }
+                TagSlot::ByteRange => tags.extend(self.byte_range().map(SegmentTag::ByteRange)),
+                TagSlot::Gap => tags.extend(self.gap().then_some(SegmentTag::Gap)),
+                TagSlot::Part => tags.extend(parts.by_ref().take(count).cloned().map(SegmentTag::Part)),
+                TagSlot::Custom(line) => tags.push(SegmentTag::Custom(line.clone())),
+            }
+        }
+        tags
+    }
+
+    /// Replaces the segment's tags, setting its discontinuity, program date time, date ranges,
+    /// byte range, gap and parts from them. Keys and maps carry over to the following segments,
+    /// so EXT-X-KEY and EXT-X-MAP entries update the segment's keys and map, but leaving them
+    /// out doesn't remove any; use [`set_keys`][Self::set_keys] and [`set_map`][Self::set_map]
+    /// for that, and to have later segments use the new ones too.
+    pub fn set_tags(&mut self, tags: Vec<SegmentTag>) {
+        let mut keys = self.keys().to_vec();
+        let (mut map, mut program_date_time, mut byte_range) = (self.map().cloned(), None, None);
+        let (mut discontinuity, mut gap, mut parts, mut date_ranges) = (false, false, Vec::new(), Vec::new());
+        let mut order = Vec::with_capacity(tags.len());
+        for tag in tags {
+            order.push(match tag {
+                SegmentTag::Discontinuity => {
+                    discontinuity = true;
+                    TagSlot::Discontinuity
+                }
+                SegmentTag::Key(key) => {
+                    key.apply_to(&mut keys);
+                    TagSlot::Key
+                }
+                SegmentTag::Map(value) => {
+                    map = Some(value);
+                    TagSlot::Map
+                }
+                SegmentTag::ProgramDateTime(value) => {
+                    program_date_time = Some(value);
+                    TagSlot::ProgramDateTime
+                }
+                SegmentTag::DateRange(date_range) => {
+                    date_ranges.push(date_range);
+                    TagSlot::DateRange
+                }
+                SegmentTag::ByteRange(value) => {
+                    byte_range = Some(value);
+                    TagSlot::ByteRange
+                }
+                SegmentTag::Gap => {
+                    gap = true;
+                    TagSlot::Gap
+                }
+                SegmentTag::Part(part) => {
+                    parts.push(part);
+                    TagSlot::Part
+                }
+                SegmentTag::Custom(line) => TagSlot::Custom(line),
+            });
+        }
+        self.set_keys(keys);
+        self.set_map(map);
+        self.set_program_date_time(program_date_time);
+        self.set_byte_range(byte_range);
+        self.set_discontinuity(discontinuity);
+        self.set_gap(gap);
+        self.set_parts(parts);
+        self.set_date_ranges(date_ranges);
+        self.set_tag_order(order);
+    }
+
+    /// Replaces the values of `URI` and `*-URI` attributes in the custom tags, for
+    /// [`MediaPlaylist::anonymize`][crate::MediaPlaylist::anonymize].
+    pub(crate) fn anonymize_custom_tags(&mut self, map: &mut dyn FnMut(&str) -> String) {
+        let order = self
+            .tag_order()
+            .iter()
+            .map(|slot| match slot {
+                TagSlot::Custom(line) => TagSlot::Custom(source::anonymize_line(line, map)),
+                slot => slot.clone(),
+            })
+            .collect();
+        self.set_tag_order(order);
+    }
+
+    /// The slots of the segment's tags, with those of the default order it has none for where
+    /// that order puts them, each with whether it stands for all the remaining tags of its kind.
+    /// Only EXT-X-KEY, EXT-X-PART, EXT-X-DATERANGE and custom tags may repeat.
+    pub(crate) fn tag_slots(&self) -> Vec<(&TagSlot, bool)> {
+        let rank = |slot: &TagSlot| DEFAULT_ORDER.iter().position(|x| x == slot);
+        let repeats =
+            |slot: &TagSlot| matches!(slot, TagSlot::Key | TagSlot::Part | TagSlot::DateRange | TagSlot::Custom(_));
+        let mut slots: Vec<(&TagSlot, bool)> = Vec::new();
+        for slot in self.tag_order() {
+            if repeats(slot) || !slots.iter().any(|(x, _)| *x == slot) {
+                slots.push((slot, false));
+            }
+        }
+        for (default_rank, slot) in DEFAULT_ORDER.iter().enumerate() {
+            let position = match slots.iter().rposition(|(x, _)| *x == slot) {
+                Some(_) if !repeats(slot) => continue,
+                //the rest of a repeated tag follows its last one
+                Some(last) => last + 1,
+                None => {
+                    let later = slots.iter().position(|(x, _)| rank(x).is_some_and(|x| x > default_rank));
+                    later.unwrap_or(slots.len())
+                }
+            };
+            slots.insert(position, (slot, true));
+        }
+        slots
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{MediaPlaylist, ParseOptions, SegmentTag, WriteOptions};
+
+    #[test]
+    fn keeps_tag_order() {
+        let mut playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-VERSION:6
+            #EXT-X-TARGETDURATION:6
+            #EXT-X-PROGRAM-DATE-TIME:2024-01-01T00:00:00Z
+            #EXT-X-MAP:URI="init.mp4"
+            #EXTINF:6,
+            0.mp4
+            #EXT-X-CUE-OUT:12
+            #EXT-X-DATERANGE:ID="ad",START-DATE="2024-01-01T00:00:06Z"
+            #EXT-X-KEY:METHOD=AES-128,URI="1.key"
+            #EXTINF:6,
+            1.mp4
+            #EXTINF:6,
+            2.mp4
+        "#})
+        .unwrap();
+        //the map carries over from the first segment
+        let tags = playlist.segments()[1].tags();
+        assert!(matches!(
+            tags.as_slice(),
+            [SegmentTag::Custom(cue), SegmentTag::Map(_), SegmentTag::DateRange(_), SegmentTag::Key(_)]
+                if cue == "#EXT-X-CUE-OUT:12"
+        ));
+        let written = playlist.to_string();
+        assert!(written.contains("#EXT-X-PROGRAM-DATE-TIME:2024-01-01T00:00:00.000Z\n#EXT-X-MAP:URI=\"init.mp4\"\n"));
+        assert!(written.contains("0.mp4\n#EXT-X-CUE-OUT:12\n#EXT-X-DATERANGE:"));
+
+        let mut tags = playlist.segments()[2].tags();
+        tags.insert(0, SegmentTag::Custom("#EXT-X-CUE-IN".to_string()));
+        tags.push(SegmentTag::Discontinuity);
+        playlist.segments_mut()[2].set_tags(tags);
+        assert!(playlist.segments()[2].discontinuity());
+        assert!(playlist.to_string().ends_with("1.mp4\n#EXT-X-CUE-IN\n#EXT-X-DISCONTINUITY\n#EXTINF:6,\n2.mp4\n"));
+
+        let mut tags = playlist.segments()[1].tags();
+        tags.retain(|x| !matches!(x, SegmentTag::Custom(_) | SegmentTag::DateRange(_)));
+        playlist.segments_mut()[1].set_tags(tags);
+        assert!(playlist.segments()[1].date_ranges().is_empty());
+        assert!(playlist.to_string().contains("0.mp4\n#EXT-X-KEY:METHOD=AES-128,URI=\"1.key\"\n#EXTINF:6,\n1.mp4"));
+
+        let source = indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-TARGETDURATION:6
+            #EXTINF:6,
+            0.ts
+            #EXT-X-CUE-OUT:6
+            #EXTINF:6,
+            1.ts
+            #EXT-X-CUE-IN
+            #EXT-X-ENDLIST
+        "};
+        let options = ParseOptions { preserve_source: true, ..ParseOptions::default() };
+        let mut preserved = MediaPlaylist::parse_with_options(source, &options).unwrap();
+        assert_eq!(preserved.write(&WriteOptions::default()), source);
+        preserved.segments_mut()[1].set_gap(true);
+        let expected = "0.ts\n#EXT-X-CUE-OUT:6\n#EXT-X-GAP\n#EXTINF:6,\n1.ts\n#EXT-X-CUE-IN\n#EXT-X-ENDLIST\n";
+        assert!(preserved.write(&WriteOptions::default()).ends_with(expected));
+
+        //where modeled tags go doesn't change a segment, which custom tags it has and their order do
+        let with_tags = |tags: &[SegmentTag]| {
+            let mut segment = preserved.segments()[0].clone();
+            segment.set_tags(tags.to_vec());
+            segment
+        };
+        let cue_out = SegmentTag::Custom("#EXT-X-CUE-OUT:6".into());
+        let cue_in = SegmentTag::Custom("#EXT-X-CUE-IN".into());
+        let segment = with_tags(&[cue_out.clone(), cue_in.clone(), SegmentTag::Discontinuity]);
+        assert_eq!(segment, with_tags(&[SegmentTag::Discontinuity, cue_out.clone(), cue_in.clone()]));
+        assert_ne!(segment, with_tags(&[cue_in.clone(), cue_out.clone(), SegmentTag::Discontinuity]));
+        assert_ne!(segment, with_tags(&[cue_out, SegmentTag::Discontinuity]));
+    }
+}
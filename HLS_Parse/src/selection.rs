@@ -0,0 +1,149 @@
+//! Resolving a variant to the renditions a player would actually play with it. See
+//! <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.4.1.1>.
+
+use crate::{LanguageTag, MasterPlaylist, MediaType, Rendition, VariantStream};
+
+/// What the user asked for, for [`MasterPlaylist::resolve`]. The default plays the DEFAULT
+/// renditions with no subtitles or captions beyond forced ones.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SelectionPreferences {
+    /// Audio languages, most preferred first.
+    pub audio_languages: Vec<LanguageTag>,
+
+    /// Subtitle and caption languages, most preferred first. Subtitles are only shown in one of
+    /// these languages, or if forced.
+    pub subtitle_languages: Vec<LanguageTag>,
+
+    /// Whether to show closed captions carried in the video.
+    pub closed_captions: bool,
+}
+
+/// A variant with the renditions chosen from each of its groups, from
+/// [`MasterPlaylist::resolve`]. `None` where the variant has no group of that type, or nothing
+/// in it should be shown.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedSelection<'a> {
+    pub variant: &'a VariantStream,
+    pub video: Option<&'a Rendition>,
+    pub audio: Option<&'a Rendition>,
+    pub subtitles: Option<&'a Rendition>,
+    pub closed_captions: Option<&'a Rendition>,
+}
+
+impl ResolvedSelection<'_> {
+    /// URIs of every media playlist to load: the variant's, then those of the chosen renditions.
+    /// Renditions without a URI are carried in the variant's own media, so they add nothing.
+    pub fn uris(&self) -> Vec<&str> {
+        let renditions = [self.video, self.audio, self.subtitles, self.closed_captions];
+        let rendition_uris = renditions.into_iter().flatten().filter_map(Rendition::uri);
+        core::iter::once(self.variant.uri()).chain(rendition_uris).collect()
+    }
+}
+
+impl MasterPlaylist {
+    /// Chooses a rendition from each group `variant` refers to, the way a player would:
+    ///
+    /// - Video and audio: the first rendition in the most preferred language which has one, else
+    ///   the DEFAULT rendition, else the first with AUTOSELECT, else the first.
+    /// - Subtitles: the first in the most preferred subtitle language which has one, else a
+    ///   DEFAULT rendition, else a FORCED rendition in the language of the chosen audio.
+    /// - Closed captions: only if asked for, chosen like video and audio but by subtitle language.
+    pub fn resolve<'a>(
+        &'a self,
+        variant: &'a VariantStream,
+        preferences: &SelectionPreferences,
+    ) -> ResolvedSelection<'a> {
+        let group = |media_type: MediaType, group_id: Option<&str>| match group_id {
+            Some(group_id) => self.rendition_group(media_type, group_id),
+            None => Vec::new(),
+        };
+
+        let video = choose(&group(MediaType::Video, variant.video()), &preferences.audio_languages);
+        let audio = choose(&group(MediaType::Audio, variant.audio()), &preferences.audio_languages);
+
+        let subtitle_group = group(MediaType::Subtitles, variant.subtitles());
+        let subtitles = in_language(&subtitle_group, &preferences.subtitle_languages)
+            .or_else(|| subtitle_group.iter().copied().find(|x| x.is_default()))
+            .or_else(|| {
+                let language = audio.and_then(Rendition::language)?.primary_language();
+                let same_language = |x: &LanguageTag| x.primary_language().eq_ignore_ascii_case(language);
+                subtitle_group.iter().copied().find(|x| x.is_forced() && x.language().is_some_and(same_language))
+            });
+
+        let closed_captions = if preferences.closed_captions {
+            choose(&self.caption_services(variant), &preferences.subtitle_languages)
+        } else {
+            None
+        };
+        ResolvedSelection { variant, video, audio, subtitles, closed_captions }
+    }
+}
+
+/// The rendition to play from a group when one has to be chosen.
+fn choose<'a>(group: &[&'a Rendition], languages: &[LanguageTag]) -> Option<&'a Rendition> {
+    in_language(group, languages)
+        .or_else(|| group.iter().copied().find(|x| x.is_default()))
+        .or_else(|| group.iter().copied().find(|x| x.is_autoselect()))
+        .or_else(|| group.first().copied())
+}
+
+/// The first rendition in the first of `languages` which any rendition is in.
+fn in_language<'a>(group: &[&'a Rendition], languages: &[LanguageTag]) -> Option<&'a Rendition> {
+    languages.iter().find_map(|language| {
+        //forced subtitles only cover parts of the audio, so they're a last resort
+        let matching = |x: &&Rendition| x.language().is_some_and(|x| x.matches(language));
+        let unforced = group.iter().copied().filter(|x| !x.is_forced()).find(matching);
+        unforced.or_else(|| group.iter().copied().find(matching))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYLIST: &str = indoc::indoc! {r#"
+        #EXTM3U
+        #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",LANGUAGE="en",NAME="English",DEFAULT=YES,AUTOSELECT=YES,URI="en.m3u8"
+        #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",LANGUAGE="fr-CA",NAME="Français",AUTOSELECT=YES,URI="fr.m3u8"
+        #EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID="subs",LANGUAGE="en",NAME="English",AUTOSELECT=YES,URI="en.vtt.m3u8"
+        #EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID="subs",LANGUAGE="fr",NAME="Français (forcé)",FORCED=YES,URI="fr-forced.m3u8"
+        #EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID="subs",LANGUAGE="fr",NAME="Français",URI="fr.vtt.m3u8"
+        #EXT-X-MEDIA:TYPE=CLOSED-CAPTIONS,GROUP-ID="cc",LANGUAGE="en",NAME="English",INSTREAM-ID="CC1"
+        #EXT-X-STREAM-INF:BANDWIDTH=1280000,AUDIO="aac",SUBTITLES="subs",CLOSED-CAPTIONS="cc"
+        video.m3u8
+    "#};
+
+    fn languages(tags: &[&str]) -> Vec<LanguageTag> {
+        tags.iter().map(|x| x.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn resolves_renditions() {
+        let playlist = MasterPlaylist::parse_ext_m3u(PLAYLIST).unwrap();
+        let variant = &playlist.variants()[0];
+
+        let selection = playlist.resolve(variant, &SelectionPreferences::default());
+        assert_eq!(selection.uris(), vec!["video.m3u8", "en.m3u8"]);
+        assert_eq!(selection.video, None);
+
+        //French audio brings in the forced French subtitles
+        let preferences = SelectionPreferences { audio_languages: languages(&["de", "fr"]), ..Default::default() };
+        let selection = playlist.resolve(variant, &preferences);
+        assert_eq!(selection.uris(), vec!["video.m3u8", "fr.m3u8", "fr-forced.m3u8"]);
+
+        let preferences = SelectionPreferences {
+            audio_languages: languages(&["fr"]),
+            subtitle_languages: languages(&["fr"]),
+            closed_captions: true,
+        };
+        let selection = playlist.resolve(variant, &preferences);
+        assert_eq!(selection.uris(), vec!["video.m3u8", "fr.m3u8", "fr.vtt.m3u8"]);
+        assert_eq!(selection.closed_captions.map(Rendition::name), Some("English"));
+
+        //audio muxed into the variant's segments has no playlist of its own to load
+        let muxed = MasterPlaylist::parse_ext_m3u(&PLAYLIST.replace(r#",URI="en.m3u8""#, "")).unwrap();
+        let selection = muxed.resolve(&muxed.variants()[0], &SelectionPreferences::default());
+        assert_eq!(selection.audio.map(Rendition::name), Some("English"));
+        assert_eq!(selection.uris(), vec!["video.m3u8"]);
+    }
+}
@@ -0,0 +1,194 @@
+//! Where live playback should start, from the hold-back of EXT-X-SERVER-CONTROL. See
+//! <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.3.8> and
+//! <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-6.3.3>.
+
+use core::time::Duration;
+
+use anyhow::Result;
+
+use crate::attributes::AttributeList;
+use crate::{MediaPlaylist, SegmentDuration};
+
+/// Attributes of an EXT-X-SERVER-CONTROL tag. The parser doesn't model the tag, so it reaches
+/// [`TagHandler`][crate::TagHandler]s, which can read it with [`ServerControl::parse`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ServerControl {
+    /// How far from the end of the playlist clients may request delta updates.
+    pub can_skip_until: Option<Duration>,
+    pub can_skip_dateranges: bool,
+
+    /// How far from the end of the playlist clients should start, three target durations if
+    /// absent.
+    pub hold_back: Option<Duration>,
+
+    /// Like `hold_back`, for low-latency playback, three part targets if absent.
+    pub part_hold_back: Option<Duration>,
+    pub can_block_reload: bool,
+}
+
+impl ServerControl {
+    /// Parses the attribute list of an EXT-X-SERVER-CONTROL tag.
+    pub fn parse(attribute_list: &str) -> Result<Self> {
+        let attributes = AttributeList::parse(attribute_list)?;
+        let seconds = |name: &str| -> Result<Option<Duration>> {
+            let Some(value) = attributes.get(name) else {
+                return Ok(None);
+            };
+            let duration: SegmentDuration = value.parse().map_err(|_| anyhow::anyhow!("Invalid {} {}", name, value))?;
+            Ok(Some(duration.as_duration()))
+        };
+        let flag = |name: &str| match attributes.get(name) {
+            Some("YES") => Ok(true),
+            Some(other) => Err(anyhow::anyhow!("Invalid {} {}", name, other)),
+            None => Ok(false),
+        };
+        let can_skip_until = seconds("CAN-SKIP-UNTIL")?;
+        let can_skip_dateranges = flag("CAN-SKIP-DATERANGES")?;
+        if can_skip_dateranges && can_skip_until.is_none() {
+            return Err(anyhow::Error::msg("CAN-SKIP-DATERANGES without CAN-SKIP-UNTIL"));
+        }
+        Ok(Self {
+            can_skip_until,
+            can_skip_dateranges,
+            hold_back: seconds("HOLD-BACK")?,
+            part_hold_back: seconds("PART-HOLD-BACK")?,
+            can_block_reload: flag("CAN-BLOCK-RELOAD")?,
+        })
+    }
+}
+
+/// Where to start playing a live playlist, from [`MediaPlaylist::live_edge`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LiveEdge {
+    /// Media sequence number of the segment to start in, one past the last listed segment if
+    /// it's the one still being written.
+    pub sequence: u64,
+
+    /// Index of the part to start in within that segment, for low-latency playback.
+    pub part: Option<usize>,
+
+    /// Time from the start of that segment or part to the start position.
+    pub offset: Duration,
+
+    /// Time from the start of the first listed segment to the start position.
+    pub position: Duration,
+}
+
+/// A segment, or a part of one, on the playlist's timeline.
+struct Unit {
+    sequence: u64,
+    part: Option<usize>,
+    start: Duration,
+    duration: Duration,
+    gap: bool,
+}
+
+impl MediaPlaylist {
+    /// The start position the hold-back of `server_control` recommends: HOLD-BACK from the end
+    /// of the playlist, or with parts, PART-HOLD-BACK from the end of the last part. Segments
+    /// and parts marked as gaps at the end don't count, since there's nothing to play there yet.
+    /// `None` if nothing is left, and the start of the playlist if it's shorter than the
+    /// hold-back.
+    pub fn live_edge(&self, server_control: &ServerControl) -> Option<LiveEdge> {
+        let low_latency = self.part_target().is_some()
+            && (!self.trailing_parts().is_empty() || self.segments().iter().any(|x| !x.parts().is_empty()));
+        let hold_back = match self.part_target().filter(|_| low_latency) {
+            Some(part_target) => server_control.part_hold_back.unwrap_or(part_target * 3),
+            None => server_control.hold_back.unwrap_or(self.target_duration() * 3),
+        };
+
+        let mut units = Vec::new();
+        let mut end = Duration::ZERO;
+        let mut next_sequence = self.media_sequence();
+        for context in self.iter_segments() {
+            let segment = context.segment;
+            if low_latency && !segment.parts().is_empty() {
+                let mut start = context.start;
+                for (index, part) in segment.parts().iter().enumerate() {
+                    let gap = part.gap() || segment.gap();
+                    let duration = part.duration();
+                    units.push(Unit { sequence: context.sequence, part: Some(index), start, duration, gap });
+                    start += part.duration();
+                }
+            } else {
+                let (start, duration, gap) = (context.start, segment.duration(), segment.gap());
+                units.push(Unit { sequence: context.sequence, part: None, start, duration, gap });
+            }
+            end = context.start + segment.duration();
+            next_sequence = context.sequence + 1;
+        }
+        if low_latency {
+            for (index, part) in self.trailing_parts().iter().enumerate() {
+                let (duration, gap) = (part.duration(), part.gap());
+                units.push(Unit { sequence: next_sequence, part: Some(index), start: end, duration, gap });
+                end += part.duration();
+            }
+        }
+
+        let playable = units.len() - units.iter().rev().take_while(|x| x.gap).count();
+        let units = &units[..playable];
+        let last = units.last()?;
+        let position = (last.start + last.duration).saturating_sub(hold_back);
+        let unit = units.iter().find(|x| position < x.start + x.duration).unwrap_or(last);
+        Some(LiveEdge { sequence: unit.sequence, part: unit.part, offset: position - unit.start, position })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holds_back_from_playable_end() {
+        let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-VERSION:8
+            #EXT-X-TARGETDURATION:4
+            #EXT-X-MEDIA-SEQUENCE:10
+            #EXTINF:4,
+            10.ts
+            #EXTINF:4,
+            11.ts
+            #EXTINF:4,
+            12.ts
+            #EXTINF:4,
+            13.ts
+            #EXTINF:4,
+            14.ts
+            #EXT-X-GAP
+            #EXTINF:4,
+            15.ts
+        "})
+        .unwrap();
+        let edge = playlist.live_edge(&ServerControl::default()).unwrap();
+        assert_eq!((edge.sequence, edge.part, edge.offset), (12, None, Duration::ZERO));
+        assert_eq!(edge.position, Duration::from_secs(8));
+        let control = ServerControl::parse("CAN-BLOCK-RELOAD=YES,HOLD-BACK=10.5").unwrap();
+        let edge = playlist.live_edge(&control).unwrap();
+        assert_eq!((edge.sequence, edge.offset), (12, Duration::from_millis(1500)));
+        let long = ServerControl { hold_back: Some(Duration::from_secs(60)), ..control };
+        assert_eq!(playlist.live_edge(&long).unwrap().sequence, 10);
+        assert!(ServerControl::parse("CAN-SKIP-DATERANGES=YES").is_err());
+
+        let low_latency = MediaPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-VERSION:9
+            #EXT-X-TARGETDURATION:4
+            #EXT-X-PART-INF:PART-TARGET=1
+            #EXT-X-MEDIA-SEQUENCE:10
+            #EXTINF:4,
+            10.mp4
+            #EXT-X-PART:DURATION=1,URI="11.0.mp4",INDEPENDENT=YES
+            #EXT-X-PART:DURATION=1,URI="11.1.mp4"
+            #EXT-X-PART:DURATION=1,URI="11.2.mp4"
+            #EXT-X-PART:DURATION=1,URI="11.3.mp4"
+            #EXTINF:4,
+            11.mp4
+            #EXT-X-PART:DURATION=1,URI="12.0.mp4",INDEPENDENT=YES
+            #EXT-X-PART:DURATION=1,URI="12.1.mp4",GAP=YES
+        "#})
+        .unwrap();
+        let edge = low_latency.live_edge(&ServerControl::default()).unwrap();
+        assert_eq!((edge.sequence, edge.part, edge.position), (11, Some(2), Duration::from_secs(6)));
+    }
+}
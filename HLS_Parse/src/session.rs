@@ -0,0 +1,310 @@
+//! Following the video, audio and subtitle media playlists of a live presentation together.
+//! [`Session`] does no I/O, like the [`LiveFollower`] it keeps for each track: callers reload
+//! each playlist when told to and report it, and get back the segments of every track in
+//! presentation order once all the tracks have caught up with them.
+
+use core::time::Duration;
+use std::time::Instant;
+
+use anyhow::Result;
+
+use crate::{
+    FollowOptions, FollowerEvent, LiveFollower, MediaPlaylist, MediaSegment, MediaType, PlaylistTransition,
+    ProgramDateTime, Rendition, ResolvedSelection, SegmentUri,
+};
+
+/// What reloads of a [`Session`]'s playlists revealed, from [`Session::reload`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionEvent {
+    /// A segment every other track has caught up with, so it can be fetched and played without
+    /// getting ahead of them. Segments are in EXT-X-PROGRAM-DATE-TIME order across tracks, and
+    /// segments without a date are ready as soon as they're listed.
+    SegmentReady {
+        track: MediaType,
+        sequence: u64,
+        segment: Box<MediaSegment>,
+        program_date_time: Option<ProgramDateTime>,
+    },
+
+    /// The track's playlist changed type or ended, see [`FollowerEvent::Transition`].
+    Transition { track: MediaType, transition: PlaylistTransition },
+
+    /// The track's playlist ended and all its segments were ready.
+    Ended { track: MediaType },
+
+    /// The track's playlist stopped growing, see [`FollowerEvent::Stalled`]. Stalled tracks don't
+    /// hold the others back until they grow again.
+    Stalled { track: MediaType, next_sequence: u64, since: Duration },
+}
+
+/// A media playlist the session follows.
+#[derive(Debug)]
+struct Track {
+    media_type: MediaType,
+
+    /// Absolute URL of the playlist, which rendition reports are matched against.
+    url: String,
+    follower: LiveFollower,
+
+    /// Whether the playlist has been loaded at all.
+    loaded: bool,
+
+    /// Date and time at the end of the last segment, `None` if the playlist has no dates.
+    edge: Option<ProgramDateTime>,
+
+    /// Media sequence the next segment will have.
+    next_sequence: u64,
+
+    /// Highest LAST-MSN other tracks have reported for this one.
+    reported_sequence: Option<u64>,
+
+    /// Segments listed but not ready yet, in media sequence order.
+    pending: Vec<(u64, MediaSegment, Option<ProgramDateTime>)>,
+    stalled: bool,
+    ended: bool,
+    ended_reported: bool,
+}
+
+impl Track {
+    /// Whether the track's edge decides which segments are ready.
+    fn aligns(&self) -> bool {
+        self.edge.is_some() && !self.stalled && !self.ended
+    }
+}
+
+/// Follows the media playlists of a [`ResolvedSelection`], one track per media type.
+#[derive(Debug)]
+pub struct Session {
+    tracks: Vec<Track>,
+}
+
+impl Session {
+    /// A session for the selection from the master playlist at `master_url`. Video comes from
+    /// the video rendition if it has a URI and from the variant otherwise, audio and subtitles
+    /// from their renditions if they have URIs. Closed captions are carried in the video.
+    pub fn new(master_url: &str, selection: &ResolvedSelection<'_>, options: FollowOptions) -> Self {
+        let video = selection.video.and_then(Rendition::uri).unwrap_or(selection.variant.uri());
+        let uris = [
+            (MediaType::Video, Some(video)),
+            (MediaType::Audio, selection.audio.and_then(Rendition::uri)),
+            (MediaType::Subtitles, selection.subtitles.and_then(Rendition::uri)),
+        ];
+        let tracks = uris
+            .into_iter()
+            .filter_map(|(media_type, uri)| {
+                Some(Track {
+                    media_type,
+                    url: SegmentUri::new(uri?).resolve(master_url).into_string(),
+                    follower: LiveFollower::new(options.clone()),
+                    loaded: false,
+                    edge: None,
+                    next_sequence: 0,
+                    reported_sequence: None,
+                    pending: Vec::new(),
+                    stalled: false,
+                    ended: false,
+                    ended_reported: false,
+                })
+            })
+            .collect();
+        Self { tracks }
+    }
+
+    /// The tracks followed, in the order their segments are ordered by when they share a date.
+    pub fn tracks(&self) -> impl Iterator<Item = MediaType> + '_ {
+        self.tracks.iter().map(|x| x.media_type)
+    }
+
+    /// Absolute URL of the track's media playlist.
+    pub fn url(&self, track: MediaType) -> Option<&str> {
+        self.track(track).map(|x| x.url.as_str())
+    }
+
+    /// The follower of the track, e.g. to report failed requests to.
+    pub fn follower_mut(&mut self, track: MediaType) -> Option<&mut LiveFollower> {
+        self.tracks.iter_mut().find(|x| x.media_type == track).map(|x| &mut x.follower)
+    }
+
+    /// Reports a successful reload of the track's playlist, returning the segments of any track
+    /// which became ready and any change in state.
+    pub fn reload(&mut self, track: MediaType, playlist: &MediaPlaylist, now: Instant) -> Result<Vec<SessionEvent>> {
+        let index = self
+            .tracks
+            .iter()
+            .position(|x| x.media_type == track)
+            .ok_or_else(|| anyhow::anyhow!("Session has no {} track", track.as_str()))?;
+        let mut events = Vec::new();
+        let current = &mut self.tracks[index];
+        let follower_events = current.follower.reload(playlist, now);
+        let dates: Vec<(u64, Option<ProgramDateTime>)> =
+            playlist.iter_segments().map(|x| (x.sequence, x.program_date_time)).collect();
+        for event in follower_events {
+            match event {
+                FollowerEvent::Segment { sequence, segment } => {
+                    let date = dates.iter().find(|x| x.0 == sequence).and_then(|x| x.1);
+                    current.edge = date.and_then(|x| x.checked_add(segment.duration()));
+                    current.next_sequence = sequence + 1;
+                    current.stalled = false;
+                    current.pending.push((sequence, *segment, date));
+                }
+                //tracks buffer whole segments
+                FollowerEvent::Part { .. } => {}
+                FollowerEvent::Transition(transition) => events.push(SessionEvent::Transition { track, transition }),
+                FollowerEvent::Ended => current.ended = true,
+                FollowerEvent::Stalled { next_sequence, since } => {
+                    current.stalled = true;
+                    events.push(SessionEvent::Stalled { track, next_sequence, since });
+                }
+            }
+        }
+        if !current.loaded {
+            current.loaded = true;
+            current.next_sequence = current.next_sequence.max(playlist.media_sequence() + playlist.skipped_segments());
+        }
+
+        //reports name other playlists relative to this one
+        let base = current.url.clone();
+        for report in playlist.rendition_reports() {
+            let url = SegmentUri::new(report.uri()).resolve(&base);
+            let other = self.tracks.iter_mut().find(|x| x.url == url.as_str());
+            if let (Some(other), Some(last_msn)) = (other, report.last_msn()) {
+                other.reported_sequence = other.reported_sequence.max(Some(last_msn));
+            }
+        }
+
+        events.extend(self.release());
+        Ok(events)
+    }
+
+    /// How long to wait before reloading the track, zero if other tracks reported segments it
+    /// hasn't seen. `None` for a track the session doesn't have or which has ended.
+    pub fn reload_delay(&self, track: MediaType) -> Option<Duration> {
+        let track = self.track(track).filter(|x| !x.ended)?;
+        if !track.loaded || track.reported_sequence.is_some_and(|x| x >= track.next_sequence) {
+            Some(Duration::ZERO)
+        } else {
+            Some(track.follower.reload_delay())
+        }
+    }
+
+    /// Tracks which other tracks' rendition reports say are behind, so should be reloaded now.
+    pub fn tracks_behind(&self) -> Vec<MediaType> {
+        let behind = |x: &&Track| !x.ended && x.reported_sequence.is_some_and(|y| y >= x.next_sequence);
+        self.tracks.iter().filter(behind).map(|x| x.media_type).collect()
+    }
+
+    /// Date and time up to which every track that has dates, isn't stalled and hasn't ended has
+    /// listed segments. Dated segments starting before it are ready.
+    pub fn live_edge(&self) -> Option<ProgramDateTime> {
+        self.tracks.iter().filter(|x| x.aligns()).filter_map(|x| x.edge).min()
+    }
+
+    /// Whether every track has ended.
+    pub fn ended(&self) -> bool {
+        self.tracks.iter().all(|x| x.ended)
+    }
+
+    fn track(&self, track: MediaType) -> Option<&Track> {
+        self.tracks.iter().find(|x| x.media_type == track)
+    }
+
+    /// Takes the pending segments which are ready, in date order.
+    fn release(&mut self) -> Vec<SessionEvent> {
+        //until every track has loaded there's no telling how far behind one is
+        let all_loaded = self.tracks.iter().all(|x| x.loaded);
+        let edge = self.live_edge();
+        let mut ready = Vec::new();
+        let mut events = Vec::new();
+        for track in &mut self.tracks {
+            let held = track.pending.iter().position(|(_, _, date)| match (date, edge) {
+                (None, _) => false,
+                (Some(_), _) if !all_loaded => true,
+                (Some(date), Some(edge)) => *date >= edge,
+                (Some(_), None) => false,
+            });
+            let released = track.pending.drain(..held.unwrap_or(track.pending.len()));
+            ready.extend(released.map(|x| (track.media_type, x)));
+            if track.ended && track.pending.is_empty() && !track.ended_reported {
+                track.ended_reported = true;
+                events.push(SessionEvent::Ended { track: track.media_type });
+            }
+        }
+        //stable, so segments sharing a date stay in track order
+        ready.sort_by_key(|(_, (_, _, date))| *date);
+        let segments = ready.into_iter().map(|(track, (sequence, segment, program_date_time))| {
+            SessionEvent::SegmentReady { track, sequence, segment: Box::new(segment), program_date_time }
+        });
+        segments.chain(events).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MasterPlaylist, SelectionPreferences};
+
+    fn ready(events: &[SessionEvent]) -> Vec<(MediaType, u64)> {
+        events
+            .iter()
+            .filter_map(|x| match x {
+                SessionEvent::SegmentReady { track, sequence, .. } => Some((*track, *sequence)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn live_playlist(first: u64, segments: u64, report: &str, ended: bool) -> MediaPlaylist {
+        let mut file = format!("#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-MEDIA-SEQUENCE:{}\n", first);
+        file.push_str(&format!("#EXT-X-PROGRAM-DATE-TIME:2024-03-01T12:00:{:02}Z\n", first * 4));
+        for sequence in first..first + segments {
+            file.push_str(&format!("#EXTINF:4,\n{}.ts\n", sequence));
+        }
+        file.push_str(report);
+        if ended {
+            file.push_str("#EXT-X-ENDLIST\n");
+        }
+        MediaPlaylist::parse_ext_m3u(&file).unwrap()
+    }
+
+    #[test]
+    fn aligns_tracks() {
+        let master = MasterPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",NAME="English",DEFAULT=YES,URI="audio/en.m3u8"
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000,AUDIO="aac"
+            video/720p.m3u8
+        "#})
+        .unwrap();
+        let selection = master.resolve(&master.variants()[0], &SelectionPreferences::default());
+        let mut session = Session::new("https://example.com/live/master.m3u8", &selection, FollowOptions::default());
+        assert_eq!(session.tracks().collect::<Vec<_>>(), vec![MediaType::Video, MediaType::Audio]);
+        assert_eq!(session.url(MediaType::Audio), Some("https://example.com/live/audio/en.m3u8"));
+
+        let now = Instant::now();
+        let video = live_playlist(0, 3, "", false);
+        assert!(session.reload(MediaType::Video, &video, now).unwrap().is_empty());
+
+        //audio is a segment behind, and tells us video has moved on
+        let report = "#EXT-X-RENDITION-REPORT:URI=\"../video/720p.m3u8\",LAST-MSN=3\n";
+        let events = session.reload(MediaType::Audio, &live_playlist(0, 2, report, false), now).unwrap();
+        assert_eq!(
+            ready(&events),
+            vec![(MediaType::Video, 0), (MediaType::Audio, 0), (MediaType::Video, 1), (MediaType::Audio, 1)]
+        );
+        assert_eq!(session.live_edge(), Some("2024-03-01T12:00:08Z".parse().unwrap()));
+        assert_eq!(session.tracks_behind(), vec![MediaType::Video]);
+        assert_eq!(session.reload_delay(MediaType::Video), Some(Duration::ZERO));
+
+        let events = session.reload(MediaType::Video, &live_playlist(1, 3, "", false), now).unwrap();
+        assert!(ready(&events).is_empty());
+        assert!(session.tracks_behind().is_empty());
+
+        let events = session.reload(MediaType::Audio, &live_playlist(1, 3, "", true), now).unwrap();
+        assert_eq!(
+            ready(&events),
+            vec![(MediaType::Video, 2), (MediaType::Audio, 2), (MediaType::Video, 3), (MediaType::Audio, 3)]
+        );
+        assert_eq!(events.last(), Some(&SessionEvent::Ended { track: MediaType::Audio }));
+        assert!(session.reload(MediaType::Subtitles, &video, now).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+//! Arbitrary data carried by a master playlist. See
+//! <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.4.4>.
+
+use core::fmt;
+
+use anyhow::Result;
+
+use crate::attributes::{AttributeList, AttributeWriter, RawAttributes};
+use crate::LanguageTag;
+
+/// How the resource at [`SessionData::uri`] is encoded, from the FORMAT attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SessionDataFormat {
+    Json,
+    /// Read as bytes, without any interpretation.
+    Raw,
+}
+
+/// Values of the FORMAT attribute.
+const FORMATS: &[&str] = &["JSON", "RAW"];
+
+/// Information from an EXT-X-SESSION-DATA tag. Carries either a value inline or the URI of a
+/// resource holding it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SessionData {
+    /// What the data is, usually in reverse DNS notation, e.g. `com.example.title`.
+    data_id: String,
+
+    value: Option<String>,
+
+    uri: Option<String>,
+
+    /// Encoding of the resource at `uri`. JSON when absent.
+    format: Option<SessionDataFormat>,
+
+    /// Language of the value or resource.
+    language: Option<LanguageTag>,
+
+    /// The attribute list as parsed.
+    raw: RawAttributes,
+}
+
+impl SessionDataFormat {
+    /// Value of the FORMAT attribute.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionDataFormat::Json => "JSON",
+            SessionDataFormat::Raw => "RAW",
+        }
+    }
+}
+
+impl fmt::Display for SessionDataFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl SessionData {
+    /// Parses the attribute list of an EXT-X-SESSION-DATA tag.
+    pub(crate) fn parse(attribute_list: &str) -> Result<Self> {
+        let attributes = AttributeList::parse(attribute_list)?;
+        let Some(data_id) = attributes.quoted_string("DATA-ID")? else {
+            return Err(anyhow::Error::msg("Session data is missing DATA-ID attribute"));
+        };
+        let value = attributes.quoted_string("VALUE")?.map(str::to_string);
+        let uri = attributes.quoted_string("URI")?.map(str::to_string);
+        if value.is_some() == uri.is_some() {
+            return Err(anyhow::Error::msg("Session data must have exactly one of VALUE and URI"));
+        }
+        let format = match attributes.get("FORMAT") {
+            None => None,
+            Some("JSON") => Some(SessionDataFormat::Json),
+            Some("RAW") => Some(SessionDataFormat::Raw),
+            Some(other) => return Err(anyhow::anyhow!("Unknown session data format {}", other)),
+        };
+        if format.is_some() && uri.is_none() {
+            return Err(anyhow::Error::msg("FORMAT is only allowed for session data with a URI"));
+        }
+        let language = match attributes.quoted_string("LANGUAGE")? {
+            Some(value) => Some(value.parse::<LanguageTag>()?),
+            None => None,
+        };
+        Ok(Self {
+            data_id: data_id.to_string(),
+            value,
+            uri,
+            format,
+            language,
+            raw: RawAttributes::new(attribute_list),
+        })
+    }
+
+    pub(crate) fn set_value(&mut self, value: Option<String>) {
+        self.value = value;
+    }
+
+    pub(crate) fn set_uri(&mut self, uri: Option<String>) {
+        self.uri = uri;
+    }
+
+    /// Raw value of the attribute `name` as parsed, including ones the crate doesn't model, see
+    /// [`RawAttributes`][crate::RawAttributes].
+    pub fn raw_attribute(&self, name: &str) -> Option<&str> {
+        self.raw.get(name)
+    }
+
+    /// Every attribute of the EXT-X-SESSION-DATA tag as parsed.
+    pub fn raw_attributes(&self) -> &RawAttributes {
+        &self.raw
+    }
+
+    pub fn data_id(&self) -> &str {
+        &self.data_id
+    }
+
+    /// The data itself, `None` if it is at [`uri`][Self::uri] instead.
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+
+    /// Resource holding the data, relative to the master playlist unless absolute.
+    pub fn uri(&self) -> Option<&str> {
+        self.uri.as_deref()
+    }
+
+    pub fn format(&self) -> Option<SessionDataFormat> {
+        self.format
+    }
+
+    pub fn language(&self) -> Option<&LanguageTag> {
+        self.language.as_ref()
+    }
+
+    /// The attribute list of the EXT-X-SESSION-DATA tag.
+    pub(crate) fn attributes(&self) -> AttributeWriter {
+        let mut list = AttributeWriter::default();
+        list.quoted("DATA-ID", &self.data_id);
+        if let Some(value) = &self.value {
+            list.quoted("VALUE", value);
+        }
+        if let Some(uri) = &self.uri {
+            list.quoted("URI", uri);
+        }
+        if let Some(format) = self.format {
+            list.enumerated("FORMAT", format.as_str(), FORMATS);
+        }
+        if let Some(language) = &self.language {
+            list.quoted("LANGUAGE", language.as_str());
+        }
+        list
+    }
+}
+
+impl fmt::Display for SessionData {
+    /// Formats the data as the attribute list of an EXT-X-SESSION-DATA tag.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.attributes().as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_session_data() {
+        let data = SessionData::parse(r#"DATA-ID="com.example.title",VALUE="Big Buck Bunny",LANGUAGE="en""#).unwrap();
+        assert_eq!(data.data_id(), "com.example.title");
+        assert_eq!(data.value(), Some("Big Buck Bunny"));
+        assert_eq!(data.uri(), None);
+        assert_eq!(data.language().map(LanguageTag::as_str), Some("en"));
+        assert_eq!(data.to_string(), r#"DATA-ID="com.example.title",VALUE="Big Buck Bunny",LANGUAGE="en""#);
+
+        let data = SessionData::parse(r#"DATA-ID="com.example.lyrics",URI="lyrics.bin",FORMAT=RAW"#).unwrap();
+        assert_eq!(data.uri(), Some("lyrics.bin"));
+        assert_eq!(data.format(), Some(SessionDataFormat::Raw));
+
+        let error = |list: &str| SessionData::parse(list).unwrap_err().to_string();
+        assert_eq!(error(r#"VALUE="x""#), "Session data is missing DATA-ID attribute");
+        assert_eq!(error(r#"DATA-ID="a""#), "Session data must have exactly one of VALUE and URI");
+        let both = r#"DATA-ID="a",VALUE="x",URI="x.json""#;
+        assert_eq!(error(both), "Session data must have exactly one of VALUE and URI");
+        assert_eq!(error(r#"DATA-ID="a",VALUE="x",FORMAT=JSON"#), "FORMAT is only allowed for session data with a URI");
+        assert_eq!(error(r#"DATA-ID="a",URI="x",FORMAT=XML"#), "Unknown session data format XML");
+    }
+}
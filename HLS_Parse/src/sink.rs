@@ -0,0 +1,205 @@
+//! Where a downloader puts the segments it fetched, so archiving tools can pick an on-disk
+//! layout without caring how the bytes were fetched. The caller does the requests and hands
+//! each segment to a [`SegmentSink`] with its [`SegmentContext`], in playback order.
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+use crate::{Container, PackedAudio, SegmentContext, SegmentMap};
+
+/// Stores fetched segments.
+pub trait SegmentSink {
+    /// Stores the body of a segment. `init` is the body of its media initialization section, if
+    /// it has one and it was fetched; sinks which need it fail without it.
+    fn write_segment(&mut self, context: &SegmentContext<'_>, init: Option<&[u8]>, data: &[u8]) -> Result<()>;
+
+    /// Called after the last segment, e.g. to flush.
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Every segment as its own file in a directory, named after its media sequence number, e.g.
+/// `00000042.ts`. Each initialization section is written once, named after the discontinuity
+/// sequence where it was first used, e.g. `init-3.mp4`.
+#[derive(Debug)]
+pub struct DirectorySink {
+    directory: PathBuf,
+    last_map: Option<SegmentMap>,
+}
+
+impl DirectorySink {
+    /// Writes into `directory`, which is created if it doesn't exist.
+    pub fn new(directory: impl Into<PathBuf>) -> Result<Self> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory).with_context(|| format!("Could not create {}", directory.display()))?;
+        Ok(Self { directory, last_map: None })
+    }
+
+    fn write_file(&self, name: &str, data: &[u8]) -> Result<()> {
+        let path = self.directory.join(name);
+        fs::write(&path, data).with_context(|| format!("Could not write {}", path.display()))
+    }
+}
+
+impl SegmentSink for DirectorySink {
+    fn write_segment(&mut self, context: &SegmentContext<'_>, init: Option<&[u8]>, data: &[u8]) -> Result<()> {
+        let container = context.segment.container_hint();
+        if let (Some(map), Some(init)) = (context.map, init) {
+            if self.last_map.as_ref() != Some(map) {
+                let extension = if container == Some(Container::MpegTs) { "ts" } else { "mp4" };
+                self.write_file(&format!("init-{}.{}", context.discontinuity_sequence, extension), init)?;
+                self.last_map = Some(map.clone());
+            }
+        }
+        self.write_file(&format!("{:08}.{}", context.sequence, extension(container)), data)
+    }
+}
+
+/// All segments concatenated into a single MPEG-2 Transport Stream. An initialization section,
+/// which for TS holds a PAT and PMT, is written ahead of the first segment using it.
+#[derive(Debug)]
+pub struct ConcatenatedTsSink<W> {
+    out: W,
+    last_map: Option<SegmentMap>,
+}
+
+impl<W: Write> ConcatenatedTsSink<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, last_map: None }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> SegmentSink for ConcatenatedTsSink<W> {
+    fn write_segment(&mut self, context: &SegmentContext<'_>, init: Option<&[u8]>, data: &[u8]) -> Result<()> {
+        let container = context.segment.container_hint();
+        if container.is_some_and(|x| x != Container::MpegTs) {
+            return Err(anyhow::anyhow!("Segment {} is not a transport stream", context.sequence));
+        }
+        if let (Some(map), Some(init)) = (context.map, init) {
+            if self.last_map.as_ref() != Some(map) {
+                self.out.write_all(init)?;
+                self.last_map = Some(map.clone());
+            }
+        }
+        self.out.write_all(data).with_context(|| format!("Could not write segment {}", context.sequence))
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        Ok(self.out.flush()?)
+    }
+}
+
+/// All segments concatenated into a single fragmented MP4, with the initialization section
+/// ahead of the first segment and again after every discontinuity or change of section, so
+/// each run of fragments can be parsed on its own.
+#[derive(Debug)]
+pub struct Fmp4Sink<W> {
+    out: W,
+
+    /// Discontinuity sequence and section of the last initialization section written.
+    last_init: Option<(u64, SegmentMap)>,
+}
+
+impl<W: Write> Fmp4Sink<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, last_init: None }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> SegmentSink for Fmp4Sink<W> {
+    fn write_segment(&mut self, context: &SegmentContext<'_>, init: Option<&[u8]>, data: &[u8]) -> Result<()> {
+        let Some(map) = context.map else {
+            return Err(anyhow::anyhow!("Segment {} has no initialization section", context.sequence));
+        };
+        let current = (context.discontinuity_sequence, map.clone());
+        if self.last_init.as_ref() != Some(&current) {
+            let init = init.with_context(|| format!("Segment {} needs its initialization section", context.sequence))?;
+            self.out.write_all(init)?;
+            self.last_init = Some(current);
+        }
+        self.out.write_all(data).with_context(|| format!("Could not write segment {}", context.sequence))
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        Ok(self.out.flush()?)
+    }
+}
+
+/// File extension for a segment of the container.
+fn extension(container: Option<Container>) -> &'static str {
+    match container {
+        Some(Container::MpegTs) => "ts",
+        Some(Container::FragmentedMp4) => "m4s",
+        Some(Container::PackedAudio(PackedAudio::Aac)) => "aac",
+        Some(Container::PackedAudio(PackedAudio::Ac3)) => "ac3",
+        Some(Container::PackedAudio(PackedAudio::Ec3)) => "ec3",
+        Some(Container::PackedAudio(PackedAudio::Mp3)) => "mp3",
+        Some(Container::WebVtt) => "vtt",
+        None => "bin",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MediaPlaylist;
+
+    fn write_all(sink: &mut dyn SegmentSink, playlist: &MediaPlaylist) -> Result<()> {
+        for context in playlist.iter_segments() {
+            let data = context.segment.url().as_str().as_bytes().to_vec();
+            let init = context.map.map(|x| format!("[{}]", x.uri()).into_bytes());
+            sink.write_segment(&context, init.as_deref(), &data)?;
+        }
+        sink.finish()
+    }
+
+    #[test]
+    fn lays_out_segments() {
+        let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-VERSION:6
+            #EXT-X-TARGETDURATION:4
+            #EXT-X-MEDIA-SEQUENCE:7
+            #EXT-X-MAP:URI="a.mp4"
+            #EXTINF:4,
+            1.m4s
+            #EXTINF:4,
+            2.m4s
+            #EXT-X-DISCONTINUITY
+            #EXTINF:4,
+            3.m4s
+        "#})
+        .unwrap();
+        let mut fmp4 = Fmp4Sink::new(Vec::new());
+        write_all(&mut fmp4, &playlist).unwrap();
+        assert_eq!(String::from_utf8(fmp4.into_inner()).unwrap(), "[a.mp4]1.m4s2.m4s[a.mp4]3.m4s");
+
+        let mut ts = ConcatenatedTsSink::new(Vec::new());
+        assert!(write_all(&mut ts, &playlist).is_err());
+        let ts_playlist = "#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXTINF:4,\n1.ts\n#EXTINF:4,\n2.ts\n";
+        let mut ts = ConcatenatedTsSink::new(Vec::new());
+        write_all(&mut ts, &MediaPlaylist::parse_ext_m3u(ts_playlist).unwrap()).unwrap();
+        assert_eq!(ts.into_inner(), b"1.ts2.ts");
+
+        let directory = std::env::temp_dir().join(format!("hls-sink-{}", std::process::id()));
+        let mut files = DirectorySink::new(&directory).unwrap();
+        write_all(&mut files, &playlist).unwrap();
+        let mut names: Vec<String> =
+            fs::read_dir(&directory).unwrap().map(|x| x.unwrap().file_name().to_string_lossy().into_owned()).collect();
+        names.sort();
+        assert_eq!(names, ["00000007.m4s", "00000008.m4s", "00000009.m4s", "init-0.mp4"]);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
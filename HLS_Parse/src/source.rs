@@ -0,0 +1,464 @@
+//! Original lines of a parsed playlist, recorded with
+//! [`ParseOptions::preserve_source`][crate::ParseOptions::preserve_source] so the playlist can
+//! be written back without reordering or reformatting anything that wasn't modified.
+
+use crate::attributes;
+use crate::events::{self, DATERANGE_TAG, PART_TAG, PRELOAD_HINT_TAG, RENDITION_REPORT_TAG};
+use crate::urls::SharedUrls;
+use crate::writer::{self, PlaylistTag, SegmentState};
+use crate::{DateRange, MediaPlaylist, MediaSegment, PartialSegment, PreloadHint, RenditionReport};
+
+/// Lines of the source in their original order, empty if the source wasn't preserved.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Source {
+    lines: Vec<SourceLine>,
+}
+
+/// The source isn't part of the model, so playlists compare equal no matter how they were
+/// formatted.
+impl PartialEq for Source {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone)]
+enum SourceLine {
+    /// The header, blank lines, comments and tags which aren't modeled, written back verbatim.
+    Verbatim(String),
+
+    /// A playlist tag, written verbatim unless its value in the model changed.
+    Tag { tag: PlaylistTag, text: String },
+
+    /// The lines from a segment's first tag up to its URI, written verbatim unless the segment
+    /// changed.
+    Segment { index: usize, original: Box<MediaSegment>, lines: Vec<SegmentLine> },
+
+    /// The lines after the last segment's URI when the playlist has trailing date ranges, parts,
+    /// preload hints or rendition reports, written verbatim unless any of them changed.
+    TrailingParts {
+        date_ranges: Vec<DateRange>,
+        original: Vec<PartialSegment>,
+        hints: Vec<PreloadHint>,
+        reports: Vec<RenditionReport>,
+        lines: Vec<SegmentLine>,
+    },
+}
+
+impl SourceLine {
+    /// Whether the line is the same playlist tag as `tag`, whatever its value.
+    fn is_tag(&self, tag: &PlaylistTag) -> bool {
+        matches!(self, SourceLine::Tag { tag: existing, .. } if existing.same_tag(tag))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SegmentLine {
+    text: String,
+
+    /// Whether the line is covered by the model (e.g. EXTINF), rather than a comment or unknown
+    /// tag. Modeled lines are regenerated when the segment changes, the rest are kept.
+    modeled: bool,
+}
+
+/// Collects [`Source`] lines as the parser classifies them.
+#[derive(Debug, Default)]
+pub(crate) struct SourceRecorder {
+    lines: Vec<SourceLine>,
+
+    /// Lines of the segment whose URI hasn't been seen yet, and where in `lines` it started.
+    block: Vec<SegmentLine>,
+    block_start: usize,
+}
+
+impl SourceRecorder {
+    /// A line the model doesn't cover.
+    pub(crate) fn verbatim(&mut self, text: &str) {
+        if self.block.is_empty() {
+            self.lines.push(SourceLine::Verbatim(text.to_string()));
+        } else {
+            self.block.push(SegmentLine { text: text.to_string(), modeled: false });
+        }
+    }
+
+    /// A playlist tag. These belong before or after segments, but if one shows up between a
+    /// segment's tags it is kept ahead of the segment.
+    pub(crate) fn tag(&mut self, tag: PlaylistTag, text: &str) {
+        self.lines.push(SourceLine::Tag { tag, text: text.to_string() });
+    }
+
+    /// A tag applying to the next segment.
+    pub(crate) fn segment_tag(&mut self, text: &str) {
+        if self.block.is_empty() {
+            self.block_start = self.lines.len();
+        }
+        self.block.push(SegmentLine { text: text.to_string(), modeled: true });
+    }
+
+    /// The URI completing the segment at `index`.
+    pub(crate) fn segment(&mut self, index: usize, original: &MediaSegment, text: &str) {
+        let mut lines = core::mem::take(&mut self.block);
+        lines.push(SegmentLine { text: text.to_string(), modeled: true });
+        self.lines.push(SourceLine::Segment { index, original: Box::new(original.clone()), lines });
+    }
+
+    /// The source so far, with the trailing date ranges, parts, preload hints and rendition
+    /// reports of `playlist` those left over after the last segment.
+    pub(crate) fn finish(mut self, playlist: &MediaPlaylist) -> Source {
+        let block = core::mem::take(&mut self.block);
+        let date_ranges = playlist.trailing_date_ranges();
+        let (parts, hints, reports) =
+            (playlist.trailing_parts(), playlist.preload_hints(), playlist.rendition_reports());
+        if date_ranges.is_empty() && parts.is_empty() && hints.is_empty() && reports.is_empty() {
+            //e.g. a key tag ahead of segments which haven't been added to a live playlist yet, kept
+            //ahead of the playlist tags after it
+            let lines = block.into_iter().map(|x| SourceLine::Verbatim(x.text));
+            self.lines.splice(self.block_start..self.block_start, lines);
+        } else {
+            //only these are regenerated, other tags still apply to the segment to come
+            let lines = block
+                .into_iter()
+                .map(|x| {
+                    let modeled = [DATERANGE_TAG, PART_TAG, PRELOAD_HINT_TAG, RENDITION_REPORT_TAG]
+                        .contains(&events::tag_name(&x.text));
+                    SegmentLine { modeled, text: x.text }
+                })
+                .collect();
+            let (original, hints, reports) = (parts.to_vec(), hints.to_vec(), reports.to_vec());
+            let date_ranges = date_ranges.to_vec();
+            self.lines.push(SourceLine::TrailingParts { date_ranges, original, hints, reports, lines });
+        }
+        Source { lines: self.lines }
+    }
+}
+
+impl Source {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Replaces URIs in the lines with `map` of them, for [`MediaPlaylist::anonymize`]: URI lines,
+    /// and the values of `URI` and `*-URI` attributes of every tag, known or not. Comments are
+    /// dropped, since they may contain anything.
+    pub(crate) fn anonymize(&mut self, map: &mut dyn FnMut(&str) -> String) {
+        let mut shared = SharedUrls::default();
+        self.lines.retain_mut(|line| {
+            match line {
+                SourceLine::Verbatim(text) if is_comment(text) => false,
+                SourceLine::Verbatim(text) | SourceLine::Tag { text, .. } => {
+                    *text = anonymize_line(text, map);
+                    true
+                }
+                SourceLine::Segment { original, lines, .. } => {
+                    original.map_urls(map, &mut shared);
+                    original.map_date_range_urls(map);
+                    original.anonymize_custom_tags(map);
+                    anonymize_lines(lines, map);
+                    true
+                }
+                SourceLine::TrailingParts { date_ranges, original, hints, reports, lines } => {
+                    for date_range in date_ranges {
+                        date_range.map_urls(map);
+                    }
+                    for part in original {
+                        part.set_uri(map(part.uri().as_str()));
+                    }
+                    for hint in hints {
+                        hint.set_uri(map(hint.uri().as_str()));
+                    }
+                    for report in reports {
+                        report.set_uri(map(report.uri()));
+                    }
+                    anonymize_lines(lines, map);
+                    true
+                }
+            }
+        });
+    }
+
+    /// Writes the source lines, regenerating those whose part of the model changed, dropping
+    /// those whose part was removed and adding lines for anything new.
+    ///
+    /// Segments are matched by position, so inserting or removing a segment causes all the
+    /// following ones to be regenerated.
+    pub(crate) fn write(&self, playlist: &MediaPlaylist) -> String {
+        let mut out = String::new();
+        let segments = playlist.segments();
+        let original_count = self.lines.iter().filter(|x| matches!(x, SourceLine::Segment { .. })).count();
+
+        //segments added to the model go after the last original one, or before the end tag
+        let append_at = self
+            .lines
+            .iter()
+            .rposition(|x| matches!(x, SourceLine::Segment { .. }))
+            .map(|x| x + 1)
+            .or_else(|| self.lines.iter().position(|x| matches!(x, SourceLine::Tag { tag: PlaylistTag::EndList, .. })))
+            .unwrap_or(self.lines.len());
+        //nothing may follow the end tag, so one a lenient parse found segments after is moved to the end
+        let end_tag = self.lines.iter().position(|x| x.is_tag(&PlaylistTag::EndList));
+        let end_in_place = end_tag.is_some_and(|end_tag| append_at <= end_tag);
+
+        let mut state = SegmentState::default();
+        let mut original_state = SegmentState::default();
+        for (position, line) in self.lines.iter().enumerate() {
+            if position == append_at {
+                self.append(&mut out, playlist, original_count, &mut state);
+            }
+            match line {
+                SourceLine::Verbatim(text) => {
+                    push_line(&mut out, text);
+                    if position == 0 {
+                        self.write_new_tags(&mut out, playlist);
+                    }
+                }
+                SourceLine::Tag { tag, .. } if self.lines[..position].iter().any(|x| x.is_tag(tag)) => {}
+                SourceLine::Tag { tag: PlaylistTag::EndList, .. } if !end_in_place => {}
+                SourceLine::Tag { tag, text } => match tag.current(playlist) {
+                    Some(current) if current == *tag => push_line(&mut out, text),
+                    Some(current) => push_line(&mut out, &current.to_string()),
+                    None => {}
+                },
+                SourceLine::Segment { index, original, lines } => {
+                    match segments.get(*index) {
+                        None => {}
+                        Some(segment)
+                            if segment == original.as_ref()
+                                && segment.tag_order() == original.tag_order()
+                                && state == original_state =>
+                        {
+                            for line in lines {
+                                push_line(&mut out, &line.text);
+                            }
+                            state.update(segment);
+                        }
+                        Some(segment) => {
+                            for line in lines.iter().filter(|x| !x.modeled) {
+                                push_line(&mut out, &line.text);
+                            }
+                            writer::write_segment(&mut out, segment, &mut state);
+                        }
+                    }
+                    original_state.update(original);
+                }
+                SourceLine::TrailingParts { date_ranges, original, hints, reports, lines } => {
+                    let unchanged = playlist.trailing_date_ranges() == date_ranges.as_slice()
+                        && playlist.trailing_parts() == original.as_slice()
+                        && playlist.preload_hints() == hints.as_slice()
+                        && playlist.rendition_reports() == reports.as_slice();
+                    if unchanged {
+                        for line in lines {
+                            push_line(&mut out, &line.text);
+                        }
+                    } else {
+                        for line in lines.iter().filter(|x| !x.modeled) {
+                            push_line(&mut out, &line.text);
+                        }
+                        writer::write_trailing(&mut out, playlist);
+                    }
+                }
+            }
+        }
+        if append_at == self.lines.len() {
+            self.append(&mut out, playlist, original_count, &mut state);
+        }
+
+        if playlist.ended() && !end_in_place {
+            push_line(&mut out, &PlaylistTag::EndList.to_string());
+        }
+        out
+    }
+
+    /// Writes the segments after the first `original_count`, which the source didn't have, and
+    /// what follows the last segment if the source had none of it.
+    fn append(&self, out: &mut String, playlist: &MediaPlaylist, original_count: usize, state: &mut SegmentState) {
+        let segments = playlist.segments();
+        for segment in &segments[original_count.min(segments.len())..] {
+            writer::write_segment(out, segment, state);
+        }
+        if !self.lines.iter().any(|x| matches!(x, SourceLine::TrailingParts { .. })) {
+            writer::write_trailing(out, playlist);
+        }
+    }
+
+    /// Writes the playlist tags which the model has but the source didn't.
+    fn write_new_tags(&self, out: &mut String, playlist: &MediaPlaylist) {
+        for tag in PlaylistTag::header_tags(playlist) {
+            let in_source = self.lines.iter().any(|x| x.is_tag(&tag));
+            if !in_source {
+                push_line(out, &tag.to_string());
+            }
+        }
+    }
+}
+
+
+fn anonymize_lines(lines: &mut Vec<SegmentLine>, map: &mut dyn FnMut(&str) -> String) {
+    lines.retain_mut(|line| {
+        line.text = anonymize_line(&line.text, map);
+        !is_comment(&line.text)
+    });
+}
+
+fn is_comment(text: &str) -> bool {
+    text.starts_with('#') && !text.starts_with("#EXT")
+}
+
+pub(crate) fn anonymize_line(text: &str, map: &mut dyn FnMut(&str) -> String) -> String {
+    if !text.starts_with('#') {
+        return if text.trim().is_empty() { text.to_string() } else { map(text.trim()) };
+    }
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(equals) = rest.find("=\"") {
+        let value_start = equals + 2;
+        let Some(length) = rest[value_start..].find('"') else {
+            break;
+        };
+        let name = &rest[rest[..equals].rfind([':', ',']).map_or(0, |x| x + 1)..equals];
+        let value = &rest[value_start..value_start + length];
+        out.push_str(&rest[..value_start]);
+        if attributes::is_uri_attribute(name) {
+            out.push_str(&map(value));
+        } else {
+            out.push_str(value);
+        }
+        out.push('"');
+        rest = &rest[value_start + length + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn push_line(out: &mut String, text: &str) {
+    out.push_str(text);
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use crate::{
+        MediaPlaylist, MediaSegment, ParseOptions, PartialSegment, PreloadHint, PreloadHintType, WriteOptions,
+    };
+
+    const PLAYLIST: &str = indoc::indoc! {r#"
+        #EXTM3U
+        #EXT-X-TARGETDURATION:10
+        #EXT-X-VERSION:4
+
+        # packaged by an encoder which likes its own tags
+        #EXT-X-INDEPENDENT-SEGMENTS
+        #EXT-X-KEY:METHOD=AES-128,URI="1.key",IV=0x01
+        #EXTINF:9.50,first
+        #EXT-X-BYTERANGE:100@0
+        main.ts
+        #EXT-X-PROGRAM-DATE-TIME:2010-02-19T14:54:23.031+08:00
+        #EXTINF:9.500,
+        #EXT-X-BYTERANGE:100
+        main.ts
+        #EXTINF:4.0,
+        last.ts
+    "#};
+
+    fn preserved(file: &str) -> MediaPlaylist {
+        MediaPlaylist::parse_with_options(file, &ParseOptions { preserve_source: true, ..ParseOptions::default() })
+            .expect("test playlist should parse")
+    }
+
+    #[test]
+    fn round_trips_unmodified_playlist() {
+        let playlist = preserved(PLAYLIST);
+        assert_eq!(playlist.write(&WriteOptions::default()), PLAYLIST);
+        assert_eq!(playlist, MediaPlaylist::parse_ext_m3u(PLAYLIST).unwrap());
+    }
+
+    #[test]
+    fn ignores_source_when_asked() {
+        let playlist = preserved(PLAYLIST);
+        let canonical = playlist.write(&WriteOptions { preserve_source: false, ..WriteOptions::default() });
+        assert!(!canonical.contains("#EXT-X-INDEPENDENT-SEGMENTS"));
+        assert_eq!(canonical, MediaPlaylist::parse_ext_m3u(PLAYLIST).unwrap().to_string());
+    }
+
+    #[test]
+    fn regenerates_only_modified_parts() {
+        let mut playlist = preserved(PLAYLIST);
+        playlist.set_target_duration(12);
+        playlist.segments_mut()[1].set_url("moved.ts");
+        playlist.segments_mut().remove(2);
+        playlist.segments_mut().push(MediaSegment::new(Duration::from_secs(6), "new.ts"));
+        playlist.set_ended(true);
+        assert_eq!(
+            playlist.write(&WriteOptions::default()),
+            indoc::indoc! {r#"
+                #EXTM3U
+                #EXT-X-TARGETDURATION:12
+                #EXT-X-VERSION:4
+
+                # packaged by an encoder which likes its own tags
+                #EXT-X-INDEPENDENT-SEGMENTS
+                #EXT-X-KEY:METHOD=AES-128,URI="1.key",IV=0x01
+                #EXTINF:9.50,first
+                #EXT-X-BYTERANGE:100@0
+                main.ts
+                #EXT-X-PROGRAM-DATE-TIME:2010-02-19T14:54:23.031+08:00
+                #EXT-X-BYTERANGE:100
+                #EXTINF:9.500,
+                moved.ts
+                #EXT-X-KEY:METHOD=NONE
+                #EXTINF:6,
+                new.ts
+                #EXT-X-ENDLIST
+            "#}
+        );
+    }
+
+    #[test]
+    fn regenerates_modified_trailing_parts() {
+        let file = indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-TARGETDURATION:4
+            #EXT-X-PART-INF:PART-TARGET=1
+            #EXTINF:4,
+            1.mp4
+            #EXT-X-PART:DURATION=1,URI="2.0.mp4"
+            #EXT-X-PRELOAD-HINT:TYPE=PART,URI="2.1.mp4"
+            #EXT-X-RENDITION-REPORT:URI="../audio/en.m3u8",LAST-MSN=1,LAST-PART=0
+        "#};
+        let mut playlist = preserved(file);
+        assert_eq!(playlist.write(&WriteOptions::default()), file);
+        let mut parts = playlist.trailing_parts().to_vec();
+        parts.push(PartialSegment::new(Duration::from_secs(1), "2.1.mp4"));
+        playlist.set_trailing_parts(parts);
+        playlist.set_preload_hints(vec![PreloadHint::new(PreloadHintType::Part, "2.2.mp4")]);
+        playlist.push_segment(MediaSegment::new(Duration::from_secs(4), "late.mp4"));
+        assert_eq!(
+            playlist.write(&WriteOptions::default()),
+            indoc::indoc! {r#"
+                #EXTM3U
+                #EXT-X-TARGETDURATION:4
+                #EXT-X-PART-INF:PART-TARGET=1
+                #EXTINF:4,
+                1.mp4
+                #EXTINF:4,
+                late.mp4
+                #EXT-X-PART:DURATION=1,URI="2.0.mp4"
+                #EXT-X-PART:DURATION=1,URI="2.1.mp4"
+                #EXT-X-PRELOAD-HINT:TYPE=PART,URI="2.2.mp4"
+                #EXT-X-RENDITION-REPORT:URI="../audio/en.m3u8",LAST-MSN=1,LAST-PART=0
+            "#}
+        );
+    }
+
+    #[test]
+    fn writes_tags_missing_from_source() {
+        let mut playlist = preserved("#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:9,\na.ts\n");
+        playlist.set_media_sequence(3);
+        playlist.set_version(3);
+        assert_eq!(
+            playlist.write(&WriteOptions::default()),
+            "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-MEDIA-SEQUENCE:3\n#EXT-X-TARGETDURATION:10\n#EXTINF:9,\na.ts\n"
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//! Inserting one playlist into another, e.g. an ad break for server-side ad insertion.
+
+use core::time::Duration;
+
+use anyhow::Result;
+
+use crate::{DateRange, MediaPlaylist, ProgramDateTime};
+
+/// Passed to [`MediaPlaylist::splice_with_options`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpliceOptions {
+    /// Marks the ad break with EXT-X-DATERANGE tags, if set.
+    pub cues: Option<SpliceCues>,
+}
+
+/// The date range written with SCTE35-OUT ahead of the first ad segment, and again with the same
+/// ID and SCTE35-IN ahead of the segment the content resumes with, or at the end after a
+/// post-roll. See
+/// <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.2.7.1>.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpliceCues {
+    pub id: String,
+
+    /// Splice info of the cue-out and cue-in points, as hexadecimal sequences, e.g. passed on
+    /// from the content's own SCTE-35 signalling.
+    pub scte35_out: String,
+    pub scte35_in: String,
+}
+
+impl MediaPlaylist {
+    /// Inserts the segments of `ad` at the segment boundary nearest to `at` from the start of the
+    /// playlist, with an EXT-X-DISCONTINUITY before the first ad segment and before the segment
+    /// following the ad. The target duration and version are raised to cover the ad if needed.
+    /// Byte ranges around the splice are written with explicit offsets, since implicit ones
+    /// would otherwise continue from the wrong segment.
+    ///
+    /// Returns an error if either playlist is a delta update, whose segment timing isn't known.
+    pub fn splice(&self, at: Duration, ad: &MediaPlaylist) -> Result<MediaPlaylist> {
+        self.splice_with_options(at, ad, &SpliceOptions::default())
+    }
+
+    /// Like [`splice`][Self::splice], optionally marking the ad break with
+    /// [`SpliceCues`]. Date ranges are placed by program date time, so the content needs one at
+    /// the splice point: the ad is dated from there, and the content after it moves later by the
+    /// ad's duration.
+    pub fn splice_with_options(
+        &self,
+        at: Duration,
+        ad: &MediaPlaylist,
+        options: &SpliceOptions,
+    ) -> Result<MediaPlaylist> {
+        if self.skipped_segments() > 0 || ad.skipped_segments() > 0 {
+            return Err(anyhow::Error::msg("Can't splice a delta update"));
+        }
+
+        //the first boundary at or after `at`, unless the one before it is nearer
+        let mut start = Duration::ZERO;
+        let mut index = self.segments().len();
+        for (position, segment) in self.segments().iter().enumerate() {
+            let end = start + segment.duration();
+            if end > at {
+                index = if at - start <= end - at { position } else { position + 1 };
+                break;
+            }
+            start = end;
+        }
+
+        let mut ad_segments = ad.segments().to_vec();
+        for (segment, byte_range) in ad_segments.iter_mut().zip(ad.resolved_byte_ranges()) {
+            segment.set_byte_range(byte_range);
+        }
+        if let Some(first) = ad_segments.first_mut() {
+            first.set_discontinuity(true);
+        }
+
+        let mut spliced = self.clone();
+        spliced.discard_source();
+        let resumed_byte_range = self.resolved_byte_ranges().get(index).copied().flatten();
+        if let Some(resumed) = spliced.segments_mut().get_mut(index).filter(|_| !ad_segments.is_empty()) {
+            resumed.set_discontinuity(true);
+            resumed.set_byte_range(resumed_byte_range);
+        }
+        let ad_len = ad_segments.len();
+        spliced.segments_mut().splice(index..index, ad_segments);
+        if let Some(cues) = options.cues.as_ref().filter(|_| ad_len > 0) {
+            spliced.mark_splice(self, index, ad_len, ad.segments().iter().map(|x| x.duration()).sum(), cues)?;
+        }
+        spliced.set_target_duration(self.target_duration().max(ad.target_duration()).as_secs());
+        spliced.set_version(self.version().max(ad.version()));
+        Ok(spliced)
+    }
+
+    /// Writes the cues around the `ad_len` segments spliced in at `index` of `content`, dating
+    /// them and the content after them.
+    fn mark_splice(
+        &mut self,
+        content: &MediaPlaylist,
+        index: usize,
+        ad_len: usize,
+        ad_duration: Duration,
+        cues: &SpliceCues,
+    ) -> Result<()> {
+        let dates: Vec<_> = content.iter_segments().map(|x| (x.program_date_time, x.segment.duration())).collect();
+        let splice_date = match dates.get(index) {
+            Some((date, _)) => *date,
+            None => dates.last().and_then(|&(date, duration)| date?.checked_add(duration)),
+        };
+        let splice_date =
+            splice_date.ok_or_else(|| anyhow::Error::msg("Can't date cues without a program date time at the splice"))?;
+        let shift = |date: ProgramDateTime| {
+            date.checked_add(ad_duration).ok_or_else(|| anyhow::Error::msg("Program date time out of range"))
+        };
+
+        let mut cue_out = DateRange::new(cues.id.clone(), splice_date);
+        cue_out.set_planned_duration(Some(ad_duration));
+        cue_out.set_scte35_out(Some(cues.scte35_out.clone()));
+        let mut cue_in = DateRange::new(cues.id.clone(), splice_date);
+        cue_in.set_duration(Some(ad_duration));
+        cue_in.set_scte35_in(Some(cues.scte35_in.clone()));
+
+        //the ad's own dates are on another timeline
+        let segments = self.segments_mut();
+        for segment in &mut segments[index..index + ad_len] {
+            segment.set_program_date_time(None);
+        }
+        let first = &mut segments[index];
+        first.set_program_date_time(Some(splice_date));
+        let mut date_ranges = first.date_ranges().to_vec();
+        date_ranges.push(cue_out);
+        first.set_date_ranges(date_ranges);
+
+        //the resumed segment follows a discontinuity, so it can't carry a date forward
+        for (offset, segment) in segments[index + ad_len..].iter_mut().enumerate() {
+            let date = if offset == 0 { dates[index].0 } else { segment.program_date_time() };
+            if let Some(date) = date {
+                segment.set_program_date_time(Some(shift(date)?));
+            }
+        }
+        match segments.get_mut(index + ad_len) {
+            Some(resumed) => {
+                let mut date_ranges = resumed.date_ranges().to_vec();
+                date_ranges.push(cue_in);
+                resumed.set_date_ranges(date_ranges);
+            }
+            None => {
+                let mut date_ranges = self.trailing_date_ranges().to_vec();
+                date_ranges.push(cue_in);
+                self.set_trailing_date_ranges(date_ranges);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playlist(file: &str) -> MediaPlaylist {
+        MediaPlaylist::parse_ext_m3u(file).expect("test playlist should parse")
+    }
+
+    const CONTENT: &str = indoc::indoc! {r#"
+        #EXTM3U
+        #EXT-X-VERSION:4
+        #EXT-X-TARGETDURATION:6
+        #EXT-X-KEY:METHOD=AES-128,URI="content.key"
+        #EXTINF:6,
+        #EXT-X-BYTERANGE:1000@0
+        content.ts
+        #EXTINF:6,
+        #EXT-X-BYTERANGE:1000
+        content.ts
+        #EXTINF:6,
+        #EXT-X-BYTERANGE:1000
+        content.ts
+        #EXT-X-ENDLIST
+    "#};
+
+    const AD: &str = indoc::indoc! {"
+        #EXTM3U
+        #EXT-X-VERSION:3
+        #EXT-X-TARGETDURATION:8
+        #EXTINF:7.5,
+        ad1.ts
+        #EXTINF:7.5,
+        ad2.ts
+        #EXT-X-ENDLIST
+    "};
+
+    #[test]
+    fn splices_at_nearest_boundary() {
+        let spliced = playlist(CONTENT).splice(Duration::from_secs(8), &playlist(AD)).expect("should splice");
+        assert_eq!(
+            spliced.to_string(),
+            indoc::indoc! {r#"
+                #EXTM3U
+                #EXT-X-VERSION:4
+                #EXT-X-TARGETDURATION:8
+                #EXT-X-KEY:METHOD=AES-128,URI="content.key"
+                #EXT-X-BYTERANGE:1000@0
+                #EXTINF:6,
+                content.ts
+                #EXT-X-DISCONTINUITY
+                #EXT-X-KEY:METHOD=NONE
+                #EXTINF:7.5,
+                ad1.ts
+                #EXTINF:7.5,
+                ad2.ts
+                #EXT-X-DISCONTINUITY
+                #EXT-X-KEY:METHOD=AES-128,URI="content.key"
+                #EXT-X-BYTERANGE:1000@1000
+                #EXTINF:6,
+                content.ts
+                #EXT-X-BYTERANGE:1000
+                #EXTINF:6,
+                content.ts
+                #EXT-X-ENDLIST
+            "#}
+        );
+        assert!(spliced.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn splices_at_either_end() {
+        let (content, ad) = (playlist(CONTENT), playlist(AD));
+        let pre_roll = content.splice(Duration::ZERO, &ad).unwrap();
+        let urls: Vec<&str> = pre_roll.segments().iter().map(|x| x.url().as_str()).collect();
+        assert_eq!(urls, vec!["ad1.ts", "ad2.ts", "content.ts", "content.ts", "content.ts"]);
+        assert!(pre_roll.segments()[0].discontinuity() && pre_roll.segments()[2].discontinuity());
+
+        let post_roll = content.splice(Duration::from_secs(60), &ad).unwrap();
+        let urls: Vec<&str> = post_roll.segments().iter().map(|x| x.url().as_str()).collect();
+        assert_eq!(urls, vec!["content.ts", "content.ts", "content.ts", "ad1.ts", "ad2.ts"]);
+        assert!(post_roll.ended());
+    }
+
+    #[test]
+    fn marks_splice_with_cues() {
+        let content = CONTENT.replace("#EXT-X-KEY", "#EXT-X-PROGRAM-DATE-TIME:2024-03-01T12:00:00.000Z\n#EXT-X-KEY");
+        let content = playlist(&content);
+        let cues =
+            SpliceCues { id: "ad-1".to_string(), scte35_out: "0xFC30".to_string(), scte35_in: "0xFC31".to_string() };
+        let options = SpliceOptions { cues: Some(cues) };
+        let spliced = content.splice_with_options(Duration::from_secs(8), &playlist(AD), &options).unwrap();
+        let written = spliced.to_string();
+        assert_eq!(
+            written,
+            indoc::indoc! {r#"
+                #EXTM3U
+                #EXT-X-VERSION:4
+                #EXT-X-TARGETDURATION:8
+                #EXT-X-PROGRAM-DATE-TIME:2024-03-01T12:00:00.000Z
+                #EXT-X-KEY:METHOD=AES-128,URI="content.key"
+                #EXT-X-BYTERANGE:1000@0
+                #EXTINF:6,
+                content.ts
+                #EXT-X-DISCONTINUITY
+                #EXT-X-KEY:METHOD=NONE
+                #EXT-X-PROGRAM-DATE-TIME:2024-03-01T12:00:06.000Z
+                #EXT-X-DATERANGE:ID="ad-1",START-DATE="2024-03-01T12:00:06.000Z",PLANNED-DURATION=15,SCTE35-OUT=0xFC30
+                #EXTINF:7.5,
+                ad1.ts
+                #EXTINF:7.5,
+                ad2.ts
+                #EXT-X-DISCONTINUITY
+                #EXT-X-KEY:METHOD=AES-128,URI="content.key"
+                #EXT-X-PROGRAM-DATE-TIME:2024-03-01T12:00:21.000Z
+                #EXT-X-DATERANGE:ID="ad-1",START-DATE="2024-03-01T12:00:06.000Z",DURATION=15,SCTE35-IN=0xFC31
+                #EXT-X-BYTERANGE:1000@1000
+                #EXTINF:6,
+                content.ts
+                #EXT-X-BYTERANGE:1000
+                #EXTINF:6,
+                content.ts
+                #EXT-X-ENDLIST
+            "#}
+        );
+        //the ad is the cued avail
+        let reparsed = playlist(&written);
+        let cued = |cues: &[crate::Cue]| cues.first().map_or((false, false), |x| (x.cue_out, x.cue_in));
+        let marked: Vec<(bool, bool)> = reparsed.segments().iter().map(|x| cued(x.cues())).collect();
+        assert_eq!(marked, vec![(false, false), (true, false), (false, true), (false, false), (false, false)]);
+        assert!(reparsed.diagnostics().is_empty());
+
+        let undated = playlist(CONTENT).splice_with_options(Duration::from_secs(8), &playlist(AD), &options);
+        assert!(undated.is_err(), "cues need a program date time");
+    }
+}
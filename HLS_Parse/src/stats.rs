@@ -0,0 +1,166 @@
+//! Summary statistics for quality control of media playlists.
+
+use core::time::Duration;
+
+use crate::{MediaPlaylist, MediaSegment};
+
+/// Returned by [`MediaPlaylist::stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaylistStats {
+    /// Shortest segment duration, `None` without segments.
+    pub min_segment_duration: Option<Duration>,
+
+    /// Longest segment duration, `None` without segments.
+    pub max_segment_duration: Option<Duration>,
+
+    /// Mean segment duration, `None` without segments.
+    pub average_segment_duration: Option<Duration>,
+
+    /// Sum of all segment durations.
+    pub total_duration: Duration,
+
+    /// Number of EXT-X-DISCONTINUITY tags.
+    pub discontinuities: usize,
+
+    /// Sum of the lengths of all EXT-X-BYTERANGE tags, saturating at `u64::MAX`.
+    pub byte_range_bytes: u64,
+
+    /// Whether any EXTINF duration, rounded to the nearest integer, exceeds EXT-X-TARGETDURATION.
+    pub exceeds_target_duration: bool,
+}
+
+impl MediaPlaylist {
+    /// Computes summary statistics over all segments.
+    pub fn stats(&self) -> PlaylistStats {
+        let segments = self.segments();
+        let durations = segments.iter().map(|x| x.duration());
+        let total_duration: Duration = durations.clone().sum();
+        PlaylistStats {
+            min_segment_duration: durations.clone().min(),
+            max_segment_duration: durations.max(),
+            average_segment_duration: u32::try_from(segments.len())
+                .ok()
+                .filter(|count| *count > 0)
+                .map(|count| total_duration / count),
+            total_duration,
+            discontinuities: segments.iter().filter(|x| x.discontinuity()).count(),
+            byte_range_bytes: segments
+                .iter()
+                .filter_map(|x| x.byte_range())
+                .fold(0, |total, x| total.saturating_add(x.length)),
+            exceeds_target_duration: segments.iter().any(|x| x.exceeds_target_duration(self.target_duration())),
+        }
+    }
+
+    /// [`MediaSegment::estimated_bitrate`] of every segment, in order, to spot encoder bit rate
+    /// spikes without downloading anything.
+    pub fn bitrate_profile(&self) -> Vec<Option<f64>> {
+        self.segments().iter().map(MediaSegment::estimated_bitrate).collect()
+    }
+}
+
+impl MediaSegment {
+    /// Bit rate in bits per second from the EXT-X-BYTERANGE length and the duration. `None`
+    /// without a byte range, since the size of a whole resource isn't in the playlist, or
+    /// without a duration.
+    pub fn estimated_bitrate(&self) -> Option<f64> {
+        let seconds = self.duration().as_secs_f64();
+        let length = self.byte_range()?.length;
+        (seconds > 0.0).then(|| length as f64 * 8.0 / seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_segments() {
+        let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-VERSION:4
+            #EXT-X-TARGETDURATION:10
+            #EXTINF:4,
+            #EXT-X-BYTERANGE:1000@0
+            main.ts
+            #EXTINF:10.6,
+            #EXT-X-BYTERANGE:500
+            main.ts
+            #EXT-X-DISCONTINUITY
+            #EXTINF:7.4,
+            ad.ts
+            #EXT-X-ENDLIST
+        "})
+        .unwrap();
+        assert_eq!(
+            playlist.stats(),
+            PlaylistStats {
+                min_segment_duration: Some(Duration::from_secs(4)),
+                max_segment_duration: Some(Duration::from_millis(10600)),
+                average_segment_duration: Some((Duration::from_secs(4) + Duration::from_millis(10600) + Duration::from_millis(7400)) / 3),
+                total_duration: Duration::from_secs(4) + Duration::from_millis(10600) + Duration::from_millis(7400),
+                discontinuities: 1,
+                byte_range_bytes: 1500,
+                exceeds_target_duration: true,
+            }
+        );
+    }
+
+    #[test]
+    fn profiles_bitrate() {
+        let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-VERSION:4
+            #EXT-X-TARGETDURATION:4
+            #EXTINF:4,
+            #EXT-X-BYTERANGE:500000@0
+            main.ts
+            #EXTINF:2,
+            #EXT-X-BYTERANGE:1000000
+            main.ts
+            #EXTINF:4,
+            other.ts
+        "})
+        .unwrap();
+        assert_eq!(playlist.bitrate_profile(), vec![Some(1_000_000.0), Some(4_000_000.0), None]);
+
+        //a length too large to count in bits as a u64
+        let huge = MediaPlaylist::parse_ext_m3u(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-VERSION:4
+            #EXT-X-TARGETDURATION:4
+            #EXTINF:4,
+            #EXT-X-BYTERANGE:9223372036854775807@0
+            main.ts
+        "})
+        .unwrap();
+        assert_eq!(huge.bitrate_profile(), vec![Some(i64::MAX as f64 * 2.0)]);
+    }
+
+    #[test]
+    fn saturates_byte_range_bytes() {
+        let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-VERSION:4
+            #EXT-X-TARGETDURATION:4
+            #EXTINF:4,
+            #EXT-X-BYTERANGE:18446744073709551615@0
+            main.ts
+            #EXTINF:4,
+            #EXT-X-BYTERANGE:1000@0
+            other.ts
+        "})
+        .unwrap();
+        assert_eq!(playlist.stats().byte_range_bytes, u64::MAX);
+    }
+
+    #[test]
+    fn handles_empty_playlist() {
+        let playlist = MediaPlaylist::parse_ext_m3u("#EXTM3U\n#EXT-X-TARGETDURATION:10\n").unwrap();
+        let stats = playlist.stats();
+        assert_eq!(stats.min_segment_duration, None);
+        assert_eq!(stats.average_segment_duration, None);
+        assert_eq!(stats.total_duration, Duration::ZERO);
+        assert!(!stats.exceeds_target_duration);
+    }
+}
@@ -0,0 +1,169 @@
+//! WebVTT subtitle playlists. See <https://datatracker.ietf.org/doc/html/rfc8216#section-3.5>.
+
+use core::ops::Range;
+use core::time::Duration;
+
+use anyhow::Result;
+
+use crate::{Container, MediaPlaylist, MediaSegment, ProgramDateTime};
+
+/// Clock rate of MPEG-2 timestamps.
+const MPEGTS_CLOCK: u128 = 90_000;
+
+/// A WebVTT segment with the part of the playlist timeline it covers, from
+/// [`MediaPlaylist::subtitles`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubtitleWindow<'a> {
+    pub segment: &'a MediaSegment,
+
+    /// Media sequence number, counting segments skipped by a delta update.
+    pub sequence: u64,
+
+    /// Discontinuity sequence number, which must match the one of the audio or video the cues are
+    /// shown with.
+    pub discontinuity_sequence: u64,
+
+    /// Time from the start of the first listed segment, the same as for the other renditions of a
+    /// variant since their segments line up.
+    pub window: Range<Duration>,
+
+    /// Date and time of the start of the window, if the playlist has any.
+    pub program_date_time: Option<ProgramDateTime>,
+}
+
+/// The X-TIMESTAMP-MAP header of a WebVTT segment, which ties the times of its cues to the MPEG-2
+/// timestamps of the audio and video.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimestampMap {
+    /// Cue time from LOCAL.
+    pub local: Duration,
+
+    /// 90kHz timestamp from MPEGTS that `local` corresponds to.
+    pub mpegts: u64,
+}
+
+impl MediaPlaylist {
+    /// The segments of a subtitle playlist with their time windows, e.g. to schedule fetching
+    /// captions ahead of the playhead. Returns an error if a segment is in another container
+    /// according to [`MediaSegment::container_hint`]; segments without a recognizable extension
+    /// are assumed to be WebVTT.
+    pub fn subtitles(&self) -> Result<Vec<SubtitleWindow<'_>>> {
+        self.iter_segments()
+            .enumerate()
+            .map(|(index, context)| match context.segment.container_hint() {
+                Some(Container::WebVtt) | None => Ok(SubtitleWindow {
+                    segment: context.segment,
+                    sequence: context.sequence,
+                    discontinuity_sequence: context.discontinuity_sequence,
+                    window: context.start..context.start + context.segment.duration(),
+                    program_date_time: context.program_date_time,
+                }),
+                Some(container) => Err(anyhow::anyhow!(
+                    "Segment {} ({}) is {:?}, not WebVTT",
+                    index + 1, context.segment.url(), container
+                )),
+            })
+            .collect()
+    }
+}
+
+impl TimestampMap {
+    /// Finds the X-TIMESTAMP-MAP header of a WebVTT file, in the lines before the first blank
+    /// one. Returns `None` if there isn't one, in which case cue times are already on the
+    /// playlist timeline.
+    pub fn from_webvtt(file: &str) -> Result<Option<Self>> {
+        let header = file.lines().take_while(|x| !x.trim().is_empty());
+        let Some(value) = header.filter_map(|x| x.strip_prefix("X-TIMESTAMP-MAP=")).next() else {
+            return Ok(None);
+        };
+        let (mut local, mut mpegts) = (None, None);
+        for attribute in value.split(',') {
+            match attribute.trim().split_once(':') {
+                Some(("LOCAL", time)) => local = Some(parse_cue_time(time)?),
+                Some(("MPEGTS", timestamp)) => match timestamp.parse::<u64>() {
+                    Ok(timestamp) => mpegts = Some(timestamp),
+                    Err(_) => return Err(anyhow::anyhow!("Invalid MPEGTS {}", timestamp)),
+                },
+                _ => return Err(anyhow::anyhow!("Invalid X-TIMESTAMP-MAP {}", value)),
+            }
+        }
+        match (local, mpegts) {
+            (Some(local), Some(mpegts)) => Ok(Some(Self { local, mpegts })),
+            _ => Err(anyhow::anyhow!("X-TIMESTAMP-MAP {} needs both LOCAL and MPEGTS", value)),
+        }
+    }
+
+    /// The 90kHz timestamp of a cue time, wrapping around at 33 bits like MPEG-2 timestamps do.
+    pub fn mpegts_at(&self, cue_time: Duration) -> u64 {
+        let ticks = |x: Duration| (x.as_nanos() * MPEGTS_CLOCK / 1_000_000_000) as i128;
+        let timestamp = self.mpegts as i128 + ticks(cue_time) - ticks(self.local);
+        timestamp.rem_euclid(1 << 33) as u64
+    }
+}
+
+/// A WebVTT timestamp, `[hh:]mm:ss.ttt`.
+fn parse_cue_time(time: &str) -> Result<Duration> {
+    let invalid = || anyhow::anyhow!("Invalid WebVTT timestamp {}", time);
+    let (rest, millis) = time.split_once('.').ok_or_else(invalid)?;
+    let fields: Vec<&str> = rest.rsplit(':').collect();
+    if !(2..=3).contains(&fields.len()) || millis.len() != 3 {
+        return Err(invalid());
+    }
+    let mut seconds = 0;
+    for (scale, field) in [1, 60, 3600].into_iter().zip(fields) {
+        seconds += scale * field.parse::<u64>().map_err(|_| invalid())?;
+    }
+    Ok(Duration::from_secs(seconds) + Duration::from_millis(millis.parse().map_err(|_| invalid())?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_subtitle_windows() {
+        let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-VERSION:3
+            #EXT-X-TARGETDURATION:6
+            #EXT-X-MEDIA-SEQUENCE:100
+            #EXTINF:6,
+            en/100.vtt
+            #EXTINF:4.5,
+            en/101.webvtt?token=a
+            #EXT-X-DISCONTINUITY
+            #EXTINF:6,
+            en/102
+        "})
+        .unwrap();
+        let windows = playlist.subtitles().unwrap();
+        let ranges: Vec<_> = windows.iter().map(|x| (x.sequence, x.discontinuity_sequence, x.window.clone())).collect();
+        assert_eq!(
+            ranges,
+            vec![
+                (100, 0, Duration::ZERO..Duration::from_secs(6)),
+                (101, 0, Duration::from_secs(6)..Duration::from_millis(10500)),
+                (102, 1, Duration::from_millis(10500)..Duration::from_millis(16500)),
+            ]
+        );
+
+        let video = MediaPlaylist::parse_ext_m3u("#EXTM3U\n#EXT-X-TARGETDURATION:6\n#EXTINF:6,\n1.ts\n").unwrap();
+        assert_eq!(video.subtitles().unwrap_err().to_string(), "Segment 1 (1.ts) is MpegTs, not WebVTT");
+    }
+
+    #[test]
+    fn maps_cue_times_to_mpegts() {
+        let file = "WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:900000,LOCAL:00:00:00.000\n\n00:01.000 --> 00:02.000\nHello\n";
+        let map = TimestampMap::from_webvtt(file).unwrap().unwrap();
+        assert_eq!(map, TimestampMap { local: Duration::ZERO, mpegts: 900_000 });
+        assert_eq!(map.mpegts_at(Duration::from_secs(1)), 990_000);
+
+        let map = TimestampMap::from_webvtt("WEBVTT\nX-TIMESTAMP-MAP=LOCAL:01:00.500,MPEGTS:0\n").unwrap().unwrap();
+        assert_eq!(map.local, Duration::from_millis(60_500));
+        assert_eq!(map.mpegts_at(Duration::from_secs(60)), (1 << 33) - 45_000);
+
+        assert_eq!(TimestampMap::from_webvtt("WEBVTT\n\nX-TIMESTAMP-MAP=MPEGTS:0,LOCAL:00:00.000\n").unwrap(), None);
+        assert!(TimestampMap::from_webvtt("WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:0\n").is_err());
+        assert!(TimestampMap::from_webvtt("WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:0,LOCAL:0.000\n").is_err());
+    }
+}
@@ -0,0 +1,166 @@
+//! Serving a VOD playlist as a simulated live stream, so player tests can exercise reloads,
+//! sliding windows, gaps and delta updates deterministically, without a packager. Like
+//! [`LiveFollower`][crate::LiveFollower], the simulation does no I/O and takes the time from
+//! the caller, as the time since the stream started.
+
+use core::time::Duration;
+
+use anyhow::Result;
+
+use crate::MediaPlaylist;
+
+/// Passed to [`SimulatedLive::new`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulatedLiveOptions {
+    /// Segments listed at once, the most recent ones.
+    pub window: usize,
+
+    /// Leave out the EXT-X-ENDLIST tag once every segment has been published, like a stream
+    /// which stalled rather than ended.
+    pub withhold_endlist: bool,
+
+    /// Media sequence numbers of segments to mark with EXT-X-GAP.
+    pub gaps: Vec<u64>,
+
+    /// Serve delta updates for a server advertising this CAN-SKIP-UNTIL, see
+    /// [`MediaPlaylist::to_delta`].
+    pub can_skip_until: Option<Duration>,
+}
+
+impl Default for SimulatedLiveOptions {
+    fn default() -> Self {
+        Self { window: 5, withhold_endlist: false, gaps: Vec::new(), can_skip_until: None }
+    }
+}
+
+/// A live stream publishing the segments of a VOD playlist as their durations pass. It starts
+/// with a full window, then each segment is published once the one before it has played.
+#[derive(Debug, Clone)]
+pub struct SimulatedLive {
+    vod: MediaPlaylist,
+    options: SimulatedLiveOptions,
+}
+
+impl SimulatedLive {
+    pub fn new(vod: MediaPlaylist, options: SimulatedLiveOptions) -> Self {
+        Self { vod, options }
+    }
+
+    /// Number of segments published `elapsed` after the stream started.
+    pub fn published(&self, elapsed: Duration) -> usize {
+        let initial = self.initial();
+        let mut end = Duration::ZERO;
+        let later = self.vod.segments()[initial..].iter().take_while(|segment| {
+            end += segment.duration();
+            end <= elapsed
+        });
+        initial + later.count()
+    }
+
+    /// Time after the start when the next segment is published, `None` once all of them are.
+    pub fn next_update(&self, elapsed: Duration) -> Option<Duration> {
+        let published = self.published(elapsed);
+        let segments = self.vod.segments();
+        (published < segments.len()).then(|| segments[self.initial()..=published].iter().map(|x| x.duration()).sum())
+    }
+
+    /// Number of segments published from the start, a full window.
+    fn initial(&self) -> usize {
+        self.options.window.max(1).min(self.vod.segments().len())
+    }
+
+    /// The playlist a server would serve `elapsed` after the stream started: the latest window
+    /// of published segments, with the media and discontinuity sequences advanced past those
+    /// that slid out, and the first segment's date and byte range made explicit. Errors if a
+    /// delta update can't be made, see [`MediaPlaylist::to_delta`].
+    pub fn playlist_at(&self, elapsed: Duration) -> Result<MediaPlaylist> {
+        let published = self.published(elapsed);
+        let first = published.saturating_sub(self.options.window.max(1));
+        let contexts: Vec<_> = self.vod.iter_segments().collect();
+
+        let mut live = self.vod.clone();
+        live.discard_source();
+        live.set_playlist_type(None);
+        live.set_trailing_date_ranges(Vec::new());
+        live.set_trailing_parts(Vec::new());
+        live.set_preload_hints(Vec::new());
+        live.set_rendition_reports(Vec::new());
+        let mut segments: Vec<_> = self.vod.segments()[first..published].to_vec();
+        if let (Some(segment), Some(context)) = (segments.first_mut(), contexts.get(first)) {
+            live.set_media_sequence(context.sequence);
+            //the first segment keeps its discontinuity tag, which the sequence already counts
+            live.set_discontinuity_sequence(context.discontinuity_sequence);
+            segment.set_program_date_time(context.program_date_time);
+            segment.set_byte_range(context.byte_range.or(segment.byte_range()));
+        }
+        for (offset, segment) in segments.iter_mut().enumerate() {
+            if self.options.gaps.contains(&(live.media_sequence() + offset as u64)) {
+                segment.set_gap(true);
+            }
+        }
+        *live.segments_mut() = segments;
+        live.set_ended(self.vod.ended() && published == contexts.len() && !self.options.withhold_endlist);
+
+        match self.options.can_skip_until {
+            Some(can_skip_until) => live.to_delta(can_skip_until),
+            None => Ok(live),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slides_window_over_vod() {
+        let mut file = String::from("#EXTM3U\n#EXT-X-VERSION:4\n#EXT-X-TARGETDURATION:4\n#EXT-X-PLAYLIST-TYPE:VOD\n");
+        file.push_str("#EXT-X-PROGRAM-DATE-TIME:2024-01-01T00:00:00Z\n");
+        for index in 0..8 {
+            if index == 3 {
+                file.push_str("#EXT-X-DISCONTINUITY\n");
+            }
+            file.push_str(&format!("#EXTINF:4,\n{}.ts\n", index));
+        }
+        file.push_str("#EXT-X-ENDLIST\n");
+        let vod = MediaPlaylist::parse_ext_m3u(&file).unwrap();
+        let options = SimulatedLiveOptions { window: 3, gaps: vec![4], ..SimulatedLiveOptions::default() };
+        let live = SimulatedLive::new(vod.clone(), options.clone());
+
+        let start = live.playlist_at(Duration::ZERO).unwrap();
+        assert_eq!((start.media_sequence(), start.segments().len(), start.ended()), (0, 3, false));
+        assert_eq!(start.playlist_type(), None);
+        assert_eq!(live.next_update(Duration::from_secs(5)), Some(Duration::from_secs(8)));
+
+        let later = live.playlist_at(Duration::from_secs(9)).unwrap();
+        assert_eq!((later.media_sequence(), later.discontinuity_sequence()), (2, 0));
+        assert_eq!(later.segments()[0].url().as_str(), "2.ts");
+        assert!(later.segments()[2].gap());
+        assert_eq!(later.segments()[0].program_date_time().unwrap().to_string(), "2024-01-01T00:00:08.000Z");
+        let at_discontinuity = live.playlist_at(Duration::from_secs(13)).unwrap();
+        assert_eq!((at_discontinuity.media_sequence(), at_discontinuity.discontinuity_sequence()), (3, 1));
+        assert!(at_discontinuity.segments()[0].discontinuity());
+        let slid = live.playlist_at(Duration::from_secs(17)).unwrap();
+        assert_eq!((slid.media_sequence(), slid.discontinuity_sequence()), (4, 1));
+        let vod_contexts: Vec<_> = vod.iter_segments().map(|x| x.discontinuity_sequence).collect();
+        let live_contexts: Vec<_> = at_discontinuity.iter_segments().map(|x| x.discontinuity_sequence).collect();
+        assert_eq!(live_contexts, vod_contexts[3..6]);
+
+        let end = live.playlist_at(Duration::from_secs(60)).unwrap();
+        assert!(end.ended());
+        assert_eq!(live.next_update(Duration::from_secs(60)), None);
+        let stalled = SimulatedLive::new(vod.clone(), SimulatedLiveOptions { withhold_endlist: true, ..options });
+        assert!(!stalled.playlist_at(Duration::from_secs(60)).unwrap().ended());
+
+        let options = SimulatedLiveOptions { can_skip_until: Some(Duration::from_secs(24)), ..Default::default() };
+        let delta = SimulatedLive::new(vod, SimulatedLiveOptions { window: 8, ..options });
+        assert_eq!(delta.playlist_at(Duration::ZERO).unwrap().skipped_segments(), 2);
+
+        //a discontinuity on the first segment of the VOD is already counted too
+        let file = "#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXT-X-DISCONTINUITY\n#EXTINF:10,\na.ts\n#EXTINF:10,\nb.ts\n";
+        let vod = MediaPlaylist::parse_ext_m3u(&format!("{}#EXT-X-ENDLIST\n", file)).unwrap();
+        let start = SimulatedLive::new(vod, SimulatedLiveOptions { window: 1, ..Default::default() });
+        let start = start.playlist_at(Duration::ZERO).unwrap();
+        assert_eq!((start.discontinuity_sequence(), start.segments()[0].discontinuity()), (0, true));
+    }
+}
@@ -0,0 +1,142 @@
+//! Estimating network throughput from finished requests and choosing a variant to match, the two
+//! halves of adaptive bitrate selection. A [`ThroughputSink`] shared with a
+//! [`FetchScheduler`][crate::FetchScheduler] as `Arc<Mutex<_>>` is fed every finished request.
+
+use core::time::Duration;
+use std::sync::{Arc, Mutex};
+
+use crate::{FetchObserver, MasterPlaylist, RequestTiming, VariantStream};
+
+/// Collects throughput samples and estimates the bandwidth available from them.
+pub trait ThroughputSink {
+    /// A transfer of `bytes` which took `duration`.
+    fn add_sample(&mut self, bytes: u64, duration: Duration);
+
+    /// Bits per second, `None` until there are samples.
+    fn estimate(&self) -> Option<u64>;
+}
+
+/// Feeds the sink the body size and transfer time of every finished request.
+impl<S: ThroughputSink + Send> FetchObserver for Arc<Mutex<S>> {
+    fn request_finished(&mut self, timing: &RequestTiming) {
+        //a poisoned sink only missed a sample
+        let mut sink = self.lock().unwrap_or_else(|x| x.into_inner());
+        sink.add_sample(timing.bytes, timing.transfer_duration());
+    }
+}
+
+/// Exponentially weighted moving average of throughput, with each sample weighted by its
+/// duration so a long transfer counts for more than a short one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EwmaEstimator {
+    /// How long it takes for a sample's weight to halve.
+    half_life: Duration,
+
+    /// Samples of fewer bytes are ignored, since their time is mostly latency.
+    min_bytes: u64,
+
+    average: f64,
+    total_weight: f64,
+}
+
+impl EwmaEstimator {
+    pub fn new(half_life: Duration, min_bytes: u64) -> Self {
+        Self { half_life, min_bytes, average: 0.0, total_weight: 0.0 }
+    }
+
+    /// How much of the average is left after `weight` seconds of newer samples.
+    fn decay(&self, weight: f64) -> f64 {
+        0.5f64.powf(weight / self.half_life.as_secs_f64().max(f64::MIN_POSITIVE))
+    }
+}
+
+/// A half-life of 3 seconds, ignoring samples under 16 KB.
+impl Default for EwmaEstimator {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(3), 16_000)
+    }
+}
+
+impl ThroughputSink for EwmaEstimator {
+    fn add_sample(&mut self, bytes: u64, duration: Duration) {
+        let weight = duration.as_secs_f64();
+        if bytes < self.min_bytes || weight <= 0.0 {
+            return;
+        }
+        let alpha = self.decay(weight);
+        let bits_per_second = bytes as f64 * 8.0 / weight;
+        self.average = bits_per_second * (1.0 - alpha) + self.average * alpha;
+        self.total_weight += weight;
+    }
+
+    fn estimate(&self) -> Option<u64> {
+        if self.total_weight == 0.0 {
+            return None;
+        }
+        //the average starts at 0, so early estimates are scaled up by however much weight is missing
+        let zero_factor = 1.0 - self.decay(self.total_weight);
+        Some((self.average / zero_factor).round() as u64)
+    }
+}
+
+impl MasterPlaylist {
+    /// The variant with the highest BANDWIDTH which fits in `estimate` bits per second, or the
+    /// lowest one if none does. The estimate should already allow for some headroom, e.g.
+    /// 80% of [`ThroughputSink::estimate`]. `None` only if there are no variants.
+    pub fn select_for_throughput(&self, estimate: u64) -> Option<&VariantStream> {
+        let variants = self.variants().iter();
+        //max_by_key keeps the last of equals, so reverse to prefer the first in playlist order
+        let fitting = variants.clone().rev().filter(|x| x.bandwidth() <= estimate).max_by_key(|x| x.bandwidth());
+        fitting.or_else(|| variants.min_by_key(|x| x.bandwidth()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::{FetchScheduler, MediaPlaylist};
+
+    #[test]
+    fn estimates_and_selects() {
+        let mut estimator = EwmaEstimator::default();
+        assert_eq!(estimator.estimate(), None);
+        estimator.add_sample(1_000_000, Duration::from_secs(1));
+        assert_eq!(estimator.estimate(), Some(8_000_000));
+        estimator.add_sample(100, Duration::from_secs(1));
+        assert_eq!(estimator.estimate(), Some(8_000_000));
+        estimator.add_sample(250_000, Duration::from_secs(1));
+        let estimate = estimator.estimate().unwrap();
+        assert!((2_000_000..8_000_000).contains(&estimate), "{}", estimate);
+
+        let master = MasterPlaylist::parse_ext_m3u(indoc::indoc! {"
+            #EXTM3U
+            #EXT-X-STREAM-INF:BANDWIDTH=2560000
+            mid.m3u8
+            #EXT-X-STREAM-INF:BANDWIDTH=640000
+            low.m3u8
+            #EXT-X-STREAM-INF:BANDWIDTH=7680000
+            high.m3u8
+        "})
+        .unwrap();
+        let select = |estimate: u64| master.select_for_throughput(estimate).map(|x| x.uri().to_string());
+        assert_eq!(select(8_000_000).as_deref(), Some("high.m3u8"));
+        assert_eq!(select(3_000_000).as_deref(), Some("mid.m3u8"));
+        assert_eq!(select(100_000).as_deref(), Some("low.m3u8"));
+    }
+
+    #[test]
+    fn learns_from_scheduler() {
+        let playlist = MediaPlaylist::parse_ext_m3u("#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXTINF:4,\n1.ts\n").unwrap();
+        let estimator = Arc::new(Mutex::new(EwmaEstimator::default()));
+        let mut scheduler = FetchScheduler::new(2);
+        scheduler.add_observer(estimator.clone());
+        scheduler.schedule(&playlist, 0, Duration::from_secs(4));
+
+        let now = Instant::now();
+        let request = scheduler.next_request(now).unwrap();
+        scheduler.finished(request.id, 500_000, now + Duration::from_millis(500));
+        assert_eq!(estimator.lock().unwrap().estimate(), Some(8_000_000));
+    }
+}
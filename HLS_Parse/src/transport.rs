@@ -0,0 +1,243 @@
+//! Issuing the requests of [`LiveFollower`] and [`FetchScheduler`][crate::FetchScheduler] over any
+//! HTTP stack. Both still do no I/O themselves: [`PlaylistTransport`] is the one place a request
+//! goes out, so hyper, isahc or an in-process mock for deterministic tests can be plugged in.
+//! With the `reqwest` feature, `reqwest::Client` is one.
+
+use core::future::Future;
+use core::time::Duration;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+
+use crate::{FetchRequest, FollowerEvent, LiveFollower, MediaPlaylist};
+
+/// A response from [`PlaylistTransport::get`], whatever its status.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransportResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl TransportResponse {
+    /// Whether the status is 2xx.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// The value of the first header called `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(x, _)| x.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+}
+
+/// Validators of a response, sent back so the server can answer a reload with 304 Not Modified
+/// and no body if the playlist hasn't changed. See <https://www.rfc-editor.org/rfc/rfc9110#section-13.1>.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Validators {
+    /// The `ETag` header, sent back as `If-None-Match`.
+    pub etag: Option<String>,
+
+    /// The `Last-Modified` header, sent back as `If-Modified-Since`.
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    pub fn from_response(response: &TransportResponse) -> Self {
+        Self {
+            etag: response.header("ETag").map(str::to_string),
+            last_modified: response.header("Last-Modified").map(str::to_string),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    /// Headers making a request conditional on the response having changed.
+    pub fn headers(&self) -> Vec<(&'static str, &str)> {
+        let etag = self.etag.as_deref().map(|x| ("If-None-Match", x));
+        etag.into_iter().chain(self.last_modified.as_deref().map(|x| ("If-Modified-Since", x))).collect()
+    }
+}
+
+/// Issues HTTP GET requests. Responses with an error status are still `Ok`; errors are for
+/// requests which got no response at all, e.g. DNS, TLS or connection failures.
+pub trait PlaylistTransport {
+    fn get(&self, url: &str, headers: &[(&str, &str)]) -> impl Future<Output = Result<TransportResponse>> + Send;
+}
+
+/// Outcome of [`LiveFollower::fetch`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum FetchedReload {
+    Reloaded(Vec<FollowerEvent>),
+
+    /// The server answered the conditional request with 304 Not Modified, so nothing was
+    /// parsed. See [`reload_unchanged`][LiveFollower::reload_unchanged].
+    Unchanged(Vec<FollowerEvent>),
+
+    /// The request failed with `status`, `None` for no response, and should be retried after
+    /// `retry_after`.
+    Failed { status: Option<u16>, retry_after: Duration },
+}
+
+impl LiveFollower {
+    /// Reloads the playlist at `url` with `transport`, reporting the response with
+    /// [`reload`][Self::reload], [`reload_unchanged`][Self::reload_unchanged] or
+    /// [`reload_failed`][Self::reload_failed]. Reloads are conditional on the validators of the
+    /// previous response from `url`, if it had any. Errors if the failure isn't retryable,
+    /// attempts are exhausted or the playlist doesn't parse. Waiting for the next reload or retry
+    /// is up to the caller.
+    pub async fn fetch(&mut self, transport: &impl PlaylistTransport, url: &str) -> Result<FetchedReload> {
+        let validators = self.validators.get(url).cloned().unwrap_or_default();
+        let status = match transport.get(url, &validators.headers()).await {
+            Ok(response) if response.status == 304 && !validators.is_empty() => {
+                return Ok(FetchedReload::Unchanged(self.reload_unchanged(Instant::now())));
+            }
+            Ok(response) if response.is_success() => {
+                let playlist = MediaPlaylist::parse_ext_m3u_bytes(&response.body)
+                    .with_context(|| format!("Could not parse {}", url))?;
+                let validators = Validators::from_response(&response);
+                if validators.is_empty() {
+                    self.validators.remove(url);
+                } else {
+                    self.validators.insert(url.to_string(), validators);
+                }
+                return Ok(FetchedReload::Reloaded(self.reload(&playlist, Instant::now())));
+            }
+            Ok(response) => Some(response.status),
+            Err(_) => None,
+        };
+        let retry_after = self.reload_failed(status)?;
+        Ok(FetchedReload::Failed { status, retry_after })
+    }
+}
+
+impl FetchRequest {
+    /// Issues the request with `transport`, resolving the URI against `playlist_url` and asking
+    /// for the range with a `Range` header.
+    pub async fn send(&self, transport: &impl PlaylistTransport, playlist_url: &str) -> Result<TransportResponse> {
+        let url = self.uri.resolve(playlist_url);
+        match self.range.to_http_range_header() {
+            Some(range) => transport.get(url.as_str(), &[("Range", &range)]).await,
+            None => transport.get(url.as_str(), &[]).await,
+        }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl PlaylistTransport for reqwest::Client {
+    fn get(&self, url: &str, headers: &[(&str, &str)]) -> impl Future<Output = Result<TransportResponse>> + Send {
+        let mut request = reqwest::Client::get(self, url);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        async move {
+            let response = request.send().await?;
+            let status = response.status().as_u16();
+            let headers = response
+                .headers()
+                .iter()
+                .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+                .collect();
+            let body = response.bytes().await?.to_vec();
+            Ok(TransportResponse { status, headers, body })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{FetchKind, FollowOptions, RequestRange, SegmentUri};
+
+    /// Serves queued responses, recording the URL and headers of each request.
+    #[derive(Default)]
+    struct Mock {
+        responses: Mutex<Vec<Result<TransportResponse>>>,
+        requests: Mutex<Vec<String>>,
+    }
+
+    impl Mock {
+        fn respond(&self, status: u16, body: &str) {
+            self.respond_with_headers(status, &[], body);
+        }
+
+        fn respond_with_headers(&self, status: u16, headers: &[(&str, &str)], body: &str) {
+            let headers = headers.iter().map(|(x, y)| (x.to_string(), y.to_string())).collect();
+            let response = TransportResponse { status, headers, body: body.as_bytes().to_vec() };
+            self.responses.lock().unwrap().insert(0, Ok(response));
+        }
+    }
+
+    impl PlaylistTransport for Mock {
+        fn get(&self, url: &str, headers: &[(&str, &str)]) -> impl Future<Output = Result<TransportResponse>> + Send {
+            let headers: String = headers.iter().map(|(x, y)| format!(" {}: {}", x, y)).collect();
+            self.requests.lock().unwrap().push(format!("{}{}", url, headers));
+            let response = self.responses.lock().unwrap().pop().unwrap_or_else(|| Err(anyhow::Error::msg("offline")));
+            async { response }
+        }
+    }
+
+    #[tokio::test]
+    async fn drives_follower_and_fetches() {
+        let transport = Mock::default();
+        let mut follower = LiveFollower::new(FollowOptions::default());
+        let url = "https://example.com/live/index.m3u8";
+
+        transport.respond(200, "#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXTINF:4,\n0.ts\n#EXTINF:4,\n1.ts\n");
+        let Ok(FetchedReload::Reloaded(events)) = follower.fetch(&transport, url).await else {
+            panic!("first reload should succeed");
+        };
+        assert_eq!(events.len(), 2);
+        transport.respond(503, "");
+        let reload = follower.fetch(&transport, url).await.unwrap();
+        assert!(matches!(reload, FetchedReload::Failed { status: Some(503), .. }));
+        assert!(matches!(follower.fetch(&transport, url).await, Ok(FetchedReload::Failed { status: None, .. })));
+        transport.respond(403, "");
+        assert!(follower.fetch(&transport, url).await.is_err());
+
+        let request = FetchRequest {
+            id: 0,
+            kind: FetchKind::Segment,
+            hinted: false,
+            uri: SegmentUri::new("1.ts"),
+            range: RequestRange::Bytes { offset: 100, length: Some(50) },
+            sequence: 1,
+            part: None,
+        };
+        transport.respond(206, "segment");
+        assert_eq!(request.send(&transport, url).await.unwrap().body, b"segment");
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(requests.len(), 5);
+        assert_eq!(requests[4], "https://example.com/live/1.ts Range: bytes=100-149");
+    }
+
+    #[tokio::test]
+    async fn reloads_conditionally() {
+        let transport = Mock::default();
+        let mut follower = LiveFollower::new(FollowOptions::default());
+        let url = "https://example.com/live/index.m3u8";
+        let playlist = "#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXTINF:4,\n0.ts\n";
+        let validators = [("ETag", "\"v1\""), ("last-modified", "Tue, 13 Oct 2026 10:00:00 GMT")];
+        transport.respond_with_headers(200, &validators, playlist);
+        assert!(matches!(follower.fetch(&transport, url).await, Ok(FetchedReload::Reloaded(_))));
+
+        transport.respond(304, "");
+        assert_eq!(follower.fetch(&transport, url).await.unwrap(), FetchedReload::Unchanged(Vec::new()));
+        assert_eq!(follower.reload_delay(), Duration::from_secs(2));
+        let mut restored = LiveFollower::restore(FollowOptions::default(), follower.state()).unwrap();
+        transport.respond(200, playlist);
+        assert!(matches!(restored.fetch(&transport, url).await, Ok(FetchedReload::Reloaded(_))));
+        //without validators there's nothing a 304 could confirm
+        transport.respond(304, "");
+        assert!(restored.fetch(&transport, url).await.is_err());
+
+        let requests = transport.requests.lock().unwrap();
+        let conditional = format!("{} If-None-Match: \"v1\" If-Modified-Since: Tue, 13 Oct 2026 10:00:00 GMT", url);
+        assert_eq!(*requests, vec![url.to_string(), conditional.clone(), conditional, url.to_string()]);
+    }
+}
@@ -0,0 +1,270 @@
+//! Segment URLs. See <https://datatracker.ietf.org/doc/html/rfc8216#section-4.1>.
+
+use core::fmt;
+
+/// URL of a media segment, kept exactly as written in the playlist. The query string and
+/// fragment are never normalized or dropped, since token-authenticated CDNs put signatures there.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
+pub struct SegmentUri(String);
+
+impl SegmentUri {
+    pub fn new(uri: impl Into<String>) -> Self {
+        Self(uri.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+
+    /// Whether the URL starts with a scheme, e.g. `https:`, rather than being relative to the
+    /// playlist.
+    pub fn is_absolute(&self) -> bool {
+        scheme(&self.0).is_some()
+    }
+
+    pub fn is_relative(&self) -> bool {
+        !self.is_absolute()
+    }
+
+    /// The URL without its query string and fragment.
+    pub fn path(&self) -> &str {
+        self.0.split(['?', '#']).next().unwrap_or_default()
+    }
+
+    /// The query string, without the leading `?`.
+    pub fn query(&self) -> Option<&str> {
+        let (_, query) = self.without_fragment().split_once('?')?;
+        Some(query)
+    }
+
+    /// The fragment, without the leading `#`.
+    pub fn fragment(&self) -> Option<&str> {
+        let (_, fragment) = self.0.split_once('#')?;
+        Some(fragment)
+    }
+
+    /// Resolves the URL against `base`, usually the URL the playlist was fetched from, following
+    /// <https://datatracker.ietf.org/doc/html/rfc3986#section-5.2>. Absolute URLs are returned as
+    /// they are, and the query string and fragment of a relative one are carried over unchanged.
+    pub fn resolve(&self, base: &str) -> SegmentUri {
+        if self.is_absolute() {
+            return self.clone();
+        }
+        let base = base.split('#').next().unwrap_or_default();
+        let (base_path, base_query) = match base.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (base, None),
+        };
+        let (base_scheme, base_rest) = match scheme(base_path) {
+            Some(scheme) => (&base_path[..=scheme.len()], &base_path[scheme.len() + 1..]),
+            None => ("", base_path),
+        };
+        if self.0.starts_with("//") {
+            return SegmentUri(format!("{}{}", base_scheme, self.0));
+        }
+        let (base_authority, base_path) = split_authority(base_rest);
+        let origin = format!("{}{}", base_scheme, base_authority);
+
+        let path = self.path();
+        let suffix = &self.0[path.len()..];
+        if path.is_empty() {
+            let query = match (self.query(), base_query) {
+                (None, Some(query)) => format!("?{}{}", query, suffix),
+                _ => suffix.to_string(),
+            };
+            return SegmentUri(format!("{}{}{}", origin, base_path, query));
+        }
+        let merged = if path.starts_with('/') {
+            path.to_string()
+        } else if !base_authority.is_empty() && base_path.is_empty() {
+            format!("/{}", path)
+        } else {
+            let directory = base_path.rfind('/').map_or("", |x| &base_path[..=x]);
+            format!("{}{}", directory, path)
+        };
+        SegmentUri(format!("{}{}{}", origin, remove_dot_segments(&merged), suffix))
+    }
+
+    fn without_fragment(&self) -> &str {
+        self.0.split('#').next().unwrap_or_default()
+    }
+}
+
+/// The scheme of `uri`, if it has one. See <https://datatracker.ietf.org/doc/html/rfc3986#section-3.1>.
+fn scheme(uri: &str) -> Option<&str> {
+    let (scheme, _) = uri.split_once(':')?;
+    let mut chars = scheme.chars();
+    let valid = chars.next().is_some_and(|x| x.is_ascii_alphabetic())
+        && chars.all(|x| x.is_ascii_alphanumeric() || matches!(x, '+' | '-' | '.'));
+    valid.then_some(scheme)
+}
+
+/// Splits the `//host` authority, if any, from the path of a URL without its scheme.
+fn split_authority(uri: &str) -> (&str, &str) {
+    match uri.strip_prefix("//") {
+        Some(rest) => uri.split_at(rest.find('/').map_or(uri.len(), |x| x + 2)),
+        None => ("", uri),
+    }
+}
+
+/// Resolves `.` and `..` segments of a path as described in
+/// <https://datatracker.ietf.org/doc/html/rfc3986#section-5.2.4>. Leading `..` segments of a
+/// relative path are kept, since they refer to parents of a directory which isn't known yet.
+fn remove_dot_segments(path: &str) -> String {
+    let (root, relative) = match path.strip_prefix('/') {
+        Some(relative) => ("/", relative),
+        None => ("", path),
+    };
+    let mut output: Vec<&str> = Vec::new();
+    let mut segments = relative.split('/').peekable();
+    while let Some(segment) = segments.next() {
+        match segment {
+            "." => {}
+            ".." if output.last().is_some_and(|x| *x != "..") => {
+                output.pop();
+            }
+            ".." if root.is_empty() => output.push(".."),
+            //never above the root of an absolute path
+            ".." => {}
+            _ => {
+                output.push(segment);
+                continue;
+            }
+        }
+        //a trailing dot segment refers to a directory
+        if segments.peek().is_none() {
+            output.push("");
+        }
+    }
+    format!("{}{}", root, output.join("/"))
+}
+
+/// [`remove_dot_segments`] on the path of `url`, keeping any scheme, authority, query and
+/// fragment.
+pub(crate) fn remove_url_dot_segments(url: &str) -> String {
+    let (url, suffix) = url.split_at(url.find(['?', '#']).unwrap_or(url.len()));
+    let scheme_len = scheme(url).map_or(0, |x| x.len() + 1);
+    let (authority, path) = split_authority(&url[scheme_len..]);
+    format!("{}{}{}{}", &url[..scheme_len], authority, remove_dot_segments(path), suffix)
+}
+
+impl fmt::Display for SegmentUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for SegmentUri {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SegmentUri {
+    fn from(uri: String) -> Self {
+        Self(uri)
+    }
+}
+
+impl From<&str> for SegmentUri {
+    fn from(uri: &str) -> Self {
+        Self(uri.to_string())
+    }
+}
+
+impl From<SegmentUri> for String {
+    fn from(uri: SegmentUri) -> Self {
+        uri.0
+    }
+}
+
+impl PartialEq<str> for SegmentUri {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for SegmentUri {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "https://cdn.example.com/live/main/index.m3u8?token=abc";
+
+    fn resolve(uri: &str) -> String {
+        SegmentUri::new(uri).resolve(BASE).into_string()
+    }
+
+    #[test]
+    fn splits_query_and_fragment() {
+        let uri = SegmentUri::new("1.ts?Policy=a%2Fb&Signature=c==#t=10");
+        assert!(uri.is_relative());
+        assert_eq!(uri.path(), "1.ts");
+        assert_eq!(uri.query(), Some("Policy=a%2Fb&Signature=c=="));
+        assert_eq!(uri.fragment(), Some("t=10"));
+
+        let uri = SegmentUri::new("https://cdn.example.com/1.ts#a?b");
+        assert!(uri.is_absolute());
+        assert_eq!(uri.query(), None);
+        assert_eq!(uri.fragment(), Some("a?b"));
+        assert!(SegmentUri::new("C:/1.ts").is_absolute());
+        assert!(SegmentUri::new("1:2.ts").is_relative());
+    }
+
+    #[test]
+    fn resolves_against_base() {
+        assert_eq!(resolve("1.ts?sig=x%2By"), "https://cdn.example.com/live/main/1.ts?sig=x%2By");
+        assert_eq!(resolve("../low/./1.ts#frag"), "https://cdn.example.com/live/low/1.ts#frag");
+        assert_eq!(resolve("../../../1.ts"), "https://cdn.example.com/1.ts");
+        assert_eq!(resolve("/vod/1.ts"), "https://cdn.example.com/vod/1.ts");
+        assert_eq!(resolve("//other.example.com/1.ts?a"), "https://other.example.com/1.ts?a");
+        assert_eq!(resolve("?other=1"), "https://cdn.example.com/live/main/index.m3u8?other=1");
+        assert_eq!(resolve(""), BASE);
+        assert_eq!(resolve("http://origin/1.ts?a=./b"), "http://origin/1.ts?a=./b");
+        assert_eq!(SegmentUri::new("1.ts").resolve("https://cdn.example.com").as_str(), "https://cdn.example.com/1.ts");
+        assert_eq!(SegmentUri::new("1.ts").resolve("video/index.m3u8").as_str(), "video/1.ts");
+        assert_eq!(SegmentUri::new("a/../b/1.ts").resolve("index.m3u8").as_str(), "b/1.ts");
+    }
+
+    #[test]
+    fn removes_dot_segments() {
+        assert_eq!(remove_url_dot_segments("a/./b/../c.ts"), "a/c.ts");
+        assert_eq!(remove_url_dot_segments("../../media/1.ts"), "../../media/1.ts");
+        assert_eq!(remove_url_dot_segments("a/../../1.ts"), "../1.ts");
+        assert_eq!(remove_url_dot_segments("/a/../../1.ts"), "/1.ts");
+        assert_eq!(remove_url_dot_segments("/a//../b"), "/a/b");
+        let url = "https://cdn.example.com/a/./b/../1.ts?p=../x";
+        assert_eq!(remove_url_dot_segments(url), "https://cdn.example.com/a/1.ts?p=../x");
+        assert_eq!(remove_url_dot_segments("https://cdn.example.com"), "https://cdn.example.com");
+        assert_eq!(remove_url_dot_segments("//cdn.example.com/a/../1.ts"), "//cdn.example.com/1.ts");
+        assert_eq!(remove_url_dot_segments("a/b/.."), "a/");
+        assert_eq!(remove_url_dot_segments("1.ts"), "1.ts");
+
+        //resolving and normalizing agree, since both remove dot segments the same way
+        for uri in ["a/./b/../c.ts", "../low/./1.ts", "../../../../1.ts", "a/b/..", "/a//../b"] {
+            let resolved = SegmentUri::new(uri).resolve(BASE);
+            assert_eq!(resolved, SegmentUri::new(remove_url_dot_segments(uri)).resolve(BASE).as_str(), "{}", uri);
+            assert_eq!(remove_url_dot_segments(resolved.as_str()), resolved.as_str());
+        }
+    }
+
+    #[test]
+    fn keeps_signatures_through_parsing() {
+        let file = "#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10,\n1.ts?Expires=1700000000&Signature=a~b%3D#t=0\n";
+        let playlist = crate::MediaPlaylist::parse_ext_m3u(file).unwrap();
+        let url = playlist.segments()[0].url();
+        assert_eq!(url.query(), Some("Expires=1700000000&Signature=a~b%3D"));
+        assert_eq!(url.resolve(BASE), "https://cdn.example.com/live/main/1.ts?Expires=1700000000&Signature=a~b%3D#t=0");
+        assert_eq!(playlist.to_string(), file);
+    }
+}
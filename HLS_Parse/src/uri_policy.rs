@@ -0,0 +1,218 @@
+//! Checking the URIs of a playlist before fetching anything it refers to, so services resolving
+//! playlists supplied by users can't be made to request local files, `data:` URIs or internal
+//! hosts on their behalf.
+
+use core::fmt;
+
+use anyhow::Result;
+
+use crate::{MasterPlaylist, MediaPlaylist, SegmentUri};
+
+/// What a URI checked by a [`UriValidator`] refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UriKind {
+    Segment,
+    Part,
+    Key,
+
+    /// A media initialization section.
+    Map,
+    PreloadHint,
+    RenditionReport,
+    Variant,
+    IFrameVariant,
+    Rendition,
+}
+
+impl fmt::Display for UriKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            UriKind::Segment => "Segment",
+            UriKind::Part => "Part",
+            UriKind::Key => "Key",
+            UriKind::Map => "Map",
+            UriKind::PreloadHint => "Preload hint",
+            UriKind::RenditionReport => "Rendition report",
+            UriKind::Variant => "Variant",
+            UriKind::IFrameVariant => "I-frame variant",
+            UriKind::Rendition => "Rendition",
+        })
+    }
+}
+
+/// Decides whether a URI may be fetched. Closures taking the resolved URI and its kind are
+/// validators too.
+pub trait UriValidator {
+    /// Fails if `uri`, already resolved against the playlist's URL, must not be fetched.
+    fn validate(&self, uri: &SegmentUri, kind: UriKind) -> Result<()>;
+}
+
+impl<F: Fn(&SegmentUri, UriKind) -> Result<()>> UriValidator for F {
+    fn validate(&self, uri: &SegmentUri, kind: UriKind) -> Result<()> {
+        self(uri, kind)
+    }
+}
+
+/// A [`UriValidator`] allowing only some schemes, `http` and `https` by default, and optionally
+/// only some hosts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UriPolicy {
+    schemes: Vec<String>,
+
+    /// `None` allows any host.
+    hosts: Option<Vec<String>>,
+}
+
+impl UriPolicy {
+    pub fn new() -> Self {
+        Self { schemes: vec!["http".to_string(), "https".to_string()], hosts: None }
+    }
+
+    /// Also allows `scheme`, e.g. `skd` for FairPlay key URIs, which players hand to the key
+    /// system rather than fetch.
+    pub fn allow_scheme(mut self, scheme: &str) -> Self {
+        self.schemes.push(scheme.to_ascii_lowercase());
+        self
+    }
+
+    /// Allows `host`, and once any host is allowed, no others. A leading dot allows every
+    /// subdomain, e.g. `.example.com` for `cdn.example.com`.
+    pub fn allow_host(mut self, host: &str) -> Self {
+        self.hosts.get_or_insert_with(Vec::new).push(host.to_ascii_lowercase());
+        self
+    }
+}
+
+impl Default for UriPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UriValidator for UriPolicy {
+    fn validate(&self, uri: &SegmentUri, kind: UriKind) -> Result<()> {
+        let Some((scheme, rest)) = uri.as_str().split_once(':').filter(|_| uri.is_absolute()) else {
+            return Err(anyhow::anyhow!("{} URI {} has no scheme", kind, uri));
+        };
+        if !self.schemes.iter().any(|x| x.eq_ignore_ascii_case(scheme)) {
+            return Err(anyhow::anyhow!("{} URI {} has disallowed scheme {}", kind, uri, scheme));
+        }
+        let Some(hosts) = &self.hosts else {
+            return Ok(());
+        };
+        let host = host(rest).to_ascii_lowercase();
+        let allowed = hosts.iter().any(|allowed| match allowed.strip_prefix('.') {
+            Some(domain) => host == domain || host.ends_with(allowed.as_str()),
+            None => host == *allowed,
+        });
+        if !allowed {
+            return Err(anyhow::anyhow!("{} URI {} has disallowed host {}", kind, uri, host));
+        }
+        Ok(())
+    }
+}
+
+/// Host of the `//user@host:port` authority at the start of `rest`, the URI after its scheme.
+/// Empty if there is none.
+fn host(rest: &str) -> &str {
+    let Some(rest) = rest.strip_prefix("//") else {
+        return "";
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    //user info can make a URI look like it's for another host, e.g. https://cdn.example.com@internal
+    let host_port = authority.rsplit_once('@').map_or(authority, |(_, x)| x);
+    match host_port.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => host_port.split(':').next().unwrap_or_default(),
+    }
+}
+
+impl MediaPlaylist {
+    /// Passes every URI a client could fetch for the playlist, resolved against `base`, the URL
+    /// it was fetched from, to `validator`, failing at the first it rejects. Date range client
+    /// attributes are left to the caller, as their meaning depends on the CLASS.
+    pub fn validate_uris(&self, base: &str, validator: &dyn UriValidator) -> Result<()> {
+        let check = |uri: &SegmentUri, kind| validator.validate(&uri.resolve(base), kind);
+        for segment in self.segments() {
+            for part in segment.parts() {
+                check(part.uri(), UriKind::Part)?;
+            }
+            check(segment.url(), UriKind::Segment)?;
+            for uri in segment.keys().iter().filter_map(|x| x.uri()) {
+                check(&SegmentUri::new(uri), UriKind::Key)?;
+            }
+            if let Some(map) = segment.map() {
+                check(&SegmentUri::new(map.uri()), UriKind::Map)?;
+            }
+        }
+        for part in self.trailing_parts() {
+            check(part.uri(), UriKind::Part)?;
+        }
+        for hint in self.preload_hints() {
+            check(hint.uri(), UriKind::PreloadHint)?;
+        }
+        for report in self.rendition_reports() {
+            check(&SegmentUri::new(report.uri()), UriKind::RenditionReport)?;
+        }
+        Ok(())
+    }
+}
+
+impl MasterPlaylist {
+    /// Like [`MediaPlaylist::validate_uris`], for the URIs of variants, I-frame variants and
+    /// renditions.
+    pub fn validate_uris(&self, base: &str, validator: &dyn UriValidator) -> Result<()> {
+        let check = |uri: &str, kind| validator.validate(&SegmentUri::new(uri).resolve(base), kind);
+        for variant in self.variants() {
+            check(variant.uri(), UriKind::Variant)?;
+        }
+        for variant in self.i_frame_variants() {
+            check(variant.uri(), UriKind::IFrameVariant)?;
+        }
+        for uri in self.renditions().iter().filter_map(|x| x.uri()) {
+            check(uri, UriKind::Rendition)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_disallowed_uris() {
+        let policy = UriPolicy::new().allow_host(".example.com");
+        let check = |uri: &str| policy.validate(&SegmentUri::new(uri), UriKind::Segment).map_err(|x| x.to_string());
+        assert!(check("https://cdn.example.com:8443/1.ts").is_ok());
+        assert!(check("http://example.com/1.ts").is_ok());
+        assert!(check("file:///etc/passwd").is_err());
+        assert!(check("data:video/mp2t;base64,AAAA").is_err());
+        assert_eq!(check("https://cdn.example.com@169.254.169.254/"), Err(
+            "Segment URI https://cdn.example.com@169.254.169.254/ has disallowed host 169.254.169.254".to_string()
+        ));
+        assert!(check("https://badexample.com/1.ts").is_err());
+        assert!(check("https://[::1]/1.ts").is_err());
+
+        let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-TARGETDURATION:10
+            #EXTINF:10,
+            1.ts
+            #EXT-X-KEY:METHOD=AES-128,URI="file:///keys/1.key"
+            #EXTINF:10,
+            2.ts
+        "#})
+        .unwrap();
+        let error = playlist.validate_uris("https://cdn.example.com/live/index.m3u8", &policy).unwrap_err();
+        assert_eq!(error.to_string(), "Key URI file:///keys/1.key has disallowed scheme file");
+        let count = core::cell::Cell::new(0);
+        let counter = |_: &SegmentUri, _| {
+            count.set(count.get() + 1);
+            Ok(())
+        };
+        playlist.validate_uris("https://cdn.example.com/live/index.m3u8", &counter).unwrap();
+        assert_eq!(count.get(), 3);
+        assert!(playlist.validate_uris("index.m3u8", &UriPolicy::new()).is_err());
+    }
+}
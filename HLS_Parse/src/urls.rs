@@ -0,0 +1,230 @@
+//! Rewriting every URL a playlist refers to, e.g. to re-sign CDN tokens or swap hostnames in a
+//! proxy before serializing the playlist again.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{EncryptionKey, MasterPlaylist, MediaPlaylist, MediaSegment, PartialSegment, SegmentMap};
+
+/// An original allocation and its mapped replacement.
+type Mapped<T> = (Arc<T>, Arc<T>);
+
+/// Keys, maps and URIs already mapped, so each is mapped once however many segments share it.
+/// Key lists and maps are found by the allocation the segments share, and the originals are kept
+/// so no other allocation can take their address while mapping.
+#[derive(Debug, Default)]
+pub(crate) struct SharedUrls {
+    uris: HashMap<String, String>,
+    keys: HashMap<*const EncryptionKey, Mapped<[EncryptionKey]>>,
+    maps: HashMap<*const SegmentMap, Mapped<SegmentMap>>,
+}
+
+impl SharedUrls {
+    fn uri(&mut self, uri: &str, map: &mut dyn FnMut(&str) -> String) -> String {
+        if let Some(mapped) = self.uris.get(uri) {
+            return mapped.clone();
+        }
+        let mapped = map(uri);
+        self.uris.insert(uri.to_string(), mapped.clone());
+        mapped
+    }
+
+    fn keys(&mut self, keys: &Arc<[EncryptionKey]>, map: &mut dyn FnMut(&str) -> String) -> Arc<[EncryptionKey]> {
+        let address = Arc::as_ptr(keys).cast::<EncryptionKey>();
+        if let Some((_, mapped)) = self.keys.get(&address) {
+            return mapped.clone();
+        }
+        let mut mapped = keys.to_vec();
+        for key in &mut mapped {
+            if let Some(uri) = key.uri() {
+                key.set_uri(Some(self.uri(uri, map)));
+            }
+        }
+        let mapped: Arc<[EncryptionKey]> = mapped.into();
+        self.keys.insert(address, (keys.clone(), mapped.clone()));
+        mapped
+    }
+
+    fn map(&mut self, segment_map: &Arc<SegmentMap>, map: &mut dyn FnMut(&str) -> String) -> Arc<SegmentMap> {
+        let address = Arc::as_ptr(segment_map);
+        if let Some((_, mapped)) = self.maps.get(&address) {
+            return mapped.clone();
+        }
+        let mut mapped = SegmentMap::clone(segment_map);
+        mapped.set_uri(self.uri(segment_map.uri(), map));
+        let mapped = Arc::new(mapped);
+        self.maps.insert(address, (segment_map.clone(), mapped.clone()));
+        mapped
+    }
+}
+
+impl MediaPlaylist {
+    /// Replaces the URL of every segment, part, preload hint, rendition report, key and media
+    /// initialization section, and the URIs in date range client attributes such as
+    /// X-ASSET-URI, with the result of `map`. Each distinct key and map URI is mapped once, so
+    /// segments sharing a key or map still share it afterwards.
+    pub fn map_urls(&mut self, mut map: impl FnMut(&str) -> String) {
+        let mut shared = SharedUrls::default();
+        for segment in self.segments_mut() {
+            segment.map_urls(&mut map, &mut shared);
+        }
+        //date ranges last, so the media is mapped in playback order first
+        for segment in self.segments_mut() {
+            segment.map_date_range_urls(&mut map);
+        }
+        let mut date_ranges = self.trailing_date_ranges().to_vec();
+        for date_range in &mut date_ranges {
+            date_range.map_urls(&mut map);
+        }
+        self.set_trailing_date_ranges(date_ranges);
+        let mut parts = self.trailing_parts().to_vec();
+        map_part_urls(&mut parts, &mut map);
+        self.set_trailing_parts(parts);
+        let mut hints = self.preload_hints().to_vec();
+        for hint in &mut hints {
+            hint.set_uri(map(hint.uri().as_str()));
+        }
+        self.set_preload_hints(hints);
+        let mut reports = self.rendition_reports().to_vec();
+        for report in &mut reports {
+            report.set_uri(map(report.uri()));
+        }
+        self.set_rendition_reports(reports);
+    }
+}
+
+impl MediaSegment {
+    /// Maps the URLs of the segment and its parts, and its keys and map unless `shared` already
+    /// has them from an earlier segment.
+    pub(crate) fn map_urls(&mut self, map: &mut dyn FnMut(&str) -> String, shared: &mut SharedUrls) {
+        if !self.parts().is_empty() {
+            let mut parts = self.parts().to_vec();
+            map_part_urls(&mut parts, map);
+            self.set_parts(parts);
+        }
+        let url = map(self.url().as_str());
+        self.set_url(url);
+        if !self.keys().is_empty() {
+            let keys = shared.keys(self.shared_keys(), map);
+            self.set_shared_keys(keys);
+        }
+        if let Some(segment_map) = self.shared_map() {
+            let segment_map = shared.map(segment_map, map);
+            self.set_shared_map(Some(segment_map));
+        }
+    }
+}
+
+impl MediaSegment {
+    pub(crate) fn map_date_range_urls(&mut self, map: &mut dyn FnMut(&str) -> String) {
+        if !self.date_ranges().is_empty() {
+            let mut date_ranges = self.date_ranges().to_vec();
+            for date_range in &mut date_ranges {
+                date_range.map_urls(map);
+            }
+            self.set_date_ranges(date_ranges);
+        }
+    }
+}
+
+fn map_part_urls(parts: &mut [PartialSegment], map: &mut dyn FnMut(&str) -> String) {
+    for part in parts {
+        part.set_uri(map(part.uri().as_str()));
+    }
+}
+
+impl MasterPlaylist {
+    /// Replaces the URI of every variant, I-frame variant, rendition, session data and session key
+    /// with the result of `map`. Renditions and session data without a URI are left as they are.
+    pub fn map_urls(&mut self, mut map: impl FnMut(&str) -> String) {
+        for variant in self.variants_mut() {
+            let uri = map(variant.uri());
+            variant.set_uri(uri);
+        }
+        for variant in self.i_frame_variants_mut() {
+            let uri = map(variant.uri());
+            variant.set_uri(uri);
+        }
+        for rendition in self.renditions_mut() {
+            let uri = rendition.uri().map(&mut map);
+            rendition.set_uri(uri);
+        }
+        for data in self.session_data_mut() {
+            let uri = data.uri().map(&mut map);
+            data.set_uri(uri);
+        }
+        for key in self.session_keys_mut() {
+            let uri = key.uri().map(&mut map);
+            key.set_uri(uri);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(url: &str) -> String {
+        format!("https://cdn.example.com/{}?token=abc", url)
+    }
+
+    #[test]
+    fn maps_media_playlist_urls() {
+        let mut playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-MAP:URI="init.mp4"
+            #EXT-X-KEY:METHOD=AES-128,URI="1.key"
+            #EXTINF:10,
+            1.ts
+            #EXTINF:10,
+            2.ts
+            #EXT-X-KEY:METHOD=NONE
+            #EXTINF:10,
+            3.ts
+        "#})
+        .unwrap();
+        let mut calls = 0;
+        playlist.map_urls(|url| {
+            calls += 1;
+            sign(url)
+        });
+        assert_eq!(calls, 5);
+        let segments = playlist.segments();
+        assert!(Arc::ptr_eq(segments[0].shared_keys(), segments[1].shared_keys()));
+        let maps: Vec<&Arc<SegmentMap>> = segments.iter().filter_map(MediaSegment::shared_map).collect();
+        assert!(maps.len() == 3 && maps.iter().all(|x| Arc::ptr_eq(x, maps[0])));
+        assert_eq!(
+            playlist.to_string(),
+            indoc::indoc! {r#"
+                #EXTM3U
+                #EXT-X-TARGETDURATION:10
+                #EXT-X-MAP:URI="https://cdn.example.com/init.mp4?token=abc"
+                #EXT-X-KEY:METHOD=AES-128,URI="https://cdn.example.com/1.key?token=abc"
+                #EXTINF:10,
+                https://cdn.example.com/1.ts?token=abc
+                #EXTINF:10,
+                https://cdn.example.com/2.ts?token=abc
+                #EXT-X-KEY:METHOD=NONE
+                #EXTINF:10,
+                https://cdn.example.com/3.ts?token=abc
+            "#}
+        );
+    }
+
+    #[test]
+    fn maps_master_playlist_urls() {
+        let mut playlist = MasterPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",NAME="English",URI="en.m3u8"
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",NAME="Muxed"
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000,AUDIO="aac"
+            low.m3u8
+        "#})
+        .unwrap();
+        playlist.map_urls(sign);
+        assert_eq!(playlist.variants()[0].uri(), "https://cdn.example.com/low.m3u8?token=abc");
+        assert_eq!(playlist.renditions()[0].uri(), Some("https://cdn.example.com/en.m3u8?token=abc"));
+        assert_eq!(playlist.renditions()[1].uri(), None);
+    }
+}
@@ -0,0 +1,78 @@
+//! Variable substitution, where EXT-X-DEFINE tags define values which later lines reference as
+//! `{$name}`. See <https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.3>.
+
+use std::borrow::Cow;
+
+use anyhow::Result;
+
+/// Variables defined so far.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Variables(Vec<(String, String)>);
+
+impl Variables {
+    pub(crate) fn define(&mut self, name: &str, value: &str) -> Result<()> {
+        if self.get(name).is_some() {
+            return Err(anyhow::anyhow!("Variable {} is defined more than once", name));
+        }
+        self.0.push((name.to_string(), value.to_string()));
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0.iter().find(|(existing, _)| existing == name).map(|(_, value)| value.as_str())
+    }
+
+    /// Replaces the variable references in a URI line, or in the quoted strings of a tag.
+    /// Comments are left alone, and references to undefined variables are errors.
+    pub(crate) fn substitute<'a>(&self, line: &'a str) -> Result<Cow<'a, str>> {
+        if !line.contains("{$") || (line.starts_with('#') && !line.starts_with("#EXT")) {
+            return Ok(Cow::Borrowed(line));
+        }
+        let tag = line.starts_with('#');
+        let mut substituted = String::with_capacity(line.len());
+        let mut quoted = false;
+        let mut rest = line;
+        while let Some(c) = rest.chars().next() {
+            if (quoted || !tag) && rest.starts_with("{$") {
+                if let Some((name, after)) = rest[2..].split_once('}').filter(|(name, _)| is_name(name)) {
+                    let value = self.get(name).ok_or_else(|| anyhow::anyhow!("Variable {} is not defined", name))?;
+                    substituted.push_str(value);
+                    rest = after;
+                    continue;
+                }
+            }
+            if c == '"' {
+                quoted = !quoted;
+            }
+            substituted.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+        Ok(Cow::Owned(substituted))
+    }
+}
+
+/// Whether `name` is a valid variable name: letters, digits, `-` and `_`.
+pub(crate) fn is_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|x| x.is_ascii_alphanumeric() || x == b'-' || x == b'_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutes_references() {
+        let mut variables = Variables::default();
+        variables.define("host", "cdn.example.com").unwrap();
+        variables.define("token", "a=1").unwrap();
+        assert!(variables.define("host", "other").is_err());
+
+        assert_eq!(variables.substitute("https://{$host}/1.ts?{$token}").unwrap(), "https://cdn.example.com/1.ts?a=1");
+        assert_eq!(
+            variables.substitute(r#"#EXT-X-MAP:URI="https://{$host}/init.mp4",BYTERANGE="{$x""#).unwrap(),
+            r#"#EXT-X-MAP:URI="https://cdn.example.com/init.mp4",BYTERANGE="{$x""#
+        );
+        assert_eq!(variables.substitute("# {$undefined}").unwrap(), "# {$undefined}");
+        assert!(variables.substitute("{$undefined}.ts").is_err());
+    }
+}
@@ -0,0 +1,472 @@
+//! Variant streams of a master playlist. See
+//! <https://datatracker.ietf.org/doc/html/rfc8216#section-4.3.4.2>.
+
+use core::fmt;
+use core::str::FromStr;
+
+use anyhow::Result;
+
+use crate::attributes::{AttributeList, AttributeWriter, RawAttributes};
+use crate::ClosedCaptions;
+
+/// Pixel dimensions from a RESOLUTION attribute, `<width>x<height>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Resolution {
+    pub width: u64,
+    pub height: u64,
+}
+
+/// Dynamic range of the video, from the VIDEO-RANGE attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VideoRange {
+    /// Standard dynamic range, assumed when the attribute is absent.
+    Sdr,
+    /// Hybrid Log-Gamma, which SDR displays can render acceptably.
+    Hlg,
+    /// Perceptual Quantizer (SMPTE ST 2084), e.g. HDR10 or Dolby Vision.
+    Pq,
+}
+
+/// Output protection the variant requires, from the HDCP-LEVEL attribute. Ordered from least to
+/// most demanding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HdcpLevel {
+    None,
+    /// Any version of HDCP.
+    Type0,
+    /// HDCP 2.2 or later, typically for 4K content.
+    Type1,
+}
+
+/// One element of a SUPPLEMENTAL-CODECS attribute, a format which clients can decode instead of
+/// the one in CODECS, e.g. `dvh1.08.07/db4h` for Dolby Vision backward compatible with HDR10.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SupplementalCodec {
+    pub codec: String,
+
+    /// Compatibility brands, e.g. `db4h`.
+    pub brands: Vec<String>,
+}
+
+/// One version of the presentation, from an EXT-X-STREAM-INF tag and the URI of the media
+/// playlist on the line after it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantStream {
+    /// Media playlist of the variant, relative to the master playlist unless absolute.
+    uri: String,
+
+    /// Peak bits per second of the variant, including all renditions it may be played with.
+    bandwidth: u64,
+
+    /// Average bits per second over the whole variant.
+    average_bandwidth: Option<u64>,
+
+    /// Comma-separated list of formats, e.g. `avc1.4d401e,mp4a.40.2`.
+    codecs: Option<String>,
+
+    resolution: Option<Resolution>,
+
+    /// Maximum frames per second of the video.
+    frame_rate: Option<f64>,
+
+    /// GROUP-ID of the audio renditions the variant plays with.
+    audio: Option<String>,
+
+    /// GROUP-ID of the video renditions the variant plays with.
+    video: Option<String>,
+
+    /// GROUP-ID of the subtitle renditions the variant plays with.
+    subtitles: Option<String>,
+
+    closed_captions: Option<ClosedCaptions>,
+
+    video_range: Option<VideoRange>,
+
+    hdcp_level: Option<HdcpLevel>,
+
+    /// Formats which can be decoded instead of those in CODECS, typically enhancement layers.
+    supplemental_codecs: Vec<SupplementalCodec>,
+
+    /// Identifier of the variant that stays the same across reloads of the master playlist, even
+    /// if its URI changes.
+    stable_variant_id: Option<String>,
+
+    /// The attribute list as parsed.
+    raw: RawAttributes,
+}
+
+impl VariantStream {
+    /// Parses the attribute list of an EXT-X-STREAM-INF tag. The URI isn't part of the tag, so it
+    /// is left empty.
+    pub(crate) fn parse(attribute_list: &str) -> Result<Self> {
+        let attributes = AttributeList::parse(attribute_list)?;
+        let Some(bandwidth) = attributes.get("BANDWIDTH") else {
+            return Err(anyhow::Error::msg("Stream is missing BANDWIDTH attribute"));
+        };
+        let bandwidth = parse_integer("BANDWIDTH", bandwidth)?;
+        let average_bandwidth = match attributes.get("AVERAGE-BANDWIDTH") {
+            Some(value) => Some(parse_integer("AVERAGE-BANDWIDTH", value)?),
+            None => None,
+        };
+        let resolution = match attributes.get("RESOLUTION") {
+            Some(value) => Some(value.parse::<Resolution>()?),
+            None => None,
+        };
+        let frame_rate = match attributes.get("FRAME-RATE") {
+            Some(value) => match value.parse::<f64>() {
+                Ok(frame_rate) if frame_rate.is_finite() && frame_rate > 0.0 => Some(frame_rate),
+                _ => return Err(anyhow::anyhow!("Invalid frame rate {}", value)),
+            },
+            None => None,
+        };
+        let video_range = match attributes.get("VIDEO-RANGE") {
+            Some("SDR") => Some(VideoRange::Sdr),
+            Some("HLG") => Some(VideoRange::Hlg),
+            Some("PQ") => Some(VideoRange::Pq),
+            Some(other) => return Err(anyhow::anyhow!("Unknown video range {}", other)),
+            None => None,
+        };
+        let hdcp_level = match attributes.get("HDCP-LEVEL") {
+            Some("NONE") => Some(HdcpLevel::None),
+            Some("TYPE-0") => Some(HdcpLevel::Type0),
+            Some("TYPE-1") => Some(HdcpLevel::Type1),
+            Some(other) => return Err(anyhow::anyhow!("Unknown HDCP level {}", other)),
+            None => None,
+        };
+        let closed_captions = match attributes.get("CLOSED-CAPTIONS") {
+            Some("NONE") => Some(ClosedCaptions::None),
+            Some(_) => attributes.quoted_string("CLOSED-CAPTIONS")?.map(|x| ClosedCaptions::Group(x.to_string())),
+            None => None,
+        };
+        let supplemental_codecs = match attributes.quoted_string("SUPPLEMENTAL-CODECS")? {
+            Some(value) => value.split(',').map(str::parse).collect::<Result<_>>()?,
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            uri: String::new(),
+            bandwidth,
+            average_bandwidth,
+            codecs: attributes.quoted_string("CODECS")?.map(str::to_string),
+            resolution,
+            frame_rate,
+            audio: attributes.quoted_string("AUDIO")?.map(str::to_string),
+            video: attributes.quoted_string("VIDEO")?.map(str::to_string),
+            subtitles: attributes.quoted_string("SUBTITLES")?.map(str::to_string),
+            closed_captions,
+            video_range,
+            hdcp_level,
+            supplemental_codecs,
+            stable_variant_id: attributes.stable_id("STABLE-VARIANT-ID")?.map(str::to_string),
+            raw: RawAttributes::new(attribute_list),
+        })
+    }
+
+    /// Parses the attribute list of an EXT-X-I-FRAME-STREAM-INF tag, which has the URI as an
+    /// attribute.
+    pub(crate) fn parse_i_frame(attribute_list: &str) -> Result<Self> {
+        let mut variant = Self::parse(attribute_list)?;
+        let attributes = AttributeList::parse(attribute_list)?;
+        //RFC8216 4.3.4.3, attributes about the full variant don't apply to its I-frames
+        let full_variant_attributes = ["FRAME-RATE", "AUDIO", "SUBTITLES", "CLOSED-CAPTIONS"];
+        if let Some(name) = full_variant_attributes.into_iter().find(|x| attributes.get(x).is_some()) {
+            return Err(anyhow::anyhow!("Attribute {} is not allowed for I-frame streams", name));
+        }
+        let Some(uri) = attributes.quoted_string("URI")? else {
+            return Err(anyhow::Error::msg("I-frame stream is missing URI attribute"));
+        };
+        variant.uri = uri.to_string();
+        Ok(variant)
+    }
+
+    pub(crate) fn set_uri(&mut self, uri: impl Into<String>) {
+        self.uri = uri.into();
+    }
+
+    /// Raw value of the attribute `name` as parsed, including ones the crate doesn't model, see
+    /// [`RawAttributes`][crate::RawAttributes].
+    pub fn raw_attribute(&self, name: &str) -> Option<&str> {
+        self.raw.get(name)
+    }
+
+    /// Every attribute of the EXT-X-STREAM-INF or EXT-X-I-FRAME-STREAM-INF tag as parsed.
+    pub fn raw_attributes(&self) -> &RawAttributes {
+        &self.raw
+    }
+
+    /// Media playlist of the variant, relative to the master playlist unless absolute.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Peak bits per second.
+    pub fn bandwidth(&self) -> u64 {
+        self.bandwidth
+    }
+
+    /// Average bits per second, if the playlist states it.
+    pub fn average_bandwidth(&self) -> Option<u64> {
+        self.average_bandwidth
+    }
+
+    /// Formats in the variant as written, e.g. `avc1.4d401e,mp4a.40.2`.
+    pub fn codecs(&self) -> Option<&str> {
+        self.codecs.as_deref()
+    }
+
+    pub fn resolution(&self) -> Option<Resolution> {
+        self.resolution
+    }
+
+    /// Maximum frames per second of the video.
+    pub fn frame_rate(&self) -> Option<f64> {
+        self.frame_rate
+    }
+
+    /// GROUP-ID of the audio renditions.
+    pub fn audio(&self) -> Option<&str> {
+        self.audio.as_deref()
+    }
+
+    /// GROUP-ID of the video renditions.
+    pub fn video(&self) -> Option<&str> {
+        self.video.as_deref()
+    }
+
+    /// GROUP-ID of the subtitle renditions.
+    pub fn subtitles(&self) -> Option<&str> {
+        self.subtitles.as_deref()
+    }
+
+    /// Closed captions in the video, `None` if the playlist doesn't say.
+    pub fn closed_captions(&self) -> Option<&ClosedCaptions> {
+        self.closed_captions.as_ref()
+    }
+
+    /// Dynamic range as stated, `None` meaning SDR.
+    pub fn video_range(&self) -> Option<VideoRange> {
+        self.video_range
+    }
+
+    /// Output protection required, `None` if not stated.
+    pub fn hdcp_level(&self) -> Option<HdcpLevel> {
+        self.hdcp_level
+    }
+
+    /// Alternative formats from SUPPLEMENTAL-CODECS.
+    pub fn supplemental_codecs(&self) -> &[SupplementalCodec] {
+        &self.supplemental_codecs
+    }
+
+    /// Identifier from STABLE-VARIANT-ID, for matching the variant across master playlist
+    /// updates.
+    pub fn stable_variant_id(&self) -> Option<&str> {
+        self.stable_variant_id.as_deref()
+    }
+}
+
+impl VariantStream {
+    /// The attribute list of the variant's EXT-X-STREAM-INF tag, without the URI.
+    pub(crate) fn attributes(&self) -> AttributeWriter {
+        let mut list = AttributeWriter::default();
+        list.value("BANDWIDTH", self.bandwidth);
+        if let Some(average_bandwidth) = self.average_bandwidth {
+            list.value("AVERAGE-BANDWIDTH", average_bandwidth);
+        }
+        if let Some(codecs) = &self.codecs {
+            list.quoted("CODECS", codecs);
+        }
+        if !self.supplemental_codecs.is_empty() {
+            let codecs: Vec<String> = self.supplemental_codecs.iter().map(ToString::to_string).collect();
+            list.quoted_list("SUPPLEMENTAL-CODECS", &codecs);
+        }
+        if let Some(resolution) = self.resolution {
+            list.value("RESOLUTION", resolution);
+        }
+        if let Some(frame_rate) = self.frame_rate {
+            list.value("FRAME-RATE", format_args!("{:.3}", frame_rate));
+        }
+        if let Some(hdcp_level) = self.hdcp_level {
+            list.enumerated("HDCP-LEVEL", hdcp_level.as_str(), &["NONE", "TYPE-0", "TYPE-1"]);
+        }
+        if let Some(video_range) = self.video_range {
+            list.enumerated("VIDEO-RANGE", video_range.as_str(), &["SDR", "HLG", "PQ"]);
+        }
+        for (name, group_id) in [("AUDIO", &self.audio), ("VIDEO", &self.video), ("SUBTITLES", &self.subtitles)] {
+            if let Some(group_id) = group_id {
+                list.quoted(name, group_id);
+            }
+        }
+        match &self.closed_captions {
+            Some(ClosedCaptions::Group(group_id)) => {
+                list.quoted("CLOSED-CAPTIONS", group_id);
+            }
+            Some(ClosedCaptions::None) => {
+                list.enumerated("CLOSED-CAPTIONS", "NONE", &["NONE"]);
+            }
+            None => {}
+        }
+        if let Some(stable_variant_id) = &self.stable_variant_id {
+            list.quoted("STABLE-VARIANT-ID", stable_variant_id);
+        }
+        list
+    }
+}
+
+impl fmt::Display for VariantStream {
+    /// Formats the variant as the attribute list of an EXT-X-STREAM-INF tag, without the URI.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.attributes().as_str())
+    }
+}
+
+impl VideoRange {
+    /// Value of the VIDEO-RANGE attribute.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VideoRange::Sdr => "SDR",
+            VideoRange::Hlg => "HLG",
+            VideoRange::Pq => "PQ",
+        }
+    }
+}
+
+impl HdcpLevel {
+    /// Value of the HDCP-LEVEL attribute.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HdcpLevel::None => "NONE",
+            HdcpLevel::Type0 => "TYPE-0",
+            HdcpLevel::Type1 => "TYPE-1",
+        }
+    }
+}
+
+impl fmt::Display for VideoRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for HdcpLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SupplementalCodec {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut parts = value.split('/');
+        let codec = parts.next().unwrap_or_default();
+        let brands: Vec<String> = parts.map(str::to_string).collect();
+        if codec.is_empty() || brands.iter().any(String::is_empty) {
+            return Err(anyhow::anyhow!("Invalid supplemental codec \"{}\"", value));
+        }
+        Ok(Self { codec: codec.to_string(), brands })
+    }
+}
+
+impl fmt::Display for SupplementalCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.codec)?;
+        for brand in &self.brands {
+            write!(f, "/{}", brand)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_integer(name: &str, value: &str) -> Result<u64> {
+    value.parse::<u64>().map_err(|_| anyhow::anyhow!("Attribute {} should be a decimal integer", name))
+}
+
+impl FromStr for Resolution {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parsed = value
+            .split_once('x')
+            .and_then(|(width, height)| Some(Self { width: width.parse().ok()?, height: height.parse().ok()? }));
+        parsed.ok_or_else(|| anyhow::anyhow!("Invalid resolution {}", value))
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_stream_inf() {
+        let variant = VariantStream::parse(
+            r#"BANDWIDTH=1280000,AVERAGE-BANDWIDTH=1000000,CODECS="avc1.4d401e,mp4a.40.2",RESOLUTION=1280x720,FRAME-RATE=29.970,AUDIO="aac""#,
+        )
+        .expect("should parse");
+        assert_eq!(variant.bandwidth(), 1280000);
+        assert_eq!(variant.average_bandwidth(), Some(1000000));
+        assert_eq!(variant.codecs(), Some("avc1.4d401e,mp4a.40.2"));
+        assert_eq!(variant.resolution(), Some(Resolution { width: 1280, height: 720 }));
+        assert_eq!(variant.frame_rate(), Some(29.97));
+        assert_eq!(variant.audio(), Some("aac"));
+        assert_eq!(variant.video(), None);
+        assert_eq!(variant.uri(), "");
+        assert_eq!(variant.raw_attribute("CODECS"), Some(r#""avc1.4d401e,mp4a.40.2""#));
+        assert_eq!(variant.raw_attribute("FRAME-RATE"), Some("29.970"));
+
+        let mut variant = VariantStream::parse(r#"BANDWIDTH=1,REQ-VIDEO-LAYOUT="CH-STEREO",SCORE=2.5"#).unwrap();
+        let names: Vec<&str> = variant.raw_attributes().iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["BANDWIDTH", "REQ-VIDEO-LAYOUT", "SCORE"]);
+        assert_eq!(variant.raw_attribute("SCORE"), Some("2.5"));
+        let unparsed = variant.clone();
+        variant.raw = RawAttributes::default();
+        assert_eq!(variant, unparsed);
+        assert_eq!(variant.raw_attribute("SCORE"), None);
+    }
+
+    #[test]
+    fn parses_dynamic_range_attributes() {
+        let variant = VariantStream::parse(
+            r#"BANDWIDTH=1,VIDEO-RANGE=PQ,HDCP-LEVEL=TYPE-1,SUPPLEMENTAL-CODECS="dvh1.08.07/db4h,dvh1.08.07""#,
+        )
+        .expect("should parse");
+        assert_eq!(variant.video_range(), Some(VideoRange::Pq));
+        assert_eq!(variant.hdcp_level(), Some(HdcpLevel::Type1));
+        assert_eq!(
+            variant.supplemental_codecs(),
+            [
+                SupplementalCodec { codec: "dvh1.08.07".to_string(), brands: vec!["db4h".to_string()] },
+                SupplementalCodec { codec: "dvh1.08.07".to_string(), brands: vec![] },
+            ]
+        );
+        assert_eq!(variant.supplemental_codecs()[0].to_string(), "dvh1.08.07/db4h");
+        assert!(HdcpLevel::None < HdcpLevel::Type0 && HdcpLevel::Type0 < HdcpLevel::Type1);
+
+        assert!(VariantStream::parse("BANDWIDTH=1,VIDEO-RANGE=HDR").is_err());
+        assert!(VariantStream::parse("BANDWIDTH=1,HDCP-LEVEL=TYPE-2").is_err());
+        assert!(VariantStream::parse(r#"BANDWIDTH=1,SUPPLEMENTAL-CODECS="dvh1.08.07/""#).is_err());
+    }
+
+    #[test]
+    fn validates_attributes() {
+        assert!(VariantStream::parse("BANDWIDTH=1").is_ok());
+        assert!(VariantStream::parse(r#"CODECS="avc1.4d401e""#).is_err());
+        assert!(VariantStream::parse("BANDWIDTH=fast").is_err());
+        assert!(VariantStream::parse("BANDWIDTH=1,RESOLUTION=1280").is_err());
+        assert!(VariantStream::parse("BANDWIDTH=1,FRAME-RATE=0").is_err());
+        assert!(VariantStream::parse("BANDWIDTH=1,AUDIO=aac").is_err());
+        assert!(VariantStream::parse("BANDWIDTH=1,CLOSED-CAPTIONS=cc").is_err());
+        assert_eq!(VariantStream::parse_i_frame(r#"BANDWIDTH=1,URI="iframes.m3u8""#).unwrap().uri(), "iframes.m3u8");
+        assert!(VariantStream::parse_i_frame("BANDWIDTH=1").is_err());
+        assert!(VariantStream::parse_i_frame(r#"BANDWIDTH=1,AUDIO="aac",URI="iframes.m3u8""#).is_err());
+        assert!(VariantStream::parse(r#"BANDWIDTH=1,STABLE-VARIANT-ID="hd/1080p=a+b""#).is_ok());
+        assert!(VariantStream::parse(r#"BANDWIDTH=1,STABLE-VARIANT-ID="1080p hd""#).is_err());
+        assert!(VariantStream::parse(r#"BANDWIDTH=1,STABLE-VARIANT-ID="""#).is_err());
+    }
+}
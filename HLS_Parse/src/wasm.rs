@@ -0,0 +1,77 @@
+//! JavaScript bindings, enabled with the `wasm-bindgen` feature. Parsed playlists are returned as
+//! plain JS objects, with durations in (fractional) seconds.
+
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::{LanguageTag, MasterPlaylist, MediaPlaylist};
+
+/// Parses a media playlist, throwing an `Error` if it does not adhere to the specification.
+///
+/// Returns `{ version, targetDuration, ended, segments: [{ duration, url }] }`.
+#[wasm_bindgen(js_name = parseMediaPlaylist)]
+pub fn parse_media_playlist(file: &str) -> Result<Object, JsValue> {
+    let playlist = MediaPlaylist::parse_ext_m3u(file).map_err(to_js_error)?;
+
+    let segments = Array::new();
+    for segment in playlist.segments() {
+        let object = Object::new();
+        set(&object, "duration", segment.duration().as_secs_f64().into())?;
+        set(&object, "url", segment.url().as_str().into())?;
+        segments.push(&object);
+    }
+
+    let object = Object::new();
+    set(&object, "version", (playlist.version() as f64).into())?;
+    set(&object, "targetDuration", playlist.target_duration().as_secs_f64().into())?;
+    set(&object, "ended", playlist.ended().into())?;
+    set(&object, "segments", segments.into())?;
+    Ok(object)
+}
+
+/// Parses a master playlist, throwing an `Error` if it does not adhere to the specification.
+///
+/// Returns `{ version, variants: [{ uri, bandwidth, codecs, resolution, audio }], renditions:
+/// [{ type, groupId, name, language, uri, default }] }`, with absent attributes as `undefined`
+/// and resolutions as `"<width>x<height>"`.
+#[wasm_bindgen(js_name = parseMasterPlaylist)]
+pub fn parse_master_playlist(file: &str) -> Result<Object, JsValue> {
+    let playlist = MasterPlaylist::parse_ext_m3u(file).map_err(to_js_error)?;
+
+    let variants = Array::new();
+    for variant in playlist.variants() {
+        let object = Object::new();
+        set(&object, "uri", variant.uri().into())?;
+        set(&object, "bandwidth", (variant.bandwidth() as f64).into())?;
+        set(&object, "codecs", variant.codecs().into())?;
+        set(&object, "resolution", variant.resolution().map(|x| x.to_string()).into())?;
+        set(&object, "audio", variant.audio().into())?;
+        variants.push(&object);
+    }
+
+    let renditions = Array::new();
+    for rendition in playlist.renditions() {
+        let object = Object::new();
+        set(&object, "type", rendition.media_type().as_str().into())?;
+        set(&object, "groupId", rendition.group_id().into())?;
+        set(&object, "name", rendition.name().into())?;
+        set(&object, "language", rendition.language().map(LanguageTag::as_str).into())?;
+        set(&object, "uri", rendition.uri().into())?;
+        set(&object, "default", rendition.is_default().into())?;
+        renditions.push(&object);
+    }
+
+    let object = Object::new();
+    set(&object, "version", (playlist.version() as f64).into())?;
+    set(&object, "variants", variants.into())?;
+    set(&object, "renditions", renditions.into())?;
+    Ok(object)
+}
+
+fn set(object: &Object, key: &str, value: JsValue) -> Result<(), JsValue> {
+    Reflect::set(object, &key.into(), &value).map(|_| ())
+}
+
+fn to_js_error(error: anyhow::Error) -> JsValue {
+    js_sys::Error::new(&error.to_string()).into()
+}
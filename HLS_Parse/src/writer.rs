@@ -0,0 +1,600 @@
+//! Serialization of playlists back into [ext-m3u][m3u] data.
+//!
+//! [m3u]: https://en.wikipedia.org/wiki/M3U#Extended_M3U
+
+use core::fmt::{self, Write};
+use core::time::Duration;
+use std::borrow::Cow;
+
+use anyhow::{Context, Result};
+
+use crate::attributes::AttributeList;
+use crate::segment_tags::TagSlot;
+use crate::events::{
+    ALLOW_CACHE_TAG, ATTRIBUTE_LIST_TAGS, BYTERANGE_TAG, DATERANGE_TAG, DISCONTINUITY_SEQUENCE_TAG, DISCONTINUITY_TAG,
+    DURATION_TAG, ENDLIST_TAG, GAP_TAG, HEADER_TAG, I_FRAMES_ONLY_TAG, I_FRAME_STREAM_INF_TAG, KEY_TAG, MAP_TAG,
+    MEDIA_SEQUENCE_TAG, MEDIA_TAG, PART_INF_TAG, PART_TAG, PLAYLIST_TYPE_TAG, PRELOAD_HINT_TAG, PROGRAM_DATE_TIME_TAG,
+    RENDITION_REPORT_TAG, SEGMENT_TAG, SESSION_DATA_TAG, SESSION_KEY_TAG, SKIP_TAG, STREAM_INF_TAG, VERSION_TAG,
+};
+use crate::{
+    DateRange, EncryptionKey, MasterPlaylist, MediaPlaylist, MediaSegment, PartialSegment, PlaylistType, PreloadHint,
+    RenditionReport, SegmentDuration, SegmentMap,
+};
+
+/// Controls how [`MediaPlaylist::write`] and [`MasterPlaylist::write`] format their output. The
+/// formatting options apply to every line, including those reused from the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Reuse the original lines of a playlist parsed with
+    /// [`ParseOptions::preserve_source`][crate::ParseOptions::preserve_source], so only the parts
+    /// of the model which were modified are written anew. Has no effect on other playlists.
+    pub preserve_source: bool,
+
+    /// Number of decimal places in EXTINF durations, rounding as needed. `None` writes each
+    /// duration exactly as it was parsed or set.
+    pub duration_precision: Option<usize>,
+
+    /// Whether to write the comma after an EXTINF duration when there is no title, as RFC8216
+    /// requires. Some legacy devices only accept it without.
+    pub trailing_comma: bool,
+
+    pub line_ending: LineEnding,
+
+    pub attribute_quoting: AttributeQuoting,
+
+    /// When to write EXT-X-VERSION. Has no effect on master playlists.
+    pub version_tag: VersionTag,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            preserve_source: true,
+            duration_precision: None,
+            trailing_comma: true,
+            line_ending: LineEnding::default(),
+            attribute_quoting: AttributeQuoting::default(),
+            version_tag: VersionTag::default(),
+        }
+    }
+}
+
+/// Line terminator of written playlists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+/// Which attribute values are written as quoted strings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AttributeQuoting {
+    /// Only those the specification defines as quoted strings, e.g. `METHOD=AES-128,URI="1.key"`.
+    #[default]
+    Spec,
+
+    /// Enumerated strings too, e.g. `METHOD="AES-128",URI="1.key"`, as some legacy packagers
+    /// wrote them. Numbers, resolutions and hexadecimal sequences stay unquoted. Playlists written
+    /// this way don't conform to the specification, and this crate doesn't parse them.
+    EnumeratedStrings,
+}
+
+/// When [`MediaPlaylist::write`] writes the EXT-X-VERSION tag.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VersionTag {
+    /// Only if the playlist has a version, see [`MediaPlaylist::version`].
+    #[default]
+    Auto,
+
+    /// Even if the playlist has no version, in which case
+    /// [`MediaPlaylist::required_version`] is written.
+    Always,
+}
+
+impl WriteOptions {
+    /// Applies the formatting options to the written lines, adding an EXT-X-VERSION tag after
+    /// the header with `missing_version` if given.
+    fn format(&self, out: String, missing_version: Option<u64>) -> String {
+        let unchanged = self.duration_precision.is_none()
+            && self.trailing_comma
+            && self.line_ending == LineEnding::Lf
+            && self.attribute_quoting == AttributeQuoting::Spec;
+        if unchanged && missing_version.is_none() {
+            return out;
+        }
+        let line_ending = match self.line_ending {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        };
+        let mut formatted = String::with_capacity(out.len());
+        for (index, line) in out.lines().enumerate() {
+            formatted.push_str(&self.format_line(line));
+            formatted.push_str(line_ending);
+            if let Some(version) = missing_version.filter(|_| index == 0) {
+                write!(formatted, "#{}:{}{}", VERSION_TAG, version, line_ending).unwrap();
+            }
+        }
+        formatted
+    }
+
+    fn format_line<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        let Some((name, value)) = line.strip_prefix('#').and_then(|x| x.split_once(':')) else {
+            return Cow::Borrowed(line);
+        };
+        if name == SEGMENT_TAG {
+            let (duration, title) = value.split_once(',').unwrap_or((value, ""));
+            let duration = match (self.duration_precision, duration.parse::<SegmentDuration>()) {
+                (Some(precision), Ok(exact)) => Cow::Owned(format!("{:.*}", precision, exact.as_secs_f64())),
+                _ => Cow::Borrowed(duration),
+            };
+            return Cow::Owned(if title.is_empty() && !self.trailing_comma {
+                format!("#{}:{}", SEGMENT_TAG, duration)
+            } else {
+                format!("#{}:{},{}", SEGMENT_TAG, duration, title)
+            });
+        }
+        if self.attribute_quoting == AttributeQuoting::EnumeratedStrings && ATTRIBUTE_LIST_TAGS.contains(&name) {
+            let Ok(attributes) = AttributeList::parse(value) else {
+                return Cow::Borrowed(line);
+            };
+            let mut quoted = format!("#{}:", name);
+            for (index, (name, value)) in attributes.iter().enumerate() {
+                let separator = if index == 0 { "" } else { "," };
+                //enumerated strings are the only unquoted values which don't start with a digit
+                if value.starts_with(|x: char| x.is_ascii_alphabetic()) {
+                    write!(quoted, "{}{}=\"{}\"", separator, name, value).unwrap();
+                } else {
+                    write!(quoted, "{}{}={}", separator, name, value).unwrap();
+                }
+            }
+            return Cow::Owned(quoted);
+        }
+        Cow::Borrowed(line)
+    }
+}
+
+/// A playlist-level tag along with its value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum PlaylistTag {
+    Version(u64),
+    TargetDuration(Duration),
+    MediaSequence(u64),
+    DiscontinuitySequence(u64),
+    AllowCache(bool),
+    PlaylistType(PlaylistType),
+    IFramesOnly,
+    PartInf(SegmentDuration),
+    Skip(u64),
+    EndList,
+}
+
+impl PlaylistTag {
+    /// The tags which go before the first segment, in order.
+    pub(crate) fn header_tags(playlist: &MediaPlaylist) -> Vec<PlaylistTag> {
+        let mut tags = Vec::new();
+        if playlist.version() > 0 {
+            tags.push(PlaylistTag::Version(playlist.version()));
+        }
+        tags.push(PlaylistTag::TargetDuration(playlist.target_duration()));
+        if playlist.media_sequence() > 0 {
+            tags.push(PlaylistTag::MediaSequence(playlist.media_sequence()));
+        }
+        if playlist.discontinuity_sequence() > 0 {
+            tags.push(PlaylistTag::DiscontinuitySequence(playlist.discontinuity_sequence()));
+        }
+        if let Some(allow_cache) = playlist.allow_cache() {
+            tags.push(PlaylistTag::AllowCache(allow_cache));
+        }
+        if let Some(playlist_type) = playlist.playlist_type() {
+            tags.push(PlaylistTag::PlaylistType(playlist_type));
+        }
+        if playlist.i_frames_only() {
+            tags.push(PlaylistTag::IFramesOnly);
+        }
+        if let Some(part_target) = playlist.exact_part_target() {
+            tags.push(PlaylistTag::PartInf(part_target));
+        }
+        if playlist.skipped_segments() > 0 {
+            tags.push(PlaylistTag::Skip(playlist.skipped_segments()));
+        }
+        tags
+    }
+
+    /// The same tag with the value the playlist has now, `None` if the playlist no longer has it.
+    pub(crate) fn current(&self, playlist: &MediaPlaylist) -> Option<PlaylistTag> {
+        match self {
+            PlaylistTag::Version(_) => Some(playlist.version()).filter(|x| *x > 0).map(PlaylistTag::Version),
+            PlaylistTag::TargetDuration(_) => Some(PlaylistTag::TargetDuration(playlist.target_duration())),
+            PlaylistTag::MediaSequence(_) => Some(PlaylistTag::MediaSequence(playlist.media_sequence())),
+            PlaylistTag::DiscontinuitySequence(_) => {
+                Some(PlaylistTag::DiscontinuitySequence(playlist.discontinuity_sequence()))
+            }
+            PlaylistTag::AllowCache(_) => playlist.allow_cache().map(PlaylistTag::AllowCache),
+            PlaylistTag::PlaylistType(_) => playlist.playlist_type().map(PlaylistTag::PlaylistType),
+            PlaylistTag::IFramesOnly => playlist.i_frames_only().then_some(PlaylistTag::IFramesOnly),
+            PlaylistTag::PartInf(_) => playlist.exact_part_target().map(PlaylistTag::PartInf),
+            PlaylistTag::Skip(_) => Some(playlist.skipped_segments()).filter(|x| *x > 0).map(PlaylistTag::Skip),
+            PlaylistTag::EndList => playlist.ended().then_some(PlaylistTag::EndList),
+        }
+    }
+
+    /// Whether both are the same tag, regardless of value.
+    pub(crate) fn same_tag(&self, other: &PlaylistTag) -> bool {
+        core::mem::discriminant(self) == core::mem::discriminant(other)
+    }
+}
+
+impl fmt::Display for PlaylistTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlaylistTag::Version(version) => write!(f, "#{}:{}", VERSION_TAG, version),
+            PlaylistTag::TargetDuration(duration) => write!(f, "#{}:{}", DURATION_TAG, duration.as_secs()),
+            PlaylistTag::MediaSequence(sequence) => write!(f, "#{}:{}", MEDIA_SEQUENCE_TAG, sequence),
+            PlaylistTag::DiscontinuitySequence(sequence) => write!(f, "#{}:{}", DISCONTINUITY_SEQUENCE_TAG, sequence),
+            PlaylistTag::AllowCache(allow_cache) => {
+                write!(f, "#{}:{}", ALLOW_CACHE_TAG, if *allow_cache { "YES" } else { "NO" })
+            }
+            PlaylistTag::PlaylistType(playlist_type) => write!(f, "#{}:{}", PLAYLIST_TYPE_TAG, playlist_type),
+            PlaylistTag::IFramesOnly => write!(f, "#{}", I_FRAMES_ONLY_TAG),
+            PlaylistTag::PartInf(part_target) => write!(f, "#{}:PART-TARGET={}", PART_INF_TAG, part_target),
+            PlaylistTag::Skip(skipped_segments) => write!(f, "#{}:SKIPPED-SEGMENTS={}", SKIP_TAG, skipped_segments),
+            PlaylistTag::EndList => write!(f, "#{}", ENDLIST_TAG),
+        }
+    }
+}
+
+impl MediaPlaylist {
+    /// Serializes the playlist into `ext-m3u` data.
+    pub fn write(&self, options: &WriteOptions) -> String {
+        let missing_version =
+            (options.version_tag == VersionTag::Always && self.version() == 0).then(|| self.required_version());
+        if options.preserve_source {
+            if let Some(source) = self.source() {
+                return options.format(source.write(self), missing_version);
+            }
+        }
+
+        let mut out = String::new();
+        writeln!(out, "#{}", HEADER_TAG).unwrap();
+        for tag in PlaylistTag::header_tags(self) {
+            writeln!(out, "{}", tag).unwrap();
+        }
+        let mut state = SegmentState::default();
+        for segment in self.segments() {
+            write_segment(&mut out, segment, &mut state);
+        }
+        write_trailing(&mut out, self);
+        if self.ended() {
+            writeln!(out, "{}", PlaylistTag::EndList).unwrap();
+        }
+        options.format(out, missing_version)
+    }
+}
+
+impl fmt::Display for MediaPlaylist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.write(&WriteOptions::default()))
+    }
+}
+
+impl MasterPlaylist {
+    /// Serializes the playlist into `ext-m3u` data, like its [`Display`][fmt::Display] output
+    /// with the formatting options applied. There is no source to preserve.
+    pub fn write(&self, options: &WriteOptions) -> String {
+        options.format(self.to_string(), None)
+    }
+
+    /// Like [`write`][Self::write], but fails rather than write a malformed playlist: one with a
+    /// quoted string holding a double quote or line break, a list item holding a comma, an
+    /// enumerated string outside its allowed values, or a variant URI which doesn't make a URI
+    /// line.
+    pub fn write_checked(&self, options: &WriteOptions) -> Result<String> {
+        for data in self.session_data() {
+            data.attributes().finish().with_context(|| format!("Invalid session data {}", data.data_id()))?;
+        }
+        for rendition in self.renditions() {
+            rendition.attributes().finish().with_context(|| format!("Invalid rendition {}", rendition.name()))?;
+        }
+        for variant in self.variants() {
+            let uri = variant.uri();
+            variant.attributes().finish().with_context(|| format!("Invalid variant {}", uri))?;
+            if uri.is_empty() || uri.starts_with('#') || uri.contains(['\r', '\n']) {
+                return Err(anyhow::anyhow!("Invalid variant URI {:?}", uri));
+            }
+        }
+        for variant in self.i_frame_variants() {
+            let mut list = variant.attributes();
+            list.quoted("URI", variant.uri());
+            list.finish().with_context(|| format!("Invalid I-frame variant {}", variant.uri()))?;
+        }
+        Ok(self.write(options))
+    }
+}
+
+/// Session data and keys go first, then renditions, then each variant followed by its URI, then
+/// the I-frame variants.
+impl fmt::Display for MasterPlaylist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "#{}", HEADER_TAG)?;
+        if self.version() > 0 {
+            writeln!(f, "#{}:{}", VERSION_TAG, self.version())?;
+        }
+        for data in self.session_data() {
+            writeln!(f, "#{}:{}", SESSION_DATA_TAG, data)?;
+        }
+        for key in self.session_keys() {
+            writeln!(f, "#{}:{}", SESSION_KEY_TAG, key)?;
+        }
+        for rendition in self.renditions() {
+            writeln!(f, "#{}:{}", MEDIA_TAG, rendition)?;
+        }
+        for variant in self.variants() {
+            writeln!(f, "#{}:{}", STREAM_INF_TAG, variant)?;
+            writeln!(f, "{}", variant.uri())?;
+        }
+        for variant in self.i_frame_variants() {
+            let mut list = variant.attributes();
+            list.quoted("URI", variant.uri());
+            writeln!(f, "#{}:{}", I_FRAME_STREAM_INF_TAG, list.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+/// Tags carried over from previous segments, which are only written again when a segment's
+/// value differs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct SegmentState {
+    keys: Vec<EncryptionKey>,
+    map: Option<SegmentMap>,
+}
+
+impl SegmentState {
+    /// The state a reader is in after `segment`. A segment without a map leaves the previous
+    /// one in effect, since there is no tag to remove it.
+    pub(crate) fn update(&mut self, segment: &MediaSegment) {
+        self.keys = segment.keys().to_vec();
+        if let Some(map) = segment.map() {
+            self.map = Some(map.clone());
+        }
+    }
+}
+
+/// Writes the tags and URI of a segment, with the tags in the segment's order. EXT-X-KEY and
+/// EXT-X-MAP tags are only written when the segment's keys or map differ from those in `state`.
+pub(crate) fn write_segment(out: &mut String, segment: &MediaSegment, state: &mut SegmentState) {
+    let (mut parts, mut date_ranges) = (segment.parts().iter(), segment.date_ranges().iter());
+    let mut keys_written = false;
+    for (slot, rest) in segment.tag_slots() {
+        let count = if rest { usize::MAX } else { 1 };
+        match slot {
+            TagSlot::Discontinuity if segment.discontinuity() => writeln!(out, "#{}", DISCONTINUITY_TAG).unwrap(),
+            TagSlot::Key if !keys_written => {
+                write_keys(out, segment, state);
+                keys_written = true;
+            }
+            TagSlot::Map => {
+                if let Some(map) = segment.map().filter(|x| state.map.as_ref() != Some(*x)) {
+                    writeln!(out, "#{}:{}", MAP_TAG, map).unwrap();
+                }
+            }
+            TagSlot::Part => {
+                for part in parts.by_ref().take(count) {
+                    writeln!(out, "#{}:{}", PART_TAG, part).unwrap();
+                }
+            }
+            TagSlot::ProgramDateTime => {
+                if let Some(date_time) = segment.program_date_time() {
+                    writeln!(out, "#{}:{}", PROGRAM_DATE_TIME_TAG, date_time).unwrap();
+                }
+            }
+            TagSlot::DateRange => {
+                for date_range in date_ranges.by_ref().take(count) {
+                    writeln!(out, "#{}:{}", DATERANGE_TAG, date_range).unwrap();
+                }
+            }
+            TagSlot::ByteRange => {
+                if let Some(byte_range) = segment.byte_range() {
+                    writeln!(out, "#{}:{}", BYTERANGE_TAG, byte_range).unwrap();
+                }
+            }
+            TagSlot::Gap if segment.gap() => writeln!(out, "#{}", GAP_TAG).unwrap(),
+            TagSlot::Custom(line) => writeln!(out, "{}", line).unwrap(),
+            TagSlot::Discontinuity | TagSlot::Key | TagSlot::Gap => {}
+        }
+    }
+    state.update(segment);
+    writeln!(out, "#{}:{},{}", SEGMENT_TAG, segment.exact_duration(), segment.title().unwrap_or_default())
+        .unwrap();
+    writeln!(out, "{}", segment.url()).unwrap();
+}
+
+/// Writes the EXT-X-KEY tags changing the keys in `state` to the segment's.
+fn write_keys(out: &mut String, segment: &MediaSegment, state: &SegmentState) {
+    if segment.keys() == state.keys {
+        return;
+    }
+    //only the changed keys if replacing those gives the segment's keys, otherwise start over
+    let changed: Vec<&EncryptionKey> = segment.keys().iter().filter(|x| !state.keys.contains(x)).collect();
+    let mut updated = state.keys.clone();
+    for key in &changed {
+        (*key).clone().apply_to(&mut updated);
+    }
+    if updated == segment.keys() {
+        for key in changed {
+            writeln!(out, "#{}:{}", KEY_TAG, key).unwrap();
+        }
+    } else {
+        writeln!(out, "#{}:METHOD=NONE", KEY_TAG).unwrap();
+        for key in segment.keys() {
+            writeln!(out, "#{}:{}", KEY_TAG, key).unwrap();
+        }
+    }
+}
+
+/// Writes what follows the last segment: date ranges, parts, preload hints and rendition reports.
+pub(crate) fn write_trailing(out: &mut String, playlist: &MediaPlaylist) {
+    write_date_ranges(out, playlist.trailing_date_ranges());
+    write_parts(out, playlist.trailing_parts());
+    write_preload_hints(out, playlist.preload_hints());
+    write_rendition_reports(out, playlist.rendition_reports());
+}
+
+fn write_date_ranges(out: &mut String, date_ranges: &[DateRange]) {
+    for date_range in date_ranges {
+        writeln!(out, "#{}:{}", DATERANGE_TAG, date_range).unwrap();
+    }
+}
+
+fn write_parts(out: &mut String, parts: &[PartialSegment]) {
+    for part in parts {
+        writeln!(out, "#{}:{}", PART_TAG, part).unwrap();
+    }
+}
+
+fn write_preload_hints(out: &mut String, hints: &[PreloadHint]) {
+    for hint in hints {
+        writeln!(out, "#{}:{}", PRELOAD_HINT_TAG, hint).unwrap();
+    }
+}
+
+fn write_rendition_reports(out: &mut String, reports: &[RenditionReport]) {
+    for report in reports {
+        writeln!(out, "#{}:{}", RENDITION_REPORT_TAG, report).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SegmentDuration;
+
+    #[test]
+    fn writes_durations_exactly() {
+        let mut segment = MediaSegment::new(Duration::from_millis(10500), "a.ts");
+        let mut out = String::new();
+        write_segment(&mut out, &segment, &mut SegmentState::default());
+        segment.set_duration("12.1660".parse::<SegmentDuration>().unwrap());
+        write_segment(&mut out, &segment, &mut SegmentState::default());
+        assert_eq!(out, "#EXTINF:10.5,\na.ts\n#EXTINF:12.1660,\na.ts\n");
+    }
+
+    #[test]
+    fn writes_canonical_playlist() {
+        let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            # comments are dropped
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-VERSION:4
+            #EXT-X-KEY:METHOD=AES-128,URI="1.key",IV=0x01
+            #EXTINF:9.50,first
+            #EXT-X-BYTERANGE:100@0
+            main.ts
+            #EXT-X-DISCONTINUITY
+            #EXT-X-KEY:METHOD=NONE
+            #EXTINF:4.000,
+            ad.ts
+            #EXT-X-ENDLIST
+        "#})
+        .unwrap();
+        let expected = indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-VERSION:4
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-KEY:METHOD=AES-128,URI="1.key",IV=0x01
+            #EXT-X-BYTERANGE:100@0
+            #EXTINF:9.50,first
+            main.ts
+            #EXT-X-DISCONTINUITY
+            #EXT-X-KEY:METHOD=NONE
+            #EXTINF:4.000,
+            ad.ts
+            #EXT-X-ENDLIST
+        "#};
+        assert_eq!(playlist.to_string(), expected);
+        assert_eq!(MediaPlaylist::parse_ext_m3u(expected).unwrap(), playlist);
+    }
+
+    #[test]
+    fn writes_maps_when_changed() {
+        let file = indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-VERSION:6
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-DISCONTINUITY-SEQUENCE:2
+            #EXT-X-MAP:URI="init.mp4",BYTERANGE="720@0"
+            #EXT-X-PROGRAM-DATE-TIME:2010-02-19T14:54:23.031+08:00
+            #EXTINF:10,
+            1.mp4
+            #EXTINF:10,
+            2.mp4
+            #EXT-X-DISCONTINUITY
+            #EXT-X-MAP:URI="other.mp4"
+            #EXT-X-PROGRAM-DATE-TIME:2010-02-19T15:00:00.000Z
+            #EXTINF:10,
+            3.mp4
+        "#};
+        let playlist = MediaPlaylist::parse_ext_m3u(file).unwrap();
+        assert_eq!(playlist.discontinuity_sequence(), 2);
+        assert_eq!(playlist.segments()[1].map(), playlist.segments()[0].map());
+        assert_eq!(playlist.to_string(), file);
+    }
+
+    #[test]
+    fn applies_formatting_options() {
+        let playlist = MediaPlaylist::parse_ext_m3u(indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-TARGETDURATION:10
+            #EXT-X-KEY:METHOD=AES-128,URI="1.key",IV=0x01
+            #EXTINF:9.5,
+            main.ts
+            #EXTINF:4.12345,title
+            ad.ts
+        "#})
+        .unwrap();
+        let options = WriteOptions {
+            duration_precision: Some(3),
+            trailing_comma: false,
+            line_ending: LineEnding::CrLf,
+            attribute_quoting: AttributeQuoting::EnumeratedStrings,
+            version_tag: VersionTag::Always,
+            ..WriteOptions::default()
+        };
+        assert_eq!(
+            playlist.write(&options),
+            "#EXTM3U\r\n#EXT-X-VERSION:3\r\n#EXT-X-TARGETDURATION:10\r\n\
+             #EXT-X-KEY:METHOD=\"AES-128\",URI=\"1.key\",IV=0x01\r\n\
+             #EXTINF:9.500\r\nmain.ts\r\n#EXTINF:4.123,title\r\nad.ts\r\n"
+        );
+        assert_eq!(playlist.write(&WriteOptions::default()), playlist.to_string());
+    }
+
+    #[test]
+    fn writes_master_playlist() {
+        let file = indoc::indoc! {r#"
+            #EXTM3U
+            #EXT-X-VERSION:6
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aac",LANGUAGE="en",NAME="English",DEFAULT=YES,AUTOSELECT=YES,CHANNELS="2",URI="en.m3u8"
+            #EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID="subs",LANGUAGE="en",NAME="English",FORCED=YES,CHARACTERISTICS="public.accessibility.transcribes-spoken-dialog",URI="subs.m3u8"
+            #EXT-X-MEDIA:TYPE=CLOSED-CAPTIONS,GROUP-ID="cc",NAME="CC1",INSTREAM-ID="CC1"
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000,AVERAGE-BANDWIDTH=1000000,CODECS="avc1.4d401e,mp4a.40.2",RESOLUTION=1280x720,FRAME-RATE=29.970,AUDIO="aac",SUBTITLES="subs",CLOSED-CAPTIONS="cc"
+            low.m3u8
+            #EXT-X-STREAM-INF:BANDWIDTH=7680000,CODECS="hvc1.2.4.L150.B0",SUPPLEMENTAL-CODECS="dvh1.08.07/db4h",RESOLUTION=3840x2160,HDCP-LEVEL=TYPE-1,VIDEO-RANGE=PQ,AUDIO="aac",SUBTITLES="subs",CLOSED-CAPTIONS="cc"
+            high.m3u8
+        "#};
+        let mut playlist = MasterPlaylist::parse_ext_m3u(file).unwrap();
+        assert_eq!(playlist.to_string(), file);
+        assert_eq!(playlist.write_checked(&WriteOptions::default()).unwrap(), file);
+
+        playlist.variants_mut()[0].set_uri("low.m3u8\n#EXT-X-ENDLIST".to_string());
+        assert!(playlist.write_checked(&WriteOptions::default()).is_err());
+        playlist.variants_mut()[0].set_uri("low.m3u8".to_string());
+        playlist.renditions_mut()[0].set_uri(Some("en\".m3u8".to_string()));
+        let error = playlist.write_checked(&WriteOptions::default()).unwrap_err();
+        assert_eq!(format!("{:#}", error), concat!(
+            "Invalid rendition English: ",
+            "Attribute URI can't hold double quotes or line breaks: \"en\\\".m3u8\""
+        ));
+    }
+}